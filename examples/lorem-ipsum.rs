@@ -561,7 +561,7 @@ impl Renderer {
                              atlas_origins: &[Point2D<f32>],
                              point_size: f32)
                              -> usize {
-        let pixels_per_unit = point_size as f32 / font.units_per_em() as f32;
+        let pixels_per_unit = font.pixels_per_unit(point_size);
 
         let (mut vertices, mut indices) = (vec![], vec![]);
         for position in glyph_positions {
@@ -574,10 +574,11 @@ impl Renderer {
             let bearing_pos = (position.x as f32 * pixels_per_unit).round() as i32;
             let baseline_pos = (position.y as f32 * pixels_per_unit).round() as i32;
 
-            let left_pos = bearing_pos + glyph_rect_i.left;
-            let top_pos = baseline_pos - glyph_rect_i.top;
-            let right_pos = bearing_pos + glyph_rect_i.right;
-            let bottom_pos = baseline_pos - glyph_rect_i.bottom;
+            let draw_offset = glyph_rect_i.draw_offset();
+            let left_pos = bearing_pos + draw_offset.x;
+            let top_pos = baseline_pos + draw_offset.y;
+            let right_pos = left_pos + glyph_rect_i.size().width;
+            let bottom_pos = top_pos + glyph_rect_i.size().height;
 
             let first_index = vertices.len() as u16;
 
@@ -14,26 +14,69 @@
 //
 // [1]: https://medium.com/@raphlinus/inside-the-fastest-font-renderer-in-the-world-75ae5270c445
 
+// Coverage below this is accumulation noise, not part of a glyph, so the floor leaves it alone.
+#define MIN_VISIBLE_COVERAGE    (0.5f / 255.0f)
+
 const sampler_t SAMPLER = CLK_NORMALIZED_COORDS_FALSE | CLK_ADDRESS_NONE | CLK_FILTER_NEAREST;
 
+float3 srgbToLinear(float3 color) {
+    float3 low = color / 12.92f;
+    float3 high = pow((color + 0.055f) / 1.055f, (float3)(2.4f));
+    return select(high, low, islessequal(color, (float3)(0.04045f)));
+}
+
 __kernel void accum(__write_only image2d_t gImage,
                     __read_only image2d_t gCoverage,
                     uint4 kAtlasRect,
-                    uint kAtlasShelfHeight) {
+                    uint kAtlasShelfHeight,
+                    uint4 kColor,
+                    uint kPremultiplied,
+                    uint kAntialias,
+                    uint4 kCoverageOrigin,
+                    uint kSrgb,
+                    uint kSignedArea,
+                    __read_only image2d_t gLayersIn,
+                    __write_only image2d_t gLayersOut,
+                    uint kComposite,
+                    uint kMinCoverage) {
     // Determine the boundaries of the column we'll be traversing.
     uint atlasWidth = kAtlasRect.z - kAtlasRect.x, atlasHeight = kAtlasRect.w - kAtlasRect.y;
     uint column = get_global_id(0) % atlasWidth, shelfIndex = get_global_id(0) / atlasWidth;
     uint firstRow = min(shelfIndex * kAtlasShelfHeight, atlasHeight);
     uint lastRow = min((shelfIndex + 1) * kAtlasShelfHeight, atlasHeight);
 
+    // The text color, premultiplied by its own alpha.
+    float4 color = convert_float4(kColor) / 255.0f;
+    if (kSrgb)
+        color.xyz = srgbToLinear(color.xyz);
+    color = (float4)(color.xyz * color.w, color.w);
+
     // Sweep down the column, accumulating coverage as we go.
     float coverage = 0.0f;
     for (uint row = firstRow; row < lastRow; row++) {
         int2 coord = (int2)((int)column, (int)row);
-        coverage += read_imagef(gCoverage, SAMPLER, coord).r;
+        coverage += read_imagef(gCoverage, SAMPLER, coord + (int2)kCoverageOrigin.xy).r;
 
         float gray = fabs(coverage);
-        write_imagef(gImage, coord + (int2)kAtlasRect.xy, (float4)(gray, gray, gray, 1.0f));
+        if (!kAntialias)
+            gray = gray >= 0.5f ? 1.0f : 0.0f;
+        else if (kMinCoverage && gray >= MIN_VISIBLE_COVERAGE)
+            gray = fmax(gray, convert_float(kMinCoverage) / 65535.0f);
+        float4 value = kPremultiplied ? color * min(gray, 1.0f) : (float4)(gray, gray, gray, 1.0f);
+        if (kSignedArea)
+            value = (float4)(coverage, 0.0f, 0.0f, 1.0f);
+
+        // Composite over the layers drawn so far, with layers without a color in white. The
+        // result goes into the other layer image, since a kernel can't read and write one image.
+        if (kComposite) {
+            if (!kPremultiplied)
+                value = (float4)(min(gray, 1.0f));
+            int2 layerCoord = coord + (int2)kCoverageOrigin.xy;
+            value += read_imagef(gLayersIn, SAMPLER, layerCoord) * (1.0f - value.w);
+            write_imagef(gLayersOut, layerCoord, value);
+        }
+
+        write_imagef(gImage, coord + (int2)kAtlasRect.xy, value);
     }
 }
 
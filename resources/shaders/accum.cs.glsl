@@ -20,6 +20,38 @@ layout(IMAGE_FORMAT, binding = 0) uniform restrict writeonly image2DRect uImage;
 layout(r32f, binding = 1) uniform restrict readonly image2DRect uCoverage;
 layout(location = 2) uniform uvec4 uAtlasRect;
 layout(location = 3) uniform uint uAtlasShelfHeight;
+// The text color in straight RGBA, from 0 to 255.
+layout(location = 4) uniform uvec4 uColor;
+// If nonzero, write the text color premultiplied by coverage instead of grayscale coverage.
+layout(location = 5) uniform uint uPremultiplied;
+// If zero, round coverage to 0 or 1 instead of antialiasing.
+layout(location = 6) uniform uint uAntialias;
+// The origin of the region of the coverage buffer that was drawn into, in the first two
+// components.
+layout(location = 7) uniform uvec4 uCoverageOrigin;
+// If nonzero, the text color is sRGB and is converted to linear before premultiplication.
+layout(location = 8) uniform uint uSrgb;
+// If nonzero, write the signed area accumulated so far, without taking its absolute value or
+// clamping it, into an r32f image.
+layout(location = 9) uniform uint uSignedArea;
+// The layers composited so far, and the image to write them into with this one on top. These
+// are the coverage buffer's two layer images, which trade places after every draw.
+layout(rgba8, binding = 10) uniform readonly image2DRect uLayersIn;
+layout(rgba8, binding = 11) uniform writeonly image2DRect uLayersOut;
+// If nonzero, composite the premultiplied color over the layers drawn so far, and write the
+// result into both the layer image and the output atlas.
+layout(location = 12) uniform uint uComposite;
+// The least coverage of pixels that glyphs touch, in 16-bit fixed point. Zero disables the floor.
+layout(location = 13) uniform uint uMinCoverage;
+
+// Coverage below this is accumulation noise, not part of a glyph, so the floor leaves it alone.
+#define MIN_VISIBLE_COVERAGE    (0.5 / 255.0)
+
+vec3 srgbToLinear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, lessThanEqual(color, vec3(0.04045)));
+}
 
 void main() {
     // Determine the boundaries of the column we'll be traversing.
@@ -33,11 +65,45 @@ void main() {
     if (firstRow >= atlasHeight)
         return;
 
+    // The text color, premultiplied by its own alpha.
+    vec4 color = vec4(uColor) / 255.0;
+    if (uSrgb != 0u)
+        color.rgb = srgbToLinear(color.rgb);
+    color = vec4(color.rgb * color.a, color.a);
+
     // Sweep down the column, accumulating coverage as we go.
     float coverage = 0.0f;
     for (uint row = firstRow; row < lastRow; row++) {
         ivec2 coord = ivec2(column, row);
-        coverage += imageLoad(uCoverage, coord).r;
-        imageStore(uImage, coord + ivec2(uAtlasRect.xy), vec4(coverage, coverage, coverage, 1.0));
+        coverage += imageLoad(uCoverage, coord + ivec2(uCoverageOrigin.xy)).r;
+
+        float gray = coverage;
+        if (uAntialias == 0u) {
+            gray = abs(coverage) >= 0.5 ? 1.0 : 0.0;
+        } else if (uMinCoverage != 0u && abs(gray) >= MIN_VISIBLE_COVERAGE) {
+            // Raise faint coverage to the floor, keeping its sign.
+            float minCoverage = float(uMinCoverage) / 65535.0;
+            if (abs(gray) < minCoverage)
+                gray = sign(gray) * minCoverage;
+        }
+
+        vec4 value;
+        if (uSignedArea != 0u)
+            value = vec4(coverage, 0.0, 0.0, 1.0);
+        else if (uPremultiplied != 0u)
+            value = color * clamp(abs(gray), 0.0, 1.0);
+        else
+            value = vec4(gray, gray, gray, 1.0);
+
+        if (uComposite != 0u) {
+            // Layers without a color are white. Blend with the "over" operator.
+            if (uPremultiplied == 0u)
+                value = vec4(clamp(abs(gray), 0.0, 1.0));
+            ivec2 layerCoord = coord + ivec2(uCoverageOrigin.xy);
+            value += imageLoad(uLayersIn, layerCoord) * (1.0 - value.a);
+            imageStore(uLayersOut, layerCoord, value);
+        }
+
+        imageStore(uImage, coord + ivec2(uAtlasRect.xy), value);
     }
 }
@@ -20,7 +20,6 @@
 #version 330
 
 #define CURVE_THRESHOLD         0.333f
-#define CURVE_TOLERANCE         3.0f
 
 #define PIXELS_TO_DEVICE(x, y)  (vec2((x), (y)) / vec2(uAtlasSize) * 2.0f - 1.0f)
 
@@ -37,6 +36,8 @@ layout(triangle_strip, max_vertices = 256) out;
 
 // The size of the atlas in pixels.
 uniform uvec2 uAtlasSize;
+// Controls how finely curves are divided into lines. Higher values produce more lines.
+uniform float uCurveTolerance;
 
 // The vertex ID, passed into this shader.
 flat in int vVertexID[];
@@ -77,7 +78,7 @@ void main() {
         if (devSq >= CURVE_THRESHOLD) {
             // Inverse square root is likely no slower and may be faster than regular square root
             // (e.g. on x86).
-            lineCount += uint(floor(inversesqrt(inversesqrt(CURVE_TOLERANCE * devSq))));
+            lineCount += uint(floor(inversesqrt(inversesqrt(uCurveTolerance * devSq))));
         }
     }
 
@@ -11,10 +11,12 @@
 #version 410
 
 #define CURVE_THRESHOLD         0.333f
-#define CURVE_TOLERANCE         3.0f
 
 layout(vertices = 1) out;
 
+// Controls how finely curves are divided into lines. Higher values produce more lines.
+uniform float uCurveTolerance;
+
 // The vertex ID, passed into this shader.
 flat in int vVertexID[];
 
@@ -46,7 +48,7 @@ void main() {
         if (devSq >= CURVE_THRESHOLD) {
             // Inverse square root is likely no slower and may be faster than regular square root
             // (e.g. on x86).
-            lineCount += floor(inversesqrt(inversesqrt(CURVE_TOLERANCE * devSq)));
+            lineCount += floor(inversesqrt(inversesqrt(uCurveTolerance * devSq)));
         }
     }
 
@@ -10,12 +10,17 @@
 
 #version 330
 
+// Storage buffers need OpenGL 4.3 or this extension. Without them, only the uniform buffer is
+// declared.
+#extension GL_ARB_shader_storage_buffer_object : enable
+
 #define MAX_GLYPHS  2048
 
 // Accessors to work around Apple driver bugs.
 #define GLYPH_DESCRIPTOR_UNITS_PER_EM(d)    (d).misc.x
 #define IMAGE_DESCRIPTOR_ATLAS_POS(d)       (d).xy
 #define IMAGE_DESCRIPTOR_POINT_SIZE(d)      (d).z
+#define IMAGE_DESCRIPTOR_ROTATED(d)         (d).w
 
 // Information about the metrics of each glyph.
 struct GlyphDescriptor {
@@ -28,10 +33,26 @@ struct GlyphDescriptor {
 // The size of the atlas in pixels.
 uniform uvec2 uAtlasSize;
 
+// Whether the image descriptor comes from `aInstanceImage` instead of `uImages`.
+uniform bool uInstanced;
+
+// Whether the glyph descriptors come from `bGlyphDescriptors` instead of `ubGlyphDescriptors`.
+uniform bool uStorageBuffer;
+
 layout(std140) uniform ubGlyphDescriptors {
     GlyphDescriptor uGlyphs[MAX_GLYPHS];
 };
 
+#ifdef GL_ARB_shader_storage_buffer_object
+// The same descriptors, without the size limit of uniform buffers. `std430` lays out this struct
+// exactly as `std140` does.
+layout(std430) buffer bGlyphDescriptors {
+    GlyphDescriptor bGlyphs[];
+};
+#endif
+
+// Only used when not drawing instances. This stays a uniform buffer even when the glyph
+// descriptors are in a storage buffer, so atlases are still limited to `MAX_GLYPHS` glyphs.
 layout(std140) uniform ubImageDescriptors {
     vec4 uImages[MAX_GLYPHS];
 };
@@ -44,16 +65,34 @@ in ivec2 aPosition;
 // TODO(pcwalton): See if this is faster as a binary search on the vertex ID.
 in uint aGlyphIndex;
 
+// The image descriptor of the instance being drawn, when drawing instances.
+in vec4 aInstanceImage;
+
 // The vertex ID, passed along onto the TCS.
 flat out int vVertexID;
 
 void main() {
     vVertexID = gl_VertexID;
 
-    vec4 image = uImages[aGlyphIndex];
-    GlyphDescriptor glyph = uGlyphs[aGlyphIndex];
+    vec4 image = uInstanced ? aInstanceImage : uImages[aGlyphIndex];
+
+    // Only read the uniform buffer if it holds the descriptors; with a storage buffer, the glyph
+    // index may be past the end of `uGlyphs`.
+    GlyphDescriptor glyph;
+#ifdef GL_ARB_shader_storage_buffer_object
+    if (uStorageBuffer)
+        glyph = bGlyphs[aGlyphIndex];
+    else
+#endif
+        glyph = uGlyphs[aGlyphIndex];
 
-    vec2 glyphPos = vec2(aPosition.x - glyph.extents.x, glyph.extents.w - aPosition.y);
+    // Rotated glyphs are turned 90° clockwise, mapping (x, y) to (y, -x) in glyph space. This
+    // preserves winding, so the rest of the pipeline needs no changes.
+    vec2 glyphPos;
+    if (IMAGE_DESCRIPTOR_ROTATED(image) != 0.0f)
+        glyphPos = vec2(aPosition.y - glyph.extents.y, aPosition.x - glyph.extents.x);
+    else
+        glyphPos = vec2(aPosition.x - glyph.extents.x, glyph.extents.w - aPosition.y);
     float pointSize = IMAGE_DESCRIPTOR_POINT_SIZE(image);
     vec2 glyphPxPos = glyphPos * pointSize / GLYPH_DESCRIPTOR_UNITS_PER_EM(glyph);
     vec2 atlasPos = glyphPxPos + IMAGE_DESCRIPTOR_ATLAS_POS(image);
@@ -10,15 +10,20 @@
 
 //! Atlases, which hold rendered glyphs on the GPU.
 
-use error::GlError;
+use compute_shader::image::Image;
+use error::{AtlasError, GlError, InstanceError, RasterError};
 use euclid::{Point2D, Rect, Size2D};
 use gl::types::{GLenum, GLsizei, GLsizeiptr, GLuint, GLvoid};
 use gl;
-use outline::Outlines;
-use rect_packer::RectPacker;
+use outline::{self, Outlines};
+use rasterizer::Rasterizer;
+use rect_packer::{self, RectPacker};
+
+pub use rect_packer::PackingMode;
 use std::mem;
 use std::os::raw::c_void;
 use std::u16;
+use std::u32;
 
 /// Places glyphs in an atlas.
 ///
@@ -33,6 +38,10 @@ use std::u16;
 pub struct AtlasBuilder {
     rect_packer: RectPacker,
     batch_builders: Vec<BatchBuilder>,
+    // The rectangle of every glyph placed so far, including padding and margin.
+    placed_rects: Vec<Rect<u32>>,
+    allow_rotation: bool,
+    margin: u32,
 }
 
 impl AtlasBuilder {
@@ -45,11 +54,46 @@ impl AtlasBuilder {
     /// The shelf height should be the maximum of all minimum shelf heights for all fonts you wish
     /// to render into the atlas. You can retrive the minimum shelf height for a font with the
     /// `Font::shelf_height()` method.
+    ///
+    /// Each glyph is surrounded by a one-pixel transparent gutter so that neighboring glyphs don't
+    /// bleed into one another under linear filtering. Use `with_padding()` or `with_options()` to
+    /// change this.
     #[inline]
     pub fn new(available_width: u32, shelf_height: u32) -> AtlasBuilder {
+        AtlasBuilder::with_options(available_width, shelf_height, AtlasOptions::default())
+    }
+
+    /// Constructs a new atlas builder that surrounds each glyph with a transparent gutter of
+    /// `padding` pixels on every side.
+    ///
+    /// `Font::shelf_height()` accounts for the default padding of one pixel only. If you use a
+    /// larger padding, add `2 * (padding - 1)` to the shelf height.
+    #[inline]
+    pub fn with_padding(available_width: u32, shelf_height: u32, padding: u32) -> AtlasBuilder {
+        AtlasBuilder::with_options(available_width, shelf_height, AtlasOptions {
+            padding: padding,
+            ..AtlasOptions::default()
+        })
+    }
+
+    /// Constructs a new atlas builder with the given width in pixels, shelf height, and options.
+    ///
+    /// See `new()` for a description of the width and shelf height.
+    #[inline]
+    pub fn with_options(available_width: u32, shelf_height: u32, options: AtlasOptions)
+                        -> AtlasBuilder {
+        let mut rect_packer = RectPacker::new(available_width,
+                                              shelf_height,
+                                              options.padding,
+                                              options.packing_mode);
+        rect_packer.set_available_height(options.available_height);
+
         AtlasBuilder {
-            rect_packer: RectPacker::new(available_width, shelf_height),
+            rect_packer: rect_packer,
             batch_builders: vec![],
+            placed_rects: vec![],
+            allow_rotation: options.allow_rotation,
+            margin: options.margin,
         }
     }
 
@@ -60,41 +104,189 @@ impl AtlasBuilder {
     /// `OutlineBuilder::add_glyph()`.
     ///
     /// Returns the subpixel origin of the glyph in the atlas if successful or an error if there is
-    /// no space left for the glyph.
+    /// no space left for the glyph (`AtlasError::OutOfSpace`) or the glyph is larger than a shelf
+    /// (`AtlasError::GlyphTooLarge`). The rectangle starting at this origin with the size of
+    /// `Outlines::glyph_pixel_bounds()` is surrounded by the atlas padding, so sampling within it
+    /// never picks up neighboring glyphs.
+    ///
+    /// If a margin is set in the atlas options, the space allocated for the glyph extends that many
+    /// pixels beyond this rectangle on every side; see `GlyphPixelBounds::expanded()`.
+    ///
+    /// If rotation is enabled in the atlas options, the glyph may have been rotated; use
+    /// `place_glyph()` to find out.
+    #[inline]
     pub fn pack_glyph(&mut self, outlines: &Outlines, glyph_index: u16, point_size: f32)
-                      -> Result<Point2D<f32>, ()> {
-        let pixel_bounds = outlines.glyph_pixel_bounds(glyph_index, point_size);
-        let atlas_origin = try!(self.rect_packer.pack(&pixel_bounds.size().cast().unwrap()));
+                      -> Result<Point2D<f32>, AtlasError> {
+        self.place_glyph(outlines, glyph_index, point_size).map(|placement| placement.origin)
+    }
+
+    /// Places a glyph into the atlas, returning both its subpixel origin and whether it was
+    /// rotated.
+    ///
+    /// This is like `pack_glyph()`, but it also reports whether the glyph was rotated. Rotation
+    /// only happens if `AtlasOptions::allow_rotation` is set.
+    pub fn place_glyph(&mut self, outlines: &Outlines, glyph_index: u16, point_size: f32)
+                       -> Result<GlyphPlacement, AtlasError> {
+        let subpixel_bounds = outlines.glyph_subpixel_bounds(glyph_index, point_size);
+        let mut pixel_size = subpixel_bounds.round_out().size();
+
+        // Turn tall glyphs on their side if that makes them shorter, so that they take up less of
+        // the shelf height.
+        let mut rotated = false;
+        if self.allow_rotation {
+            let rotated_pixel_size = subpixel_bounds.rotated().round_out().size();
+            if rotated_pixel_size.height < pixel_size.height {
+                pixel_size = rotated_pixel_size;
+                rotated = true
+            }
+        }
+
+        // Allocate room for the margin, and draw the glyph inside it.
+        let margin = self.margin;
+        let allocated_size = pixel_size.cast().unwrap() + Size2D::new(margin * 2, margin * 2);
+        let allocated_origin = try!(self.rect_packer.pack(&allocated_size));
+        let padding = self.rect_packer.padding();
+        self.placed_rects.push(Rect::new(allocated_origin - Point2D::new(padding, padding),
+                                         allocated_size + Size2D::new(padding * 2, padding * 2)));
+        let atlas_origin = allocated_origin + Point2D::new(margin, margin);
 
         for batch_builder in &mut self.batch_builders {
-            if let Ok(atlas_origin) = batch_builder.add_glyph(outlines,
-                                                              &atlas_origin,
-                                                              glyph_index,
-                                                              point_size) {
-                return Ok(atlas_origin)
+            if let Ok(placement) = batch_builder.add_glyph(outlines,
+                                                           &atlas_origin,
+                                                           glyph_index,
+                                                           point_size,
+                                                           rotated) {
+                return Ok(placement)
             }
         }
 
+        // A fresh batch always has room for the glyph.
         let mut batch_builder = BatchBuilder::new();
-        let atlas_origin = try!(batch_builder.add_glyph(outlines,
-                                                        &atlas_origin,
-                                                        glyph_index,
-                                                        point_size));
+        let placement = batch_builder.add_glyph(outlines,
+                                                &atlas_origin,
+                                                glyph_index,
+                                                point_size,
+                                                rotated).unwrap();
         self.batch_builders.push(batch_builder);
-        Ok(atlas_origin)
+        Ok(placement)
+    }
+
+    /// Places a glyph into the atlas with the top-left corner of its pixel bounds at the given
+    /// position, instead of letting the packer choose where it goes.
+    ///
+    /// This is useful for deterministic test baselines and for tools that manage the layout of
+    /// the atlas themselves. Returns the subpixel origin of the glyph, as `pack_glyph()` does;
+    /// its whole-pixel part is `position`. The padding and margin surround the glyph as usual,
+    /// so `position` must leave room for them, and the glyph is never rotated.
+    ///
+    /// Returns `AtlasError::OutOfBounds` if the glyph, with its padding and margin, would extend
+    /// past the edges of the atlas or across the boundary between two shelves, and
+    /// `AtlasError::Overlapping` if it would overlap a glyph already in the atlas, padding
+    /// included. Glyphs packed automatically afterward are kept clear of it.
+    pub fn pack_glyph_at(&mut self,
+                         outlines: &Outlines,
+                         glyph_index: u16,
+                         point_size: f32,
+                         position: &Point2D<u32>)
+                         -> Result<Point2D<f32>, AtlasError> {
+        let pixel_size = outlines.glyph_pixel_bounds(glyph_index, point_size).size();
+        let border = self.rect_packer.padding() + self.margin;
+        let size = pixel_size.cast().unwrap() + Size2D::new(border * 2, border * 2);
+        if position.x < border || position.y < border {
+            let far_corner = *position + Point2D::new(size.width - border, size.height - border);
+            return Err(AtlasError::OutOfBounds {
+                rect: Rect::new(Point2D::zero(), Size2D::new(far_corner.x, far_corner.y)),
+            })
+        }
+        let rect = Rect::new(*position - Point2D::new(border, border), size);
+
+        if let Some(existing) = self.placed_rects.iter().find(|existing| {
+            existing.intersects(&rect)
+        }) {
+            return Err(AtlasError::Overlapping {
+                rect: rect,
+                existing: *existing,
+            })
+        }
+
+        try!(self.rect_packer.reserve(&rect));
+        self.placed_rects.push(rect);
+
+        // The glyph may already be in a batch at another position, so find one that lacks it.
+        for batch_builder in &mut self.batch_builders {
+            if !batch_builder.has_glyph(glyph_index) {
+                if let Ok(placement) = batch_builder.add_glyph(outlines,
+                                                               position,
+                                                               glyph_index,
+                                                               point_size,
+                                                               false) {
+                    return Ok(placement.origin)
+                }
+            }
+        }
+
+        let mut batch_builder = BatchBuilder::new();
+        let placement = batch_builder.add_glyph(outlines,
+                                                position,
+                                                glyph_index,
+                                                point_size,
+                                                false).unwrap();
+        self.batch_builders.push(batch_builder);
+        Ok(placement.origin)
+    }
+
+    /// Returns true if a glyph of the given size in pixels, not including padding or margin, would
+    /// fit in the atlas.
+    ///
+    /// This doesn't take rotation into account. If it returns false, packing a glyph of this size
+    /// will fail, and you may want to start a new atlas.
+    #[inline]
+    pub fn can_fit(&self, size: &Size2D<u32>) -> bool {
+        self.rect_packer.can_pack(&(*size + Size2D::new(self.margin * 2, self.margin * 2)))
+    }
+
+    /// Places several glyphs into the atlas, tallest first.
+    ///
+    /// Packing glyphs in order of decreasing height uses space considerably more efficiently than
+    /// packing them in an arbitrary order, especially with `PackingMode::Skyline`.
+    ///
+    /// Returns the placements of the glyphs in the same order as `glyph_indices`, or an error if
+    /// there is no space left for one of them. See `place_glyph()` for details.
+    pub fn pack_glyphs(&mut self, outlines: &Outlines, glyph_indices: &[u16], point_size: f32)
+                       -> Result<Vec<GlyphPlacement>, AtlasError> {
+        // This is a stable sort, so the result is deterministic.
+        let mut order: Vec<usize> = (0..glyph_indices.len()).collect();
+        order.sort_by(|&a, &b| {
+            let a_height = outlines.glyph_pixel_bounds(glyph_indices[a], point_size).size().height;
+            let b_height = outlines.glyph_pixel_bounds(glyph_indices[b], point_size).size().height;
+            b_height.cmp(&a_height)
+        });
+
+        let mut placements = vec![None; glyph_indices.len()];
+        for index in order {
+            placements[index] = Some(try!(self.place_glyph(outlines,
+                                                           glyph_indices[index],
+                                                           point_size)));
+        }
+        Ok(placements.into_iter().map(|placement| placement.unwrap()).collect())
     }
 
     /// Creates an atlas by uploading the atlas info to the GPU.
     pub fn create_atlas(mut self) -> Result<Atlas, GlError> {
-        let mut batches = vec![];
+        let (mut batches, mut placements) = (vec![], vec![]);
         for batch_builder in self.batch_builders.into_iter() {
+            placements.extend(batch_builder.placements.iter().filter_map(|placement| *placement));
             batches.push(try!(batch_builder.create_batch()))
         }
 
         Ok(Atlas {
             batches: batches,
+            placements: placements,
             shelf_height: self.rect_packer.shelf_height(),
             shelf_columns: self.rect_packer.shelf_columns(),
+            padding: self.rect_packer.padding(),
+            margin: self.margin,
+            occupancy: self.rect_packer.occupancy(),
         })
     }
 }
@@ -102,6 +294,8 @@ impl AtlasBuilder {
 struct BatchBuilder {
     image_descriptors: Vec<ImageDescriptor>,
     image_metadata: Vec<ImageMetadata>,
+    // Where each glyph in this batch was placed, indexed by glyph index like the descriptors.
+    placements: Vec<Option<GlyphPlacement>>,
 }
 
 impl BatchBuilder {
@@ -109,6 +303,14 @@ impl BatchBuilder {
         BatchBuilder {
             image_descriptors: vec![],
             image_metadata: vec![],
+            placements: vec![],
+        }
+    }
+
+    fn has_glyph(&self, glyph_index: u16) -> bool {
+        match self.image_descriptors.get(glyph_index as usize) {
+            None => false,
+            Some(image_descriptor) => image_descriptor.point_size != 0.0,
         }
     }
 
@@ -116,41 +318,45 @@ impl BatchBuilder {
                  outlines: &Outlines,
                  atlas_origin: &Point2D<u32>,
                  glyph_index: u16,
-                 point_size: f32)
-                 -> Result<Point2D<f32>, ()> {
-        // Check to see if we're already rendering this glyph.
+                 point_size: f32,
+                 rotated: bool)
+                 -> Result<GlyphPlacement, ()> {
+        // Check to see if we're already rendering this glyph. Unused slots have a zero point
+        // size.
         if let Some(image_descriptor) = self.image_descriptors.get(glyph_index as usize) {
             if image_descriptor.point_size == point_size {
                 // Glyph is already present.
-                return Ok(Point2D::new(image_descriptor.atlas_x, image_descriptor.atlas_y))
-            } else {
+                return Ok(self.placements[glyph_index as usize].unwrap())
+            } else if image_descriptor.point_size != 0.0 {
                 // Glyph is present at a different font size. We need a new batch.
                 return Err(())
             }
         }
 
-        let subpixel_bounds = outlines.glyph_subpixel_bounds(glyph_index, point_size);
+        let upright_bounds = outlines.glyph_subpixel_bounds(glyph_index, point_size);
+        let subpixel_bounds = if rotated { upright_bounds.rotated() } else { upright_bounds };
         let glyph_id = outlines.glyph_id(glyph_index);
-        let glyph_index = self.image_descriptors.len() as u16;
 
+        // The image descriptors are indexed by glyph index in the shader, so glyphs may be added
+        // in any order.
         while self.image_descriptors.len() < glyph_index as usize + 1 {
-            self.image_descriptors.push(ImageDescriptor::default())
-        }
-
-        while self.image_metadata.len() < glyph_index as usize + 1 {
-            self.image_metadata.push(ImageMetadata::default())
+            self.image_descriptors.push(ImageDescriptor::default());
+            self.placements.push(None)
         }
 
+        let pixel_size = subpixel_bounds.round_out().size();
+        let rect = Rect::new(*atlas_origin, Size2D::new(pixel_size.width as u32,
+                                                        pixel_size.height as u32));
         let atlas_origin = Point2D::new(atlas_origin.x as f32 + subpixel_bounds.left.fract(),
                                         atlas_origin.y as f32 + 1.0 - subpixel_bounds.top.fract());
         self.image_descriptors[glyph_index as usize] = ImageDescriptor {
             atlas_x: atlas_origin.x,
             atlas_y: atlas_origin.y,
             point_size: point_size,
-            glyph_index: glyph_index as f32,
+            rotated: if rotated { 1.0 } else { 0.0 },
         };
 
-        self.image_metadata[glyph_index as usize] = ImageMetadata {
+        self.image_metadata.push(ImageMetadata {
             glyph_index: glyph_index as u32,
             glyph_id: glyph_id,
             start_index: outlines.descriptor(glyph_index).unwrap().start_index(),
@@ -158,9 +364,17 @@ impl BatchBuilder {
                 Some(descriptor) => descriptor.start_index() as u32,
                 None => outlines.indices_count() as u32,
             },
-        };
+        });
 
-        Ok(atlas_origin)
+        let placement = GlyphPlacement {
+            origin: atlas_origin,
+            rotated: rotated,
+            glyph_id: glyph_id,
+            rect: rect,
+            draw_offset: upright_bounds.round_out().draw_offset(),
+        };
+        self.placements[glyph_index as usize] = Some(placement);
+        Ok(placement)
     }
 
     /// Uploads this batch data to the GPU.
@@ -179,7 +393,7 @@ impl BatchBuilder {
                 }
                 Some((current_first, current_last)) => {
                     counts.push((current_last - current_first) as GLsizei);
-                    start_indices.push(current_first as usize);
+                    start_indices.push(current_first as usize * mem::size_of::<u32>());
                     current_range = Some((start_index, end_index))
                 }
                 None => current_range = Some((start_index, end_index)),
@@ -187,7 +401,7 @@ impl BatchBuilder {
         }
         if let Some((current_first, current_last)) = current_range {
             counts.push((current_last - current_first) as GLsizei);
-            start_indices.push(current_first as usize);
+            start_indices.push(current_first as usize * mem::size_of::<u32>());
         }
 
         // TODO(pcwalton): Try using `glMapBuffer` here.
@@ -209,11 +423,83 @@ impl BatchBuilder {
     }
 }
 
+/// Options that control how glyphs are placed in an atlas.
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasOptions {
+    /// The width of the transparent gutter placed around each glyph on every side, in pixels.
+    ///
+    /// The default is 1, which is enough to prevent bleeding under linear filtering.
+    pub padding: u32,
+    /// The algorithm used to place glyphs within each shelf.
+    ///
+    /// The default is `PackingMode::Guillotine`.
+    pub packing_mode: PackingMode,
+    /// Whether glyphs may be rotated 90° clockwise to fit them into less shelf height.
+    ///
+    /// Tall, narrow glyphs are turned on their side when this makes them shorter. Use
+    /// `AtlasBuilder::place_glyph()` to find out which glyphs were rotated so that you can rotate
+    /// them back when sampling the atlas. The default is false.
+    pub allow_rotation: bool,
+    /// The width of extra space around each glyph on every side, in pixels, that belongs to the
+    /// glyph's rectangle.
+    ///
+    /// Unlike the padding, the margin is meant to be drawn into: use it to leave room for effects
+    /// such as glows and shadows. Its coverage is zero after rasterization. Add `2 * margin` to the
+    /// shelf height. The default is 0.
+    pub margin: u32,
+    /// The maximum height of the atlas in pixels.
+    ///
+    /// Once the shelves fill this height, packing fails with `AtlasError::OutOfSpace`. Typically
+    /// this is the height of the image you will render the atlas into. The default is unlimited.
+    pub available_height: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> AtlasOptions {
+        AtlasOptions {
+            padding: rect_packer::DEFAULT_PADDING,
+            packing_mode: PackingMode::Guillotine,
+            allow_rotation: false,
+            margin: 0,
+            available_height: u32::MAX,
+        }
+    }
+}
+
+/// Where a glyph was placed in an atlas.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphPlacement {
+    /// The subpixel origin of the glyph in the atlas. See `AtlasBuilder::pack_glyph()`.
+    pub origin: Point2D<f32>,
+    /// True if the glyph was rotated 90° clockwise when it was placed.
+    ///
+    /// A rotated glyph occupies a rectangle with its width and height swapped: its extent is that
+    /// of `GlyphSubpixelBounds::rotated()`. To draw it upright, map the texture coordinates of the
+    /// quad's top-left, top-right, bottom-right, and bottom-left corners to the top-right,
+    /// bottom-right, bottom-left, and top-left corners of the atlas rectangle respectively.
+    pub rotated: bool,
+    /// The ID of the glyph in its font.
+    pub glyph_id: u16,
+    /// The whole-pixel rectangle that the glyph covers in the atlas, not including padding or
+    /// margin.
+    ///
+    /// Its size is that of the rounded-out pixel bounds of the glyph, with width and height
+    /// swapped if the glyph was rotated.
+    pub rect: Rect<u32>,
+    /// The offset from the pen position on the baseline to the top-left corner of the upright
+    /// glyph, in y-down pixels. See `GlyphPixelBounds::draw_offset()`.
+    pub draw_offset: Point2D<i32>,
+}
+
 /// An atlas holding rendered glyphs on the GPU.
 pub struct Atlas {
     batches: Vec<Batch>,
+    placements: Vec<GlyphPlacement>,
     shelf_height: u32,
     shelf_columns: u32,
+    padding: u32,
+    margin: u32,
+    occupancy: f32,
 }
 
 impl Atlas {
@@ -230,15 +516,360 @@ impl Atlas {
         self.shelf_height
     }
 
+    /// Returns the width of the transparent gutter around each glyph, in pixels.
+    #[inline]
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    /// Returns the width of the extra space around each glyph's rectangle, in pixels.
+    ///
+    /// See `AtlasOptions::margin`.
+    #[inline]
+    pub fn margin(&self) -> u32 {
+        self.margin
+    }
+
+    /// Returns the fraction of the atlas area that glyphs occupy, from 0.0 to 1.0.
+    ///
+    /// The area considered is that of all shelves in use. Padding is counted as unoccupied space.
+    /// This is useful to measure how efficiently glyphs were packed.
+    #[inline]
+    pub fn occupancy(&self) -> f32 {
+        self.occupancy
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn shelf_columns(&self) -> u32 {
         self.shelf_columns
     }
+
+    /// Returns where every glyph in the atlas was placed.
+    ///
+    /// A glyph placed at several point sizes appears once per size.
+    #[inline]
+    pub fn placements(&self) -> &[GlyphPlacement] {
+        &self.placements
+    }
+
+    /// Reads the rendered atlas back to the CPU, returning its pixels along with the placement of
+    /// every glyph in it.
+    ///
+    /// `image` and `rect` must be the image and rectangle that were passed to
+    /// `Rasterizer::draw_atlas()`. The coverage buffer only holds intermediate results, so the
+    /// pixels come from the destination image instead. They're one byte per pixel for `R8` images
+    /// and four for `RGBA8`, in rows from top to bottom, `rect.size.width` pixels wide. The
+    /// `rect` of each placement is relative to the top-left corner of `rect`, so it indexes the
+    /// returned pixels directly.
+    ///
+    /// This is meant for offline tools that bake atlases into files; it waits for the GPU to
+    /// finish drawing.
+    pub fn export(&self, rasterizer: &Rasterizer, image: &Image, rect: &Rect<u32>)
+                  -> Result<(Vec<u8>, Vec<GlyphPlacement>), RasterError> {
+        let pixels = try!(rasterizer.read_image(image, rect));
+        Ok((pixels, self.placements.clone()))
+    }
+}
+
+/// Places glyphs into a series of atlas pages, starting a new page whenever the existing ones
+/// run out of space.
+///
+/// Each page is an ordinary atlas built with the same width, shelf height, and options. For pages
+/// to ever fill up, `AtlasOptions::available_height` must be set; otherwise everything goes on
+/// the first page.
+pub struct AtlasArrayBuilder {
+    available_width: u32,
+    shelf_height: u32,
+    options: AtlasOptions,
+    pages: Vec<AtlasBuilder>,
+}
+
+impl AtlasArrayBuilder {
+    /// Constructs a new atlas array builder that creates pages with the given width in pixels,
+    /// shelf height, and options.
+    ///
+    /// See `AtlasBuilder::new()` for a description of the width and shelf height.
+    #[inline]
+    pub fn new(available_width: u32, shelf_height: u32, options: AtlasOptions)
+               -> AtlasArrayBuilder {
+        AtlasArrayBuilder {
+            available_width: available_width,
+            shelf_height: shelf_height,
+            options: options,
+            pages: vec![],
+        }
+    }
+
+    /// Places a glyph into the first page that has room for it, adding a new page if necessary.
+    ///
+    /// Returns the index of the page and the rectangle the glyph occupies on it, including the
+    /// margin but not the padding. Without a margin, the origin of the rectangle is the subpixel
+    /// origin of the glyph, as returned by `AtlasBuilder::pack_glyph()`. If the glyph was rotated,
+    /// the rectangle is rotated too.
+    ///
+    /// Returns `AtlasError::GlyphTooLarge` if the glyph can't fit on any page.
+    pub fn pack_glyph(&mut self, outlines: &Outlines, glyph_index: u16, point_size: f32)
+                      -> Result<(usize, Rect<f32>), AtlasError> {
+        let (page_index, placement) = try!(self.place_glyph(outlines, glyph_index, point_size));
+
+        let mut subpixel_bounds = outlines.glyph_subpixel_bounds(glyph_index, point_size);
+        if placement.rotated {
+            subpixel_bounds = subpixel_bounds.rotated()
+        }
+        let margin = self.options.margin as i32;
+        let pixel_size = subpixel_bounds.round_out().expanded(margin).size();
+        let origin = placement.origin - Point2D::new(margin as f32, margin as f32);
+        Ok((page_index, Rect::new(origin, pixel_size.cast().unwrap())))
+    }
+
+    /// Places a glyph into the first page that has room for it, adding a new page if necessary.
+    ///
+    /// This is like `pack_glyph()`, but it returns the placement of the glyph on its page instead
+    /// of its rectangle.
+    pub fn place_glyph(&mut self, outlines: &Outlines, glyph_index: u16, point_size: f32)
+                       -> Result<(usize, GlyphPlacement), AtlasError> {
+        // Packing doesn't modify a page if it fails, so it's fine to just try each one in turn.
+        for (page_index, page) in self.pages.iter_mut().enumerate() {
+            match page.place_glyph(outlines, glyph_index, point_size) {
+                Ok(placement) => return Ok((page_index, placement)),
+                Err(AtlasError::OutOfSpace { .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut page = AtlasBuilder::with_options(self.available_width,
+                                                  self.shelf_height,
+                                                  self.options);
+        let placement = try!(page.place_glyph(outlines, glyph_index, point_size));
+        self.pages.push(page);
+        Ok((self.pages.len() - 1, placement))
+    }
+
+    /// Returns the number of pages created so far.
+    #[inline]
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Creates the atlas array by uploading the info for each page to the GPU.
+    pub fn create_atlas_array(self) -> Result<AtlasArray, GlError> {
+        let mut pages = vec![];
+        for page in self.pages.into_iter() {
+            pages.push(try!(page.create_atlas()))
+        }
+
+        Ok(AtlasArray {
+            pages: pages,
+        })
+    }
+}
+
+/// A series of atlas pages holding rendered glyphs on the GPU.
+///
+/// Render each page into its own coverage buffer with `Rasterizer::draw_atlas()`.
+pub struct AtlasArray {
+    pages: Vec<Atlas>,
+}
+
+impl AtlasArray {
+    /// Returns the page with the given index, as returned by `AtlasArrayBuilder::pack_glyph()`.
+    #[inline]
+    pub fn page(&self, page_index: usize) -> Option<&Atlas> {
+        self.pages.get(page_index)
+    }
+
+    /// Returns all the pages, in order.
+    #[inline]
+    pub fn pages(&self) -> &[Atlas] {
+        &self.pages
+    }
+
+    /// Returns the number of pages.
+    #[inline]
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// One copy of a glyph to draw with `Rasterizer::draw_instances()`.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphInstance {
+    /// The glyph to draw, as returned by `OutlineBuilder::add_glyph()`.
+    pub glyph_index: u16,
+    /// Where the top left corner of the glyph's subpixel bounds lands, in pixels from the top left
+    /// of the drawing rectangle.
+    ///
+    /// For a glyph whose pen position is `(x, baseline)`, this is
+    /// `(x + bounds.left, baseline - bounds.top)`, where `bounds` is
+    /// `Outlines::glyph_subpixel_bounds()` at the instance's point size.
+    pub origin: Point2D<f32>,
+    /// The size to draw the glyph at, in pixels per em.
+    pub point_size: f32,
+}
+
+/// A buffer of glyph instances on the GPU, drawn all at once with `Rasterizer::draw_instances()`.
+///
+/// Unlike an atlas, which holds each glyph at most once and packs it itself, an instance buffer
+/// may draw the same glyph any number of times, at positions of your choosing. Instances of the
+/// same glyph are drawn together with one `glDrawElementsInstanced()` call, so a paragraph of
+/// thousands of glyphs takes one draw call per distinct glyph. Call `update()` to move the
+/// instances, for example every frame; this reuses the buffer.
+///
+/// Instances may overlap, but their coverage adds up where they do, so overlapping translucent
+/// edges come out darker than they would if composited.
+pub struct GlyphInstanceBuffer {
+    instances_buffer: GLuint,
+    draws: Vec<InstanceDraw>,
+    len: usize,
+}
+
+impl GlyphInstanceBuffer {
+    /// Uploads the given instances of glyphs from `outlines` to the GPU.
+    ///
+    /// Returns `InstanceError::GlyphIndexOutOfRange` if any glyph index is out of range for
+    /// `outlines`.
+    pub fn new(outlines: &Outlines, instances: &[GlyphInstance])
+               -> Result<GlyphInstanceBuffer, InstanceError> {
+        let mut instance_buffer = GlyphInstanceBuffer {
+            instances_buffer: 0,
+            draws: vec![],
+            len: 0,
+        };
+        unsafe {
+            gl::GenBuffers(1, &mut instance_buffer.instances_buffer);
+        }
+        try!(instance_buffer.update(outlines, instances));
+        Ok(instance_buffer)
+    }
+
+    /// Replaces the instances with the given ones, reusing the buffer.
+    ///
+    /// Returns `InstanceError::GlyphIndexOutOfRange` if any glyph index is out of range for
+    /// `outlines`, leaving the instances unchanged.
+    pub fn update(&mut self, outlines: &Outlines, instances: &[GlyphInstance])
+                  -> Result<(), InstanceError> {
+        // Sort the instances by glyph so that each glyph's instances are contiguous.
+        let mut sorted_instances = instances.to_vec();
+        sorted_instances.sort_by_key(|instance| instance.glyph_index);
+
+        let mut draws: Vec<InstanceDraw> = vec![];
+        for (instance_index, instance) in sorted_instances.iter().enumerate() {
+            if let Some(draw) = draws.last_mut() {
+                if draw.glyph_index == instance.glyph_index {
+                    draw.instance_count += 1;
+                    continue
+                }
+            }
+
+            let index_range = match outline::glyph_index_range(outlines.descriptor_slice(),
+                                                               outlines.indices_count(),
+                                                               instance.glyph_index) {
+                None => return Err(InstanceError::GlyphIndexOutOfRange(instance.glyph_index)),
+                Some(index_range) => index_range,
+            };
+            draws.push(InstanceDraw {
+                glyph_index: instance.glyph_index,
+                start_index: index_range.start as usize,
+                count: (index_range.end - index_range.start) as GLsizei,
+                first_instance: instance_index,
+                instance_count: 1,
+            })
+        }
+        self.draws = draws;
+
+        // The instances use the same layout as the image descriptors of an atlas. See
+        // `draw.vs.glsl`.
+        let image_descriptors: Vec<ImageDescriptor> = sorted_instances.iter().map(|instance| {
+            ImageDescriptor {
+                atlas_x: instance.origin.x,
+                atlas_y: instance.origin.y,
+                point_size: instance.point_size,
+                rotated: 0.0,
+            }
+        }).collect();
+
+        unsafe {
+            let length = image_descriptors.len() * mem::size_of::<ImageDescriptor>();
+            let ptr = image_descriptors.as_ptr() as *const ImageDescriptor as *const c_void;
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instances_buffer);
+            gl::BufferData(gl::ARRAY_BUFFER, length as GLsizeiptr, ptr, gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        self.len = instances.len();
+        Ok(())
+    }
+
+    /// Returns the number of instances.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no instances.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of draw calls that drawing these instances takes: one per distinct
+    /// glyph.
+    #[inline]
+    pub fn draw_count(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// Returns the OpenGL buffer holding the instances, sorted by glyph index, as four floats
+    /// each: the x and y of the origin, the point size, and zero.
+    #[inline]
+    pub fn instances_buffer(&self) -> GLuint {
+        self.instances_buffer
+    }
+
+    // Draws every instance, with the instance attribute at the given location.
+    #[doc(hidden)]
+    pub unsafe fn draw(&self, primitive: GLenum, instance_attribute: GLuint) {
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.instances_buffer);
+        for draw in &self.draws {
+            // Point the attribute at the glyph's first instance, since base instances need
+            // OpenGL 4.2.
+            let offset = draw.first_instance * mem::size_of::<ImageDescriptor>();
+            gl::VertexAttribPointer(instance_attribute,
+                                    4,
+                                    gl::FLOAT,
+                                    gl::FALSE,
+                                    mem::size_of::<ImageDescriptor>() as GLsizei,
+                                    offset as *const GLvoid);
+            gl::DrawElementsInstanced(primitive,
+                                      draw.count,
+                                      gl::UNSIGNED_INT,
+                                      (draw.start_index * mem::size_of::<u32>()) as *const GLvoid,
+                                      draw.instance_count);
+        }
+    }
+}
+
+impl Drop for GlyphInstanceBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &mut self.instances_buffer);
+        }
+    }
+}
+
+// The instances of one glyph, drawn with one call.
+struct InstanceDraw {
+    glyph_index: u16,
+    start_index: usize,
+    count: GLsizei,
+    first_instance: usize,
+    instance_count: GLsizei,
 }
 
 struct Batch {
     images_buffer: GLuint,
+    // The byte offset of the first index of each range in the index buffer.
     start_indices: Vec<usize>,
     counts: Vec<GLsizei>,
 }
@@ -274,7 +905,8 @@ pub struct ImageDescriptor {
     atlas_x: f32,
     atlas_y: f32,
     point_size: f32,
-    glyph_index: f32,
+    // 1.0 if the glyph is rotated 90° clockwise or 0.0 otherwise. See `draw.vs.glsl`.
+    rotated: f32,
 }
 
 // Information about each image that we keep around ourselves.
@@ -12,6 +12,8 @@
 //!
 //! Consulting this table is typically the first step when rendering some text.
 
+use std::cmp;
+
 /// A consecutive series of Unicode codepoints.
 #[derive(Clone, Copy, Debug)]
 pub struct CodepointRange {
@@ -73,6 +75,68 @@ impl CodepointRanges {
     }
 }
 
+/// A set of Unicode codepoints, such as those that a font has glyphs for, stored as sorted ranges
+/// so that membership tests take logarithmic time.
+///
+/// `Font::coverage_set()` returns the set of characters a font supports. Build it once per font
+/// and keep it around when choosing fallback fonts for many characters.
+#[derive(Clone, Debug)]
+pub struct CoverageSet {
+    ranges: Vec<CodepointRange>,
+}
+
+impl CoverageSet {
+    /// Creates a set containing every codepoint in the given ranges, which may be in any order
+    /// and may overlap.
+    pub fn new(mut ranges: Vec<CodepointRange>) -> CoverageSet {
+        ranges.retain(|range| range.start <= range.end);
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged_ranges: Vec<CodepointRange> = vec![];
+        for range in ranges {
+            if let Some(last_range) = merged_ranges.last_mut() {
+                if range.start <= last_range.end.saturating_add(1) {
+                    last_range.end = cmp::max(last_range.end, range.end);
+                    continue
+                }
+            }
+            merged_ranges.push(range)
+        }
+
+        CoverageSet {
+            ranges: merged_ranges,
+        }
+    }
+
+    /// Returns true if the set contains the given character.
+    #[inline]
+    pub fn contains(&self, character: char) -> bool {
+        self.contains_codepoint(character as u32)
+    }
+
+    /// Returns true if the set contains the given codepoint.
+    pub fn contains_codepoint(&self, codepoint: u32) -> bool {
+        let (mut lo, mut hi) = (0, self.ranges.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if codepoint < self.ranges[mid].start {
+                hi = mid
+            } else if codepoint > self.ranges[mid].end {
+                lo = mid + 1
+            } else {
+                return true
+            }
+        }
+        false
+    }
+
+    /// Returns the ranges of the set, sorted, with no two ranges overlapping or adjacent.
+    #[inline]
+    pub fn ranges(&self) -> &[CodepointRange] {
+        &self.ranges
+    }
+}
+
 /// An iterator over all codepoints in a range.
 pub struct CodepointRangeIter {
     start: u32,
@@ -9,69 +9,333 @@
 // except according to those terms.
 
 //! An intermediate surface on the GPU used during the rasterization process.
+//!
+//! Coverage is always linear: a pixel half covered by a glyph has a coverage of 0.5. Use it as
+//! alpha, not as a color. To blend text correctly into sRGB surfaces, choose
+//! `ColorSpace::Srgb` for premultiplied output; see `CoverageBuffer::set_color_space()`.
 
 use compute_shader::buffer::Protection;
 use compute_shader::device::Device;
 use compute_shader::image::{ExternalImage, Format, Image};
 use error::InitError;
 use euclid::size::Size2D;
-use gl::types::{GLint, GLuint};
+use gl::types::{GLenum, GLint, GLuint};
 use gl;
+use std::cell::Cell;
+use std::cmp;
 
 /// An intermediate surface on the GPU used during the rasterization process.
 ///
 /// You can reuse this surface from draw operation to draw operation. It only needs to be at least
-/// as large as every atlas you will draw into it.
+/// as large as every atlas you will draw into it; `required_size()` computes that size. Any width
+/// and height up to `max_size()` will do; they needn't be powers of two.
 ///
-/// The GPU memory usage of this buffer is `4 * width * height` bytes.
+/// The GPU memory usage of this buffer is `4 * width * height` bytes, or three times that for
+/// buffers made with `new_rgba()`.
 pub struct CoverageBuffer {
     image: Image,
+    // The two layer images of a buffer made with `new_rgba()`, and which of them holds the layers
+    // composited so far. Each draw reads that one and writes the other, since OpenCL 1.x kernels
+    // can't read and write the same image.
+    layer_images: Vec<Image>,
+    current_layer_image: Cell<usize>,
     framebuffer: GLuint,
+    size: Size2D<u32>,
+    filter: Filter,
+    premultiplied_color: Option<[u8; 4]>,
+    color_space: ColorSpace,
+    accumulation_mode: AccumulationMode,
 }
 
 impl CoverageBuffer {
     /// Creates a new coverage buffer of the given size.
     ///
-    /// The size must be at least as large as every atlas you will render with it.
+    /// The size must be at least as large as every atlas you will render with it;
+    /// `Rasterizer::draw_atlas()` fails with `RasterError::CoverageTooSmall` otherwise.
+    ///
+    /// The texture uses linear filtering. To choose the filtering mode, use `with_filter()`.
+    ///
+    /// Returns `InitError::SizeTooLarge` if either dimension exceeds `max_size()`.
     pub fn new(device: &Device, size: &Size2D<u32>) -> Result<CoverageBuffer, InitError> {
-        let image = try!(device.create_image(Format::R32F, Protection::ReadWrite, size)
-                               .map_err(InitError::ComputeError));
-
-        let mut framebuffer = 0;
-        unsafe {
-            let mut gl_texture = 0;
-            gl::GenTextures(1, &mut gl_texture);
-            try!(image.bind_to(&ExternalImage::GlTexture(gl_texture))
-                      .map_err(InitError::ComputeError));
-
-            gl::BindTexture(gl::TEXTURE_RECTANGLE, gl_texture);
-            gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-            gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-            gl::TexParameteri(gl::TEXTURE_RECTANGLE,
-                              gl::TEXTURE_WRAP_S,
-                              gl::CLAMP_TO_EDGE as GLint);
-            gl::TexParameteri(gl::TEXTURE_RECTANGLE,
-                              gl::TEXTURE_WRAP_T,
-                              gl::CLAMP_TO_EDGE as GLint);
-
-            gl::GenFramebuffers(1, &mut framebuffer);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
-            gl::FramebufferTexture2D(gl::FRAMEBUFFER,
-                                     gl::COLOR_ATTACHMENT0,
-                                     gl::TEXTURE_RECTANGLE,
-                                     gl_texture,
-                                     0);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-        }
+        CoverageBuffer::with_filter(device, size, Filter::Linear)
+    }
 
+    /// Creates a new coverage buffer of the given size whose texture is sampled with the given
+    /// filtering mode.
+    ///
+    /// Pathfinder itself reads the coverage buffer one texel at a time, so the filter only affects
+    /// shaders of your own that sample its texture. With `Filter::Linear`, a sample taken less
+    /// than one texel from the edge of a glyph's rectangle picks up coverage from outside it.
+    /// Atlases surround each glyph with one texel of empty space, which absorbs this as long as
+    /// samples stay within half a texel of the glyph's rectangle; if your shader samples any
+    /// farther out, use `Filter::Nearest`, which also gives exact values for pixel-by-pixel
+    /// readback.
+    pub fn with_filter(device: &Device, size: &Size2D<u32>, filter: Filter)
+                       -> Result<CoverageBuffer, InitError> {
+        let (image, framebuffer) = try!(create_image_and_framebuffer(device, size, filter));
         Ok(CoverageBuffer {
             image: image,
+            layer_images: vec![],
+            current_layer_image: Cell::new(0),
             framebuffer: framebuffer,
+            size: *size,
+            filter: filter,
+            premultiplied_color: None,
+            color_space: ColorSpace::Linear,
+            accumulation_mode: AccumulationMode::Coverage,
         })
     }
 
+    /// Creates a new coverage buffer of the given size that produces premultiplied-alpha output.
+    ///
+    /// Atlases rendered with this coverage buffer contain the given text color, in straight
+    /// (non-premultiplied) RGBA order, with both its color and alpha channels multiplied by the
+    /// coverage of each pixel. Pixels outside glyphs become fully transparent. Composite such an
+    /// atlas over a background with `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)` to avoid the
+    /// dark fringes that straight coverage produces over colored backgrounds.
+    ///
+    /// Premultiplied output requires an RGBA8 destination image; `Rasterizer::draw_atlas()` fails
+    /// with `RasterError::UnsupportedImageFormat` for R8 images.
+    pub fn new_premultiplied(device: &Device, size: &Size2D<u32>, color: [u8; 4])
+                             -> Result<CoverageBuffer, InitError> {
+        let mut coverage_buffer = try!(CoverageBuffer::new(device, size));
+        coverage_buffer.premultiplied_color = Some(color);
+        Ok(coverage_buffer)
+    }
+
+    /// Creates a new coverage buffer of the given size that composites the glyphs drawn with it,
+    /// layer over layer, into an RGBA surface of its own.
+    ///
+    /// This is meant for layered color glyphs, such as those of `Font::color_layers()`: set each
+    /// layer's color with `set_premultiplied_color()` and draw the layers from bottom to top with
+    /// `Rasterizer::draw_atlas()`. Each draw blends the layer's premultiplied colored coverage
+    /// over what the earlier layers left, with the "over" operator of
+    /// `glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA)`, and writes the result both into the layer
+    /// image and into the destination atlas, which therefore holds the finished premultiplied
+    /// glyph after the last layer. Layers drawn without a premultiplied color are white.
+    ///
+    /// The layers are composited into a pair of images that take turns: each draw reads the
+    /// layers so far from one and writes the result into the other, which `layer_image()` then
+    /// returns. Both have the format `Format::RGBA8` and the size of the buffer, and are attached
+    /// to the framebuffer as `GL_COLOR_ATTACHMENT1` and `GL_COLOR_ATTACHMENT2`;
+    /// `layer_attachment()` tells which one holds the result, so it can also be read back or
+    /// blitted from directly. Only the part of it under the last atlas drawn is up to date.
+    /// Pixels are indexed as in the coverage image, so layers of one glyph must be drawn with
+    /// atlases at the same place in the coverage buffer. Clear `layer_image()` to transparent with
+    /// `Queue::submit_clear()` before the first layer of each glyph; nothing clears it
+    /// automatically. The destination atlas must be an RGBA8 image; `Rasterizer::draw_atlas()`
+    /// fails with `RasterError::UnsupportedImageFormat` otherwise, as it does in
+    /// `AccumulationMode::SignedArea`.
+    pub fn new_rgba(device: &Device, size: &Size2D<u32>) -> Result<CoverageBuffer, InitError> {
+        let mut coverage_buffer = try!(CoverageBuffer::new(device, size));
+        coverage_buffer.layer_images = try!(create_layer_images(device,
+                                                                size,
+                                                                coverage_buffer.framebuffer));
+        Ok(coverage_buffer)
+    }
+
+    /// Returns the RGBA image holding the layers composited so far, or `None` if this buffer
+    /// wasn't made with `new_rgba()`.
+    ///
+    /// This alternates between two images from draw to draw, so call it again after each one.
+    /// Like `image()`, the images are replaced by `recreate()`.
+    #[inline]
+    pub fn layer_image(&self) -> Option<&Image> {
+        self.layer_images.get(self.current_layer_image.get())
+    }
+
+    /// Returns the framebuffer attachment of `layer_image()`, either `GL_COLOR_ATTACHMENT1` or
+    /// `GL_COLOR_ATTACHMENT2`, or `None` if this buffer wasn't made with `new_rgba()`.
+    #[inline]
+    pub fn layer_attachment(&self) -> Option<GLenum> {
+        self.layer_image().map(|_| gl::COLOR_ATTACHMENT1 + self.current_layer_image.get() as GLenum)
+    }
+
+    // Returns the layer image to read the layers so far from and the one to write the result
+    // into, or `None` if this buffer wasn't made with `new_rgba()`.
     #[doc(hidden)]
     #[inline]
+    pub fn layer_images_to_composite(&self) -> Option<(&Image, &Image)> {
+        let current_layer_image = self.current_layer_image.get();
+        match (self.layer_images.get(current_layer_image),
+               self.layer_images.get(1 - current_layer_image)) {
+            (Some(layers_in), Some(layers_out)) => Some((layers_in, layers_out)),
+            _ => None,
+        }
+    }
+
+    // Makes the layer image that was last written to the one that `layer_image()` returns.
+    #[doc(hidden)]
+    #[inline]
+    pub fn swap_layer_images(&self) {
+        self.current_layer_image.set(1 - self.current_layer_image.get())
+    }
+
+    /// Returns the text color that atlases are premultiplied with, or `None` if this buffer
+    /// produces straight coverage.
+    #[inline]
+    pub fn premultiplied_color(&self) -> Option<[u8; 4]> {
+        self.premultiplied_color
+    }
+
+    /// Changes the text color that atlases are premultiplied with, or switches to straight
+    /// coverage if `None` is given. See `new_premultiplied()`.
+    #[inline]
+    pub fn set_premultiplied_color(&mut self, color: Option<[u8; 4]>) {
+        self.premultiplied_color = color
+    }
+
+    /// Returns the color space of premultiplied output.
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Sets the color space of premultiplied output. The default is `ColorSpace::Linear`.
+    ///
+    /// Choose `ColorSpace::Srgb` if you composite atlases into a framebuffer with
+    /// `GL_FRAMEBUFFER_SRGB` enabled, such as the default framebuffer of a platform whose UI is
+    /// sRGB. The text color is then taken to be sRGB, as colors in UI toolkits are, and converted
+    /// to linear before it's multiplied by the coverage, so that atlases hold the linear values
+    /// that an sRGB framebuffer expects shaders to output; the hardware blends them in linear
+    /// space and encodes the result. Atlases still use 8 bits per channel, so dark colors lose
+    /// some precision.
+    ///
+    /// Straight coverage is linear regardless, so this has no effect on buffers without a
+    /// premultiplied color.
+    #[inline]
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space
+    }
+
+    /// Returns what atlases rendered with this coverage buffer hold.
+    #[inline]
+    pub fn accumulation_mode(&self) -> AccumulationMode {
+        self.accumulation_mode
+    }
+
+    /// Sets what atlases rendered with this coverage buffer hold. The default is
+    /// `AccumulationMode::Coverage`.
+    ///
+    /// `AccumulationMode::SignedArea` requires an R32F destination image;
+    /// `Rasterizer::draw_atlas()` fails with `RasterError::UnsupportedImageFormat` for other
+    /// formats. The premultiplied color, color space, and antialiasing mode are ignored in that
+    /// mode.
+    #[inline]
+    pub fn set_accumulation_mode(&mut self, accumulation_mode: AccumulationMode) {
+        self.accumulation_mode = accumulation_mode
+    }
+
+    /// Forgets the OpenGL texture and framebuffer without deleting them.
+    ///
+    /// Call this after the OpenGL context has been lost, since the names of the objects may refer
+    /// to unrelated objects in a new context. Dropping the coverage buffer afterward won't delete
+    /// them. The coverage buffer can't be used until `recreate()` is called.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.framebuffer = 0
+    }
+
+    /// Deletes the OpenGL texture and framebuffer immediately, consuming the coverage buffer.
+    ///
+    /// Dropping the coverage buffer deletes them too, but only whenever and wherever the value
+    /// happens to be dropped. Call this instead to release them at a known point while the
+    /// OpenGL context that created them is still current, such as during an engine's shutdown.
+    #[inline]
+    pub fn destroy(mut self) {
+        self.delete_framebuffer()
+    }
+
+    // Deletes the texture and framebuffer and forgets them, so that deleting them again does
+    // nothing.
+    fn delete_framebuffer(&mut self) {
+        // The buffer was invalidated, so we don't own any OpenGL objects.
+        if self.framebuffer == 0 {
+            return
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            let attachments = if !self.layer_images.is_empty() {
+                &[gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1, gl::COLOR_ATTACHMENT2][..]
+            } else {
+                &[gl::COLOR_ATTACHMENT0][..]
+            };
+            for &attachment in attachments {
+                let mut gl_texture = 0;
+                gl::GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER,
+                                                        attachment,
+                                                        gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME,
+                                                        &mut gl_texture as *mut GLuint as
+                                                        *mut GLint);
+                gl::DeleteTextures(1, &mut gl_texture);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &mut self.framebuffer);
+        }
+        self.invalidate()
+    }
+
+    /// Creates a new image, texture, and framebuffer of the same size in the current context.
+    ///
+    /// Call this after the OpenGL context has been lost and a new one made current. The old
+    /// texture and framebuffer are forgotten without being deleted, as with `invalidate()`.
+    pub fn recreate(&mut self, device: &Device) -> Result<(), InitError> {
+        self.invalidate();
+
+        let (image, framebuffer) = try!(create_image_and_framebuffer(device,
+                                                                     &self.size,
+                                                                     self.filter));
+        self.image = image;
+        self.framebuffer = framebuffer;
+        if !self.layer_images.is_empty() {
+            self.layer_images = try!(create_layer_images(device, &self.size, framebuffer));
+            self.current_layer_image.set(0)
+        }
+        Ok(())
+    }
+
+    /// Returns the largest width or height of a coverage buffer that the given device supports.
+    ///
+    /// This is the smaller of `GL_MAX_TEXTURE_SIZE` and `GL_MAX_RECTANGLE_TEXTURE_SIZE`, since
+    /// coverage buffers are rectangle textures. The limits are queried from the current OpenGL
+    /// context, which must be the one that `device` was created on. Clamp requested sizes to this
+    /// to avoid `InitError::SizeTooLarge`.
+    pub fn max_size(_device: &Device) -> u32 {
+        unsafe {
+            let (mut max_texture_size, mut max_rectangle_texture_size) = (0, 0);
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size);
+            gl::GetIntegerv(gl::MAX_RECTANGLE_TEXTURE_SIZE, &mut max_rectangle_texture_size);
+            cmp::min(max_texture_size, max_rectangle_texture_size) as u32
+        }
+    }
+
+    /// Returns the size of this coverage buffer in pixels.
+    #[inline]
+    pub fn size(&self) -> Size2D<u32> {
+        self.size
+    }
+
+    /// Returns the filtering mode of this coverage buffer's texture.
+    #[inline]
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Returns the compute-shader image backing this coverage buffer, so that compute passes of
+    /// your own can run over it.
+    ///
+    /// The image has the format `Format::R32F` and the size of the coverage buffer, and it's
+    /// created readable and writable, so you can pass it to `Queue::submit_compute()` as
+    /// `Uniform::Image` for either reading or writing. After `Rasterizer::draw_atlas()`, the part
+    /// of the image covering the atlas holds signed coverage *deltas*: summing a column from row 0
+    /// down to a pixel gives that pixel's coverage, which is what Pathfinder's own
+    /// accumulation pass does. Nothing else reads the image between draws, so a pass of yours
+    /// may overwrite it freely; every draw clears the part it uses first.
+    ///
+    /// The image is replaced by `recreate()`, so don't hold on to it across a context loss.
+    #[inline]
     pub fn image(&self) -> &Image {
         &self.image
     }
@@ -84,19 +348,164 @@ impl CoverageBuffer {
 }
 
 impl Drop for CoverageBuffer {
+    #[inline]
     fn drop(&mut self) {
+        self.delete_framebuffer()
+    }
+}
+
+/// Returns the smallest coverage buffer size that can draw atlases of all the given sizes: the
+/// largest width and the largest height among them, which may come from different atlases.
+///
+/// Returns a zero size if there are no atlases.
+pub fn required_size(atlas_sizes: &[Size2D<u32>]) -> Size2D<u32> {
+    atlas_sizes.iter().fold(Size2D::zero(), |required_size, atlas_size| {
+        Size2D::new(cmp::max(required_size.width, atlas_size.width),
+                    cmp::max(required_size.height, atlas_size.height))
+    })
+}
+
+/// What the accumulation pass writes into each pixel of an atlas.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AccumulationMode {
+    /// The fraction of the pixel covered by the glyph, from 0 to 1, with overlapping contours
+    /// counted once. This is what you want for ordinary text.
+    Coverage,
+    /// The signed area of the glyph within the pixel, before the absolute value is taken and the
+    /// result clamped to 1.
+    ///
+    /// Each pixel holds the area of the pixel enclosed by each contour, weighted by the direction
+    /// the contour winds around it, and summed over all contours. A contour wound clockwise in
+    /// font units, with y pointing up, counts positively, as TrueType outer contours do, and a
+    /// contour wound counterclockwise counts negatively, as TrueType holes and CFF outer contours
+    /// do. A pixel entirely inside two overlapping clockwise contours therefore holds 2, a pixel
+    /// inside a clockwise contour and the counterclockwise hole within it holds 0, and a pixel
+    /// that a contour crosses holds a fraction in between. The value is the nonzero winding
+    /// number of the pixel, weighted by area, so you can apply even-odd or any other fill rule
+    /// yourself, or detect overlapping contours.
+    SignedArea,
+}
+
+impl Default for AccumulationMode {
+    #[inline]
+    fn default() -> AccumulationMode {
+        AccumulationMode::Coverage
+    }
+}
+
+/// The color space of the text color in premultiplied output.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorSpace {
+    /// The text color is multiplied by the coverage as is, for compositing into linear
+    /// framebuffers or when you handle conversion yourself.
+    Linear,
+    /// The text color is sRGB and is converted to linear before it's multiplied by the coverage,
+    /// for compositing into framebuffers with `GL_FRAMEBUFFER_SRGB` enabled.
+    Srgb,
+}
+
+impl Default for ColorSpace {
+    #[inline]
+    fn default() -> ColorSpace {
+        ColorSpace::Linear
+    }
+}
+
+/// How a coverage buffer's texture is sampled between texels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Filter {
+    /// Each sample takes the value of the nearest texel (`GL_NEAREST`).
+    Nearest,
+    /// Each sample interpolates between the four nearest texels (`GL_LINEAR`).
+    Linear,
+}
+
+impl Filter {
+    #[inline]
+    fn to_gl(self) -> GLint {
+        match self {
+            Filter::Nearest => gl::NEAREST as GLint,
+            Filter::Linear => gl::LINEAR as GLint,
+        }
+    }
+}
+
+fn create_image_and_framebuffer(device: &Device, size: &Size2D<u32>, filter: Filter)
+                                -> Result<(Image, GLuint), InitError> {
+    // Check the size up front, since the framebuffer would otherwise just be incomplete.
+    let max_size = CoverageBuffer::max_size(device);
+    if size.width > max_size || size.height > max_size {
+        return Err(InitError::SizeTooLarge {
+            requested: *size,
+            max: max_size,
+        })
+    }
+
+    let image = try!(device.create_image(Format::R32F, Protection::ReadWrite, size)
+                           .map_err(InitError::ComputeError));
+
+    let mut framebuffer = 0;
+    unsafe {
+        let mut gl_texture = 0;
+        gl::GenTextures(1, &mut gl_texture);
+        try!(image.bind_to(&ExternalImage::GlTexture(gl_texture))
+                  .map_err(InitError::ComputeError));
+
+        gl::BindTexture(gl::TEXTURE_RECTANGLE, gl_texture);
+        gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MIN_FILTER, filter.to_gl());
+        gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MAG_FILTER, filter.to_gl());
+        gl::TexParameteri(gl::TEXTURE_RECTANGLE,
+                          gl::TEXTURE_WRAP_S,
+                          gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_RECTANGLE,
+                          gl::TEXTURE_WRAP_T,
+                          gl::CLAMP_TO_EDGE as GLint);
+
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER,
+                                 gl::COLOR_ATTACHMENT0,
+                                 gl::TEXTURE_RECTANGLE,
+                                 gl_texture,
+                                 0);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    Ok((image, framebuffer))
+}
+
+// Creates the two RGBA layer images of a coverage buffer made with `new_rgba()` and attaches
+// them to the buffer's framebuffer as its second and third color attachments.
+fn create_layer_images(device: &Device, size: &Size2D<u32>, framebuffer: GLuint)
+                       -> Result<Vec<Image>, InitError> {
+    let mut images = vec![];
+    for &attachment in &[gl::COLOR_ATTACHMENT1, gl::COLOR_ATTACHMENT2] {
+        let image = try!(device.create_image(Format::RGBA8, Protection::ReadWrite, size)
+                               .map_err(InitError::ComputeError));
+
         unsafe {
             let mut gl_texture = 0;
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
-            gl::GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER,
-                                                    gl::COLOR_ATTACHMENT0,
-                                                    gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME,
-                                                    &mut gl_texture as *mut GLuint as *mut GLint);
-            gl::DeleteTextures(1, &mut gl_texture);
+            gl::GenTextures(1, &mut gl_texture);
+            try!(image.bind_to(&ExternalImage::GlTexture(gl_texture))
+                      .map_err(InitError::ComputeError));
+
+            gl::BindTexture(gl::TEXTURE_RECTANGLE, gl_texture);
+            gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
 
+            // The draw pass only writes the first color attachment, which is the default draw
+            // buffer.
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER,
+                                     attachment,
+                                     gl::TEXTURE_RECTANGLE,
+                                     gl_texture,
+                                     0);
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-            gl::DeleteFramebuffers(1, &mut self.framebuffer);
         }
+
+        images.push(image)
     }
-}
 
+    Ok(images)
+}
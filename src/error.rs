@@ -10,18 +10,26 @@
 
 //! Errors.
 
+#[cfg(feature = "gpu")]
 use compute_shader;
+use euclid::{Rect, Size2D};
+#[cfg(feature = "gpu")]
 use gl::types::GLenum;
+#[cfg(feature = "gpu")]
+use otf;
+#[cfg(feature = "gpu")]
 use std::io;
 
 /// An OpenGL error with the given code.
 ///
 /// You cannot depend on these being reliably returned. Pathfinder does not call `glGetError()`
 /// unless necessary, to avoid driver stalls.
+#[cfg(feature = "gpu")]
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct GlError(pub GLenum);
 
 /// An initialization error. This could be an OpenGL error or a shader compilation/link error.
+#[cfg(feature = "gpu")]
 #[derive(Debug)]
 pub enum InitError {
     /// An OpenGL error occurred.
@@ -46,9 +54,19 @@ pub enum InitError {
 
     /// One of the rasterization options had an invalid syntax.
     InvalidSetting,
+
+    /// The requested size of a coverage buffer is larger than the OpenGL implementation supports.
+    ///
+    /// `requested` is the requested size, and `max` is the largest width or height supported, as
+    /// returned by `CoverageBuffer::max_size()`. Nothing was created.
+    SizeTooLarge {
+        requested: Size2D<u32>,
+        max: u32,
+    },
 }
 
 /// A rasterization error. This could be an OpenGL error or a compute error.
+#[cfg(feature = "gpu")]
 #[derive(Debug)]
 pub enum RasterError {
     /// An OpenGL error occurred.
@@ -59,5 +77,90 @@ pub enum RasterError {
     ///
     /// Currently supported formats are R8 and RGBA8.
     UnsupportedImageFormat,
+    /// The glyph to rasterize couldn't be read from the font.
+    FontError(otf::Error),
+    /// The coverage buffer is smaller than the atlas rectangle to be rendered, so glyphs near its
+    /// edges would be clipped.
+    ///
+    /// `required` is the size of the atlas rectangle, and `actual` is the size of the coverage
+    /// buffer. Nothing was drawn.
+    CoverageTooSmall {
+        required: Size2D<u32>,
+        actual: Size2D<u32>,
+    },
+    /// The outlines were uploaded with `StorageKind::Ssbo`, but the OpenGL implementation doesn't
+    /// support shader storage buffers. Nothing was drawn.
+    StorageBuffersUnsupported,
+    /// The outlines were built without the placeholder vertex that the draw shaders need to tell
+    /// lines from curves. Nothing was drawn.
+    ///
+    /// See `OutlineOptions::dummy_vertex`.
+    MissingDummyVertex,
+    /// The coverage buffer needed for the glyph is larger than the OpenGL implementation
+    /// supports, as `InitError::SizeTooLarge` describes. Nothing was drawn.
+    SizeTooLarge {
+        requested: Size2D<u32>,
+        max: u32,
+    },
 }
 
+/// An error that occurred while uploading glyph instances.
+#[cfg(feature = "gpu")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InstanceError {
+    /// An OpenGL error occurred.
+    GlError(GlError),
+    /// An instance refers to a glyph index that the outlines don't have. The instances were left
+    /// unchanged.
+    GlyphIndexOutOfRange(u16),
+}
+
+/// An error that occurred while placing a glyph into an atlas.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AtlasError {
+    /// The glyph is larger than a shelf, so it can never fit in this atlas.
+    ///
+    /// `needed` is the size of the glyph including padding, and `shelf_size` is the size of each
+    /// shelf of the atlas.
+    GlyphTooLarge {
+        needed: Size2D<u32>,
+        shelf_size: Size2D<u32>,
+    },
+
+    /// The atlas has no room left for the glyph.
+    ///
+    /// `needed` is the size of the glyph including padding. `remaining` is the size of the space
+    /// below the last shelf that isn't yet allocated to any shelf; it is too short to hold another
+    /// shelf. This is the signal to start a new atlas page.
+    OutOfSpace {
+        needed: Size2D<u32>,
+        remaining: Size2D<u32>,
+    },
+
+    /// The glyph was placed at a position that puts part of it outside the atlas or on more than
+    /// one shelf.
+    ///
+    /// `rect` is the rectangle the glyph needed, including padding and margin. If the glyph would
+    /// have begun above or to the left of the atlas, the rectangle is cut off at its edges.
+    OutOfBounds {
+        rect: Rect<u32>,
+    },
+
+    /// The glyph was placed at a position that overlaps a glyph already in the atlas.
+    ///
+    /// `rect` is the rectangle the glyph needed and `existing` is the rectangle of the glyph it
+    /// overlaps, both including padding and margin.
+    Overlapping {
+        rect: Rect<u32>,
+        existing: Rect<u32>,
+    },
+}
+
+/// An error that occurred while combining glyphs from different outline builders.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutlineError {
+    /// The glyphs were laid out with and without the placeholder vertex, as
+    /// `OutlineOptions::dummy_vertex` chooses, so their indices can't be combined. Nothing was
+    /// added.
+    DummyVertexMismatch,
+}
@@ -0,0 +1,384 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Approximation of glyph outlines with line segments or quadratic curves on the CPU.
+
+use euclid::Point2D;
+use otf::{Contour, Error, Font, OutlinePoint, OutlineVisitor};
+
+/// Returns the closed polygons that approximate each contour of the given glyph, in font units.
+///
+/// Curves are subdivided so that no point on the curve is more than `tolerance` font units away
+/// from the polygons. The last point of each polygon is the same as its first.
+pub fn flatten_contours(font: &Font, glyph_id: u16, tolerance: f32)
+                        -> Result<Vec<Vec<Point2D<f32>>>, Error> {
+    let mut contours: Vec<Vec<Point2D<f32>>> = vec![];
+    {
+        let mut flattener = Flattener::new(tolerance, |from, to, starts_contour| {
+            if starts_contour {
+                contours.push(vec![*from])
+            }
+            contours.last_mut().unwrap().push(*to)
+        });
+        try!(font.walk_glyph(glyph_id, &mut flattener));
+    }
+    Ok(contours)
+}
+
+// Calls the callback with the endpoints of each line segment and whether it's the first segment
+// of its contour.
+struct Flattener<F> where F: FnMut(&Point2D<f32>, &Point2D<f32>, bool) {
+    callback: F,
+    tolerance: f32,
+    current_point: Point2D<f32>,
+    contour_started: bool,
+}
+
+impl<F> Flattener<F> where F: FnMut(&Point2D<f32>, &Point2D<f32>, bool) {
+    fn new(tolerance: f32, callback: F) -> Flattener<F> {
+        Flattener {
+            callback: callback,
+            tolerance: tolerance,
+            current_point: Point2D::zero(),
+            contour_started: false,
+        }
+    }
+
+    fn emit(&mut self, to: &Point2D<f32>) {
+        (self.callback)(&self.current_point, to, self.contour_started);
+        self.current_point = *to;
+        self.contour_started = false
+    }
+
+    // Emits `segment_count` chords of the curve given by `evaluate`, which maps the parameter `t`
+    // in [0, 1] to a point.
+    fn emit_chords<G>(&mut self, to: &Point2D<f32>, segment_count: u32, evaluate: G)
+                      where G: Fn(f32) -> Point2D<f32> {
+        for segment_index in 1..(segment_count + 1) {
+            let point = if segment_index == segment_count {
+                *to
+            } else {
+                evaluate(segment_index as f32 / segment_count as f32)
+            };
+            self.emit(&point)
+        }
+    }
+
+    // Returns the number of evenly-spaced chords needed to keep within the tolerance of a curve
+    // whose chords deviate from it by at most `deviation / n^2`.
+    fn segment_count(&self, deviation: f32) -> u32 {
+        if self.tolerance > 0.0 {
+            ((deviation / self.tolerance).sqrt().ceil() as u32).max(1)
+        } else {
+            1
+        }
+    }
+}
+
+impl<F> OutlineVisitor for Flattener<F> where F: FnMut(&Point2D<f32>, &Point2D<f32>, bool) {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        self.current_point = *to;
+        self.contour_started = true
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        self.emit(to)
+    }
+
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>) {
+        // The distance between a quadratic curve and `n` evenly-spaced chords is at most
+        // `|from - 2 * control_point + to| / (4 * n^2)`.
+        let from = self.current_point;
+        let segment_count = self.segment_count(length(&(from - *control_point * 2.0 + *to)) / 4.0);
+        let (control_point, to) = (*control_point, *to);
+        self.emit_chords(&to, segment_count, |t| {
+            let u = 1.0 - t;
+            from * (u * u) + control_point * (2.0 * u * t) + to * (t * t)
+        })
+    }
+
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>) {
+        // The distance between a cubic curve and `n` evenly-spaced chords is at most
+        // `3 * max(|from - 2 * control_point_0 + control_point_1|,
+        //          |control_point_0 - 2 * control_point_1 + to|) / (4 * n^2)`.
+        let from = self.current_point;
+        let (control_point_0, control_point_1, to) = (*control_point_0, *control_point_1, *to);
+        let deviation =
+            length(&(from - control_point_0 * 2.0 + control_point_1))
+                .max(length(&(control_point_0 - control_point_1 * 2.0 + to))) * 0.75;
+        let segment_count = self.segment_count(deviation);
+        self.emit_chords(&to, segment_count, |t| {
+            let u = 1.0 - t;
+            from * (u * u * u) + control_point_0 * (3.0 * u * u * t) +
+                control_point_1 * (3.0 * u * t * t) + to * (t * t * t)
+        })
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Returns the contours of the given glyph with lines and quadratic curves only, in font units.
+///
+/// Cubic curves are replaced by quadratic curves that stay within `tolerance` font units of them.
+pub fn quadratic_contours(font: &Font, glyph_id: u16, tolerance: f32)
+                          -> Result<Vec<Contour>, Error> {
+    let mut converter = QuadraticConverter {
+        contours: vec![],
+        tolerance: tolerance,
+        current_point: Point2D::zero(),
+    };
+    try!(font.walk_glyph(glyph_id, &mut converter));
+    Ok(converter.contours)
+}
+
+struct QuadraticConverter {
+    contours: Vec<Contour>,
+    tolerance: f32,
+    current_point: Point2D<f32>,
+}
+
+impl QuadraticConverter {
+    fn push(&mut self, position: &Point2D<f32>, on_curve: bool) {
+        self.contours.last_mut().unwrap().points.push(OutlinePoint {
+            position: *position,
+            on_curve: on_curve,
+        })
+    }
+}
+
+impl OutlineVisitor for QuadraticConverter {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        self.contours.push(Contour {
+            points: vec![],
+        });
+        self.push(to, true);
+        self.current_point = *to
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        self.push(to, true);
+        self.current_point = *to
+    }
+
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>) {
+        self.push(control_point, false);
+        self.push(to, true);
+        self.current_point = *to
+    }
+
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>) {
+        let (from, tolerance) = (self.current_point, self.tolerance);
+        cubic_to_quadratics(&from,
+                            control_point_0,
+                            control_point_1,
+                            to,
+                            tolerance,
+                            |control_point, to| self.quad_to(control_point, to))
+    }
+
+    fn close(&mut self) {
+        // Contours close implicitly, so drop the point that repeats the start.
+        let contour = self.contours.last_mut().unwrap();
+        if contour.points.len() > 1 && contour.points.last() == contour.points.first() {
+            contour.points.pop();
+        }
+    }
+}
+
+/// Returns the lower-left and upper-right corners of the exact bounding box of the given glyph's
+/// outline in font units, or `None` if it has no outline.
+///
+/// Unlike the bounding box of the glyph's points, this box touches the curves themselves, not
+/// their off-curve control points.
+pub fn ink_bounds(font: &Font, glyph_id: u16)
+                  -> Result<Option<(Point2D<f32>, Point2D<f32>)>, Error> {
+    let mut bounds = InkBounds {
+        bounds: None,
+        current_point: Point2D::zero(),
+    };
+    try!(font.walk_glyph(glyph_id, &mut bounds));
+    Ok(bounds.bounds)
+}
+
+struct InkBounds {
+    bounds: Option<(Point2D<f32>, Point2D<f32>)>,
+    current_point: Point2D<f32>,
+}
+
+impl InkBounds {
+    fn add_point(&mut self, point: &Point2D<f32>) {
+        self.bounds = Some(match self.bounds {
+            None => (*point, *point),
+            Some((lower_left, upper_right)) => {
+                (Point2D::new(lower_left.x.min(point.x), lower_left.y.min(point.y)),
+                 Point2D::new(upper_right.x.max(point.x), upper_right.y.max(point.y)))
+            }
+        })
+    }
+
+    // Adds the points of the curve given by `evaluate` at the parameters in `extrema` that lie
+    // strictly between 0 and 1, then moves to `to`.
+    fn add_curve<G>(&mut self, to: &Point2D<f32>, extrema: &[f32], evaluate: G)
+                    where G: Fn(f32) -> Point2D<f32> {
+        for &t in extrema {
+            if t > 0.0 && t < 1.0 {
+                self.add_point(&evaluate(t))
+            }
+        }
+        self.add_point(to);
+        self.current_point = *to
+    }
+}
+
+impl OutlineVisitor for InkBounds {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        self.add_point(to);
+        self.current_point = *to
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        self.add_point(to);
+        self.current_point = *to
+    }
+
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>) {
+        // Each coordinate has an extremum where its derivative,
+        // `2 * ((control_point - from) * (1 - t) + (to - control_point) * t)`, is zero.
+        let (from, control_point, to) = (self.current_point, *control_point, *to);
+        let extremum = |from: f32, control: f32, to: f32| {
+            let denominator = from - 2.0 * control + to;
+            if denominator == 0.0 { -1.0 } else { (from - control) / denominator }
+        };
+        let extrema = [
+            extremum(from.x, control_point.x, to.x),
+            extremum(from.y, control_point.y, to.y),
+        ];
+        self.add_curve(&to, &extrema, |t| {
+            let u = 1.0 - t;
+            from * (u * u) + control_point * (2.0 * u * t) + to * (t * t)
+        })
+    }
+
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>) {
+        // Each coordinate has extrema where its derivative, a quadratic `a * t^2 + b * t + c`
+        // scaled by 3, is zero.
+        let from = self.current_point;
+        let (control_point_0, control_point_1, to) = (*control_point_0, *control_point_1, *to);
+        let mut extrema = vec![];
+        for &(from, control_0, control_1, to) in &[
+            (from.x, control_point_0.x, control_point_1.x, to.x),
+            (from.y, control_point_0.y, control_point_1.y, to.y),
+        ] {
+            let a = to - 3.0 * control_1 + 3.0 * control_0 - from;
+            let b = 2.0 * (from - 2.0 * control_0 + control_1);
+            let c = control_0 - from;
+            if a == 0.0 {
+                if b != 0.0 {
+                    extrema.push(-c / b)
+                }
+                continue
+            }
+
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant >= 0.0 {
+                let root = discriminant.sqrt();
+                extrema.push((-b + root) / (2.0 * a));
+                extrema.push((-b - root) / (2.0 * a))
+            }
+        }
+        self.add_curve(&to, &extrema, |t| {
+            let u = 1.0 - t;
+            from * (u * u * u) + control_point_0 * (3.0 * u * u * t) +
+                control_point_1 * (3.0 * u * t * t) + to * (t * t * t)
+        })
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Approximates the cubic curve from `from` to `to` with quadratic curves that stay within
+/// `tolerance` of it, calling the callback with the control point and end point of each.
+///
+/// The curve is split into pieces of equal parameter length, and each piece is replaced by the
+/// quadratic curve whose control point is the average of the two that extending the piece's end
+/// tangents would give.
+pub fn cubic_to_quadratics<F>(from: &Point2D<f32>,
+                              control_point_0: &Point2D<f32>,
+                              control_point_1: &Point2D<f32>,
+                              to: &Point2D<f32>,
+                              tolerance: f32,
+                              mut callback: F)
+                              where F: FnMut(&Point2D<f32>, &Point2D<f32>) {
+    let (from, control_point_0, control_point_1, to) =
+        (*from, *control_point_0, *control_point_1, *to);
+
+    // The distance between a cubic curve and this quadratic curve is at most
+    // `sqrt(3) / 36 * |to - 3 * control_point_1 + 3 * control_point_0 - from|`, and splitting the
+    // cubic into `n` pieces divides that by `n^3`.
+    let deviation = length(&(to - control_point_1 * 3.0 + control_point_0 * 3.0 - from)) *
+        3.0f32.sqrt() / 36.0;
+    let piece_count = if tolerance > 0.0 {
+        ((deviation / tolerance).cbrt().ceil() as u32).max(1)
+    } else {
+        1
+    };
+
+    let evaluate = |t: f32| {
+        let u = 1.0 - t;
+        from * (u * u * u) + control_point_0 * (3.0 * u * u * t) +
+            control_point_1 * (3.0 * u * t * t) + to * (t * t * t)
+    };
+    let derivative = |t: f32| {
+        let u = 1.0 - t;
+        (control_point_0 - from) * (3.0 * u * u) +
+            (control_point_1 - control_point_0) * (6.0 * u * t) +
+            (to - control_point_1) * (3.0 * t * t)
+    };
+
+    let step = 1.0 / piece_count as f32;
+    let mut piece_from = from;
+    for piece_index in 0..piece_count {
+        let (t0, t1) = (piece_index as f32 * step, (piece_index + 1) as f32 * step);
+        let piece_to = if piece_index + 1 == piece_count { to } else { evaluate(t1) };
+        let piece_control_point_0 = piece_from + derivative(t0) * (step / 3.0);
+        let piece_control_point_1 = piece_to - derivative(t1) * (step / 3.0);
+        let control_point = ((piece_control_point_0 + piece_control_point_1) * 3.0 - piece_from -
+                             piece_to) * 0.25;
+        callback(&control_point, &piece_to);
+        piece_from = piece_to
+    }
+}
+
+#[inline]
+fn length(vector: &Point2D<f32>) -> f32 {
+    vector.dot(*vector).sqrt()
+}
+
+/// Returns the point on the line segment from `from` to `to` that is nearest to `point`.
+pub fn nearest_point_on_line_segment(from: &Point2D<f32>, to: &Point2D<f32>, point: &Point2D<f32>)
+                                     -> Point2D<f32> {
+    let direction = *to - *from;
+    let length_squared = direction.dot(direction);
+    if length_squared == 0.0 {
+        return *from
+    }
+
+    let t = ((*point - *from).dot(direction) / length_squared).max(0.0).min(1.0);
+    *from + direction * t
+}
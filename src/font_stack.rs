@@ -0,0 +1,62 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font fallback lists.
+//!
+//! Real-world text frequently mixes scripts that no single font covers. A font stack resolves
+//! each character to the first font in a preference-ordered list that can render it.
+
+use otf::Font;
+
+/// An ordered list of fonts to search when resolving characters to glyphs.
+///
+/// The first font is the most preferred; later fonts are consulted only if earlier ones don't
+/// contain the character.
+pub struct FontStack<'a> {
+    fonts: Vec<Font<'a>>,
+}
+
+impl<'a> FontStack<'a> {
+    /// Creates a new font stack from the given fonts, in order of preference.
+    #[inline]
+    pub fn new(fonts: Vec<Font<'a>>) -> FontStack<'a> {
+        FontStack {
+            fonts: fonts,
+        }
+    }
+
+    /// Appends a font to the end of the stack, making it the least preferred.
+    #[inline]
+    pub fn push(&mut self, font: Font<'a>) {
+        self.fonts.push(font)
+    }
+
+    /// Returns the fonts in this stack, in order of preference.
+    ///
+    /// The font indices returned by `glyph_for_char()` index into this slice, so you can pass
+    /// `&font_stack.fonts()[index]` to `OutlineBuilder::add_glyph()`.
+    #[inline]
+    pub fn fonts(&self) -> &[Font<'a>] {
+        &self.fonts
+    }
+
+    /// Returns the index of the first font in the stack that contains the given character, along
+    /// with the ID of the glyph in that font.
+    ///
+    /// Returns `None` if no font in the stack maps the character.
+    pub fn glyph_for_char(&self, character: char) -> Option<(usize, u16)> {
+        for (font_index, font) in self.fonts.iter().enumerate() {
+            if let Some(glyph_id) = font.glyph_for_char(character) {
+                return Some((font_index, glyph_id))
+            }
+        }
+        None
+    }
+}
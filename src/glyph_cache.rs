@@ -0,0 +1,266 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A least-recently-used cache of rasterized glyphs in a fixed-size texture.
+
+use error::AtlasError;
+use euclid::{Rect, Size2D};
+use rect_packer::{self, PackingMode, RectPacker};
+use std::collections::{BTreeMap, HashMap};
+
+/// Identifies a rasterized glyph in a `GlyphCache`.
+///
+/// The point size and subpixel offset are quantized to 1/64 of a unit, so sizes and offsets that
+/// differ by less than that share an entry.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GlyphCacheKey {
+    font_id: u32,
+    glyph_id: u16,
+    point_size: i32,
+    subpixel_offset: i32,
+}
+
+impl GlyphCacheKey {
+    /// Creates a key for the given glyph of the font with the given caller-assigned ID, rendered
+    /// at the given point size and horizontal subpixel offset in pixels.
+    #[inline]
+    pub fn new(font_id: u32, glyph_id: u16, point_size: f32, subpixel_offset: f32)
+               -> GlyphCacheKey {
+        GlyphCacheKey {
+            font_id: font_id,
+            glyph_id: glyph_id,
+            point_size: (point_size * 64.0).round() as i32,
+            subpixel_offset: (subpixel_offset * 64.0).round() as i32,
+        }
+    }
+
+    /// Creates a key for the given glyph drawn with the pen at the horizontal position `pen_x`,
+    /// rounding the subpixel part of the position to the nearest of `buckets` equal divisions of
+    /// a pixel.
+    ///
+    /// Returns the key along with the whole pixel that the pen position was rounded down to. The
+    /// key's subpixel offset is the bucket divided by `buckets`, so at most `buckets` horizontal
+    /// variants of each glyph are cached: rasterize the glyph with its bounds translated by
+    /// `subpixel_offset()`, and draw the cached image with the pen at the returned whole pixel.
+    /// Positions within half a bucket below a whole pixel round up to bucket 0 of the next pixel.
+    /// Fewer buckets save texture space at the cost of positioning accuracy; 4 is typical. A
+    /// `buckets` of 0 is treated as 1.
+    pub fn for_pen_position(font_id: u32,
+                            glyph_id: u16,
+                            point_size: f32,
+                            pen_x: f32,
+                            buckets: u32)
+                            -> (GlyphCacheKey, i32) {
+        let buckets = buckets.max(1);
+        let position = (pen_x * buckets as f32).round() as i32;
+        let whole_pixel = if position >= 0 {
+            position / buckets as i32
+        } else {
+            (position - buckets as i32 + 1) / buckets as i32
+        };
+        let bucket = position - whole_pixel * buckets as i32;
+        let key = GlyphCacheKey::new(font_id,
+                                     glyph_id,
+                                     point_size,
+                                     bucket as f32 / buckets as f32);
+        (key, whole_pixel)
+    }
+
+    /// Returns the caller-assigned ID of the font.
+    #[inline]
+    pub fn font_id(&self) -> u32 {
+        self.font_id
+    }
+
+    /// Returns the glyph ID.
+    #[inline]
+    pub fn glyph_id(&self) -> u16 {
+        self.glyph_id
+    }
+
+    /// Returns the point size, quantized to 1/64 of a point.
+    #[inline]
+    pub fn point_size(&self) -> f32 {
+        self.point_size as f32 / 64.0
+    }
+
+    /// Returns the subpixel offset, quantized to 1/64 of a pixel.
+    #[inline]
+    pub fn subpixel_offset(&self) -> f32 {
+        self.subpixel_offset as f32 / 64.0
+    }
+}
+
+/// Keeps track of which glyphs have been rasterized into a texture of a fixed size, evicting the
+/// least recently used glyphs to make room for new ones when the texture fills up.
+///
+/// The cache only manages space; it doesn't own the texture. Glyphs are packed into shelves the
+/// same way `AtlasBuilder` packs them, with the default padding around each one so that
+/// neighboring glyphs don't bleed into each other.
+pub struct GlyphCache {
+    rect_packer: RectPacker,
+    available_width: u32,
+    available_height: u32,
+    shelf_height: u32,
+    entries: HashMap<GlyphCacheKey, CacheEntry>,
+    // Maps the time of last use of each entry to its key, oldest first.
+    lru: BTreeMap<u64, GlyphCacheKey>,
+    clock: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CacheEntry {
+    rect: Rect<u32>,
+    last_used: u64,
+}
+
+impl GlyphCache {
+    /// Creates an empty cache for a texture of the given size, divided into shelves of the given
+    /// height.
+    ///
+    /// As with `AtlasBuilder`, `Font::shelf_height()` gives a suitable shelf height for the
+    /// largest point size you'll cache.
+    pub fn new(available_width: u32, available_height: u32, shelf_height: u32) -> GlyphCache {
+        GlyphCache {
+            rect_packer: new_rect_packer(available_width, available_height, shelf_height),
+            available_width: available_width,
+            available_height: available_height,
+            shelf_height: shelf_height,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the rectangle of the texture holding the given glyph, marking it as recently used.
+    ///
+    /// Returns `None` if the glyph isn't in the cache.
+    pub fn get(&mut self, key: &GlyphCacheKey) -> Option<Rect<u32>> {
+        let last_used = match self.entries.get(key) {
+            None => return None,
+            Some(entry) => entry.last_used,
+        };
+
+        self.clock += 1;
+        self.lru.remove(&last_used);
+        self.lru.insert(self.clock, *key);
+
+        let entry = self.entries.get_mut(key).unwrap();
+        entry.last_used = self.clock;
+        Some(entry.rect)
+    }
+
+    /// Returns the rectangle of the texture holding the given glyph, allocating space for it and
+    /// calling `rasterize` if it isn't in the cache yet.
+    ///
+    /// `size` is the size of the glyph in pixels, such as the size of
+    /// `Outlines::glyph_pixel_bounds()`; it's only used if the glyph isn't cached. `rasterize` is
+    /// called with the newly allocated rectangle, which it should fill with the glyph's coverage,
+    /// for example by uploading the result of `Rasterizer::rasterize_glyph()` to it.
+    ///
+    /// If the texture is full, the least recently used glyphs are evicted until the new glyph
+    /// fits. Their rectangles may be handed out again, so any references to them must be
+    /// considered stale. Returns `AtlasError::GlyphTooLarge` if the glyph can never fit, in which
+    /// case nothing is evicted.
+    pub fn get_or_insert<F>(&mut self, key: GlyphCacheKey, size: &Size2D<u32>, rasterize: F)
+                            -> Result<Rect<u32>, AtlasError> where F: FnOnce(&Rect<u32>) {
+        if let Some(rect) = self.get(&key) {
+            return Ok(rect)
+        }
+
+        let origin;
+        loop {
+            match self.rect_packer.pack(size) {
+                Ok(packed_origin) => {
+                    origin = packed_origin;
+                    break
+                }
+                Err(AtlasError::OutOfSpace { .. }) if !self.entries.is_empty() => {
+                    self.evict_least_recently_used()
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let rect = Rect::new(origin, *size);
+        rasterize(&rect);
+
+        self.clock += 1;
+        self.lru.insert(self.clock, key);
+        self.entries.insert(key, CacheEntry {
+            rect: rect,
+            last_used: self.clock,
+        });
+        Ok(rect)
+    }
+
+    /// Removes the given glyph from the cache, freeing its space for reuse.
+    ///
+    /// Returns the rectangle it occupied, or `None` if it wasn't in the cache.
+    pub fn remove(&mut self, key: &GlyphCacheKey) -> Option<Rect<u32>> {
+        let entry = match self.entries.remove(key) {
+            None => return None,
+            Some(entry) => entry,
+        };
+
+        self.lru.remove(&entry.last_used);
+        self.free(&entry.rect);
+        Some(entry.rect)
+    }
+
+    /// Removes all glyphs from the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.rect_packer = new_rect_packer(self.available_width,
+                                           self.available_height,
+                                           self.shelf_height);
+    }
+
+    /// Returns the number of glyphs in the cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache holds no glyphs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let key = match self.lru.values().next() {
+            None => return,
+            Some(key) => *key,
+        };
+        self.remove(&key);
+    }
+
+    fn free(&mut self, rect: &Rect<u32>) {
+        // Start over once the cache is empty so that fragmentation can't accumulate.
+        if self.entries.is_empty() {
+            self.clear();
+            return
+        }
+
+        self.rect_packer.free(rect)
+    }
+}
+
+fn new_rect_packer(available_width: u32, available_height: u32, shelf_height: u32)
+                   -> RectPacker {
+    let mut rect_packer = RectPacker::new(available_width,
+                                          shelf_height,
+                                          rect_packer::DEFAULT_PADDING,
+                                          PackingMode::Guillotine);
+    rect_packer.set_available_height(available_height);
+    rect_packer
+}
@@ -70,34 +70,73 @@
 //!
 //! Pathfinder requires at least OpenGL 3.3 and either OpenGL 4.3 compute shader or OpenCL 1.2.
 //! Intel GPUs in Sandy Bridge processors or later should be OK.
+//!
+//! ## Using Pathfinder without a GPU
+//!
+//! Everything that touches OpenGL or GPU compute is behind the `gpu` feature, which is on by
+//! default. With `default-features = false`, Pathfinder doesn't depend on `gl` or
+//! `compute-shader`, and only the font parsing, shaping, and CPU-side outline modules remain:
+//! `otf`, `charmap`, `font_stack`, `glyph_cache`, `shaper`, and `OutlineBuilder` without
+//! `create_buffers()`. This suits tools that only inspect fonts.
+//!
+//! ## Optional features
+//!
+//! The `rayon` feature, which is off by default, adds `OutlineBuilder::par_add_glyphs()`, which
+//! reads and tessellates many glyphs at once on Rayon's thread pool.
+//!
+//! The `metrics` feature, also off by default, adds `OutlineBuilder::stats()` and
+//! `Outlines::stats()`, which count the glyphs and vertices tessellated and time the tessellation
+//! and upload, for profiling harnesses to query after a build.
+//!
+//! The `hinting` feature, also off by default, adds a TrueType bytecode interpreter that runs a
+//! font's hinting instructions to fit outlines to the pixel grid of one size: see `Font::hinter()`
+//! and `OutlineBuilder::add_hinted_glyph()`.
 
 #![cfg_attr(test, feature(test))]
 
 #[macro_use]
 extern crate bitflags;
 extern crate byteorder;
+#[cfg(feature = "gpu")]
 extern crate compute_shader;
 extern crate euclid;
+#[cfg(feature = "gpu")]
 extern crate gl;
 #[cfg(test)]
 extern crate memmap;
 #[cfg(test)]
 #[macro_use]
 extern crate quickcheck;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 #[cfg(test)]
 extern crate test;
+#[cfg(feature = "metrics")]
+extern crate time;
 
+#[cfg(feature = "gpu")]
 pub mod atlas;
 pub mod charmap;
+#[cfg(feature = "gpu")]
 pub mod coverage;
 pub mod error;
+pub mod font_stack;
+pub mod glyph_cache;
 pub mod otf;
 pub mod outline;
+#[cfg(feature = "gpu")]
 pub mod rasterizer;
 pub mod shaper;
 
+mod flatten;
+mod msdf;
+mod overlap;
 mod rect_packer;
+mod simplify;
+mod tofu;
+mod triangulate;
 mod util;
+mod winding;
 
 #[cfg(test)]
 mod tests;
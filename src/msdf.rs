@@ -0,0 +1,494 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generation of multi-channel signed distance fields on the CPU.
+//!
+//! This follows Chlumský's method: the edges of each contour are colored so that the two edges
+//! meeting at every sharp corner share only one of the red, green, and blue channels, and each
+//! channel then stores the signed pseudo-distance to the nearest edge of that channel. The median
+//! of the three channels reconstructs the outline with its corners intact.
+
+use euclid::Point2D;
+use otf::{Error, Font, OutlineVisitor};
+use outline::GlyphPixelBounds;
+
+pub const RED: u8 = 1;
+pub const GREEN: u8 = 2;
+pub const BLUE: u8 = 4;
+pub const CYAN: u8 = GREEN | BLUE;
+pub const MAGENTA: u8 = RED | BLUE;
+pub const YELLOW: u8 = RED | GREEN;
+pub const WHITE: u8 = RED | GREEN | BLUE;
+
+// Two edges meet at a corner if the sine of the angle between them exceeds this, which is
+// `sin(3)`: that is, if they turn by more than about 8 degrees.
+const CORNER_CROSS_THRESHOLD: f32 = 0.14112;
+
+// Distances within this many pixels of each other are considered equal when picking the nearest
+// edge, so that the two edges sharing an endpoint are told apart by their angles instead.
+const DISTANCE_EPSILON: f32 = 1e-4;
+
+const NEAREST_POINT_SAMPLES: u32 = 8;
+const NEAREST_POINT_ITERATIONS: u32 = 4;
+
+/// Returns the pixel bounds of the field that `generate()` produces: the glyph's pixel bounds
+/// grown by half the range, rounded up, on every side.
+pub fn bounds(font: &Font, glyph_id: u16, point_size: f32, range: f32)
+              -> Result<GlyphPixelBounds, Error> {
+    let glyph_bounds = try!(font.glyph_bounds(glyph_id));
+    let margin = (range * 0.5).ceil().max(0.0) as i32;
+    Ok(glyph_bounds.subpixel_bounds(font.units_per_em(), point_size).round_out().expanded(margin))
+}
+
+/// Generates the multi-channel signed distance field of the given glyph at the given point size,
+/// covering `bounds()`, in rows from top to bottom.
+///
+/// Each channel maps signed distances from `-range / 2` to `range / 2` pixels onto 0 to 255, with
+/// positive distances inside the glyph. A glyph with no outline has no pixels.
+pub fn generate(font: &Font, glyph_id: u16, point_size: f32, range: f32)
+                -> Result<Vec<[u8; 3]>, Error> {
+    if !(range > 0.0) {
+        return Err(Error::Failed)
+    }
+
+    let mut collector = SegmentCollector {
+        contours: vec![],
+        current_point: Point2D::zero(),
+        scale: font.pixels_per_unit(point_size),
+    };
+    try!(font.walk_glyph(glyph_id, &mut collector));
+    let mut contours = collector.contours;
+    contours.retain(|contour| !contour.is_empty());
+    if contours.is_empty() {
+        return Ok(vec![])
+    }
+
+    // TrueType outlines wind their outer contours clockwise, which puts the inside of each edge on
+    // its right. Flip the distances for outlines wound the other way.
+    let orientation = if signed_area(&contours) > 0.0 { -1.0 } else { 1.0 };
+
+    let edges: Vec<(Segment, u8)> = contours.iter()
+                                            .flat_map(|contour| color_edges(contour))
+                                            .collect();
+
+    let bounds = try!(bounds(font, glyph_id, point_size, range));
+    let size = bounds.size();
+    let mut pixels = Vec::with_capacity(size.width as usize * size.height as usize);
+    let mut distances = Vec::with_capacity(edges.len());
+    for y in 0..size.height {
+        for x in 0..size.width {
+            let point = Point2D::new((bounds.left + x) as f32 + 0.5,
+                                     (bounds.top - y) as f32 - 0.5);
+            distances.clear();
+            distances.extend(edges.iter().map(|&(ref segment, _)| segment.distance(&point)));
+
+            let mut pixel = [0; 3];
+            for (channel_index, &channel) in [RED, GREEN, BLUE].iter().enumerate() {
+                let mut nearest: Option<usize> = None;
+                for (edge_index, &(_, color)) in edges.iter().enumerate() {
+                    if color & channel == 0 {
+                        continue
+                    }
+                    match nearest {
+                        Some(nearest_index) if !distances[edge_index].is_closer_than(
+                            &distances[nearest_index]) => {}
+                        _ => nearest = Some(edge_index),
+                    }
+                }
+
+                // Every contour with colored edges uses at least two colors, so this only happens
+                // for hand-built colorings; fall back to a plain signed distance.
+                let nearest = nearest.unwrap_or_else(|| {
+                    (0..edges.len()).fold(0, |nearest, edge_index| {
+                        if distances[edge_index].is_closer_than(&distances[nearest]) {
+                            edge_index
+                        } else {
+                            nearest
+                        }
+                    })
+                });
+
+                let distance = edges[nearest].0.pseudo_distance(&point, &distances[nearest]);
+                pixel[channel_index] = encode(distance * orientation, range)
+            }
+            pixels.push(pixel)
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Assigns colors to the edges of a closed contour so that the two edges meeting at each corner
+/// differ, returning the edges with their colors.
+///
+/// A smooth contour is white throughout. A contour with one corner, such as a teardrop, is split
+/// into three runs of magenta, white, and yellow starting at the corner, with its edges cut into
+/// thirds first if there are fewer than three. Otherwise, the color cycles through cyan, magenta,
+/// and yellow at each corner, skipping the first color at the last corner so that the edges on
+/// either side of the first corner differ too.
+pub fn color_edges(contour: &[Segment]) -> Vec<(Segment, u8)> {
+    let corners: Vec<usize> = (0..contour.len()).filter(|&index| {
+        let previous = &contour[(index + contour.len() - 1) % contour.len()];
+        is_corner(&previous.direction(1.0), &contour[index].direction(0.0))
+    }).collect();
+
+    if corners.is_empty() {
+        return contour.iter().map(|segment| (*segment, WHITE)).collect()
+    }
+
+    if corners.len() == 1 {
+        let (segments, corner) = if contour.len() < 3 {
+            (contour.iter().flat_map(|segment| segment.split_in_thirds().to_vec()).collect(),
+             corners[0] * 3)
+        } else {
+            (contour.to_vec(), corners[0])
+        };
+
+        let colors = [MAGENTA, WHITE, YELLOW];
+        let mut edges: Vec<(Segment, u8)> = segments.iter().map(|segment| (*segment, WHITE))
+                                                    .collect();
+        for position in 0..edges.len() {
+            let index = (corner + position) % edges.len();
+            edges[index].1 = colors[symmetrical_trichotomy(position, edges.len())]
+        }
+        return edges
+    }
+
+    let mut edges: Vec<(Segment, u8)> = contour.iter().map(|segment| (*segment, WHITE)).collect();
+    let initial_color = CYAN;
+    let mut color = initial_color;
+    let mut spline = 0;
+    for position in 0..edges.len() {
+        let index = (corners[0] + position) % edges.len();
+        if spline + 1 < corners.len() && corners[spline + 1] == index {
+            spline += 1;
+            let banned = if spline == corners.len() - 1 { Some(initial_color) } else { None };
+            color = switch_color(color, banned)
+        }
+        edges[index].1 = color
+    }
+    edges
+}
+
+// Maps `position` out of `count` edges to 0, 1, or 2, splitting them into three runs of nearly
+// equal length with the middle run centered.
+fn symmetrical_trichotomy(position: usize, count: usize) -> usize {
+    if count < 2 {
+        return 1
+    }
+    let value = 3.0 + 2.875 * position as f32 / (count - 1) as f32 - 1.4375 + 0.5;
+    (value as usize).saturating_sub(2).min(2)
+}
+
+// Returns the next of cyan, magenta, and yellow after `color`, or the third one if that would be
+// `banned`.
+fn switch_color(color: u8, banned: Option<u8>) -> u8 {
+    let next = match color {
+        CYAN => MAGENTA,
+        MAGENTA => YELLOW,
+        _ => CYAN,
+    };
+    match banned {
+        // The three colors cancel out under exclusive or, so this leaves the third.
+        Some(banned) if banned == next => color ^ banned,
+        _ => next,
+    }
+}
+
+fn is_corner(incoming: &Point2D<f32>, outgoing: &Point2D<f32>) -> bool {
+    let (incoming, outgoing) = (normalize(incoming), normalize(outgoing));
+    incoming.dot(outgoing) <= 0.0 || incoming.cross(outgoing).abs() > CORNER_CROSS_THRESHOLD
+}
+
+fn encode(distance: f32, range: f32) -> u8 {
+    ((distance / range + 0.5).max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+// Returns twice the signed area enclosed by the endpoints of the segments, positive for
+// counterclockwise outlines.
+fn signed_area(contours: &[Vec<Segment>]) -> f32 {
+    contours.iter().flat_map(|contour| contour.iter()).map(|segment| {
+        let (from, to) = (segment.point(0.0), segment.point(1.0));
+        from.cross(to)
+    }).fold(0.0, |sum, area| sum + area)
+}
+
+#[inline]
+fn length(vector: &Point2D<f32>) -> f32 {
+    vector.dot(*vector).sqrt()
+}
+
+#[inline]
+fn normalize(vector: &Point2D<f32>) -> Point2D<f32> {
+    let length = length(vector);
+    if length == 0.0 { Point2D::zero() } else { *vector / length }
+}
+
+/// A line, quadratic curve, or cubic curve, in pixels.
+#[derive(Copy, Clone, Debug)]
+pub enum Segment {
+    Line(Point2D<f32>, Point2D<f32>),
+    Quad(Point2D<f32>, Point2D<f32>, Point2D<f32>),
+    Cubic(Point2D<f32>, Point2D<f32>, Point2D<f32>, Point2D<f32>),
+}
+
+/// The signed distance from a point to the nearest point on a segment.
+#[derive(Copy, Clone, Debug)]
+pub struct SignedDistance {
+    /// The distance, positive on the right of the segment's direction.
+    pub distance: f32,
+    /// The absolute cosine of the angle between the segment and the line to the point, which is
+    /// zero unless the nearest point is an endpoint.
+    pub dot: f32,
+    /// The parameter of the nearest point on the segment, from 0 to 1.
+    pub t: f32,
+}
+
+impl SignedDistance {
+    // Distances tie when they're equal, in which case the more orthogonal segment wins.
+    fn is_closer_than(&self, other: &SignedDistance) -> bool {
+        let (distance, other_distance) = (self.distance.abs(), other.distance.abs());
+        if (distance - other_distance).abs() <= DISTANCE_EPSILON {
+            self.dot < other.dot
+        } else {
+            distance < other_distance
+        }
+    }
+}
+
+impl Segment {
+    /// Returns the point at parameter `t`.
+    pub fn point(&self, t: f32) -> Point2D<f32> {
+        let u = 1.0 - t;
+        match *self {
+            Segment::Line(from, to) => from * u + to * t,
+            Segment::Quad(from, control_point, to) => {
+                from * (u * u) + control_point * (2.0 * u * t) + to * (t * t)
+            }
+            Segment::Cubic(from, control_point_0, control_point_1, to) => {
+                from * (u * u * u) + control_point_0 * (3.0 * u * u * t) +
+                    control_point_1 * (3.0 * u * t * t) + to * (t * t * t)
+            }
+        }
+    }
+
+    /// Returns the direction of the segment at parameter `t`, falling back to the direction from
+    /// its start to its end where its derivative vanishes.
+    pub fn direction(&self, t: f32) -> Point2D<f32> {
+        let derivative = self.derivative(t);
+        if derivative != Point2D::zero() {
+            return derivative
+        }
+        match *self {
+            Segment::Cubic(from, _, control_point_1, _) if t == 0.0 => control_point_1 - from,
+            Segment::Cubic(_, control_point_0, _, to) if t == 1.0 => to - control_point_0,
+            _ => self.point(1.0) - self.point(0.0),
+        }
+    }
+
+    fn derivative(&self, t: f32) -> Point2D<f32> {
+        let u = 1.0 - t;
+        match *self {
+            Segment::Line(from, to) => to - from,
+            Segment::Quad(from, control_point, to) => {
+                (control_point - from) * (2.0 * u) + (to - control_point) * (2.0 * t)
+            }
+            Segment::Cubic(from, control_point_0, control_point_1, to) => {
+                (control_point_0 - from) * (3.0 * u * u) +
+                    (control_point_1 - control_point_0) * (6.0 * u * t) +
+                    (to - control_point_1) * (3.0 * t * t)
+            }
+        }
+    }
+
+    fn second_derivative(&self, t: f32) -> Point2D<f32> {
+        match *self {
+            Segment::Line(..) => Point2D::zero(),
+            Segment::Quad(from, control_point, to) => (from - control_point * 2.0 + to) * 2.0,
+            Segment::Cubic(from, control_point_0, control_point_1, to) => {
+                (from - control_point_0 * 2.0 + control_point_1) * (6.0 * (1.0 - t)) +
+                    (control_point_0 - control_point_1 * 2.0 + to) * (6.0 * t)
+            }
+        }
+    }
+
+    /// Returns whether all of this segment's points coincide.
+    pub fn is_degenerate(&self) -> bool {
+        match *self {
+            Segment::Line(from, to) => from == to,
+            Segment::Quad(from, control_point, to) => from == control_point && from == to,
+            Segment::Cubic(from, control_point_0, control_point_1, to) => {
+                from == control_point_0 && from == control_point_1 && from == to
+            }
+        }
+    }
+
+    /// Splits this segment into three pieces of equal parameter length.
+    pub fn split_in_thirds(&self) -> [Segment; 3] {
+        [self.piece(0.0, 1.0 / 3.0), self.piece(1.0 / 3.0, 2.0 / 3.0), self.piece(2.0 / 3.0, 1.0)]
+    }
+
+    // Returns the part of this segment between parameters `t0` and `t1`.
+    fn piece(&self, t0: f32, t1: f32) -> Segment {
+        let (from, to) = (self.point(t0), self.point(t1));
+        let step = t1 - t0;
+        match *self {
+            Segment::Line(..) => Segment::Line(from, to),
+            Segment::Quad(..) => {
+                Segment::Quad(from, from + self.derivative(t0) * (step * 0.5), to)
+            }
+            Segment::Cubic(..) => {
+                Segment::Cubic(from,
+                               from + self.derivative(t0) * (step / 3.0),
+                               to - self.derivative(t1) * (step / 3.0),
+                               to)
+            }
+        }
+    }
+
+    /// Returns the signed distance from `point` to the nearest point on this segment.
+    pub fn distance(&self, point: &Point2D<f32>) -> SignedDistance {
+        let t = match *self {
+            Segment::Line(from, to) => {
+                let direction = to - from;
+                let length_squared = direction.dot(direction);
+                if length_squared == 0.0 {
+                    0.0
+                } else {
+                    ((*point - from).dot(direction) / length_squared).max(0.0).min(1.0)
+                }
+            }
+            Segment::Quad(..) | Segment::Cubic(..) => self.nearest_parameter(point),
+        };
+
+        let nearest_point = self.point(t);
+        let vector = *point - nearest_point;
+        let direction = normalize(&self.direction(t));
+        let distance = length(&vector);
+        let sign = if vector.cross(direction) < 0.0 { -1.0 } else { 1.0 };
+        let dot = if t == 0.0 || t == 1.0 {
+            direction.dot(normalize(&vector)).abs()
+        } else {
+            0.0
+        };
+
+        SignedDistance {
+            distance: distance * sign,
+            dot: dot,
+            t: t,
+        }
+    }
+
+    // Finds the parameter of the nearest point to `point` by sampling the curve evenly and
+    // refining the best sample with Newton's method on the derivative of the squared distance.
+    fn nearest_parameter(&self, point: &Point2D<f32>) -> f32 {
+        let distance_squared = |t: f32| {
+            let vector = self.point(t) - *point;
+            vector.dot(vector)
+        };
+
+        let mut best_t = 0.0;
+        let mut best_distance_squared = distance_squared(0.0);
+        for sample in 1..(NEAREST_POINT_SAMPLES + 1) {
+            let t = sample as f32 / NEAREST_POINT_SAMPLES as f32;
+            let sample_distance_squared = distance_squared(t);
+            if sample_distance_squared < best_distance_squared {
+                best_t = t;
+                best_distance_squared = sample_distance_squared
+            }
+        }
+
+        let mut t = best_t;
+        for _ in 0..NEAREST_POINT_ITERATIONS {
+            let vector = self.point(t) - *point;
+            let derivative = self.derivative(t);
+            let numerator = vector.dot(derivative);
+            let denominator = derivative.dot(derivative) + vector.dot(self.second_derivative(t));
+            if denominator == 0.0 {
+                break
+            }
+            t = (t - numerator / denominator).max(0.0).min(1.0)
+        }
+
+        if distance_squared(t) < best_distance_squared { t } else { best_t }
+    }
+
+    /// Returns the signed pseudo-distance from `point` to this segment, given its signed distance.
+    ///
+    /// Where the nearest point is an endpoint and `point` lies beyond it, this is the distance to
+    /// the line extending the segment from that endpoint, if that's nearer. This keeps the
+    /// distance field straight past corners instead of rounding them off.
+    pub fn pseudo_distance(&self, point: &Point2D<f32>, distance: &SignedDistance) -> f32 {
+        let (endpoint, beyond_sign) = if distance.t == 0.0 {
+            (self.point(0.0), -1.0)
+        } else if distance.t == 1.0 {
+            (self.point(1.0), 1.0)
+        } else {
+            return distance.distance
+        };
+
+        let direction = normalize(&self.direction(distance.t));
+        let vector = *point - endpoint;
+        if vector.dot(direction) * beyond_sign > 0.0 {
+            let pseudo_distance = vector.cross(direction);
+            if pseudo_distance.abs() <= distance.distance.abs() {
+                return pseudo_distance
+            }
+        }
+        distance.distance
+    }
+}
+
+// Collects the segments of each contour, scaled to pixels, leaving out degenerate ones.
+struct SegmentCollector {
+    contours: Vec<Vec<Segment>>,
+    current_point: Point2D<f32>,
+    scale: f32,
+}
+
+impl SegmentCollector {
+    fn add(&mut self, segment: Segment, to: &Point2D<f32>) {
+        self.current_point = *to;
+        if !segment.is_degenerate() {
+            self.contours.last_mut().unwrap().push(segment)
+        }
+    }
+}
+
+impl OutlineVisitor for SegmentCollector {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        self.contours.push(vec![]);
+        self.current_point = *to * self.scale
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        let to = *to * self.scale;
+        self.add(Segment::Line(self.current_point, to), &to)
+    }
+
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>) {
+        let to = *to * self.scale;
+        self.add(Segment::Quad(self.current_point, *control_point * self.scale, to), &to)
+    }
+
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>) {
+        let to = *to * self.scale;
+        self.add(Segment::Cubic(self.current_point,
+                                *control_point_0 * self.scale,
+                                *control_point_1 * self.scale,
+                                to),
+                 &to)
+    }
+
+    fn close(&mut self) {}
+}
@@ -0,0 +1,86 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use otf::fvar;
+use util::Jump;
+
+/// The axis variations table, which bends the normalization of each axis of a variable font
+/// along a piecewise linear map.
+#[derive(Clone, Copy, Debug)]
+pub struct AvarTable<'a> {
+    table: FontTable<'a>,
+    axis_count: u16,
+}
+
+impl<'a> AvarTable<'a> {
+    pub fn new(table: FontTable) -> Result<AvarTable, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if major_version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Skip over the minor version and the reserved field.
+        try!(reader.jump(4).map_err(Error::eof));
+        let axis_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        Ok(AvarTable {
+            table: table,
+            axis_count: axis_count,
+        })
+    }
+
+    /// Maps the given normalized coordinates, one per axis in `fvar` order, through the segment
+    /// map of each axis.
+    pub fn map(&self, coordinates: &mut [f32]) -> Result<(), Error> {
+        let mut reader = &self.table.bytes[8..];
+        for axis_index in 0..self.axis_count as usize {
+            let position_map_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let mut segments = Vec::with_capacity(position_map_count as usize);
+            for _ in 0..position_map_count {
+                let from = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+                let to = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+                segments.push((from as f32 / 16384.0, to as f32 / 16384.0))
+            }
+
+            if let Some(coordinate) = coordinates.get_mut(axis_index) {
+                *coordinate = fvar::round_to_f2dot14(map_coordinate(*coordinate, &segments))
+            }
+        }
+        Ok(())
+    }
+}
+
+// Maps a coordinate through the given segments, which are sorted by the coordinates that they
+// map from. Coordinates past either end are moved by the same amount as the end.
+fn map_coordinate(coordinate: f32, segments: &[(f32, f32)]) -> f32 {
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return coordinate,
+    };
+    if coordinate <= first.0 {
+        return coordinate + first.1 - first.0
+    }
+    if coordinate >= last.0 {
+        return coordinate + last.1 - last.0
+    }
+
+    for segment in segments.windows(2) {
+        let ((from_0, to_0), (from_1, to_1)) = (segment[0], segment[1]);
+        if coordinate >= from_0 && coordinate <= from_1 {
+            if from_1 == from_0 {
+                return to_0
+            }
+            return to_0 + (to_1 - to_0) * (coordinate - from_0) / (from_1 - from_0)
+        }
+    }
+    coordinate
+}
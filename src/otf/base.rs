@@ -0,0 +1,165 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::layout;
+use otf::{Error, FontTable, Tag, tag};
+use std::mem;
+use util::Jump;
+
+/// The baseline table, which gives the positions of the baselines that scripts align to.
+#[derive(Clone, Copy, Debug)]
+pub struct BaseTable<'a> {
+    horizontal_axis: Option<&'a [u8]>,
+}
+
+impl<'a> BaseTable<'a> {
+    pub fn new(table: FontTable) -> Result<BaseTable, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if major_version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Only horizontal text is supported, so skip the vertical axis.
+        try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let horizontal_axis_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let horizontal_axis = if horizontal_axis_offset == 0 {
+            None
+        } else {
+            Some(try!(layout::offset_slice(table.bytes, horizontal_axis_offset as u32)))
+        };
+
+        Ok(BaseTable {
+            horizontal_axis: horizontal_axis,
+        })
+    }
+
+    /// Returns the position of the given baseline for the given script along the horizontal
+    /// axis, or `None` if the table doesn't give one.
+    ///
+    /// If the table has no entry for the script, the entry for the `DFLT` script, if any, is
+    /// used instead.
+    pub fn baseline_offset(&self, baseline: BaselineTag, script: Tag)
+                           -> Result<Option<i16>, Error> {
+        let axis = match self.horizontal_axis {
+            None => return Ok(None),
+            Some(axis) => axis,
+        };
+
+        let mut reader = axis;
+        let base_tag_list_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let base_script_list_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if base_tag_list_offset == 0 {
+            return Ok(None)
+        }
+
+        // Find the index of the baseline in the tag list, which is the index of its coordinate
+        // in each script's values.
+        let mut reader = try!(layout::offset_slice(axis, base_tag_list_offset as u32));
+        let base_tag_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let mut baseline_index = None;
+        for index in 0..base_tag_count {
+            if try!(reader.read_u32::<BigEndian>().map_err(Error::eof)) == baseline.tag() {
+                baseline_index = Some(index);
+                break
+            }
+        }
+        let baseline_index = match baseline_index {
+            None => return Ok(None),
+            Some(baseline_index) => baseline_index,
+        };
+
+        let base_script_list = try!(layout::offset_slice(axis, base_script_list_offset as u32));
+        let base_script = match try!(find_base_script(base_script_list, script)) {
+            Some(base_script) => base_script,
+            None => {
+                match try!(find_base_script(base_script_list, tag(b"DFLT"))) {
+                    Some(base_script) => base_script,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let mut reader = base_script;
+        let base_values_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if base_values_offset == 0 {
+            return Ok(None)
+        }
+
+        // Skip the default baseline index to the coordinates.
+        let base_values = try!(layout::offset_slice(base_script, base_values_offset as u32));
+        let mut reader = base_values;
+        try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let base_coord_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if baseline_index >= base_coord_count {
+            return Ok(None)
+        }
+        try!(reader.jump(baseline_index as usize * mem::size_of::<u16>()).map_err(Error::eof));
+        let base_coord_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        // Every format of coordinate begins with the format and the coordinate itself. The
+        // device and variation adjustments of formats 2 and 3 are ignored.
+        let mut reader = try!(layout::offset_slice(base_values, base_coord_offset as u32));
+        let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if format < 1 || format > 3 {
+            return Err(Error::UnknownFormat)
+        }
+        Ok(Some(try!(reader.read_i16::<BigEndian>().map_err(Error::eof))))
+    }
+}
+
+// Returns the base script table for the given script, if the list has one.
+fn find_base_script(base_script_list: &[u8], script: Tag) -> Result<Option<&[u8]>, Error> {
+    let mut reader = base_script_list;
+    let base_script_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    for _ in 0..base_script_count {
+        let base_script_tag = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let base_script_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if base_script_tag == script {
+            return layout::offset_slice(base_script_list, base_script_offset as u32).map(Some)
+        }
+    }
+    Ok(None)
+}
+
+/// A baseline that glyphs of some scripts align to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BaselineTag {
+    /// The alphabetic baseline of Latin, Greek, Cyrillic, and similar scripts (`romn`).
+    Roman,
+    /// The hanging baseline of scripts such as Devanagari and Tibetan (`hang`).
+    Hanging,
+    /// The bottom of the ideographic em box (`ideo`).
+    IdeographicBottom,
+    /// The top of the ideographic em box (`idtp`).
+    IdeographicTop,
+    /// The bottom of the ideographic character face (`icfb`).
+    IdeographicFaceBottom,
+    /// The top of the ideographic character face (`icft`).
+    IdeographicFaceTop,
+    /// The baseline that mathematical characters center on (`math`).
+    Math,
+}
+
+impl BaselineTag {
+    /// Returns the tag that identifies this baseline in the `BASE` table.
+    pub fn tag(self) -> Tag {
+        tag(match self {
+            BaselineTag::Roman => b"romn",
+            BaselineTag::Hanging => b"hang",
+            BaselineTag::IdeographicBottom => b"ideo",
+            BaselineTag::IdeographicTop => b"idtp",
+            BaselineTag::IdeographicFaceBottom => b"icfb",
+            BaselineTag::IdeographicFaceTop => b"icft",
+            BaselineTag::Math => b"math",
+        })
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+// The size of a bitmap size record.
+const BITMAP_SIZE_RECORD_SIZE: usize = 48;
+
+// The offset of the vertical size in pixels per em within a bitmap size record.
+const PPEM_Y_OFFSET: usize = 45;
+
+/// The color bitmap location table, or the embedded bitmap location table that it extends, which
+/// says where the bitmaps of each strike are.
+///
+/// The two tables share a format, differing only in the major version: 3 for `CBLC` and 2 for
+/// `EBLC`.
+#[derive(Clone, Copy, Debug)]
+pub struct CblcTable<'a> {
+    table: FontTable<'a>,
+    num_sizes: u32,
+}
+
+impl<'a> CblcTable<'a> {
+    pub fn new(table: FontTable) -> Result<CblcTable, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if major_version != 2 && major_version != 3 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Skip over the minor version.
+        try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        let num_sizes = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        Ok(CblcTable {
+            table: table,
+            num_sizes: num_sizes,
+        })
+    }
+
+    /// Returns the vertical size of each strike in pixels per em, in the order they're stored.
+    pub fn strike_sizes(&self) -> Result<Vec<u16>, Error> {
+        let mut sizes = vec![];
+        for size_index in 0..(self.num_sizes as usize) {
+            let mut record = self.table.bytes;
+            try!(record.jump(8 + size_index * BITMAP_SIZE_RECORD_SIZE + PPEM_Y_OFFSET)
+                       .map_err(Error::eof));
+            sizes.push(try!(record.read_u8().map_err(Error::eof)) as u16)
+        }
+        Ok(sizes)
+    }
+}
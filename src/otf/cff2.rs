@@ -0,0 +1,936 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The `CFF2` table, which describes glyph outlines as Type 2 charstrings whose numbers may vary
+//! along the axes of a variable font.
+//!
+//! https://docs.microsoft.com/en-us/typography/opentype/spec/cff2
+
+use byteorder::{BigEndian, ReadBytesExt};
+use euclid::Point2D;
+use flatten;
+use otf::glyf::Point;
+use otf::{Error, FontTable, OutlineVisitor, ParseLimits};
+use util::Jump;
+
+// The Top DICT operators that we read.
+const CHAR_STRINGS_OPERATOR: u16 = 17;
+const VSTORE_OPERATOR: u16 = 24;
+const FD_ARRAY_OPERATOR: u16 = (12 << 8) | 36;
+const FD_SELECT_OPERATOR: u16 = (12 << 8) | 37;
+
+// The Font DICT and Private DICT operators that we read.
+const PRIVATE_OPERATOR: u16 = 18;
+const SUBRS_OPERATOR: u16 = 19;
+const VSINDEX_OPERATOR: u16 = 22;
+const BLEND_OPERATOR: u16 = 23;
+
+// The maximum depth of the argument stack that CFF2 allows.
+const MAX_STACK_DEPTH: usize = 513;
+
+// The maximum nesting depth of subroutine calls that CFF2 allows.
+const MAX_SUBR_DEPTH: u32 = 10;
+
+// How far, in font units, the quadratic curves that `for_each_point()` reports may stray from the
+// cubic curves of the outline.
+const QUADRATIC_TOLERANCE: f32 = 0.25;
+
+/// The Compact Font Format version 2 table, which holds the cubic outlines of variable
+/// OpenType fonts.
+#[derive(Clone, Debug)]
+pub struct Cff2Table<'a> {
+    char_strings: Index<'a>,
+    global_subrs: Index<'a>,
+    font_dicts: Vec<PrivateDict<'a>>,
+    fd_select: Option<&'a [u8]>,
+    variation_store: Option<VariationStore<'a>>,
+    max_points_per_glyph: u32,
+}
+
+// The parts of the Private DICT of each Font DICT that charstrings use.
+#[derive(Clone, Copy, Debug)]
+struct PrivateDict<'a> {
+    local_subrs: Index<'a>,
+    vsindex: u16,
+}
+
+impl<'a> Cff2Table<'a> {
+    pub fn new<'b>(table: FontTable<'b>, limits: &ParseLimits) -> Result<Cff2Table<'b>, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u8().map_err(Error::eof));
+        if major_version != 2 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Skip over the minor version. The Top DICT follows the header, and the global
+        // subroutines follow the Top DICT.
+        try!(reader.jump(1).map_err(Error::eof));
+        let header_size = try!(reader.read_u8().map_err(Error::eof)) as usize;
+        let top_dict_length = try!(reader.read_u16::<BigEndian>().map_err(Error::eof)) as usize;
+        let top_dict = try!(table.bytes
+                                 .get(header_size..header_size + top_dict_length)
+                                 .ok_or(Error::UnexpectedEof));
+        let mut reader = &table.bytes[header_size + top_dict_length..];
+        let global_subrs = try!(Index::new(&mut reader));
+
+        let (mut char_strings_offset, mut fd_array_offset) = (None, None);
+        let (mut fd_select_offset, mut vstore_offset) = (None, None);
+        try!(for_each_dict_entry(top_dict, |operator, operands| {
+            let offset = match operands.last() {
+                Some(&offset) if offset >= 0.0 => Some(offset as usize),
+                _ => None,
+            };
+            match operator {
+                CHAR_STRINGS_OPERATOR => char_strings_offset = offset,
+                FD_ARRAY_OPERATOR => fd_array_offset = offset,
+                FD_SELECT_OPERATOR => fd_select_offset = offset,
+                VSTORE_OPERATOR => vstore_offset = offset,
+                _ => {}
+            }
+        }));
+
+        // Every CFF2 font has charstrings and at least one Font DICT.
+        let char_strings_offset = try!(char_strings_offset.ok_or(Error::Failed));
+        let char_strings = try!(Index::at(table.bytes, char_strings_offset));
+        let fd_array = try!(Index::at(table.bytes, try!(fd_array_offset.ok_or(Error::Failed))));
+        let mut font_dicts = Vec::with_capacity(fd_array.count as usize);
+        for font_dict_index in 0..fd_array.count {
+            let font_dict = try!(fd_array.get(font_dict_index));
+            font_dicts.push(try!(PrivateDict::new(table.bytes, font_dict)))
+        }
+
+        let fd_select = match fd_select_offset {
+            None => None,
+            Some(offset) => Some(try!(table.bytes.get(offset..).ok_or(Error::UnexpectedEof))),
+        };
+
+        // The variation store begins with its length.
+        let variation_store = match vstore_offset {
+            None => None,
+            Some(offset) => {
+                let store = try!(table.bytes.get(offset + 2..).ok_or(Error::UnexpectedEof));
+                Some(try!(VariationStore::new(store)))
+            }
+        };
+
+        Ok(Cff2Table {
+            char_strings: char_strings,
+            global_subrs: global_subrs,
+            font_dicts: font_dicts,
+            fd_select: fd_select,
+            variation_store: variation_store,
+            max_points_per_glyph: limits.max_points_per_glyph,
+        })
+    }
+
+    /// Walks the outline of the given glyph at the instance with the given normalized
+    /// coordinates, one per axis in `fvar` order, calling the visitor for each contour and
+    /// segment.
+    ///
+    /// Each contour ends with a line back to its starting point if it doesn't end there already.
+    pub fn walk_glyph(&self, glyph_id: u16, coordinates: &[f32], visitor: &mut OutlineVisitor)
+                      -> Result<(), Error> {
+        let char_string = try!(self.char_strings.get(glyph_id as u32));
+        let private_dict = try!(self.private_dict_for_glyph(glyph_id));
+        let mut interpreter = Interpreter {
+            table: self,
+            local_subrs: private_dict.local_subrs,
+            coordinates: coordinates,
+            vsindex: private_dict.vsindex,
+            scalars: None,
+            stack: vec![],
+            stem_count: 0,
+            position: Point2D::zero(),
+            contour_start: None,
+            visitor: visitor,
+        };
+        try!(interpreter.run(char_string, 0));
+        interpreter.close_contour();
+        Ok(())
+    }
+
+    /// Calls the given callback with each point of the given glyph, as `GlyfTable` does for
+    /// TrueType outlines.
+    ///
+    /// Cubic curves are approximated with quadratic curves to within a quarter of a font unit, and
+    /// the points are rounded to whole font units.
+    pub fn for_each_point<F>(&self, glyph_id: u16, coordinates: &[f32], callback: F)
+                             -> Result<(), Error> where F: FnMut(&Point) {
+        let mut emitter = PointEmitter {
+            callback: callback,
+            current_point: Point2D::zero(),
+            last_position: None,
+            index_in_contour: 0,
+            point_count: 0,
+            max_point_count: self.max_points_per_glyph,
+        };
+        try!(self.walk_glyph(glyph_id, coordinates, &mut emitter));
+        if emitter.point_count > emitter.max_point_count {
+            return Err(Error::TooManyPoints)
+        }
+        Ok(())
+    }
+
+    // Returns the Private DICT of the Font DICT that the FDSelect structure picks for the given
+    // glyph. Without an FDSelect structure, every glyph uses the first Font DICT.
+    fn private_dict_for_glyph(&self, glyph_id: u16) -> Result<PrivateDict<'a>, Error> {
+        let font_dict_index = match self.fd_select {
+            None => 0,
+            Some(fd_select) => try!(font_dict_index_for_glyph(fd_select, glyph_id)),
+        };
+        self.font_dicts.get(font_dict_index as usize).cloned().ok_or(Error::MalformedGlyph)
+    }
+}
+
+impl<'a> PrivateDict<'a> {
+    // Reads the Private DICT that the given Font DICT points to.
+    fn new(table: &'a [u8], font_dict: &[u8]) -> Result<PrivateDict<'a>, Error> {
+        let mut private_range = None;
+        try!(for_each_dict_entry(font_dict, |operator, operands| {
+            if operator == PRIVATE_OPERATOR && operands.len() >= 2 &&
+                    operands[0] >= 0.0 && operands[1] >= 0.0 {
+                private_range = Some((operands[1] as usize, operands[0] as usize))
+            }
+        }));
+
+        let (private_offset, private_size) = try!(private_range.ok_or(Error::Failed));
+        let private_dict = try!(table.get(private_offset..private_offset + private_size)
+                                     .ok_or(Error::UnexpectedEof));
+        let (mut subrs_offset, mut vsindex) = (None, 0);
+        try!(for_each_dict_entry(private_dict, |operator, operands| {
+            match (operator, operands.last()) {
+                (SUBRS_OPERATOR, Some(&offset)) if offset >= 0.0 => {
+                    subrs_offset = Some(offset as usize)
+                }
+                (VSINDEX_OPERATOR, Some(&index)) if index >= 0.0 => vsindex = index as u16,
+                _ => {}
+            }
+        }));
+
+        // Local subroutines are found relative to the Private DICT.
+        let local_subrs = match subrs_offset {
+            None => Index::empty(),
+            Some(offset) => try!(Index::at(table, private_offset + offset)),
+        };
+        Ok(PrivateDict {
+            local_subrs: local_subrs,
+            vsindex: vsindex,
+        })
+    }
+}
+
+// An INDEX structure: an array of variable-length objects.
+#[derive(Clone, Copy, Debug)]
+struct Index<'a> {
+    count: u32,
+    offset_size: usize,
+    offsets: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Index<'a> {
+    #[inline]
+    fn empty() -> Index<'a> {
+        Index {
+            count: 0,
+            offset_size: 1,
+            offsets: &[],
+            data: &[],
+        }
+    }
+
+    // Reads the INDEX at the given offset in the table.
+    fn at(table: &'a [u8], offset: usize) -> Result<Index<'a>, Error> {
+        let mut reader = try!(table.get(offset..).ok_or(Error::UnexpectedEof));
+        Index::new(&mut reader)
+    }
+
+    // Reads the INDEX at the start of the reader, and moves the reader past it.
+    fn new(reader: &mut &'a [u8]) -> Result<Index<'a>, Error> {
+        let count = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        if count == 0 {
+            return Ok(Index::empty())
+        }
+
+        let offset_size = try!(reader.read_u8().map_err(Error::eof)) as usize;
+        if offset_size < 1 || offset_size > 4 {
+            return Err(Error::Failed)
+        }
+        let offsets_length = try!((count as usize).checked_add(1)
+                                                  .and_then(|n| n.checked_mul(offset_size))
+                                                  .ok_or(Error::UnexpectedEof));
+        let offsets = try!(reader.get(0..offsets_length).ok_or(Error::UnexpectedEof));
+        try!(reader.jump(offsets_length).map_err(Error::eof));
+
+        // Offsets count from the byte before the data.
+        let data_length = try!(read_offset(&offsets[offsets_length - offset_size..], offset_size)
+                                   .checked_sub(1)
+                                   .ok_or(Error::Failed));
+        let data = try!(reader.get(0..data_length).ok_or(Error::UnexpectedEof));
+        try!(reader.jump(data_length).map_err(Error::eof));
+
+        Ok(Index {
+            count: count,
+            offset_size: offset_size,
+            offsets: offsets,
+            data: data,
+        })
+    }
+
+    // Returns the object with the given index.
+    fn get(&self, index: u32) -> Result<&'a [u8], Error> {
+        if index >= self.count {
+            return Err(Error::UnexpectedEof)
+        }
+        let position = index as usize * self.offset_size;
+        let start = read_offset(&self.offsets[position..], self.offset_size);
+        let end = read_offset(&self.offsets[position + self.offset_size..], self.offset_size);
+        match (start.checked_sub(1), end.checked_sub(1)) {
+            (Some(start), Some(end)) if start <= end => {
+                self.data.get(start..end).ok_or(Error::UnexpectedEof)
+            }
+            _ => Err(Error::Failed),
+        }
+    }
+}
+
+// Reads a big-endian offset of the given size, from 1 to 4 bytes.
+fn read_offset(bytes: &[u8], offset_size: usize) -> usize {
+    bytes[0..offset_size].iter().fold(0, |offset, &byte| (offset << 8) | byte as usize)
+}
+
+// Calls the given callback with each operator of the given DICT and the operands before it.
+// Two-byte operators are given as `(12 << 8) | second_byte`.
+//
+// The `blend` operator of a Private DICT leaves its operands on the stack, since none of the
+// values that we read are blended.
+fn for_each_dict_entry<F>(mut reader: &[u8], mut callback: F) -> Result<(), Error>
+                          where F: FnMut(u16, &[f64]) {
+    let mut operands = vec![];
+    while let Some(b0) = reader.read_u8().ok() {
+        match b0 {
+            12 => {
+                let b1 = try!(reader.read_u8().map_err(Error::eof));
+                callback((12 << 8) | b1 as u16, &operands);
+                operands.clear()
+            }
+            b0 if b0 as u16 == BLEND_OPERATOR => {}
+            b0 if b0 <= 24 => {
+                callback(b0 as u16, &operands);
+                operands.clear()
+            }
+            28 => operands.push(try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as f64),
+            29 => operands.push(try!(reader.read_i32::<BigEndian>().map_err(Error::eof)) as f64),
+            30 => operands.push(try!(read_real(&mut reader))),
+            b0 if b0 >= 32 && b0 <= 254 => {
+                operands.push(try!(read_small_integer(b0, &mut reader)) as f64)
+            }
+            _ => return Err(Error::Failed),
+        }
+
+        if operands.len() > MAX_STACK_DEPTH {
+            return Err(Error::Failed)
+        }
+    }
+    Ok(())
+}
+
+// Reads an integer encoded in one or two bytes, the first of which, from 32 to 254, has been read
+// already.
+fn read_small_integer(b0: u8, reader: &mut &[u8]) -> Result<i32, Error> {
+    match b0 {
+        b0 if b0 <= 246 => Ok(b0 as i32 - 139),
+        b0 if b0 <= 250 => {
+            let b1 = try!(reader.read_u8().map_err(Error::eof));
+            Ok((b0 as i32 - 247) * 256 + b1 as i32 + 108)
+        }
+        b0 => {
+            let b1 = try!(reader.read_u8().map_err(Error::eof));
+            Ok(-(b0 as i32 - 251) * 256 - b1 as i32 - 108)
+        }
+    }
+}
+
+// Reads a real number, stored as decimal digits in nibbles, whose first byte has been read
+// already. A number that doesn't parse reads as 0.
+fn read_real(reader: &mut &[u8]) -> Result<f64, Error> {
+    let mut string = String::new();
+    loop {
+        let byte = try!(reader.read_u8().map_err(Error::eof));
+        for &nibble in &[byte >> 4, byte & 0xf] {
+            match nibble {
+                nibble if nibble <= 9 => string.push((b'0' + nibble) as char),
+                0xa => string.push('.'),
+                0xb => string.push('E'),
+                0xc => string.push_str("E-"),
+                0xe => string.push('-'),
+                0xf => return Ok(string.parse().unwrap_or(0.0)),
+                _ => return Err(Error::Failed),
+            }
+        }
+    }
+}
+
+// Returns the index of the Font DICT that the given FDSelect structure picks for the given glyph.
+fn font_dict_index_for_glyph(mut reader: &[u8], glyph_id: u16) -> Result<u16, Error> {
+    match try!(reader.read_u8().map_err(Error::eof)) {
+        0 => {
+            try!(reader.jump(glyph_id as usize).map_err(Error::eof));
+            Ok(try!(reader.read_u8().map_err(Error::eof)) as u16)
+        }
+        3 => {
+            // Each range runs from its first glyph to the first glyph of the next, and a sentinel
+            // ends the last.
+            let range_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let mut first = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            for _ in 0..range_count {
+                let font_dict_index = try!(reader.read_u8().map_err(Error::eof));
+                let next_first = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+                if glyph_id >= first && glyph_id < next_first {
+                    return Ok(font_dict_index as u16)
+                }
+                first = next_first
+            }
+            Err(Error::MalformedGlyph)
+        }
+        4 => {
+            let range_count = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+            let mut first = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+            for _ in 0..range_count {
+                let font_dict_index = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+                let next_first = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+                if glyph_id as u32 >= first && (glyph_id as u32) < next_first {
+                    return Ok(font_dict_index)
+                }
+                first = next_first
+            }
+            Err(Error::MalformedGlyph)
+        }
+        _ => Err(Error::UnknownFormat),
+    }
+}
+
+// An item variation store, which holds the regions of the design space that blended values vary
+// over.
+#[derive(Clone, Copy, Debug)]
+struct VariationStore<'a> {
+    store: &'a [u8],
+    axis_count: u16,
+    region_count: u16,
+    regions: &'a [u8],
+    data_count: u16,
+}
+
+impl<'a> VariationStore<'a> {
+    fn new(store: &'a [u8]) -> Result<VariationStore<'a>, Error> {
+        let mut reader = store;
+        let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if format != 1 {
+            return Err(Error::UnknownFormat)
+        }
+        let region_list_offset = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let data_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        let mut region_list = try!(store.get(region_list_offset as usize..)
+                                        .ok_or(Error::UnexpectedEof));
+        let axis_count = try!(region_list.read_u16::<BigEndian>().map_err(Error::eof));
+        let region_count = try!(region_list.read_u16::<BigEndian>().map_err(Error::eof));
+        Ok(VariationStore {
+            store: store,
+            axis_count: axis_count,
+            region_count: region_count,
+            regions: region_list,
+            data_count: data_count,
+        })
+    }
+
+    // Returns the scalar of each region that the item variation data with the given index uses, at
+    // the instance with the given normalized coordinates. Values blended with that data move by
+    // the sum of their deltas times these scalars.
+    fn scalars(&self, data_index: u16, coordinates: &[f32]) -> Result<Vec<f32>, Error> {
+        if data_index >= self.data_count {
+            return Err(Error::MalformedGlyph)
+        }
+        let mut reader = self.store;
+        try!(reader.jump(8 + data_index as usize * 4).map_err(Error::eof));
+        let data_offset = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+
+        // Skip over the item count and the count of deltas stored as words.
+        let mut reader = try!(self.store.get(data_offset as usize..).ok_or(Error::UnexpectedEof));
+        try!(reader.jump(4).map_err(Error::eof));
+        let region_index_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let mut scalars = Vec::with_capacity(region_index_count as usize);
+        for _ in 0..region_index_count {
+            let region_index = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            scalars.push(try!(self.region_scalar(region_index, coordinates)))
+        }
+        Ok(scalars)
+    }
+
+    // Returns the scalar of the region with the given index: the product over each axis of how
+    // far the instance has come from the start or end of the region's range towards its peak.
+    fn region_scalar(&self, region_index: u16, coordinates: &[f32]) -> Result<f32, Error> {
+        if region_index >= self.region_count {
+            return Err(Error::MalformedGlyph)
+        }
+        let mut reader = self.regions;
+        try!(reader.jump(region_index as usize * self.axis_count as usize * 6)
+                   .map_err(Error::eof));
+
+        let mut scalar = 1.0;
+        for axis_index in 0..self.axis_count as usize {
+            let start = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as f32 / 16384.0;
+            let peak = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as f32 / 16384.0;
+            let end = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as f32 / 16384.0;
+            let coordinate = coordinates.get(axis_index).cloned().unwrap_or(0.0);
+
+            // Axes without a peak, and malformed ranges, don't affect the region.
+            if peak == 0.0 || start > peak || peak > end || (start < 0.0 && end > 0.0) ||
+                    coordinate == peak {
+                continue
+            }
+            if coordinate <= start || coordinate >= end {
+                return Ok(0.0)
+            }
+            scalar *= if coordinate < peak {
+                (coordinate - start) / (peak - start)
+            } else {
+                (end - coordinate) / (end - peak)
+            }
+        }
+        Ok(scalar)
+    }
+}
+
+// Runs charstrings, sending the outline that they draw to a visitor.
+struct Interpreter<'a, 'b> {
+    table: &'b Cff2Table<'a>,
+    local_subrs: Index<'a>,
+    coordinates: &'b [f32],
+    vsindex: u16,
+    // The scalars of the regions of the current item variation data, once a blend needs them.
+    scalars: Option<Vec<f32>>,
+    stack: Vec<f32>,
+    // The number of stem hints so far, which gives the length of each hint mask.
+    stem_count: u32,
+    position: Point2D<f32>,
+    contour_start: Option<Point2D<f32>>,
+    visitor: &'b mut OutlineVisitor,
+}
+
+impl<'a, 'b> Interpreter<'a, 'b> {
+    fn run(&mut self, mut reader: &[u8], depth: u32) -> Result<(), Error> {
+        while let Some(b0) = reader.read_u8().ok() {
+            match b0 {
+                28 => {
+                    let value = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+                    try!(self.push(value as f32))
+                }
+                b0 if b0 >= 32 && b0 <= 254 => {
+                    let value = try!(read_small_integer(b0, &mut reader));
+                    try!(self.push(value as f32))
+                }
+                255 => {
+                    let value = try!(reader.read_i32::<BigEndian>().map_err(Error::eof));
+                    try!(self.push(value as f32 / 65536.0))
+                }
+
+                // Hints don't affect the outline, but the hint masks that follow them are as long
+                // as there are stems. A hint mask after stems for `vstem` leaves out the operator.
+                1 | 3 | 18 | 23 => self.add_stems(),
+                19 | 20 => {
+                    self.add_stems();
+                    try!(reader.jump((self.stem_count as usize + 7) / 8).map_err(Error::eof))
+                }
+
+                10 => {
+                    let subr = try!(self.subr(self.local_subrs));
+                    try!(self.call(subr, depth))
+                }
+                29 => {
+                    let subr = try!(self.subr(self.table.global_subrs));
+                    try!(self.call(subr, depth))
+                }
+
+                15 => {
+                    self.vsindex = try!(self.pop()) as u16;
+                    self.scalars = None
+                }
+                16 => try!(self.blend()),
+
+                21 => {
+                    let (dx, dy) = try!(self.args_2());
+                    self.move_to(dx, dy)
+                }
+                22 => {
+                    let dx = try!(self.arg());
+                    self.move_to(dx, 0.0)
+                }
+                4 => {
+                    let dy = try!(self.arg());
+                    self.move_to(0.0, dy)
+                }
+
+                5 => {
+                    for pair in self.stack.chunks(2) {
+                        if pair.len() == 2 {
+                            let to = self.position + Point2D::new(pair[0], pair[1]);
+                            line_to(self.visitor, &mut self.position, to)
+                        }
+                    }
+                }
+                6 | 7 => {
+                    let mut horizontal = b0 == 6;
+                    for &delta in &self.stack {
+                        let to = if horizontal {
+                            self.position + Point2D::new(delta, 0.0)
+                        } else {
+                            self.position + Point2D::new(0.0, delta)
+                        };
+                        line_to(self.visitor, &mut self.position, to);
+                        horizontal = !horizontal
+                    }
+                }
+
+                8 => {
+                    for args in self.stack.chunks(6) {
+                        if args.len() == 6 {
+                            curve_to(self.visitor, &mut self.position, args)
+                        }
+                    }
+                }
+                24 => {
+                    // Curves, then a line.
+                    let curve_args_length = self.stack.len().saturating_sub(2) / 6 * 6;
+                    for args in self.stack[..curve_args_length].chunks(6) {
+                        curve_to(self.visitor, &mut self.position, args)
+                    }
+                    if let Some(args) = self.stack.get(curve_args_length..curve_args_length + 2) {
+                        let to = self.position + Point2D::new(args[0], args[1]);
+                        line_to(self.visitor, &mut self.position, to)
+                    }
+                }
+                25 => {
+                    // Lines, then a curve.
+                    let line_args_length = self.stack.len().saturating_sub(6) / 2 * 2;
+                    for pair in self.stack[..line_args_length].chunks(2) {
+                        let to = self.position + Point2D::new(pair[0], pair[1]);
+                        line_to(self.visitor, &mut self.position, to)
+                    }
+                    if let Some(args) = self.stack.get(line_args_length..line_args_length + 6) {
+                        curve_to(self.visitor, &mut self.position, args)
+                    }
+                }
+                26 | 27 => {
+                    // Curves that start and end vertically (`vvcurveto`) or horizontally
+                    // (`hhcurveto`). An odd argument first offsets the start of the first one.
+                    let vertical = b0 == 26;
+                    let (mut offset, mut args) = (0.0, &self.stack[..]);
+                    if args.len() % 4 == 1 {
+                        offset = args[0];
+                        args = &args[1..]
+                    }
+                    for curve_args in args.chunks(4) {
+                        if curve_args.len() < 4 {
+                            break
+                        }
+                        let args = if vertical {
+                            [offset, curve_args[0], curve_args[1], curve_args[2], 0.0,
+                             curve_args[3]]
+                        } else {
+                            [curve_args[0], offset, curve_args[1], curve_args[2], curve_args[3],
+                             0.0]
+                        };
+                        curve_to(self.visitor, &mut self.position, &args);
+                        offset = 0.0
+                    }
+                }
+                30 | 31 => {
+                    // Curves that alternate between starting vertically and ending horizontally
+                    // (`vhcurveto`) and the reverse (`hvcurveto`). An extra argument after the
+                    // last curve moves its end along the other axis.
+                    let mut horizontal = b0 == 31;
+                    let mut args = &self.stack[..];
+                    while args.len() >= 4 {
+                        let last = if args.len() == 5 { args[4] } else { 0.0 };
+                        let curve_args = if horizontal {
+                            [args[0], 0.0, args[1], args[2], last, args[3]]
+                        } else {
+                            [0.0, args[0], args[1], args[2], args[3], last]
+                        };
+                        curve_to(self.visitor, &mut self.position, &curve_args);
+                        args = &args[4..];
+                        horizontal = !horizontal
+                    }
+                }
+
+                12 => {
+                    let b1 = try!(reader.read_u8().map_err(Error::eof));
+                    try!(self.flex(b1))
+                }
+
+                _ => return Err(Error::MalformedGlyph),
+            }
+
+            // Every operator but the ones that only manipulate the stack clears it.
+            match b0 {
+                10 | 16 | 28 | 29 => {}
+                b0 if b0 >= 32 => {}
+                _ => self.stack.clear(),
+            }
+        }
+        Ok(())
+    }
+
+    // Draws the flex operators, which are pairs of curves that may be drawn as a line when small.
+    fn flex(&mut self, operator: u8) -> Result<(), Error> {
+        let start = self.position;
+        let args = &self.stack[..];
+        let curves = match operator {
+            35 if args.len() >= 13 => [
+                [args[0], args[1], args[2], args[3], args[4], args[5]],
+                [args[6], args[7], args[8], args[9], args[10], args[11]],
+            ],
+            34 if args.len() >= 7 => [
+                [args[0], 0.0, args[1], args[2], args[3], 0.0],
+                [args[4], 0.0, args[5], -args[2], args[6], 0.0],
+            ],
+            36 if args.len() >= 9 => {
+                let dy = args[1] + args[3] + args[7];
+                [
+                    [args[0], args[1], args[2], args[3], args[4], 0.0],
+                    [args[5], 0.0, args[6], args[7], args[8], -dy],
+                ]
+            }
+            37 if args.len() >= 11 => {
+                // The last argument moves the end along the axis that the curves move along the
+                // most, and the end comes back level with the start along the other.
+                let dx = args[0] + args[2] + args[4] + args[6] + args[8];
+                let dy = args[1] + args[3] + args[5] + args[7] + args[9];
+                let (last_dx, last_dy) = if dx.abs() > dy.abs() {
+                    (args[10], -dy)
+                } else {
+                    (-dx, args[10])
+                };
+                [
+                    [args[0], args[1], args[2], args[3], args[4], args[5]],
+                    [args[6], args[7], args[8], args[9], last_dx, last_dy],
+                ]
+            }
+            _ => return Err(Error::MalformedGlyph),
+        };
+
+        curve_to(self.visitor, &mut self.position, &curves[0]);
+        curve_to(self.visitor, &mut self.position, &curves[1]);
+
+        // Rounding errors in the sums above shouldn't leave the end a hair away from level.
+        match operator {
+            34 | 36 => self.position.y = start.y,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn push(&mut self, value: f32) -> Result<(), Error> {
+        if self.stack.len() >= MAX_STACK_DEPTH {
+            return Err(Error::MalformedGlyph)
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Result<f32, Error> {
+        self.stack.pop().ok_or(Error::MalformedGlyph)
+    }
+
+    // Returns the last argument on the stack, for operators that take one.
+    #[inline]
+    fn arg(&self) -> Result<f32, Error> {
+        self.stack.last().cloned().ok_or(Error::MalformedGlyph)
+    }
+
+    // Returns the last two arguments on the stack, for operators that take two.
+    #[inline]
+    fn args_2(&self) -> Result<(f32, f32), Error> {
+        match self.stack.len() {
+            length if length >= 2 => Ok((self.stack[length - 2], self.stack[length - 1])),
+            _ => Err(Error::MalformedGlyph),
+        }
+    }
+
+    #[inline]
+    fn add_stems(&mut self) {
+        self.stem_count += self.stack.len() as u32 / 2
+    }
+
+    // Returns the subroutine whose biased index is on top of the stack.
+    fn subr(&mut self, subrs: Index<'a>) -> Result<&'a [u8], Error> {
+        let bias = if subrs.count < 1240 {
+            107
+        } else if subrs.count < 33900 {
+            1131
+        } else {
+            32768
+        };
+        let index = try!(self.pop()) as i32 + bias;
+        if index < 0 {
+            return Err(Error::MalformedGlyph)
+        }
+        subrs.get(index as u32).map_err(|_| Error::MalformedGlyph)
+    }
+
+    #[inline]
+    fn call(&mut self, subr: &[u8], depth: u32) -> Result<(), Error> {
+        if depth >= MAX_SUBR_DEPTH {
+            return Err(Error::MalformedGlyph)
+        }
+        self.run(subr, depth + 1)
+    }
+
+    // Replaces each of the values on the stack that the blend operator names with its default
+    // plus its deltas times the scalars of their regions.
+    fn blend(&mut self) -> Result<(), Error> {
+        if self.scalars.is_none() {
+            let store = try!(self.table.variation_store.ok_or(Error::MalformedGlyph));
+            self.scalars = Some(try!(store.scalars(self.vsindex, self.coordinates)))
+        }
+        let scalars = self.scalars.as_ref().unwrap();
+
+        let value_count = try!(self.stack.pop().ok_or(Error::MalformedGlyph));
+        if value_count < 0.0 {
+            return Err(Error::MalformedGlyph)
+        }
+        let value_count = value_count as usize;
+        let args_length = try!(value_count.checked_mul(scalars.len() + 1)
+                                          .ok_or(Error::MalformedGlyph));
+        let first = try!(self.stack.len().checked_sub(args_length).ok_or(Error::MalformedGlyph));
+
+        let deltas_start = first + value_count;
+        for value_index in 0..value_count {
+            let deltas = &self.stack[deltas_start + value_index * scalars.len()..]
+                [..scalars.len()];
+            let delta = deltas.iter()
+                              .zip(scalars.iter())
+                              .fold(0.0, |sum, (&delta, &scalar)| sum + delta * scalar);
+            self.stack[first + value_index] += delta
+        }
+        self.stack.truncate(deltas_start);
+        Ok(())
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.close_contour();
+        self.position = self.position + Point2D::new(dx, dy);
+        self.visitor.move_to(&self.position);
+        self.contour_start = Some(self.position)
+    }
+
+    // Ends the current contour, if there is one, with a line back to its start.
+    fn close_contour(&mut self) {
+        if let Some(contour_start) = self.contour_start.take() {
+            if self.position != contour_start {
+                self.visitor.line_to(&contour_start)
+            }
+            self.visitor.close()
+        }
+    }
+}
+
+#[inline]
+fn line_to(visitor: &mut OutlineVisitor, position: &mut Point2D<f32>, to: Point2D<f32>) {
+    visitor.line_to(&to);
+    *position = to
+}
+
+// Draws a curve whose two control points and end are each given relative to the point before.
+#[inline]
+fn curve_to(visitor: &mut OutlineVisitor, position: &mut Point2D<f32>, args: &[f32]) {
+    let control_point_0 = *position + Point2D::new(args[0], args[1]);
+    let control_point_1 = control_point_0 + Point2D::new(args[2], args[3]);
+    let to = control_point_1 + Point2D::new(args[4], args[5]);
+    visitor.cubic_to(&control_point_0, &control_point_1, &to);
+    *position = to
+}
+
+// Turns the segments of an outline into the points that `Font::for_each_point()` reports, with
+// cubic curves replaced by quadratic ones.
+struct PointEmitter<F> {
+    callback: F,
+    current_point: Point2D<f32>,
+    last_position: Option<Point2D<i16>>,
+    index_in_contour: u16,
+    point_count: u32,
+    max_point_count: u32,
+}
+
+impl<F> PointEmitter<F> where F: FnMut(&Point) {
+    fn emit(&mut self, position: &Point2D<f32>, on_curve: bool) {
+        let position = Point2D::new(round_to_i16(position.x), round_to_i16(position.y));
+
+        // Rounding can collapse short lines, which are left out. Stop at the limit on points, so
+        // that a huge glyph is reported without being drawn.
+        if on_curve && self.last_position == Some(position) && self.index_in_contour > 0 {
+            return
+        }
+        self.point_count += 1;
+        if self.point_count > self.max_point_count {
+            return
+        }
+
+        (self.callback)(&Point {
+            position: position,
+            index_in_contour: self.index_in_contour,
+            on_curve: on_curve,
+        });
+        self.last_position = if on_curve { Some(position) } else { None };
+        self.index_in_contour += 1
+    }
+}
+
+impl<F> OutlineVisitor for PointEmitter<F> where F: FnMut(&Point) {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        self.index_in_contour = 0;
+        self.emit(to, true);
+        self.current_point = *to
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        self.emit(to, true);
+        self.current_point = *to
+    }
+
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>) {
+        self.emit(control_point, false);
+        self.emit(to, true);
+        self.current_point = *to
+    }
+
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>) {
+        let from = self.current_point;
+        flatten::cubic_to_quadratics(&from,
+                                     control_point_0,
+                                     control_point_1,
+                                     to,
+                                     QUADRATIC_TOLERANCE,
+                                     |control_point, to| self.quad_to(control_point, to))
+    }
+
+    fn close(&mut self) {}
+}
+
+#[inline]
+fn round_to_i16(value: f32) -> i16 {
+    value.round().max(i16::min_value() as f32).min(i16::max_value() as f32) as i16
+}
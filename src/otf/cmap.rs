@@ -20,14 +20,22 @@ use util::Jump;
 const PLATFORM_ID_UNICODE: u16 = 0;
 const PLATFORM_ID_MICROSOFT: u16 = 3;
 
+const UNICODE_ENCODING_ID_VARIATION_SEQUENCES: u16 = 5;
+
 const MICROSOFT_ENCODING_ID_UNICODE_BMP: u16 = 1;
 const MICROSOFT_ENCODING_ID_UNICODE_UCS4: u16 = 10;
 
 const FORMAT_SEGMENT_MAPPING_TO_DELTA_VALUES: u16 = 4;
 const FORMAT_SEGMENTED_COVERAGE: u16 = 12;
+const FORMAT_UNICODE_VARIATION_SEQUENCES: u16 = 14;
 
 const MISSING_GLYPH: u16 = 0;
 
+// The sizes of a variation selector record and of a non-default UVS mapping in a format 14
+// subtable.
+const VAR_SELECTOR_RECORD_SIZE: usize = 11;
+const UVS_MAPPING_SIZE: usize = 5;
+
 #[derive(Clone, Copy)]
 pub struct CmapTable<'a> {
     table: FontTable<'a>,
@@ -42,6 +50,40 @@ impl<'a> CmapTable<'a> {
 
     pub fn glyph_mapping_for_codepoint_ranges(&self, codepoint_ranges: &[CodepointRange])
                                               -> Result<GlyphMapping, Error> {
+        let mut cmap_reader = try!(self.character_subtable());
+
+        // Check the mapping table format.
+        let format = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        match format {
+            FORMAT_SEGMENT_MAPPING_TO_DELTA_VALUES => {
+                self.glyph_mapping_for_codepoint_ranges_segment_mapping_format(cmap_reader,
+                                                                               codepoint_ranges)
+            }
+            FORMAT_SEGMENTED_COVERAGE => {
+                self.glyph_mapping_for_codepoint_ranges_segmented_coverage(cmap_reader,
+                                                                           codepoint_ranges)
+            }
+            _ => Err(Error::UnsupportedCmapFormat),
+        }
+    }
+
+    /// Returns ranges covering every codepoint that the character map maps to a glyph other than
+    /// the missing glyph. The ranges may be unsorted and adjacent to one another.
+    pub fn coverage(&self) -> Result<Vec<CodepointRange>, Error> {
+        let mut cmap_reader = try!(self.character_subtable());
+
+        let format = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        match format {
+            FORMAT_SEGMENT_MAPPING_TO_DELTA_VALUES => {
+                self.coverage_segment_mapping_format(cmap_reader)
+            }
+            FORMAT_SEGMENTED_COVERAGE => self.coverage_segmented_coverage(cmap_reader),
+            _ => Err(Error::UnsupportedCmapFormat),
+        }
+    }
+
+    // Returns the subtable that maps individual characters to glyphs, starting at its format.
+    fn character_subtable(&self) -> Result<&'a [u8], Error> {
         let mut cmap_reader = self.table.bytes;
 
         // Check version.
@@ -59,6 +101,8 @@ impl<'a> CmapTable<'a> {
             let encoding_id = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
             let offset = try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof));
             match (platform_id, encoding_id) {
+                // This subtable only maps variation sequences, not individual characters.
+                (PLATFORM_ID_UNICODE, UNICODE_ENCODING_ID_VARIATION_SEQUENCES) => {}
                 (PLATFORM_ID_UNICODE, _) |
                 (PLATFORM_ID_MICROSOFT, MICROSOFT_ENCODING_ID_UNICODE_BMP) |
                 (PLATFORM_ID_MICROSOFT, MICROSOFT_ENCODING_ID_UNICODE_UCS4) => {
@@ -76,19 +120,108 @@ impl<'a> CmapTable<'a> {
             return Err(Error::UnsupportedCmapEncoding)
         }
 
-        // Check the mapping table format.
-        let format = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
-        match format {
-            FORMAT_SEGMENT_MAPPING_TO_DELTA_VALUES => {
-                self.glyph_mapping_for_codepoint_ranges_segment_mapping_format(cmap_reader,
-                                                                               codepoint_ranges)
+        Ok(cmap_reader)
+    }
+
+    /// Returns the glyph that the format 14 subtable maps the given variation sequence to, if it
+    /// maps it to a glyph other than that of the base character alone.
+    ///
+    /// Returns `None` if the font has no such subtable, if the sequence is unknown, or if the
+    /// sequence uses the default glyph of the base character.
+    pub fn glyph_for_variation_sequence(&self, base: u32, selector: u32)
+                                        -> Result<Option<u16>, Error> {
+        let mut cmap_reader = self.table.bytes;
+
+        // Check version.
+        if try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof)) != 0 {
+            return Err(Error::UnsupportedCmapVersion)
+        }
+
+        // Find the Unicode Variation Sequences subtable.
+        let num_tables = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let mut subtable_offset = None;
+        for _ in 0..num_tables {
+            let platform_id = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let encoding_id = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let offset = try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof));
+            if (platform_id, encoding_id) == (PLATFORM_ID_UNICODE,
+                                              UNICODE_ENCODING_ID_VARIATION_SEQUENCES) {
+                subtable_offset = Some(offset as usize);
+                break
             }
-            FORMAT_SEGMENTED_COVERAGE => {
-                self.glyph_mapping_for_codepoint_ranges_segmented_coverage(cmap_reader,
-                                                                           codepoint_ranges)
+        }
+
+        let subtable = match subtable_offset {
+            None => return Ok(None),
+            Some(subtable_offset) => {
+                let mut subtable = self.table.bytes;
+                try!(subtable.jump(subtable_offset).map_err(Error::eof));
+                subtable
             }
-            _ => Err(Error::UnsupportedCmapFormat),
+        };
+
+        let mut reader = subtable;
+        if try!(reader.read_u16::<BigEndian>().map_err(Error::eof)) !=
+                FORMAT_UNICODE_VARIATION_SEQUENCES {
+            return Err(Error::UnsupportedCmapFormat)
         }
+        let _length = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let num_var_selector_records = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+
+        // Binary search for the variation selector. Each record is a 24-bit selector followed by
+        // the offsets of the default and non-default mappings.
+        let (mut low, mut high) = (0, num_var_selector_records);
+        let mut non_default_uvs_offset = None;
+        while low < high {
+            let mid = (low + high) / 2;
+
+            let mut record = reader;
+            try!(record.jump(mid as usize * VAR_SELECTOR_RECORD_SIZE).map_err(Error::eof));
+            let var_selector = try!(read_u24(&mut record));
+            if selector < var_selector {
+                high = mid
+            } else if selector > var_selector {
+                low = mid + 1
+            } else {
+                // Sequences using the default glyph don't need to be looked up, since the caller
+                // falls back to the base character anyway.
+                let _default_uvs_offset = try!(record.read_u32::<BigEndian>()
+                                                     .map_err(Error::eof));
+                non_default_uvs_offset = Some(try!(record.read_u32::<BigEndian>()
+                                                         .map_err(Error::eof)));
+                break
+            }
+        }
+
+        let mut reader = match non_default_uvs_offset {
+            None | Some(0) => return Ok(None),
+            Some(non_default_uvs_offset) => {
+                let mut reader = subtable;
+                try!(reader.jump(non_default_uvs_offset as usize).map_err(Error::eof));
+                reader
+            }
+        };
+
+        // Binary search for the base character among the non-default mappings, each of which is a
+        // 24-bit character followed by a glyph ID.
+        let num_uvs_mappings = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let (mut low, mut high) = (0, num_uvs_mappings);
+        while low < high {
+            let mid = (low + high) / 2;
+
+            let mut mapping = reader;
+            try!(mapping.jump(mid as usize * UVS_MAPPING_SIZE).map_err(Error::eof));
+            let unicode_value = try!(read_u24(&mut mapping));
+            if base < unicode_value {
+                high = mid
+            } else if base > unicode_value {
+                low = mid + 1
+            } else {
+                return Ok(Some(try!(mapping.read_u16::<BigEndian>().map_err(Error::eof))))
+            }
+        }
+
+        Ok(None)
     }
 
     fn glyph_mapping_for_codepoint_ranges_segment_mapping_format(
@@ -197,7 +330,7 @@ impl<'a> CmapTable<'a> {
                 let id_delta = try!(id_delta.read_i16::<BigEndian>().map_err(Error::eof));
 
                 end_codepoint_range = cmp::min(end_codepoint_range, end_code);
-                codepoint_range.start = (end_codepoint_range + 1) as u32;
+                codepoint_range.start = end_codepoint_range as u32 + 1;
 
                 let start_code_offset = start_codepoint_range - start_code;
                 let end_code_offset = end_codepoint_range - start_code;
@@ -318,6 +451,110 @@ impl<'a> CmapTable<'a> {
 
         Ok(glyph_mapping)
     }
+
+    fn coverage_segment_mapping_format(&self, mut cmap_reader: &[u8])
+                                       -> Result<Vec<CodepointRange>, Error> {
+        // Read the mapping table header.
+        let _length = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let _language = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let seg_count = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof)) / 2;
+        try!(cmap_reader.jump(mem::size_of::<u16>() * 3).map_err(Error::eof));
+
+        // Set up parallel array pointers, as in the lookup above.
+        let (mut end_codes, mut start_codes) = (cmap_reader, cmap_reader);
+        try!(start_codes.jump((seg_count as usize + 1) * mem::size_of::<u16>())
+                        .map_err(Error::eof));
+        let mut id_deltas = start_codes;
+        try!(id_deltas.jump(seg_count as usize * mem::size_of::<u16>()).map_err(Error::eof));
+        let mut id_range_offsets = id_deltas;
+        try!(id_range_offsets.jump(seg_count as usize * mem::size_of::<u16>())
+                             .map_err(Error::eof));
+        let id_range_offsets_start = id_range_offsets;
+
+        let mut ranges = vec![];
+        for segment_index in 0..seg_count {
+            let end_code = try!(end_codes.read_u16::<BigEndian>().map_err(Error::eof)) as u32;
+            let start_code = try!(start_codes.read_u16::<BigEndian>().map_err(Error::eof)) as u32;
+            let id_delta = try!(id_deltas.read_i16::<BigEndian>().map_err(Error::eof));
+            let id_range_offset = try!(id_range_offsets.read_u16::<BigEndian>()
+                                                       .map_err(Error::eof));
+            if start_code > end_code {
+                continue
+            }
+
+            if id_range_offset == 0 {
+                // Every code in the segment is mapped except the one, if any, that the delta
+                // wraps around to the missing glyph.
+                let missing_code = 0u16.wrapping_sub(id_delta as u16) as u32;
+                if missing_code < start_code || missing_code > end_code {
+                    ranges.push(CodepointRange::new(start_code, end_code))
+                } else {
+                    if missing_code > start_code {
+                        ranges.push(CodepointRange::new(start_code, missing_code - 1))
+                    }
+                    if missing_code < end_code {
+                        ranges.push(CodepointRange::new(missing_code + 1, end_code))
+                    }
+                }
+                continue
+            }
+
+            // Otherwise, look up the glyphs individually.
+            for code in start_code..(end_code + 1) {
+                let mut reader = id_range_offsets_start;
+                try!(reader.jump(segment_index as usize * 2 +
+                                 (code - start_code) as usize * 2 +
+                                 id_range_offset as usize).map_err(Error::eof));
+                let glyph_id = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+                if glyph_id != MISSING_GLYPH &&
+                        (glyph_id as i16).wrapping_add(id_delta) as u16 != MISSING_GLYPH {
+                    ranges.push(CodepointRange::new(code, code))
+                }
+            }
+        }
+
+        Ok(ranges)
+    }
+
+    fn coverage_segmented_coverage(&self, mut cmap_reader: &[u8])
+                                   -> Result<Vec<CodepointRange>, Error> {
+        let _reserved = try!(cmap_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let _length = try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let _language = try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let num_groups = try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof));
+
+        let mut ranges = vec![];
+        for _ in 0..num_groups {
+            let segment = Segment {
+                start_char_code: try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof)),
+                end_char_code: try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof)),
+                start_glyph_id: try!(cmap_reader.read_u32::<BigEndian>().map_err(Error::eof)),
+            };
+
+            // Only the first code of a group can map to the missing glyph. Groups are sorted, so
+            // if that's the largest code there is, no codes remain.
+            let start_char_code = if segment.start_glyph_id == MISSING_GLYPH as u32 {
+                match segment.start_char_code.checked_add(1) {
+                    Some(start_char_code) => start_char_code,
+                    None => break,
+                }
+            } else {
+                segment.start_char_code
+            };
+            let end_char_code = cmp::min(segment.end_char_code, char::MAX as u32);
+            if start_char_code <= end_char_code {
+                ranges.push(CodepointRange::new(start_char_code, end_char_code))
+            }
+        }
+
+        Ok(ranges)
+    }
+}
+
+fn read_u24(reader: &mut &[u8]) -> Result<u32, Error> {
+    let high = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let low = try!(reader.read_u8().map_err(Error::eof));
+    Ok(((high as u32) << 8) | (low as u32))
 }
 
 #[derive(Clone, Copy)]
@@ -0,0 +1,102 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+// The sizes of a base glyph record and of a layer record.
+const BASE_GLYPH_RECORD_SIZE: usize = 6;
+const LAYER_RECORD_SIZE: usize = 4;
+
+// The palette entry index that means the text color.
+const FOREGROUND_PALETTE_ENTRY: u16 = 0xffff;
+
+/// The color table, which describes color glyphs as stacks of layers, each of which is another
+/// glyph drawn in a color from the palette table.
+///
+/// Only the version 0 layer records are read. Version 1 tables start with the same records, which
+/// serve as a fallback for their gradients.
+#[derive(Clone, Copy, Debug)]
+pub struct ColrTable<'a> {
+    table: FontTable<'a>,
+    num_base_glyph_records: u16,
+    base_glyph_records_offset: u32,
+    layer_records_offset: u32,
+    num_layer_records: u16,
+}
+
+impl<'a> ColrTable<'a> {
+    pub fn new(table: FontTable) -> Result<ColrTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version > 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        Ok(ColrTable {
+            table: table,
+            num_base_glyph_records: try!(reader.read_u16::<BigEndian>().map_err(Error::eof)),
+            base_glyph_records_offset: try!(reader.read_u32::<BigEndian>().map_err(Error::eof)),
+            layer_records_offset: try!(reader.read_u32::<BigEndian>().map_err(Error::eof)),
+            num_layer_records: try!(reader.read_u16::<BigEndian>().map_err(Error::eof)),
+        })
+    }
+
+    /// Calls the given callback with the glyph ID and palette entry index of each layer of the
+    /// given glyph, from bottom to top. The palette entry index is `None` for layers drawn in the
+    /// text color. Glyphs without layers produce no calls.
+    pub fn for_each_layer<F>(&self, glyph_id: u16, mut callback: F) -> Result<(), Error>
+                             where F: FnMut(u16, Option<u16>) {
+        // Binary search for the base glyph record. These are sorted by glyph ID.
+        let (mut low, mut high) = (0, self.num_base_glyph_records as usize);
+        let mut layers = None;
+        while low < high {
+            let mid = (low + high) / 2;
+
+            let mut record = self.table.bytes;
+            try!(record.jump(self.base_glyph_records_offset as usize +
+                             mid * BASE_GLYPH_RECORD_SIZE).map_err(Error::eof));
+            let base_glyph_id = try!(record.read_u16::<BigEndian>().map_err(Error::eof));
+            if glyph_id < base_glyph_id {
+                high = mid
+            } else if glyph_id > base_glyph_id {
+                low = mid + 1
+            } else {
+                let first_layer_index = try!(record.read_u16::<BigEndian>().map_err(Error::eof));
+                let num_layers = try!(record.read_u16::<BigEndian>().map_err(Error::eof));
+                layers = Some((first_layer_index as usize, num_layers as usize));
+                break
+            }
+        }
+
+        let (first_layer_index, num_layers) = match layers {
+            None => return Ok(()),
+            Some(layers) => layers,
+        };
+        if first_layer_index + num_layers > self.num_layer_records as usize {
+            return Err(Error::Failed)
+        }
+
+        let mut reader = self.table.bytes;
+        try!(reader.jump(self.layer_records_offset as usize +
+                         first_layer_index * LAYER_RECORD_SIZE).map_err(Error::eof));
+        for _ in 0..num_layers {
+            let layer_glyph_id = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let palette_entry_index = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if palette_entry_index == FOREGROUND_PALETTE_ENTRY {
+                callback(layer_glyph_id, None)
+            } else {
+                callback(layer_glyph_id, Some(palette_entry_index))
+            }
+        }
+        Ok(())
+    }
+}
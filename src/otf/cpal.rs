@@ -0,0 +1,70 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+// The size of a color record, in blue, green, red, alpha order.
+const COLOR_RECORD_SIZE: usize = 4;
+
+/// The color palette table, which holds one or more palettes of colors for color glyphs.
+#[derive(Clone, Copy, Debug)]
+pub struct CpalTable<'a> {
+    table: FontTable<'a>,
+    num_palette_entries: u16,
+    pub num_palettes: u16,
+    num_color_records: u16,
+    color_records_offset: u32,
+}
+
+impl<'a> CpalTable<'a> {
+    pub fn new(table: FontTable) -> Result<CpalTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version > 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        Ok(CpalTable {
+            table: table,
+            num_palette_entries: try!(reader.read_u16::<BigEndian>().map_err(Error::eof)),
+            num_palettes: try!(reader.read_u16::<BigEndian>().map_err(Error::eof)),
+            num_color_records: try!(reader.read_u16::<BigEndian>().map_err(Error::eof)),
+            color_records_offset: try!(reader.read_u32::<BigEndian>().map_err(Error::eof)),
+        })
+    }
+
+    /// Returns the color of the given entry of the given palette in straight RGBA order, or
+    /// `None` if the entry is out of range.
+    pub fn color(&self, palette_index: u16, entry_index: u16) -> Result<Option<[u8; 4]>, Error> {
+        if palette_index >= self.num_palettes || entry_index >= self.num_palette_entries {
+            return Ok(None)
+        }
+
+        // The header is 12 bytes long and is followed by the index of each palette's first color.
+        let mut reader = self.table.bytes;
+        try!(reader.jump(12 + palette_index as usize * 2).map_err(Error::eof));
+        let first_color_index = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let color_index = first_color_index as usize + entry_index as usize;
+        if color_index >= self.num_color_records as usize {
+            return Err(Error::Failed)
+        }
+
+        let mut reader = self.table.bytes;
+        try!(reader.jump(self.color_records_offset as usize + color_index * COLOR_RECORD_SIZE)
+                   .map_err(Error::eof));
+        let blue = try!(reader.read_u8().map_err(Error::eof));
+        let green = try!(reader.read_u8().map_err(Error::eof));
+        let red = try!(reader.read_u8().map_err(Error::eof));
+        let alpha = try!(reader.read_u8().map_err(Error::eof));
+        Ok(Some([red, green, blue, alpha]))
+    }
+}
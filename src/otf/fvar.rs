@@ -0,0 +1,127 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable, Tag};
+use util::Jump;
+
+// The flag that asks for an axis to be left out of user interfaces.
+const HIDDEN_AXIS: u16 = 0x0001;
+
+// The size of an axis record in version 1.0 of the table.
+const AXIS_RECORD_SIZE: u16 = 20;
+
+/// An axis along which the glyphs of a variable font vary, such as weight or width, as returned
+/// by `Font::variation_axes()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VariationAxis {
+    /// The tag of the axis, such as `otf::tag(b"wght")`.
+    pub tag: Tag,
+    /// The smallest value that the axis takes.
+    pub min_value: f32,
+    /// The value that the axis takes unless `Font::set_variations()` says otherwise.
+    pub default_value: f32,
+    /// The largest value that the axis takes.
+    pub max_value: f32,
+    /// Whether the font asks for the axis to be left out of user interfaces.
+    pub hidden: bool,
+}
+
+/// The font variations table, which lists the axes of a variable font.
+#[derive(Clone, Copy, Debug)]
+pub struct FvarTable<'a> {
+    table: FontTable<'a>,
+    axes_offset: u16,
+    axis_count: u16,
+    axis_size: u16,
+}
+
+impl<'a> FvarTable<'a> {
+    pub fn new(table: FontTable) -> Result<FvarTable, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if major_version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Skip over the minor version.
+        try!(reader.jump(2).map_err(Error::eof));
+        let axes_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        // Skip over the reserved field.
+        try!(reader.jump(2).map_err(Error::eof));
+        let axis_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let axis_size = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if axis_size < AXIS_RECORD_SIZE {
+            return Err(Error::Failed)
+        }
+
+        Ok(FvarTable {
+            table: table,
+            axes_offset: axes_offset,
+            axis_count: axis_count,
+            axis_size: axis_size,
+        })
+    }
+
+    /// Returns the axes of the font, in the order that normalized coordinates are given in.
+    pub fn axes(&self) -> Result<Vec<VariationAxis>, Error> {
+        let mut axes = Vec::with_capacity(self.axis_count as usize);
+        for axis_index in 0..self.axis_count {
+            let mut reader = self.table.bytes;
+            try!(reader.jump(self.axes_offset as usize +
+                             axis_index as usize * self.axis_size as usize).map_err(Error::eof));
+            let tag = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+            let min_value = try!(reader.read_i32::<BigEndian>().map_err(Error::eof));
+            let default_value = try!(reader.read_i32::<BigEndian>().map_err(Error::eof));
+            let max_value = try!(reader.read_i32::<BigEndian>().map_err(Error::eof));
+            let flags = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            axes.push(VariationAxis {
+                tag: tag,
+                min_value: min_value as f32 / 65536.0,
+                default_value: default_value as f32 / 65536.0,
+                max_value: max_value as f32 / 65536.0,
+                hidden: (flags & HIDDEN_AXIS) != 0,
+            })
+        }
+        Ok(axes)
+    }
+
+    /// Returns the normalized coordinates, from -1 to 1, of the instance with the given values
+    /// along each axis. Axes that aren't given keep their default values, which normalize to 0,
+    /// and values outside the range of an axis are clamped to it.
+    ///
+    /// This is the default normalization only; `avar` adjusts it further.
+    pub fn normalized_coordinates(&self, variations: &[(Tag, f32)]) -> Result<Vec<f32>, Error> {
+        let axes = try!(self.axes());
+        Ok(axes.iter().map(|axis| {
+            let value = match variations.iter().rev().find(|&&(tag, _)| tag == axis.tag) {
+                None => return 0.0,
+                Some(&(_, value)) => value.max(axis.min_value).min(axis.max_value),
+            };
+
+            let coordinate = if value < axis.default_value && axis.default_value > axis.min_value {
+                (value - axis.default_value) / (axis.default_value - axis.min_value)
+            } else if value > axis.default_value && axis.max_value > axis.default_value {
+                (value - axis.default_value) / (axis.max_value - axis.default_value)
+            } else {
+                0.0
+            };
+            round_to_f2dot14(coordinate)
+        }).collect())
+    }
+}
+
+/// Rounds the given value to the nearest 2.14 fixed-point number, the precision of normalized
+/// coordinates.
+#[inline]
+pub fn round_to_f2dot14(value: f32) -> f32 {
+    (value * 16384.0).round() / 16384.0
+}
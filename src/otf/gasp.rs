@@ -0,0 +1,77 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+
+// The flags that version 0 tables may use.
+const VERSION_0_FLAGS: u16 = 0x0003;
+
+bitflags! {
+    /// How a glyph should be rendered at a given size, according to the font's `gasp` table, as
+    /// returned by `Font::gasp_behavior()`.
+    pub flags GaspBehavior: u16 {
+        /// Grid-fit the outline with the font's hinting instructions.
+        const GASP_GRIDFIT = 1 << 0,
+        /// Antialias the edges of the glyph in grayscale.
+        const GASP_DOGRAY = 1 << 1,
+        /// Grid-fit only in the direction perpendicular to the subpixels when rendering with
+        /// subpixel antialiasing.
+        const GASP_SYMMETRIC_GRIDFIT = 1 << 2,
+        /// Smooth along the direction of the subpixels when rendering with subpixel antialiasing.
+        const GASP_SYMMETRIC_SMOOTHING = 1 << 3,
+    }
+}
+
+/// The grid-fitting and scan-conversion procedure table, which tells at which sizes glyphs should
+/// be antialiased or grid-fitted.
+#[derive(Clone, Copy, Debug)]
+pub struct GaspTable<'a> {
+    table: FontTable<'a>,
+    version: u16,
+    num_ranges: u16,
+}
+
+impl<'a> GaspTable<'a> {
+    pub fn new(table: FontTable) -> Result<GaspTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version > 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        let num_ranges = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        Ok(GaspTable {
+            table: table,
+            version: version,
+            num_ranges: num_ranges,
+        })
+    }
+
+    /// Returns the behavior for the given size in pixels per em, or `None` if it's larger than
+    /// every range in the table.
+    pub fn behavior(&self, ppem: u16) -> Result<Option<GaspBehavior>, Error> {
+        // The ranges are sorted by their maximum size, and each starts just above the last.
+        let mut reader = &self.table.bytes[4..];
+        for _ in 0..self.num_ranges {
+            let range_max_ppem = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let mut flags = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if ppem > range_max_ppem {
+                continue
+            }
+
+            if self.version == 0 {
+                flags &= VERSION_0_FLAGS
+            }
+            return Ok(Some(GaspBehavior::from_bits_truncate(flags)))
+        }
+        Ok(None)
+    }
+}
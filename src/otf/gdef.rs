@@ -0,0 +1,74 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::layout;
+use otf::{Error, FontTable};
+
+/// The glyph definition table, which classifies glyphs for layout.
+#[derive(Clone, Copy, Debug)]
+pub struct GdefTable<'a> {
+    glyph_class_def: Option<&'a [u8]>,
+}
+
+impl<'a> GdefTable<'a> {
+    pub fn new(table: FontTable) -> Result<GdefTable, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if major_version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Every minor version begins with the offset to the glyph class definitions, which is
+        // zero if there are none.
+        try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let glyph_class_def_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let glyph_class_def = if glyph_class_def_offset == 0 {
+            None
+        } else {
+            Some(try!(layout::offset_slice(table.bytes, glyph_class_def_offset as u32)))
+        };
+
+        Ok(GdefTable {
+            glyph_class_def: glyph_class_def,
+        })
+    }
+
+    /// Returns the class of the given glyph.
+    pub fn glyph_class(&self, glyph_id: u16) -> Result<GlyphClass, Error> {
+        let glyph_class_def = match self.glyph_class_def {
+            None => return Ok(GlyphClass::Unassigned),
+            Some(glyph_class_def) => glyph_class_def,
+        };
+
+        Ok(match try!(layout::glyph_class(glyph_class_def, glyph_id)) {
+            1 => GlyphClass::Base,
+            2 => GlyphClass::Ligature,
+            3 => GlyphClass::Mark,
+            4 => GlyphClass::Component,
+            _ => GlyphClass::Unassigned,
+        })
+    }
+}
+
+/// The role of a glyph in layout, as the font's `GDEF` table defines it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GlyphClass {
+    /// The font doesn't classify the glyph.
+    Unassigned,
+    /// A single character, spacing glyph, such as a letter or digit.
+    Base,
+    /// A spacing glyph that represents several characters, such as an "fi" ligature.
+    Ligature,
+    /// A non-spacing combining glyph, such as an accent, that attaches to the preceding base.
+    Mark,
+    /// Part of a single character that is drawn with several glyphs.
+    Component,
+}
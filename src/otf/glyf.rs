@@ -11,12 +11,19 @@
 use byteorder::{BigEndian, ReadBytesExt};
 use euclid::Point2D;
 use otf::head::HeadTable;
+#[cfg(feature = "hinting")]
+use otf::hint::{self, GlyphOutline, Hinter};
 use otf::loca::LocaTable;
-use otf::{Error, FontTable};
-use outline::GlyphBounds;
+use otf::{Error, FontTable, ParseLimits};
+use outline::{GlyphBounds, GlyphFixedBounds};
+use std::cmp;
 use std::mem;
 use std::ops::Mul;
-use util::Jump;
+use std::u32;
+use util::{self, Jump};
+
+// The default maximum nesting depth of composite glyphs, to guard against cycles.
+pub const MAX_COMPONENT_DEPTH: u32 = 8;
 
 const F2DOT14_ZERO: F2Dot14 = F2Dot14(0);
 const F2DOT14_ONE:  F2Dot14 = F2Dot14(0b0100_0000_0000_0000);
@@ -55,13 +62,17 @@ pub struct Point {
 #[derive(Clone, Copy, Debug)]
 pub struct GlyfTable<'a> {
     pub table: FontTable<'a>,
+    max_points_per_glyph: u32,
+    max_component_depth: u32,
 }
 
 impl<'a> GlyfTable<'a> {
     #[inline]
-    pub fn new(table: FontTable) -> GlyfTable {
+    pub fn new<'b>(table: FontTable<'b>, limits: &ParseLimits) -> GlyfTable<'b> {
         GlyfTable {
             table: table,
+            max_points_per_glyph: limits.max_points_per_glyph,
+            max_component_depth: limits.max_component_depth,
         }
     }
 
@@ -81,34 +92,92 @@ impl<'a> GlyfTable<'a> {
             Some(offset) => try!(reader.jump(offset as usize).map_err(Error::eof)),
         }
 
-        let glyph_start = reader;
-        let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
-        if number_of_contours >= 0 {
-            self.for_each_point_in_simple_glyph(glyph_start, callback)
-        } else {
-            self.for_each_point_in_composite_glyph(glyph_start, head_table, loca_table, callback)
+        self.for_each_point_in_glyph(reader, head_table, loca_table, 0, &mut callback)
+    }
+
+    // Calls the given callback with each point of the glyph whose data starts at the given reader.
+    // Components of composite glyphs may themselves be composite, up to the maximum component
+    // depth.
+    fn for_each_point_in_glyph(&self,
+                               reader: &[u8],
+                               head_table: &HeadTable,
+                               loca_table: &LocaTable,
+                               depth: u32,
+                               callback: &mut FnMut(&Point))
+                               -> Result<(), Error> {
+        // A glyph with no contours is empty, even if it has hinting instructions.
+        let mut header = reader;
+        let number_of_contours = try!(header.read_i16::<BigEndian>().map_err(Error::eof));
+        match number_of_contours {
+            0 => Ok(()),
+            number_of_contours if number_of_contours > 0 => {
+                self.for_each_point_in_simple_glyph(reader, |point| callback(point))
+            }
+            _ => {
+                self.for_each_point_in_composite_glyph(reader,
+                                                       head_table,
+                                                       loca_table,
+                                                       depth,
+                                                       callback)
+            }
         }
     }
 
-    fn for_each_point_in_simple_glyph<F>(&self, mut reader: &[u8], mut callback: F)
+    fn for_each_point_in_simple_glyph<F>(&self, reader: &[u8], mut callback: F)
                                          -> Result<(), Error> where F: FnMut(&Point) {
+        let mut contour = ContourEmitter::new();
+        try!(self.for_each_stored_point(reader, |position, on_curve, last_in_contour| {
+            contour.push(position, on_curve, &mut callback);
+            if last_in_contour {
+                mem::replace(&mut contour, ContourEmitter::new()).close(&mut callback)
+            }
+        }));
+        Ok(())
+    }
+
+    // Calls the given callback with the position of each point of the simple glyph whose data
+    // starts at the given reader, exactly as the points are stored, whether the point is on the
+    // curve, and whether it ends its contour. Returns the glyph's instructions.
+    //
+    // A glyph with no contours has no points, but may still have instructions. A composite glyph
+    // fails with `Error::CompositeGlyph`, and contour end points that decrease fail with
+    // `Error::MalformedGlyph`.
+    fn for_each_stored_point<'b, F>(&self, mut reader: &'b [u8], mut callback: F)
+                                    -> Result<&'b [u8], Error>
+                                    where F: FnMut(Point2D<i16>, bool, bool) {
         // Determine how many contours we have.
         let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
-        if number_of_contours == 0 {
-            return Ok(())
+        if number_of_contours < 0 {
+            return Err(Error::CompositeGlyph)
         }
 
         // Skip over the rest of the header.
         try!(reader.jump(mem::size_of::<i16>() * 4).map_err(Error::eof));
 
+        if number_of_contours == 0 {
+            // Empty glyphs are often cut off after the header, so treat missing instructions as
+            // none at all.
+            let instruction_length = match reader.read_u16::<BigEndian>() {
+                Ok(instruction_length) => instruction_length as usize,
+                Err(_) => return Ok(&[]),
+            };
+            return Ok(reader.get(0..instruction_length).unwrap_or(&[]))
+        }
+
         // Find out how many points we have.
         let mut endpoints_reader = reader;
         try!(reader.jump(mem::size_of::<u16>() as usize * (number_of_contours as usize - 1))
                    .map_err(Error::eof));
-        let number_of_points = try!(reader.read_u16::<BigEndian>().map_err(Error::eof)) + 1;
+        let number_of_points = try!(reader.read_u16::<BigEndian>().map_err(Error::eof)) as u32 +
+            1;
+        if number_of_points > self.max_points_per_glyph {
+            return Err(Error::TooManyPoints)
+        }
 
         // Skip over hinting instructions.
         let instruction_length = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let instructions = try!(reader.get(0..instruction_length as usize)
+                                      .ok_or(Error::UnexpectedEof));
         try!(reader.jump(instruction_length as usize).map_err(Error::eof));
 
         // Find the offsets of the X and Y coordinates.
@@ -122,16 +191,15 @@ impl<'a> GlyfTable<'a> {
         try!(reader.jump(x_coordinate_length as usize).map_err(Error::eof));
         let mut y_coordinate_reader = reader;
 
-        // Now parse the contours.
+        // Now parse the contours. Each ends after the last, though an empty contour, whose end
+        // point repeats the last one, is harmless.
         let (mut position, mut point_index) = (Point2D::new(0, 0), 0);
         for _ in 0..number_of_contours {
-            let contour_point_count = try!(endpoints_reader.read_u16::<BigEndian>()
-                                                           .map_err(Error::eof)) - point_index + 1;
-
-            let mut first_on_curve_point = None;
-            let mut initial_off_curve_point = None;
-            let mut last_point_was_off_curve = false;
-            let mut point_index_in_contour = 0;
+            let endpoint = try!(endpoints_reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if endpoint as u32 + 1 < point_index {
+                return Err(Error::MalformedGlyph)
+            }
+            let contour_point_count = endpoint as u32 + 1 - point_index;
 
             for contour_point_index in 0..contour_point_count {
                 let flags = SimpleFlags::from_bits_truncate(*flag_parser.current);
@@ -155,88 +223,79 @@ impl<'a> GlyfTable<'a> {
                     delta.y = try!(y_coordinate_reader.read_i16::<BigEndian>().map_err(Error::eof))
                 }
 
-                if last_point_was_off_curve && !flags.contains(ON_CURVE) {
-                    let position = position + delta / 2;
-
-                    // An important edge case!
-                    if first_on_curve_point.is_none() {
-                        first_on_curve_point = Some(position)
-                    }
-
-                    callback(&Point {
-                        position: position,
-                        index_in_contour: point_index_in_contour,
-                        on_curve: true,
-                    });
-                    point_index_in_contour += 1
-                }
-
                 position = position + delta;
-
-                if flags.contains(ON_CURVE) && first_on_curve_point.is_none() {
-                    first_on_curve_point = Some(position)
-                }
-
-                // Sometimes the initial point is an off curve point. In that case, save it so we
-                // can emit it later when closing the path.
-                if !flags.contains(ON_CURVE) && first_on_curve_point.is_none() {
-                    debug_assert!(initial_off_curve_point.is_none());
-                    initial_off_curve_point = Some(position)
-                } else {
-                    callback(&Point {
-                        position: position,
-                        on_curve: flags.contains(ON_CURVE),
-                        index_in_contour: point_index_in_contour,
-                    });
-                    point_index_in_contour += 1
-                }
-
-                last_point_was_off_curve = !flags.contains(ON_CURVE);
+                callback(position,
+                         flags.contains(ON_CURVE),
+                         contour_point_index + 1 == contour_point_count);
                 point_index += 1;
             }
+        }
 
-            // We're about to close the path. Emit the initial off curve point if there was one.
-            if let Some(initial_off_curve_point) = initial_off_curve_point {
-                if last_point_was_off_curve {
-                    // Another important edge case!
-                    let position = position + (initial_off_curve_point - position) / 2;
-                    callback(&Point {
-                        position: position,
-                        index_in_contour: point_index_in_contour,
-                        on_curve: true,
-                    });
-                    point_index_in_contour += 1
-                }
+        Ok(instructions)
+    }
 
-                callback(&Point {
-                    position: initial_off_curve_point,
-                    on_curve: false,
-                    index_in_contour: point_index_in_contour,
-                });
-                point_index_in_contour += 1
-            }
+    // TODO(pcwalton): Consider rasterizing pieces of composite glyphs independently and
+    // compositing them together.
+    fn for_each_point_in_composite_glyph(&self,
+                                         reader: &[u8],
+                                         head_table: &HeadTable,
+                                         loca_table: &LocaTable,
+                                         depth: u32,
+                                         callback: &mut FnMut(&Point))
+                                         -> Result<(), Error> {
+        if depth >= self.max_component_depth {
+            return Err(Error::ComponentsTooDeep)
+        }
+        try!(self.check_component_point_count(reader, head_table, loca_table));
+
+        self.for_each_component(reader, head_table, loca_table, |reader, _, transform, _| {
+            self.for_each_point_in_glyph(reader,
+                                         head_table,
+                                         loca_table,
+                                         depth + 1,
+                                         &mut |point| callback(&transform.transform(point)))
+        })
+    }
 
-            // Close the path.
-            if let Some(first_on_curve_point) = first_on_curve_point {
-                callback(&Point {
-                    position: first_on_curve_point,
-                    on_curve: true,
-                    index_in_contour: point_index_in_contour,
-                })
-            }
+    // Fails if the simple glyphs among the components of the given composite glyph have more
+    // points together than the limit allows.
+    fn check_component_point_count(&self,
+                                   reader: &[u8],
+                                   head_table: &HeadTable,
+                                   loca_table: &LocaTable)
+                                   -> Result<(), Error> {
+        if self.max_points_per_glyph == u32::MAX {
+            return Ok(())
         }
 
-        Ok(())
+        let mut point_count = 0u32;
+        self.for_each_component(reader, head_table, loca_table, |mut reader, _, _, _| {
+            let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+            if number_of_contours > 0 {
+                let endpoint_offset = mem::size_of::<i16>() * 4 +
+                    mem::size_of::<u16>() * (number_of_contours as usize - 1);
+                try!(reader.jump(endpoint_offset).map_err(Error::eof));
+                let last_endpoint = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+                point_count = point_count.saturating_add(last_endpoint as u32 + 1);
+                if point_count > self.max_points_per_glyph {
+                    return Err(Error::TooManyPoints)
+                }
+            }
+            Ok(())
+        })
     }
 
-    // TODO(pcwalton): Consider rasterizing pieces of composite glyphs independently and
-    // compositing them together.
-    fn for_each_point_in_composite_glyph<F>(&self,
-                                            mut reader: &[u8],
-                                            head_table: &HeadTable,
-                                            loca_table: &LocaTable,
-                                            mut callback: F)
-                                            -> Result<(), Error> where F: FnMut(&Point) {
+    // Calls the given callback with a reader positioned at the start of each component glyph's
+    // data, along with the component's glyph ID, the transform to apply to its points, and its
+    // flags.
+    fn for_each_component<F>(&self,
+                             mut reader: &[u8],
+                             head_table: &HeadTable,
+                             loca_table: &LocaTable,
+                             mut callback: F)
+                             -> Result<(), Error>
+                             where F: FnMut(&[u8], u16, &Mat3x2, CompositeFlags)
+                                            -> Result<(), Error> {
         try!(reader.jump(mem::size_of::<i16>() * 5).map_err(Error::eof));
 
         loop {
@@ -276,9 +335,7 @@ impl<'a> GlyfTable<'a> {
             if let Some(offset) = try!(loca_table.location_of(head_table, glyph_index)) {
                 let mut reader = self.table.bytes;
                 try!(reader.jump(offset as usize).map_err(Error::eof));
-                self.for_each_point_in_simple_glyph(reader, |point| {
-                    callback(&transform.transform(&point))
-                });
+                try!(callback(reader, glyph_index, &transform, flags));
             }
 
             if !flags.contains(MORE_COMPONENTS) {
@@ -289,6 +346,201 @@ impl<'a> GlyfTable<'a> {
         Ok(())
     }
 
+    /// Calls the given callback with each point of the given glyph after grid-fitting it with the
+    /// given hinter, in font units, as `for_each_point()` does for the original outline.
+    ///
+    /// `phantom_points` returns the phantom points of the glyph with the given ID, whose bounds
+    /// begin at the given left edge, in font units. The components of composite glyphs are hinted
+    /// separately and then placed; their offsets are rounded to whole pixels if the component
+    /// asks for it.
+    #[cfg(feature = "hinting")]
+    pub fn for_each_hinted_point<F, P>(&self,
+                                       head_table: &HeadTable,
+                                       loca_table: &LocaTable,
+                                       hinter: &Hinter,
+                                       phantom_points: P,
+                                       glyph_id: u16,
+                                       mut callback: F)
+                                       -> Result<(), Error>
+                                       where F: FnMut(&Point),
+                                             P: Fn(u16, i16) -> Result<[Point2D<i32>; 4], Error> {
+        let mut reader = self.table.bytes;
+
+        match try!(loca_table.location_of(head_table, glyph_id)) {
+            None => return Ok(()),
+            Some(offset) => try!(reader.jump(offset as usize).map_err(Error::eof)),
+        }
+
+        let glyph_start = reader;
+        let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        let x_min = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        if number_of_contours >= 0 {
+            let phantom_points = try!(phantom_points(glyph_id, x_min));
+            return self.for_each_hinted_point_in_simple_glyph(glyph_start,
+                                                              hinter,
+                                                              &phantom_points,
+                                                              |x, y| (x, y),
+                                                              &mut callback)
+        }
+
+        // Components are read as simple glyphs, as in `for_each_point()`.
+        if self.max_component_depth == 0 {
+            return Err(Error::ComponentsTooDeep)
+        }
+        try!(self.check_component_point_count(glyph_start, head_table, loca_table));
+
+        self.for_each_component(glyph_start,
+                                head_table,
+                                loca_table,
+                                |mut reader, component_id, transform, flags| {
+            let component_start = reader;
+            let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+            let x_min = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+            if number_of_contours < 0 {
+                return Ok(())
+            }
+
+            let mut offset_x = hint::to_26_6(hinter, transform.m02 as i32);
+            let mut offset_y = hint::to_26_6(hinter, transform.m12 as i32);
+            if flags.contains(ROUND_XY_TO_GRID) {
+                offset_x = hint::round_to_grid(offset_x);
+                offset_y = hint::round_to_grid(offset_y);
+            }
+
+            let phantom_points = try!(phantom_points(component_id, x_min));
+            self.for_each_hinted_point_in_simple_glyph(component_start,
+                                                       hinter,
+                                                       &phantom_points,
+                                                       |x, y| {
+                let (x, y) = transform.transform_fixed(x, y);
+                (x + offset_x, y + offset_y)
+            }, &mut callback)
+        })
+    }
+
+    // Hints the simple glyph whose data starts at the given reader and calls the given callback
+    // with each of its points, after applying the given transform to them in 26.6 fixed-point
+    // pixels and converting them back to font units. If the glyph's instructions fail, the points
+    // of the original outline are used instead.
+    #[cfg(feature = "hinting")]
+    fn for_each_hinted_point_in_simple_glyph<F, T>(&self,
+                                                   reader: &[u8],
+                                                   hinter: &Hinter,
+                                                   phantom_points: &[Point2D<i32>; 4],
+                                                   transform: T,
+                                                   callback: &mut F)
+                                                   -> Result<(), Error>
+                                                   where F: FnMut(&Point),
+                                                         T: Fn(i32, i32) -> (i32, i32) {
+        let mut outline = GlyphOutline {
+            points: vec![],
+            on_curve: vec![],
+            contour_ends: vec![],
+        };
+        let instructions = try!(self.for_each_stored_point(reader, |position,
+                                                                    on_curve,
+                                                                    last_in_contour| {
+            if last_in_contour {
+                outline.contour_ends.push(outline.points.len() as u16)
+            }
+            outline.points.push(Point2D::new(position.x as i32, position.y as i32));
+            outline.on_curve.push(on_curve);
+        }));
+
+        let scale = |point: &Point2D<i32>| {
+            Point2D::new(hint::to_26_6(hinter, point.x), hint::to_26_6(hinter, point.y))
+        };
+        let points = match hint::hint_glyph(hinter, &outline, phantom_points, instructions) {
+            Ok(points) => points,
+            Err(_) => outline.points.iter().chain(phantom_points.iter()).map(scale).collect(),
+        };
+
+        // Keep the glyph's origin where it was, in case hinting moved the first phantom point.
+        let point_count = outline.points.len();
+        let origin_shift = points[point_count].x - scale(&phantom_points[0]).x;
+
+        let mut contour = ContourEmitter::new();
+        let mut contour_ends = outline.contour_ends.iter().peekable();
+        for (point_index, point) in points[0..point_count].iter().enumerate() {
+            let (x, y) = transform(point.x - origin_shift, point.y);
+            let position = Point2D::new(hint::to_font_units(hinter, x),
+                                        hint::to_font_units(hinter, y));
+            contour.push(position, outline.on_curve[point_index], callback);
+            if contour_ends.peek() == Some(&&(point_index as u16)) {
+                contour_ends.next();
+                mem::replace(&mut contour, ContourEmitter::new()).close(callback)
+            }
+        }
+        Ok(())
+    }
+
+    // Calls the given callback with each point of the glyph whose data starts at the given reader,
+    // scaled to 26.6 fixed-point pixels. Components of composite glyphs may themselves be
+    // composite, up to the maximum component depth.
+    fn for_each_fixed_point(&self,
+                            reader: &[u8],
+                            head_table: &HeadTable,
+                            loca_table: &LocaTable,
+                            scale: i32,
+                            depth: u32,
+                            callback: &mut FnMut(i32, i32))
+                            -> Result<(), Error> {
+        let mut header = reader;
+        let number_of_contours = try!(header.read_i16::<BigEndian>().map_err(Error::eof));
+        if number_of_contours >= 0 {
+            return self.for_each_point_in_simple_glyph(reader, |point| {
+                let (x, y) = (point.position.x as i32, point.position.y as i32);
+                callback(util::mul_fix(x, scale), util::mul_fix(y, scale))
+            })
+        }
+
+        if depth >= self.max_component_depth {
+            return Err(Error::ComponentsTooDeep)
+        }
+        try!(self.check_component_point_count(reader, head_table, loca_table));
+
+        self.for_each_component(reader, head_table, loca_table, |reader, _, transform, _| {
+            let offset_x = util::mul_fix(transform.m02 as i32, scale);
+            let offset_y = util::mul_fix(transform.m12 as i32, scale);
+            let mut callback = |x, y| {
+                let (x, y) = transform.transform_fixed(x, y);
+                callback(x + offset_x, y + offset_y)
+            };
+            self.for_each_fixed_point(reader,
+                                      head_table,
+                                      loca_table,
+                                      scale,
+                                      depth + 1,
+                                      &mut callback)
+        })
+    }
+
+    /// Returns the control box of the given glyph's points after scaling them to 26.6 fixed-point
+    /// pixels by the given 16.16 fixed-point factor, or `None` if the glyph has no points.
+    ///
+    /// The points are scaled the same way FreeType scales them when loading an unhinted glyph:
+    /// each coordinate is scaled individually, and the points of each component of a composite
+    /// glyph are scaled and transformed before the scaled component offset is added.
+    pub fn fixed_point_bounds(&self,
+                              head_table: &HeadTable,
+                              loca_table: &LocaTable,
+                              glyph_id: u16,
+                              scale: i32)
+                              -> Result<Option<GlyphFixedBounds>, Error> {
+        let mut reader = self.table.bytes;
+
+        match try!(loca_table.location_of(head_table, glyph_id)) {
+            None => return Ok(None),
+            Some(offset) => try!(reader.jump(offset as usize).map_err(Error::eof)),
+        }
+
+        let mut bounds = None;
+        try!(self.for_each_fixed_point(reader, head_table, loca_table, scale, 0, &mut |x, y| {
+            include_point(&mut bounds, x, y)
+        }));
+        Ok(bounds)
+    }
+
     pub fn glyph_bounds(&self, head_table: &HeadTable, loca_table: &LocaTable, glyph_id: u16)
                         -> Result<GlyphBounds, Error> {
         let mut reader = self.table.bytes;
@@ -306,8 +558,16 @@ impl<'a> GlyfTable<'a> {
             Some(offset) => try!(reader.jump(offset as usize).map_err(Error::eof)),
         }
 
-        // Skip over the number of contours.
-        try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        // A glyph with no contours is empty, whatever its header says.
+        let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        if number_of_contours == 0 {
+            return Ok(GlyphBounds {
+                left: 0,
+                bottom: 0,
+                right: 0,
+                top: 0,
+            })
+        }
 
         let x_min = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
         let y_min = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
@@ -322,18 +582,70 @@ impl<'a> GlyfTable<'a> {
     }
 }
 
+// Grows the given bounds, if any, to include the given point.
+#[inline]
+// Returns the glyph IDs of the components of the glyph with the given data, or none if the glyph
+// is a simple glyph.
+pub fn component_glyph_ids(mut reader: &[u8]) -> Result<Vec<u16>, Error> {
+    let number_of_contours = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+    if number_of_contours >= 0 {
+        return Ok(vec![])
+    }
+    try!(reader.jump(mem::size_of::<i16>() * 4).map_err(Error::eof));
+
+    let mut glyph_ids = vec![];
+    loop {
+        let flags = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let flags = CompositeFlags::from_bits_truncate(flags);
+        glyph_ids.push(try!(reader.read_u16::<BigEndian>().map_err(Error::eof)));
+
+        // Skip the arguments and the transform.
+        let argument_count = if flags.contains(ARG_1_AND_2_ARE_WORDS) { 2 } else { 1 };
+        let transform_count = if flags.contains(WE_HAVE_A_SCALE) {
+            1
+        } else if flags.contains(WE_HAVE_AN_X_AND_Y_SCALE) {
+            2
+        } else if flags.contains(WE_HAVE_A_TWO_BY_TWO) {
+            4
+        } else {
+            0
+        };
+        try!(reader.jump(mem::size_of::<u16>() * (argument_count + transform_count))
+                   .map_err(Error::eof));
+
+        if !flags.contains(MORE_COMPONENTS) {
+            break
+        }
+    }
+    Ok(glyph_ids)
+}
+
+fn include_point(bounds: &mut Option<GlyphFixedBounds>, x: i32, y: i32) {
+    *bounds = Some(match *bounds {
+        None => GlyphFixedBounds { left: x, bottom: y, right: x, top: y },
+        Some(bounds) => {
+            GlyphFixedBounds {
+                left: cmp::min(bounds.left, x),
+                bottom: cmp::min(bounds.bottom, y),
+                right: cmp::max(bounds.right, x),
+                top: cmp::max(bounds.top, y),
+            }
+        }
+    })
+}
+
 // Given a reader pointing to the start of the list of flags, returns the size in bytes of the list
 // of X coordinates and positions the reader at the start of that list.
 #[inline]
-fn calculate_size_of_x_coordinates<'a, 'b>(reader: &'a mut &'b [u8], number_of_points: u16)
-                                           -> Result<u16, Error> {
+fn calculate_size_of_x_coordinates<'a, 'b>(reader: &'a mut &'b [u8], number_of_points: u32)
+                                           -> Result<u32, Error> {
     let (mut x_coordinate_length, mut points_left) = (0, number_of_points);
     while points_left > 0 {
         let flags = SimpleFlags::from_bits_truncate(try!(reader.read_u8().map_err(Error::eof)));
         let repeat_count = if !flags.contains(REPEAT) {
             1
         } else {
-            try!(reader.read_u8().map_err(Error::eof)) as u16 + 1
+            try!(reader.read_u8().map_err(Error::eof)) as u32 + 1
         };
 
         if flags.contains(X_SHORT_VECTOR) {
@@ -342,12 +654,103 @@ fn calculate_size_of_x_coordinates<'a, 'b>(reader: &'a mut &'b [u8], number_of_p
             x_coordinate_length += repeat_count * 2
         }
 
-        points_left -= repeat_count
+        // A repeat that runs past the last point is cut short.
+        points_left = points_left.saturating_sub(repeat_count)
     }
 
     Ok(x_coordinate_length)
 }
 
+// Turns the points of a contour, as they're stored, into the points that `for_each_point()`
+// reports: an on-curve point is inserted midway between each pair of consecutive off-curve points,
+// a contour that begins off the curve is rotated to begin at its first on-curve point, and the
+// contour ends by repeating its first point.
+struct ContourEmitter {
+    first_on_curve_point: Option<Point2D<i16>>,
+    initial_off_curve_point: Option<Point2D<i16>>,
+    last_position: Point2D<i16>,
+    last_point_was_off_curve: bool,
+    point_index_in_contour: u16,
+}
+
+impl ContourEmitter {
+    #[inline]
+    fn new() -> ContourEmitter {
+        ContourEmitter {
+            first_on_curve_point: None,
+            initial_off_curve_point: None,
+            last_position: Point2D::zero(),
+            last_point_was_off_curve: false,
+            point_index_in_contour: 0,
+        }
+    }
+
+    #[inline]
+    fn push<F>(&mut self, position: Point2D<i16>, on_curve: bool, callback: &mut F)
+               where F: FnMut(&Point) {
+        if self.last_point_was_off_curve && !on_curve {
+            let midpoint = self.last_position + (position - self.last_position) / 2;
+
+            // An important edge case!
+            if self.first_on_curve_point.is_none() {
+                self.first_on_curve_point = Some(midpoint)
+            }
+
+            self.emit(midpoint, true, callback)
+        }
+
+        if on_curve && self.first_on_curve_point.is_none() {
+            self.first_on_curve_point = Some(position)
+        }
+
+        // Sometimes the initial point is an off curve point. In that case, save it so we can emit
+        // it later when closing the path.
+        if !on_curve && self.first_on_curve_point.is_none() {
+            debug_assert!(self.initial_off_curve_point.is_none());
+            self.initial_off_curve_point = Some(position)
+        } else {
+            self.emit(position, on_curve, callback)
+        }
+
+        self.last_position = position;
+        self.last_point_was_off_curve = !on_curve
+    }
+
+    fn close<F>(mut self, callback: &mut F) where F: FnMut(&Point) {
+        // We're about to close the path. Emit the initial off curve point if there was one.
+        if let Some(initial_off_curve_point) = self.initial_off_curve_point {
+            if self.last_point_was_off_curve {
+                // Another important edge case!
+                let position = self.last_position +
+                    (initial_off_curve_point - self.last_position) / 2;
+                self.emit(position, true, callback)
+            }
+
+            self.emit(initial_off_curve_point, false, callback)
+        }
+
+        // Close the path.
+        if let Some(first_on_curve_point) = self.first_on_curve_point {
+            callback(&Point {
+                position: first_on_curve_point,
+                on_curve: true,
+                index_in_contour: self.point_index_in_contour,
+            })
+        }
+    }
+
+    #[inline]
+    fn emit<F>(&mut self, position: Point2D<i16>, on_curve: bool, callback: &mut F)
+               where F: FnMut(&Point) {
+        callback(&Point {
+            position: position,
+            on_curve: on_curve,
+            index_in_contour: self.point_index_in_contour,
+        });
+        self.point_index_in_contour += 1
+    }
+}
+
 struct FlagParser<'a> {
     next: &'a [u8],
     current: &'a u8,
@@ -359,7 +762,7 @@ impl<'a> FlagParser<'a> {
     fn new(buffer: &[u8]) -> Result<FlagParser, Error> {
         let mut parser = FlagParser {
             next: buffer,
-            current: &buffer[0],
+            current: try!(buffer.get(0).ok_or(Error::UnexpectedEof)),
             repeats_left: 0,
         };
         try!(parser.next());
@@ -423,11 +826,25 @@ impl Mat3x2 {
             ..*point
         }
     }
+
+    // Applies the linear part of this transform to a point in 26.6 fixed-point pixels, the way
+    // FreeType's `FT_Vector_Transform()` does.
+    fn transform_fixed(&self, x: i32, y: i32) -> (i32, i32) {
+        (util::mul_fix(x, self.m00.to_16_16()) + util::mul_fix(y, self.m01.to_16_16()),
+         util::mul_fix(x, self.m10.to_16_16()) + util::mul_fix(y, self.m11.to_16_16()))
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
 struct F2Dot14(i16);
 
+impl F2Dot14 {
+    #[inline]
+    fn to_16_16(self) -> i32 {
+        (self.0 as i32) << 2
+    }
+}
+
 impl Mul<i16> for F2Dot14 {
     type Output = i16;
 
@@ -0,0 +1,361 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::layout::{self, LayoutTable, Lookup};
+use otf::{Error, FontTable, Tag};
+use std::mem;
+use util::Jump;
+
+const LOOKUP_TYPE_PAIR_ADJUSTMENT: u16 = 2;
+const LOOKUP_TYPE_MARK_TO_BASE_ATTACHMENT: u16 = 4;
+const LOOKUP_TYPE_EXTENSION: u16 = 9;
+
+bitflags! {
+    flags ValueFormat: u16 {
+        const X_PLACEMENT = 1 << 0,
+        const Y_PLACEMENT = 1 << 1,
+        const X_ADVANCE = 1 << 2,
+        const Y_ADVANCE = 1 << 3,
+        const X_PLACEMENT_DEVICE = 1 << 4,
+        const Y_PLACEMENT_DEVICE = 1 << 5,
+        const X_ADVANCE_DEVICE = 1 << 6,
+        const Y_ADVANCE_DEVICE = 1 << 7,
+    }
+}
+
+/// How far to move a glyph from its default position, in font units.
+///
+/// The placement moves the glyph itself without affecting the glyphs after it; the advance
+/// changes how far the pen moves after drawing the glyph.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct GlyphAdjustment {
+    /// The horizontal offset of the glyph. Positive values move the glyph right.
+    pub x_placement: i32,
+    /// The vertical offset of the glyph. Positive values move the glyph up.
+    pub y_placement: i32,
+    /// The change to the horizontal advance of the glyph.
+    pub x_advance: i32,
+    /// The change to the vertical advance of the glyph.
+    pub y_advance: i32,
+}
+
+impl GlyphAdjustment {
+    #[inline]
+    fn accumulate(&mut self, other: &GlyphAdjustment) {
+        self.x_placement += other.x_placement;
+        self.y_placement += other.y_placement;
+        self.x_advance += other.x_advance;
+        self.y_advance += other.y_advance;
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct GposTable<'a> {
+    layout: LayoutTable<'a>,
+}
+
+impl<'a> GposTable<'a> {
+    pub fn new(table: FontTable) -> Result<GposTable, Error> {
+        Ok(GposTable {
+            layout: try!(LayoutTable::new(table)),
+        })
+    }
+
+    /// `advances` holds the horizontal advance of each glyph in font units.
+    pub fn position(&self, glyphs: &[u16], advances: &[i32], features: &[Tag])
+                    -> Result<Vec<GlyphAdjustment>, Error> {
+        debug_assert!(glyphs.len() == advances.len());
+
+        let mut adjustments = vec![GlyphAdjustment::default(); glyphs.len()];
+        for lookup_index in try!(self.layout.lookup_indices_for_features(features)) {
+            let lookup = try!(self.layout.lookup(lookup_index, LOOKUP_TYPE_EXTENSION));
+            match lookup.lookup_type {
+                LOOKUP_TYPE_PAIR_ADJUSTMENT => {
+                    try!(apply_pair_adjustment_lookup(&lookup, glyphs, &mut adjustments))
+                }
+                LOOKUP_TYPE_MARK_TO_BASE_ATTACHMENT => {
+                    try!(apply_mark_to_base_lookup(&lookup, glyphs, advances, &mut adjustments))
+                }
+                _ => {}
+            }
+        }
+        Ok(adjustments)
+    }
+}
+
+// TODO: Respect the lookup flags. Skipping marks and the like requires the `GDEF` table.
+fn apply_pair_adjustment_lookup(lookup: &Lookup,
+                                glyphs: &[u16],
+                                adjustments: &mut [GlyphAdjustment])
+                                -> Result<(), Error> {
+    let mut position = 0;
+    while position + 1 < glyphs.len() {
+        // The first subtable that applies wins.
+        let mut pair_adjustment = None;
+        for subtable in &lookup.subtables {
+            pair_adjustment = try!(adjust_pair(subtable, glyphs[position], glyphs[position + 1]));
+            if pair_adjustment.is_some() {
+                break
+            }
+        }
+
+        match pair_adjustment {
+            Some((first_adjustment, second_adjustment)) => {
+                adjustments[position].accumulate(&first_adjustment);
+                adjustments[position + 1].accumulate(&second_adjustment);
+
+                // If the second glyph was adjusted, it can't start another pair.
+                if second_adjustment != GlyphAdjustment::default() {
+                    position += 2
+                } else {
+                    position += 1
+                }
+            }
+            None => position += 1,
+        }
+    }
+    Ok(())
+}
+
+fn adjust_pair(subtable: &[u8], first_glyph_id: u16, second_glyph_id: u16)
+               -> Result<Option<(GlyphAdjustment, GlyphAdjustment)>, Error> {
+    let mut reader = subtable;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let coverage_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let coverage = try!(layout::offset_slice(subtable, coverage_offset as u32));
+    let coverage_index = match try!(layout::coverage_index(coverage, first_glyph_id)) {
+        None => return Ok(None),
+        Some(coverage_index) => coverage_index,
+    };
+
+    let first_value_format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let second_value_format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let first_value_format = ValueFormat::from_bits_truncate(first_value_format);
+    let second_value_format = ValueFormat::from_bits_truncate(second_value_format);
+    let value_records_size = value_record_size(first_value_format) +
+        value_record_size(second_value_format);
+
+    match format {
+        1 => {
+            // Find the pair set for the first glyph.
+            let pair_set_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if coverage_index >= pair_set_count {
+                return Err(Error::Failed)
+            }
+            try!(reader.jump(coverage_index as usize * mem::size_of::<u16>())
+                       .map_err(Error::eof));
+            let pair_set_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let mut pair_set_reader = try!(layout::offset_slice(subtable,
+                                                                pair_set_offset as u32));
+
+            // The pair value records are sorted by second glyph, so binary search them.
+            let pair_value_count = try!(pair_set_reader.read_u16::<BigEndian>()
+                                                       .map_err(Error::eof));
+            let record_size = mem::size_of::<u16>() + value_records_size;
+            let (mut low, mut high) = (0, pair_value_count);
+            while low < high {
+                let mid = (low + high) / 2;
+                let mut record_reader = pair_set_reader;
+                try!(record_reader.jump(mid as usize * record_size).map_err(Error::eof));
+                let glyph_id = try!(record_reader.read_u16::<BigEndian>().map_err(Error::eof));
+                if second_glyph_id < glyph_id {
+                    high = mid
+                } else if second_glyph_id > glyph_id {
+                    low = mid + 1
+                } else {
+                    let first_adjustment = try!(read_value_record(&mut record_reader,
+                                                                  first_value_format));
+                    let second_adjustment = try!(read_value_record(&mut record_reader,
+                                                                   second_value_format));
+                    return Ok(Some((first_adjustment, second_adjustment)))
+                }
+            }
+            Ok(None)
+        }
+        2 => {
+            let first_class_def_offset = try!(reader.read_u16::<BigEndian>()
+                                                    .map_err(Error::eof));
+            let second_class_def_offset = try!(reader.read_u16::<BigEndian>()
+                                                     .map_err(Error::eof));
+            let first_class_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let second_class_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+            let first_class_def = try!(layout::offset_slice(subtable,
+                                                            first_class_def_offset as u32));
+            let second_class_def = try!(layout::offset_slice(subtable,
+                                                             second_class_def_offset as u32));
+            let first_class = try!(layout::glyph_class(first_class_def, first_glyph_id));
+            let second_class = try!(layout::glyph_class(second_class_def, second_glyph_id));
+            if first_class >= first_class_count || second_class >= second_class_count {
+                return Err(Error::Failed)
+            }
+
+            let record_index = first_class as usize * second_class_count as usize +
+                second_class as usize;
+            try!(reader.jump(record_index * value_records_size).map_err(Error::eof));
+            let first_adjustment = try!(read_value_record(&mut reader, first_value_format));
+            let second_adjustment = try!(read_value_record(&mut reader, second_value_format));
+            Ok(Some((first_adjustment, second_adjustment)))
+        }
+        _ => Err(Error::UnknownFormat),
+    }
+}
+
+fn apply_mark_to_base_lookup(lookup: &Lookup,
+                             glyphs: &[u16],
+                             advances: &[i32],
+                             adjustments: &mut [GlyphAdjustment])
+                             -> Result<(), Error> {
+    for mark_position in 1..glyphs.len() {
+        for subtable in &lookup.subtables {
+            if try!(attach_mark_to_base(subtable, glyphs, advances, adjustments, mark_position)) {
+                break
+            }
+        }
+    }
+    Ok(())
+}
+
+// Returns true if the mark was attached.
+fn attach_mark_to_base(subtable: &[u8],
+                       glyphs: &[u16],
+                       advances: &[i32],
+                       adjustments: &mut [GlyphAdjustment],
+                       mark_position: usize)
+                       -> Result<bool, Error> {
+    let mut reader = subtable;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    if format != 1 {
+        return Err(Error::UnknownFormat)
+    }
+
+    let mark_coverage_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let base_coverage_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let mark_class_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let mark_array_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let base_array_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+    let mark_coverage = try!(layout::offset_slice(subtable, mark_coverage_offset as u32));
+    let mark_coverage_index = match try!(layout::coverage_index(mark_coverage,
+                                                                glyphs[mark_position])) {
+        None => return Ok(false),
+        Some(mark_coverage_index) => mark_coverage_index,
+    };
+
+    // Find the base, skipping over any other marks attached to it. Without `GDEF`, we treat the
+    // glyphs that this subtable covers as marks.
+    let mut base_position = mark_position - 1;
+    while try!(layout::coverage_index(mark_coverage, glyphs[base_position])).is_some() {
+        if base_position == 0 {
+            return Ok(false)
+        }
+        base_position -= 1
+    }
+
+    let base_coverage = try!(layout::offset_slice(subtable, base_coverage_offset as u32));
+    let base_coverage_index = match try!(layout::coverage_index(base_coverage,
+                                                                glyphs[base_position])) {
+        None => return Ok(false),
+        Some(base_coverage_index) => base_coverage_index,
+    };
+
+    // Look up the mark's class and anchor.
+    let mark_array = try!(layout::offset_slice(subtable, mark_array_offset as u32));
+    let mut mark_array_reader = mark_array;
+    let mark_count = try!(mark_array_reader.read_u16::<BigEndian>().map_err(Error::eof));
+    if mark_coverage_index >= mark_count {
+        return Err(Error::Failed)
+    }
+    try!(mark_array_reader.jump(mark_coverage_index as usize * mem::size_of::<[u16; 2]>())
+                          .map_err(Error::eof));
+    let mark_class = try!(mark_array_reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let mark_anchor_offset = try!(mark_array_reader.read_u16::<BigEndian>()
+                                                   .map_err(Error::eof));
+    if mark_class >= mark_class_count {
+        return Err(Error::Failed)
+    }
+    let mark_anchor = try!(read_anchor(try!(layout::offset_slice(mark_array,
+                                                                 mark_anchor_offset as u32))));
+
+    // Look up the base's anchor for that class.
+    let base_array = try!(layout::offset_slice(subtable, base_array_offset as u32));
+    let mut base_array_reader = base_array;
+    let base_count = try!(base_array_reader.read_u16::<BigEndian>().map_err(Error::eof));
+    if base_coverage_index >= base_count {
+        return Err(Error::Failed)
+    }
+    let base_anchor_index = base_coverage_index as usize * mark_class_count as usize +
+        mark_class as usize;
+    try!(base_array_reader.jump(base_anchor_index * mem::size_of::<u16>()).map_err(Error::eof));
+    let base_anchor_offset = try!(base_array_reader.read_u16::<BigEndian>()
+                                                   .map_err(Error::eof));
+    if base_anchor_offset == 0 {
+        // The base has no anchor for this class of mark.
+        return Ok(false)
+    }
+    let base_anchor = try!(read_anchor(try!(layout::offset_slice(base_array,
+                                                                 base_anchor_offset as u32))));
+
+    // Line the mark's anchor up with the base's, taking into account the pen movement between the
+    // base and the mark.
+    let mut pen_movement = 0;
+    for position in base_position..mark_position {
+        pen_movement += advances[position] + adjustments[position].x_advance
+    }
+    let base_adjustment = adjustments[base_position];
+    let mark_adjustment = &mut adjustments[mark_position];
+    mark_adjustment.x_placement = base_adjustment.x_placement + base_anchor.0 - mark_anchor.0 -
+        pen_movement;
+    mark_adjustment.y_placement = base_adjustment.y_placement + base_anchor.1 - mark_anchor.1;
+    Ok(true)
+}
+
+fn read_value_record(reader: &mut &[u8], value_format: ValueFormat)
+                     -> Result<GlyphAdjustment, Error> {
+    let mut adjustment = GlyphAdjustment::default();
+    if value_format.contains(X_PLACEMENT) {
+        adjustment.x_placement = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as i32
+    }
+    if value_format.contains(Y_PLACEMENT) {
+        adjustment.y_placement = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as i32
+    }
+    if value_format.contains(X_ADVANCE) {
+        adjustment.x_advance = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as i32
+    }
+    if value_format.contains(Y_ADVANCE) {
+        adjustment.y_advance = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as i32
+    }
+
+    // Skip over the device table offsets. We don't apply device adjustments.
+    let device_offset_count = (value_format & (X_PLACEMENT_DEVICE | Y_PLACEMENT_DEVICE |
+                                               X_ADVANCE_DEVICE | Y_ADVANCE_DEVICE)).bits()
+                                                                                   .count_ones();
+    try!(reader.jump(device_offset_count as usize * mem::size_of::<u16>()).map_err(Error::eof));
+    Ok(adjustment)
+}
+
+#[inline]
+fn value_record_size(value_format: ValueFormat) -> usize {
+    value_format.bits().count_ones() as usize * mem::size_of::<u16>()
+}
+
+// Returns the x and y coordinates of the anchor. All anchor formats start with these; we ignore
+// the contour point and device adjustments of formats 2 and 3.
+fn read_anchor(anchor: &[u8]) -> Result<(i32, i32), Error> {
+    let mut reader = anchor;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    if format < 1 || format > 3 {
+        return Err(Error::UnknownFormat)
+    }
+    let x = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+    let y = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+    Ok((x as i32, y as i32))
+}
@@ -0,0 +1,161 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::layout::{self, LayoutTable, Lookup};
+use otf::{Error, FontTable, Tag};
+use std::cmp;
+use std::mem;
+use util::Jump;
+
+const LOOKUP_TYPE_SINGLE: u16 = 1;
+const LOOKUP_TYPE_LIGATURE: u16 = 4;
+const LOOKUP_TYPE_EXTENSION: u16 = 7;
+
+#[derive(Clone, Copy)]
+pub struct GsubTable<'a> {
+    layout: LayoutTable<'a>,
+}
+
+impl<'a> GsubTable<'a> {
+    pub fn new(table: FontTable) -> Result<GsubTable, Error> {
+        Ok(GsubTable {
+            layout: try!(LayoutTable::new(table)),
+        })
+    }
+
+    pub fn substitute(&self, glyphs: &[u16], features: &[Tag]) -> Result<Vec<u16>, Error> {
+        let mut glyphs = glyphs.to_vec();
+        for lookup_index in try!(self.layout.lookup_indices_for_features(features)) {
+            let lookup = try!(self.layout.lookup(lookup_index, LOOKUP_TYPE_EXTENSION));
+            glyphs = try!(apply_lookup(&lookup, &glyphs));
+        }
+        Ok(glyphs)
+    }
+}
+
+// TODO: Respect the lookup flags. Skipping marks and the like requires the `GDEF` table.
+fn apply_lookup(lookup: &Lookup, glyphs: &[u16]) -> Result<Vec<u16>, Error> {
+    let mut output = Vec::with_capacity(glyphs.len());
+    let mut position = 0;
+    while position < glyphs.len() {
+        // The first subtable that applies wins.
+        let mut substitution = None;
+        for subtable in &lookup.subtables {
+            substitution = match lookup.lookup_type {
+                LOOKUP_TYPE_SINGLE => {
+                    try!(substitute_single(subtable, glyphs[position])).map(|glyph| (glyph, 1))
+                }
+                LOOKUP_TYPE_LIGATURE => try!(substitute_ligature(subtable, &glyphs[position..])),
+                _ => None,
+            };
+            if substitution.is_some() {
+                break
+            }
+        }
+
+        match substitution {
+            Some((glyph, consumed)) => {
+                output.push(glyph);
+                position += consumed
+            }
+            None => {
+                output.push(glyphs[position]);
+                position += 1
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn substitute_single(subtable: &[u8], glyph_id: u16) -> Result<Option<u16>, Error> {
+    let mut reader = subtable;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let coverage_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let coverage = try!(layout::offset_slice(subtable, coverage_offset as u32));
+    let coverage_index = match try!(layout::coverage_index(coverage, glyph_id)) {
+        None => return Ok(None),
+        Some(coverage_index) => coverage_index,
+    };
+
+    match format {
+        1 => {
+            // Glyph IDs wrap around modulo 65536.
+            let delta = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+            Ok(Some((glyph_id as i32 + delta as i32) as u16))
+        }
+        2 => {
+            let glyph_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if coverage_index >= glyph_count {
+                return Err(Error::Failed)
+            }
+            try!(reader.jump(coverage_index as usize * mem::size_of::<u16>())
+                       .map_err(Error::eof));
+            Ok(Some(try!(reader.read_u16::<BigEndian>().map_err(Error::eof))))
+        }
+        _ => Err(Error::UnknownFormat),
+    }
+}
+
+// Returns the ligature glyph and the number of glyphs it replaces.
+fn substitute_ligature(subtable: &[u8], glyphs: &[u16]) -> Result<Option<(u16, usize)>, Error> {
+    let mut reader = subtable;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    if format != 1 {
+        return Err(Error::UnknownFormat)
+    }
+
+    let coverage_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let coverage = try!(layout::offset_slice(subtable, coverage_offset as u32));
+    let coverage_index = match try!(layout::coverage_index(coverage, glyphs[0])) {
+        None => return Ok(None),
+        Some(coverage_index) => coverage_index,
+    };
+
+    let ligature_set_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    if coverage_index >= ligature_set_count {
+        return Err(Error::Failed)
+    }
+    try!(reader.jump(coverage_index as usize * mem::size_of::<u16>()).map_err(Error::eof));
+    let ligature_set_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let ligature_set = try!(layout::offset_slice(subtable, ligature_set_offset as u32));
+
+    // Ligatures are ordered by preference, so take the first one that matches.
+    let mut ligature_set_reader = ligature_set;
+    let ligature_count = try!(ligature_set_reader.read_u16::<BigEndian>().map_err(Error::eof));
+    for _ in 0..ligature_count {
+        let ligature_offset = try!(ligature_set_reader.read_u16::<BigEndian>()
+                                                      .map_err(Error::eof));
+        let mut ligature_reader = try!(layout::offset_slice(ligature_set,
+                                                            ligature_offset as u32));
+        let ligature_glyph = try!(ligature_reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let component_count = try!(ligature_reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        // The first component is the covered glyph and isn't stored.
+        let component_count = cmp::max(component_count as usize, 1);
+        if component_count > glyphs.len() {
+            continue
+        }
+
+        let mut matches = true;
+        for &glyph_id in &glyphs[1..component_count] {
+            let component = try!(ligature_reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if component != glyph_id {
+                matches = false;
+                break
+            }
+        }
+        if matches {
+            return Ok(Some((ligature_glyph, component_count)))
+        }
+    }
+
+    Ok(None)
+}
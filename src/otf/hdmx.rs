@@ -0,0 +1,74 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+/// The horizontal device metrics table, which holds hinted advance widths at specific sizes.
+#[derive(Clone, Copy, Debug)]
+pub struct HdmxTable<'a> {
+    table: FontTable<'a>,
+    num_records: u16,
+    record_size: usize,
+    num_glyphs: u16,
+}
+
+impl<'a> HdmxTable<'a> {
+    pub fn new(table: FontTable, num_glyphs: u16) -> Result<HdmxTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version != 0 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        let num_records = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        let record_size = try!(reader.read_i32::<BigEndian>().map_err(Error::eof));
+
+        // Each record holds the pixel size, the maximum width, and a width for every glyph.
+        if num_records < 0 || (record_size as i64) < 2 + num_glyphs as i64 {
+            return Err(Error::Failed)
+        }
+
+        Ok(HdmxTable {
+            table: table,
+            num_records: num_records as u16,
+            record_size: record_size as usize,
+            num_glyphs: num_glyphs,
+        })
+    }
+
+    /// Returns the advance width in pixels of the given glyph at the given size, or `None` if the
+    /// table has no record for that size.
+    pub fn device_advance(&self, glyph_id: u16, ppem: u8) -> Result<Option<u8>, Error> {
+        if glyph_id >= self.num_glyphs {
+            return Ok(None)
+        }
+
+        for record_index in 0..(self.num_records as usize) {
+            let mut reader = self.table.bytes;
+            try!(reader.jump(8 + record_index * self.record_size).map_err(Error::eof));
+
+            // Records are sorted by size, so stop once we're past the requested one.
+            let pixel_size = try!(reader.read_u8().map_err(Error::eof));
+            if pixel_size > ppem {
+                break
+            }
+            if pixel_size < ppem {
+                continue
+            }
+
+            try!(reader.jump(1 + glyph_id as usize).map_err(Error::eof));
+            return Ok(Some(try!(reader.read_u8().map_err(Error::eof))))
+        }
+
+        Ok(None)
+    }
+}
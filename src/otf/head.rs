@@ -16,11 +16,16 @@ use util::Jump;
 
 const MAGIC_NUMBER: u32 = 0x5f0f3cf5;
 
+// The largest number of units per em that the OpenType specification allows.
+const MAX_UNITS_PER_EM: u16 = 16384;
+
 #[derive(Clone, Debug)]
 pub struct HeadTable {
     pub units_per_em: u16,
     pub index_to_loc_format: i16,
     pub max_glyph_bounds: GlyphBounds,
+    pub mac_style: u16,
+    pub checksum_adjustment: u32,
 }
 
 impl HeadTable {
@@ -34,8 +39,9 @@ impl HeadTable {
             return Err(Error::UnsupportedHeadVersion)
         }
 
-        // Check the magic number.
-        try!(reader.jump(mem::size_of::<u32>() * 2).map_err(Error::eof));
+        // Read the checksum adjustment, and check the magic number.
+        try!(reader.jump(mem::size_of::<u32>()).map_err(Error::eof));
+        let checksum_adjustment = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
         let magic_number = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
         if magic_number != MAGIC_NUMBER {
             return Err(Error::UnknownFormat)
@@ -44,6 +50,9 @@ impl HeadTable {
         // Read the units per em.
         try!(reader.jump(mem::size_of::<u16>()).map_err(Error::eof));
         let units_per_em = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if units_per_em == 0 || units_per_em > MAX_UNITS_PER_EM {
+            return Err(Error::InvalidUnitsPerEm)
+        }
 
         // Read the maximum bounds.
         try!(reader.jump(mem::size_of::<i64>() * 2).map_err(Error::eof));
@@ -58,8 +67,9 @@ impl HeadTable {
             top: y_max as i32,
         };
 
-        // Read the index-to-location format.
-        try!(reader.jump(mem::size_of::<u16>() * 2 + mem::size_of::<i16>()).map_err(Error::eof));
+        // Read the style, and skip to the index-to-location format.
+        let mac_style = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        try!(reader.jump(mem::size_of::<u16>() + mem::size_of::<i16>()).map_err(Error::eof));
         let index_to_loc_format = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
 
         // Check the glyph data format.
@@ -72,6 +82,8 @@ impl HeadTable {
             units_per_em: units_per_em,
             index_to_loc_format: index_to_loc_format,
             max_glyph_bounds: max_glyph_bounds,
+            mac_style: mac_style,
+            checksum_adjustment: checksum_adjustment,
         })
     }
 }
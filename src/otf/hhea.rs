@@ -15,6 +15,8 @@ use util::Jump;
 
 #[derive(Clone, Debug)]
 pub struct HheaTable {
+    pub ascender: i16,
+    pub descender: i16,
     pub line_gap: i16,
     pub number_of_h_metrics: u16,
 }
@@ -31,8 +33,8 @@ impl HheaTable {
         }
 
         // Read the height-related metrics.
-        let _ascender = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
-        let _descender = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        let ascender = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
+        let descender = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
         let line_gap = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
 
         // Read the number of `hmtx` entries.
@@ -40,6 +42,8 @@ impl HheaTable {
         let number_of_h_metrics = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
 
         Ok(HheaTable {
+            ascender: ascender,
+            descender: descender,
             line_gap: line_gap,
             number_of_h_metrics: number_of_h_metrics,
         })
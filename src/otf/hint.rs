@@ -0,0 +1,1820 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A TrueType bytecode interpreter, which grid-fits glyph outlines at a given size.
+//!
+//! This follows the TrueType specification as FreeType's "v35" interpreter does, hinting in both
+//! directions like the original Windows rasterizer. The differences from FreeType are that each
+//! glyph starts from the state that the font and control value programs left behind, so that the
+//! result doesn't depend on the order in which glyphs are hinted, and that composite glyphs are
+//! hinted one component at a time, without running the composite glyph's own instructions.
+
+use byteorder::{BigEndian, ByteOrder};
+use euclid::Point2D;
+use otf::Error;
+use std::cmp;
+use std::i16;
+use std::i32;
+use std::mem;
+use std::sync::Arc;
+use util;
+
+// The most instructions that one program may execute, which guards against infinite loops.
+const MAX_INSTRUCTIONS: u32 = 1_000_000;
+
+// The deepest that function calls may nest.
+const MAX_CALL_DEPTH: u32 = 64;
+
+// Extra stack space beyond what `maxp` declares, since some fonts underestimate it.
+const EXTRA_STACK_DEPTH: usize = 32;
+
+// The version of the Windows rasterizer that `GETINFO` reports, as FreeType does in v35 mode.
+const RASTERIZER_VERSION: i32 = 35;
+
+// The bit that `GETINFO` sets to report grayscale rendering.
+const GETINFO_GRAYSCALE: i32 = 1 << 12;
+
+const TWILIGHT_ZONE: usize = 0;
+const GLYPH_ZONE: usize = 1;
+
+const TOUCHED_X: u8 = 1 << 0;
+const TOUCHED_Y: u8 = 1 << 1;
+
+const X_AXIS: Vector = Vector { x: 1.0, y: 0.0 };
+const Y_AXIS: Vector = Vector { x: 0.0, y: 1.0 };
+
+/// Grid-fits glyph outlines at one size by running the font's TrueType instructions.
+///
+/// Create one with `Font::hinter()`, which runs the font program (`fpgm`) and the control value
+/// program (`prep`) once for the size, and pass it to `Font::for_each_hinted_point()` or
+/// `OutlineBuilder::add_hinted_glyph()` for each glyph. A hinter only applies to the font that
+/// created it. Hinting snaps outlines to the pixel grid of one size, so glyphs hinted with it must
+/// be drawn at `pixels_per_em()` pixels per em.
+///
+/// Hinting a glyph doesn't change the hinter, so one hinter can be shared among threads.
+///
+/// This is only available with the `hinting` feature.
+#[derive(Clone, Debug)]
+pub struct Hinter {
+    pixels_per_em: u16,
+    scale: i32,
+    cvt: Vec<i32>,
+    storage: Vec<i32>,
+    functions: Vec<Option<Arc<Vec<u8>>>>,
+    instruction_definitions: Vec<(u8, Arc<Vec<u8>>)>,
+    twilight: Zone,
+    graphics_state: GraphicsState,
+    max_stack_depth: usize,
+}
+
+impl Hinter {
+    /// Returns the size that this hinter grid-fits outlines for, in whole pixels per em.
+    #[inline]
+    pub fn pixels_per_em(&self) -> u16 {
+        self.pixels_per_em
+    }
+}
+
+/// The points of a simple glyph as they're stored in the font, in font units.
+pub struct GlyphOutline {
+    pub points: Vec<Point2D<i32>>,
+    pub on_curve: Vec<bool>,
+    pub contour_ends: Vec<u16>,
+}
+
+/// Creates a hinter for the given size in pixels per em, which is rounded to a whole number, and
+/// runs the font program and control value program.
+///
+/// `maxp` and `cvt` are the contents of the font's tables of those names; missing tables may be
+/// given as empty.
+pub fn new(fpgm: &[u8],
+           prep: &[u8],
+           cvt: &[u8],
+           maxp: &[u8],
+           units_per_em: u16,
+           point_size: f32)
+           -> Result<Hinter, Error> {
+    let pixels_per_em = point_size.round().max(1.0).min(i16::MAX as f32) as u16;
+    let scale = util::scale_26_6(units_per_em, pixels_per_em as f32);
+
+    // These fields only exist in version 1.0 `maxp` tables, which all TrueType fonts have.
+    let maxp_field = |offset: usize| {
+        maxp.get(offset..offset + 2).map_or(0, |field| BigEndian::read_u16(field) as usize)
+    };
+    let (twilight_point_count, storage_size) = (maxp_field(16), maxp_field(18));
+    let (function_count, max_stack_depth) = (maxp_field(20), maxp_field(24));
+
+    let cvt = cvt.chunks(2).filter(|value| value.len() == 2).map(|value| {
+        util::mul_fix(BigEndian::read_i16(value) as i32, scale)
+    }).collect();
+
+    let mut hinter = Hinter {
+        pixels_per_em: pixels_per_em,
+        scale: scale,
+        cvt: cvt,
+        storage: vec![0; storage_size],
+        functions: vec![None; function_count],
+        instruction_definitions: vec![],
+        twilight: Zone::new(twilight_point_count),
+        graphics_state: GraphicsState::default(),
+        max_stack_depth: max_stack_depth + EXTRA_STACK_DEPTH,
+    };
+
+    let mut interpreter = Interpreter::new(&hinter, Zone::new(0), Program::Font);
+    try!(interpreter.execute(fpgm, 0));
+
+    // The control value program starts from the default graphics state too, and the state it
+    // leaves behind becomes the default for glyphs.
+    interpreter.program = Program::ControlValue;
+    interpreter.state = GraphicsState::default();
+    interpreter.stack.clear();
+    interpreter.instruction_count = 0;
+    try!(interpreter.execute(prep, 0));
+
+    hinter.cvt = interpreter.cvt;
+    hinter.storage = interpreter.storage;
+    hinter.functions = interpreter.functions;
+    hinter.instruction_definitions = interpreter.instruction_definitions;
+    // Like the Windows rasterizer, don't let the control value program change the vectors,
+    // reference points, zone pointers, or loop count that glyphs start with.
+    let default_state = GraphicsState::default();
+    hinter.graphics_state = GraphicsState {
+        projection_vector: default_state.projection_vector,
+        freedom_vector: default_state.freedom_vector,
+        dual_projection_vector: default_state.dual_projection_vector,
+        reference_points: default_state.reference_points,
+        zone_pointers: default_state.zone_pointers,
+        loop_count: default_state.loop_count,
+        ..interpreter.state
+    };
+    hinter.twilight = mem::replace(&mut interpreter.zones[TWILIGHT_ZONE], Zone::new(0));
+    Ok(hinter)
+}
+
+/// Grid-fits a simple glyph by running its instructions.
+///
+/// `phantom_points` are the glyph's origin and advance on the horizontal baseline, followed by its
+/// top and bottom on the vertical one, in font units. Returns the hinted positions of the glyph's
+/// points in 26.6 fixed-point pixels, followed by those of the phantom points.
+pub fn hint_glyph(hinter: &Hinter,
+                  outline: &GlyphOutline,
+                  phantom_points: &[Point2D<i32>; 4],
+                  instructions: &[u8])
+                  -> Result<Vec<Point2D<i32>>, Error> {
+    let mut zone = Zone::new(0);
+    zone.unscaled = outline.points.iter().chain(phantom_points.iter()).cloned().collect();
+    zone.original = zone.unscaled.iter().map(|point| {
+        Point2D::new(util::mul_fix(point.x, hinter.scale), util::mul_fix(point.y, hinter.scale))
+    }).collect();
+    zone.current = zone.original.clone();
+    zone.touched = vec![0; zone.original.len()];
+    zone.on_curve = outline.on_curve.iter().cloned().chain([false; 4].iter().cloned()).collect();
+    zone.contour_ends = outline.contour_ends.clone();
+
+    // Round the phantom points, as FreeType does, so that the glyph's origin and advance fall on
+    // the pixel grid.
+    let phantom_start = outline.points.len();
+    zone.current[phantom_start].x = round_to_grid(zone.current[phantom_start].x);
+    zone.current[phantom_start + 1].x = round_to_grid(zone.current[phantom_start + 1].x);
+    zone.current[phantom_start + 2].y = round_to_grid(zone.current[phantom_start + 2].y);
+    zone.current[phantom_start + 3].y = round_to_grid(zone.current[phantom_start + 3].y);
+
+    let mut interpreter = Interpreter::new(hinter, zone, Program::Glyph);
+
+    // Bit 0 of the instruction control flags disables glyph instructions, and bit 1 makes glyphs
+    // ignore the graphics state that the control value program set.
+    if hinter.graphics_state.instruct_control & 1 == 0 {
+        if hinter.graphics_state.instruct_control & 2 != 0 {
+            interpreter.state = GraphicsState::default()
+        }
+        try!(interpreter.execute(instructions, 0));
+    }
+
+    Ok(mem::replace(&mut interpreter.zones[GLYPH_ZONE].current, vec![]))
+}
+
+/// Converts a distance in 26.6 fixed-point pixels back to font units, rounding to the nearest
+/// unit.
+pub fn to_font_units(hinter: &Hinter, distance: i32) -> i16 {
+    if hinter.scale == 0 {
+        return 0
+    }
+    let units = util::div_fix(distance, hinter.scale) as i64;
+    cmp::max(cmp::min(units, i16::MAX as i64), i16::MIN as i64) as i16
+}
+
+/// Scales a distance in font units to 26.6 fixed-point pixels.
+#[inline]
+pub fn to_26_6(hinter: &Hinter, distance: i32) -> i32 {
+    util::mul_fix(distance, hinter.scale)
+}
+
+/// Rounds a distance in 26.6 fixed-point pixels to the nearest whole pixel.
+#[inline]
+pub fn round_to_grid(distance: i32) -> i32 {
+    round_with(distance, |distance| distance.wrapping_add(32) & !63)
+}
+
+// Which of the font's programs is running.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Program {
+    Font,
+    ControlValue,
+    Glyph,
+}
+
+// A unit vector, with each component rounded to 2.14 fixed point as in the TrueType rasterizer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Vector {
+    x: f32,
+    y: f32,
+}
+
+impl Vector {
+    // Returns the unit vector in the direction of the given one, or the x axis if it's zero.
+    fn normalize(x: f64, y: f64) -> Vector {
+        let length = (x * x + y * y).sqrt();
+        if length == 0.0 {
+            return X_AXIS
+        }
+        Vector {
+            x: ((x / length * 16384.0).round() / 16384.0) as f32,
+            y: ((y / length * 16384.0).round() / 16384.0) as f32,
+        }
+    }
+
+    // Returns this vector rotated 90° counterclockwise.
+    #[inline]
+    fn perpendicular(&self) -> Vector {
+        Vector {
+            x: -self.y,
+            y: self.x,
+        }
+    }
+
+    #[inline]
+    fn dot(&self, x: i32, y: i32) -> i32 {
+        (x as f32 * self.x + y as f32 * self.y).round() as i32
+    }
+
+    // Returns the components in 2.14 fixed point.
+    #[inline]
+    fn to_2_14(&self) -> (i32, i32) {
+        ((self.x * 16384.0).round() as i32, (self.y * 16384.0).round() as i32)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum RoundState {
+    HalfGrid,
+    Grid,
+    DoubleGrid,
+    DownToGrid,
+    UpToGrid,
+    Off,
+    // Set by `SROUND` or `S45ROUND`, with the period, phase, and threshold.
+    Super(i32, i32, i32),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct GraphicsState {
+    projection_vector: Vector,
+    freedom_vector: Vector,
+    dual_projection_vector: Vector,
+    reference_points: [usize; 3],
+    zone_pointers: [usize; 3],
+    loop_count: i32,
+    round_state: RoundState,
+    minimum_distance: i32,
+    control_value_cut_in: i32,
+    single_width_cut_in: i32,
+    single_width_value: i32,
+    auto_flip: bool,
+    delta_base: i32,
+    delta_shift: i32,
+    instruct_control: i32,
+}
+
+impl Default for GraphicsState {
+    fn default() -> GraphicsState {
+        GraphicsState {
+            projection_vector: X_AXIS,
+            freedom_vector: X_AXIS,
+            dual_projection_vector: X_AXIS,
+            reference_points: [0; 3],
+            zone_pointers: [GLYPH_ZONE; 3],
+            loop_count: 1,
+            round_state: RoundState::Grid,
+            minimum_distance: 64,
+            control_value_cut_in: 68,
+            single_width_cut_in: 0,
+            single_width_value: 0,
+            auto_flip: true,
+            delta_base: 9,
+            delta_shift: 3,
+            instruct_control: 0,
+        }
+    }
+}
+
+// A set of points that instructions can move: the twilight zone, which holds scratch points, or
+// the glyph zone, which holds the glyph's points and its four phantom points.
+#[derive(Clone, Debug)]
+struct Zone {
+    // The points in font units. This is empty for the twilight zone.
+    unscaled: Vec<Point2D<i32>>,
+    // The scaled points, before hinting.
+    original: Vec<Point2D<i32>>,
+    current: Vec<Point2D<i32>>,
+    touched: Vec<u8>,
+    on_curve: Vec<bool>,
+    contour_ends: Vec<u16>,
+}
+
+impl Zone {
+    fn new(point_count: usize) -> Zone {
+        Zone {
+            unscaled: vec![],
+            original: vec![Point2D::zero(); point_count],
+            current: vec![Point2D::zero(); point_count],
+            touched: vec![0; point_count],
+            on_curve: vec![false; point_count],
+            contour_ends: vec![],
+        }
+    }
+
+    // Returns the range of points that make up the given contour.
+    fn contour(&self, contour: i32) -> Result<(usize, usize), Error> {
+        if contour < 0 || contour as usize >= self.contour_ends.len() {
+            return Err(Error::HintingFailed)
+        }
+        let contour = contour as usize;
+        let start = if contour == 0 {
+            0
+        } else {
+            self.contour_ends[contour - 1] as usize + 1
+        };
+        let end = self.contour_ends[contour] as usize + 1;
+        if start > end || end > self.current.len() {
+            return Err(Error::HintingFailed)
+        }
+        Ok((start, end))
+    }
+
+    // Returns the number of points that belong to contours, leaving out the phantom points.
+    fn contour_point_count(&self) -> usize {
+        match self.contour_ends.last() {
+            None => self.current.len(),
+            Some(&end) => cmp::min(end as usize + 1, self.current.len()),
+        }
+    }
+}
+
+struct Interpreter {
+    program: Program,
+    pixels_per_em: u16,
+    scale: i32,
+    cvt: Vec<i32>,
+    storage: Vec<i32>,
+    functions: Vec<Option<Arc<Vec<u8>>>>,
+    instruction_definitions: Vec<(u8, Arc<Vec<u8>>)>,
+    zones: [Zone; 2],
+    state: GraphicsState,
+    stack: Vec<i32>,
+    max_stack_depth: usize,
+    instruction_count: u32,
+}
+
+impl Interpreter {
+    fn new(hinter: &Hinter, glyph: Zone, program: Program) -> Interpreter {
+        Interpreter {
+            program: program,
+            pixels_per_em: hinter.pixels_per_em,
+            scale: hinter.scale,
+            cvt: hinter.cvt.clone(),
+            storage: hinter.storage.clone(),
+            functions: hinter.functions.clone(),
+            instruction_definitions: hinter.instruction_definitions.clone(),
+            zones: [hinter.twilight.clone(), glyph],
+            state: hinter.graphics_state,
+            stack: vec![],
+            max_stack_depth: hinter.max_stack_depth,
+            instruction_count: 0,
+        }
+    }
+
+    // Runs the given instructions, which are a function body if `depth` is positive.
+    fn execute(&mut self, code: &[u8], depth: u32) -> Result<(), Error> {
+        if depth > MAX_CALL_DEPTH {
+            return Err(Error::HintingFailed)
+        }
+
+        let mut pc = 0;
+        while pc < code.len() {
+            self.instruction_count += 1;
+            if self.instruction_count > MAX_INSTRUCTIONS {
+                return Err(Error::HintingFailed)
+            }
+
+            let opcode = code[pc];
+            let mut next_pc = pc + try!(instruction_length(code, pc));
+            if next_pc > code.len() {
+                return Err(Error::HintingFailed)
+            }
+
+            if opcode >= 0xe0 {
+                try!(self.move_indirect_relative_point(opcode))
+            } else if opcode >= 0xc0 {
+                try!(self.move_direct_relative_point(opcode))
+            } else if opcode >= 0xb8 {
+                // PUSHW[abc]
+                for word in code[pc + 1..next_pc].chunks(2) {
+                    try!(self.push(BigEndian::read_i16(word) as i32))
+                }
+            } else if opcode >= 0xb0 {
+                // PUSHB[abc]
+                for &byte in &code[pc + 1..next_pc] {
+                    try!(self.push(byte as i32))
+                }
+            } else {
+                match opcode {
+                    // SVTCA[a]
+                    0x00 | 0x01 => {
+                        let axis = if opcode == 0x00 { Y_AXIS } else { X_AXIS };
+                        self.state.projection_vector = axis;
+                        self.state.dual_projection_vector = axis;
+                        self.state.freedom_vector = axis;
+                    }
+                    // SPVTCA[a]
+                    0x02 | 0x03 => {
+                        let axis = if opcode == 0x02 { Y_AXIS } else { X_AXIS };
+                        self.state.projection_vector = axis;
+                        self.state.dual_projection_vector = axis;
+                    }
+                    // SFVTCA[a]
+                    0x04 | 0x05 => {
+                        self.state.freedom_vector = if opcode == 0x04 { Y_AXIS } else { X_AXIS }
+                    }
+                    // SPVTL[a]
+                    0x06 | 0x07 => {
+                        let vector = try!(self.vector_to_line(opcode, false));
+                        self.state.projection_vector = vector;
+                        self.state.dual_projection_vector = vector;
+                    }
+                    // SFVTL[a]
+                    0x08 | 0x09 => {
+                        self.state.freedom_vector = try!(self.vector_to_line(opcode, false))
+                    }
+                    // SPVFS
+                    0x0a => {
+                        let (y, x) = (try!(self.pop()), try!(self.pop()));
+                        let vector = Vector::normalize(x as i16 as f64, y as i16 as f64);
+                        self.state.projection_vector = vector;
+                        self.state.dual_projection_vector = vector;
+                    }
+                    // SFVFS
+                    0x0b => {
+                        let (y, x) = (try!(self.pop()), try!(self.pop()));
+                        self.state.freedom_vector = Vector::normalize(x as i16 as f64,
+                                                                      y as i16 as f64)
+                    }
+                    // GPV
+                    0x0c => {
+                        let (x, y) = self.state.projection_vector.to_2_14();
+                        try!(self.push(x));
+                        try!(self.push(y));
+                    }
+                    // GFV
+                    0x0d => {
+                        let (x, y) = self.state.freedom_vector.to_2_14();
+                        try!(self.push(x));
+                        try!(self.push(y));
+                    }
+                    // SFVTPV
+                    0x0e => self.state.freedom_vector = self.state.projection_vector,
+                    // ISECT
+                    0x0f => try!(self.intersect()),
+                    // SRP0, SRP1, SRP2
+                    0x10 | 0x11 | 0x12 => {
+                        let point = try!(self.pop_index());
+                        self.state.reference_points[(opcode - 0x10) as usize] = point
+                    }
+                    // SZP0, SZP1, SZP2
+                    0x13 | 0x14 | 0x15 => {
+                        let zone = try!(self.pop_zone());
+                        self.state.zone_pointers[(opcode - 0x13) as usize] = zone
+                    }
+                    // SZPS
+                    0x16 => self.state.zone_pointers = [try!(self.pop_zone()); 3],
+                    // SLOOP
+                    0x17 => {
+                        let count = try!(self.pop());
+                        if count < 0 {
+                            return Err(Error::HintingFailed)
+                        }
+                        self.state.loop_count = count
+                    }
+                    // RTG
+                    0x18 => self.state.round_state = RoundState::Grid,
+                    // RTHG
+                    0x19 => self.state.round_state = RoundState::HalfGrid,
+                    // SMD
+                    0x1a => self.state.minimum_distance = try!(self.pop()),
+                    // ELSE, reached at the end of the `IF` branch
+                    0x1b => next_pc = try!(skip_conditional(code, next_pc, false)),
+                    // JMPR
+                    0x1c => {
+                        let offset = try!(self.pop());
+                        next_pc = try!(jump(code, pc, offset))
+                    }
+                    // SCVTCI
+                    0x1d => self.state.control_value_cut_in = try!(self.pop()),
+                    // SSWCI
+                    0x1e => self.state.single_width_cut_in = try!(self.pop()),
+                    // SSW
+                    0x1f => {
+                        let value = try!(self.pop());
+                        self.state.single_width_value = util::mul_fix(value, self.scale)
+                    }
+                    // DUP
+                    0x20 => {
+                        let value = try!(self.pop());
+                        try!(self.push(value));
+                        try!(self.push(value));
+                    }
+                    // POP
+                    0x21 => {
+                        try!(self.pop());
+                    }
+                    // CLEAR
+                    0x22 => self.stack.clear(),
+                    // SWAP
+                    0x23 => {
+                        let (b, a) = (try!(self.pop()), try!(self.pop()));
+                        try!(self.push(b));
+                        try!(self.push(a));
+                    }
+                    // DEPTH
+                    0x24 => {
+                        let depth = self.stack.len() as i32;
+                        try!(self.push(depth))
+                    }
+                    // CINDEX
+                    0x25 => {
+                        let index = try!(self.stack_index());
+                        let value = self.stack[index];
+                        try!(self.push(value))
+                    }
+                    // MINDEX
+                    0x26 => {
+                        let index = try!(self.stack_index());
+                        let value = self.stack.remove(index);
+                        try!(self.push(value))
+                    }
+                    // ALIGNPTS
+                    0x27 => {
+                        let (point_1_zone, point_2_zone) = (self.zone(1), self.zone(0));
+                        let point_2 = try!(self.pop_point(point_2_zone));
+                        let point_1 = try!(self.pop_point(point_1_zone));
+                        let distance = self.project(self.current(point_2_zone, point_2),
+                                                    self.current(point_1_zone, point_1)) / 2;
+                        self.move_point(point_1_zone, point_1, distance, true);
+                        self.move_point(point_2_zone, point_2, -distance, true);
+                    }
+                    // UTP
+                    0x29 => {
+                        let zone = self.zone(0);
+                        let point = try!(self.pop_point(zone));
+                        let freedom_vector = self.state.freedom_vector;
+                        let touched = &mut self.zones[zone].touched[point];
+                        if freedom_vector.x != 0.0 {
+                            *touched &= !TOUCHED_X
+                        }
+                        if freedom_vector.y != 0.0 {
+                            *touched &= !TOUCHED_Y
+                        }
+                    }
+                    // LOOPCALL
+                    0x2a => {
+                        let function = try!(self.pop());
+                        let count = try!(self.pop());
+                        let body = try!(self.function(function));
+                        for _ in 0..cmp::max(count, 0) {
+                            try!(self.execute(&body, depth + 1))
+                        }
+                    }
+                    // CALL
+                    0x2b => {
+                        let function = try!(self.pop());
+                        let body = try!(self.function(function));
+                        try!(self.execute(&body, depth + 1))
+                    }
+                    // FDEF
+                    0x2c => {
+                        let function = try!(self.pop_index());
+                        let end = try!(find_end_of_definition(code, next_pc));
+                        let body = Arc::new(code[next_pc..end].to_vec());
+                        match self.functions.get_mut(function) {
+                            Some(slot) => *slot = Some(body),
+                            None => return Err(Error::HintingFailed),
+                        }
+                        next_pc = end + 1
+                    }
+                    // MDAP[a]
+                    0x2e | 0x2f => {
+                        let zone = self.zone(0);
+                        let point = try!(self.pop_point(zone));
+                        let distance = if opcode == 0x2f {
+                            let position = self.current(zone, point);
+                            let current_distance = self.project(position, Point2D::zero());
+                            self.round(current_distance).wrapping_sub(current_distance)
+                        } else {
+                            0
+                        };
+                        self.move_point(zone, point, distance, true);
+                        self.state.reference_points[0] = point;
+                        self.state.reference_points[1] = point;
+                    }
+                    // IUP[a]
+                    0x30 | 0x31 => self.interpolate_untouched_points(opcode == 0x31),
+                    // SHP[a]
+                    0x32 | 0x33 => {
+                        let (_, _, dx, dy) = try!(self.reference_displacement(opcode));
+                        let zone = self.zone(2);
+                        for _ in 0..self.take_loop_count() {
+                            let point = try!(self.pop_point(zone));
+                            self.shift_point(zone, point, dx, dy, true)
+                        }
+                    }
+                    // SHC[a]
+                    0x34 | 0x35 => {
+                        let (reference_zone, reference_point, dx, dy) =
+                            try!(self.reference_displacement(opcode));
+                        let zone = self.zone(2);
+                        let contour = try!(self.pop());
+                        let (start, end) = try!(self.zones[zone].contour(contour));
+                        for point in start..end {
+                            if zone != reference_zone || point != reference_point {
+                                self.shift_point(zone, point, dx, dy, true)
+                            }
+                        }
+                    }
+                    // SHZ[a]
+                    0x36 | 0x37 => {
+                        let (reference_zone, reference_point, dx, dy) =
+                            try!(self.reference_displacement(opcode));
+                        // Like FreeType, shift the zone that `zp2` points to, whatever the
+                        // argument, and leave the phantom points alone.
+                        try!(self.pop_zone());
+                        let zone = self.zone(2);
+                        for point in 0..self.zones[zone].contour_point_count() {
+                            if zone != reference_zone || point != reference_point {
+                                self.shift_point(zone, point, dx, dy, false)
+                            }
+                        }
+                    }
+                    // SHPIX
+                    0x38 => {
+                        let distance = try!(self.pop());
+                        let freedom_vector = self.state.freedom_vector;
+                        let dx = (distance as f32 * freedom_vector.x).round() as i32;
+                        let dy = (distance as f32 * freedom_vector.y).round() as i32;
+                        let zone = self.zone(2);
+                        for _ in 0..self.take_loop_count() {
+                            let point = try!(self.pop_point(zone));
+                            self.shift_point(zone, point, dx, dy, true)
+                        }
+                    }
+                    // IP
+                    0x39 => try!(self.interpolate_points()),
+                    // MSIRP[a]
+                    0x3a | 0x3b => {
+                        let distance = try!(self.pop());
+                        let (reference_zone, zone) = (self.zone(0), self.zone(1));
+                        let point = try!(self.pop_point(zone));
+                        let reference_point = try!(self.reference_point(0, reference_zone));
+
+                        // The twilight zone has no original outline, so the point is first
+                        // placed at the given distance in the original outline too.
+                        if zone == TWILIGHT_ZONE {
+                            let reference = self.zones[reference_zone].original[reference_point];
+                            self.zones[zone].original[point] = reference;
+                            self.move_original_point(zone, point, distance);
+                            self.zones[zone].current[point] = self.zones[zone].original[point];
+                        }
+
+                        let current_distance =
+                            self.project(self.current(zone, point),
+                                         self.current(reference_zone, reference_point));
+                        self.move_point(zone, point, distance.wrapping_sub(current_distance), true);
+                        self.state.reference_points[1] = reference_point;
+                        self.state.reference_points[2] = point;
+                        if opcode == 0x3b {
+                            self.state.reference_points[0] = point
+                        }
+                    }
+                    // ALIGNRP
+                    0x3c => {
+                        let (reference_zone, zone) = (self.zone(0), self.zone(1));
+                        let reference_point = try!(self.reference_point(0, reference_zone));
+                        let reference = self.current(reference_zone, reference_point);
+                        for _ in 0..self.take_loop_count() {
+                            let point = try!(self.pop_point(zone));
+                            let distance = self.project(self.current(zone, point), reference);
+                            self.move_point(zone, point, distance.wrapping_neg(), true)
+                        }
+                    }
+                    // RTDG
+                    0x3d => self.state.round_state = RoundState::DoubleGrid,
+                    // MIAP[a]
+                    0x3e | 0x3f => {
+                        let cvt_index = try!(self.pop());
+                        let zone = self.zone(0);
+                        let point = try!(self.pop_point(zone));
+                        let mut distance = try!(self.read_cvt(cvt_index));
+
+                        if zone == TWILIGHT_ZONE {
+                            let freedom_vector = self.state.freedom_vector;
+                            let position =
+                                Point2D::new((distance as f32 * freedom_vector.x).round() as i32,
+                                             (distance as f32 * freedom_vector.y).round() as i32);
+                            self.zones[zone].original[point] = position;
+                            self.zones[zone].current[point] = position;
+                        }
+
+                        let current_distance = self.project(self.current(zone, point),
+                                                            Point2D::zero());
+                        if opcode == 0x3f {
+                            if (distance as i64 - current_distance as i64).abs() >
+                                    self.state.control_value_cut_in as i64 {
+                                distance = current_distance
+                            }
+                            distance = self.round(distance)
+                        }
+                        self.move_point(zone, point, distance.wrapping_sub(current_distance), true);
+                        self.state.reference_points[0] = point;
+                        self.state.reference_points[1] = point;
+                    }
+                    // NPUSHB
+                    0x40 => {
+                        for &byte in &code[pc + 2..next_pc] {
+                            try!(self.push(byte as i32))
+                        }
+                    }
+                    // NPUSHW
+                    0x41 => {
+                        for word in code[pc + 2..next_pc].chunks(2) {
+                            try!(self.push(BigEndian::read_i16(word) as i32))
+                        }
+                    }
+                    // WS
+                    0x42 => {
+                        let value = try!(self.pop());
+                        let index = try!(self.pop_index());
+                        match self.storage.get_mut(index) {
+                            Some(slot) => *slot = value,
+                            None => return Err(Error::HintingFailed),
+                        }
+                    }
+                    // RS
+                    0x43 => {
+                        let index = try!(self.pop_index());
+                        let value = try!(self.storage.get(index).cloned()
+                                                     .ok_or(Error::HintingFailed));
+                        try!(self.push(value))
+                    }
+                    // WCVTP
+                    0x44 => {
+                        let value = try!(self.pop());
+                        let index = try!(self.pop());
+                        try!(self.write_cvt(index, value))
+                    }
+                    // RCVT
+                    0x45 => {
+                        let index = try!(self.pop());
+                        let value = try!(self.read_cvt(index));
+                        try!(self.push(value))
+                    }
+                    // GC[a]
+                    0x46 | 0x47 => {
+                        let zone = self.zone(2);
+                        let point = try!(self.pop_point(zone));
+                        let value = if opcode == 0x46 {
+                            self.project(self.current(zone, point), Point2D::zero())
+                        } else {
+                            let position = self.zones[zone].original[point];
+                            self.state.dual_projection_vector.dot(position.x, position.y)
+                        };
+                        try!(self.push(value))
+                    }
+                    // SCFS
+                    0x48 => {
+                        let value = try!(self.pop());
+                        let zone = self.zone(2);
+                        let point = try!(self.pop_point(zone));
+                        let current_distance = self.project(self.current(zone, point),
+                                                            Point2D::zero());
+                        self.move_point(zone, point, value.wrapping_sub(current_distance), true);
+                        if zone == TWILIGHT_ZONE {
+                            self.zones[zone].original[point] = self.zones[zone].current[point]
+                        }
+                    }
+                    // MD[a]
+                    0x49 | 0x4a => {
+                        let (zone_1, zone_0) = (self.zone(1), self.zone(0));
+                        let point_1 = try!(self.pop_point(zone_1));
+                        let point_0 = try!(self.pop_point(zone_0));
+                        let distance = if opcode == 0x49 {
+                            self.project(self.current(zone_0, point_0),
+                                         self.current(zone_1, point_1))
+                        } else {
+                            self.original_distance(zone_0, point_0, zone_1, point_1)
+                        };
+                        try!(self.push(distance))
+                    }
+                    // MPPEM, and MPS, which returns the same as FreeType's v35 interpreter does.
+                    0x4b | 0x4c => {
+                        let pixels_per_em = self.pixels_per_em as i32;
+                        try!(self.push(pixels_per_em))
+                    }
+                    // FLIPON
+                    0x4d => self.state.auto_flip = true,
+                    // FLIPOFF
+                    0x4e => self.state.auto_flip = false,
+                    // DEBUG, SANGW, AA, SCANCTRL, SCANTYPE: ignored.
+                    0x4f | 0x7e | 0x7f | 0x85 | 0x8d => {
+                        try!(self.pop());
+                    }
+                    // LT, LTEQ, GT, GTEQ, EQ, NEQ
+                    0x50 | 0x51 | 0x52 | 0x53 | 0x54 | 0x55 => {
+                        let (b, a) = (try!(self.pop()), try!(self.pop()));
+                        let result = match opcode {
+                            0x50 => a < b,
+                            0x51 => a <= b,
+                            0x52 => a > b,
+                            0x53 => a >= b,
+                            0x54 => a == b,
+                            _ => a != b,
+                        };
+                        try!(self.push(result as i32))
+                    }
+                    // ODD, EVEN
+                    0x56 | 0x57 => {
+                        let value = try!(self.pop());
+                        let rounded = self.round(value) & 127;
+                        let result = if opcode == 0x56 { rounded == 64 } else { rounded == 0 };
+                        try!(self.push(result as i32))
+                    }
+                    // IF
+                    0x58 => {
+                        if try!(self.pop()) == 0 {
+                            next_pc = try!(skip_conditional(code, next_pc, true))
+                        }
+                    }
+                    // EIF
+                    0x59 => {}
+                    // AND, OR
+                    0x5a | 0x5b => {
+                        let (b, a) = (try!(self.pop()) != 0, try!(self.pop()) != 0);
+                        let result = if opcode == 0x5a { a && b } else { a || b };
+                        try!(self.push(result as i32))
+                    }
+                    // NOT
+                    0x5c => {
+                        let value = try!(self.pop());
+                        try!(self.push((value == 0) as i32))
+                    }
+                    // DELTAP1, DELTAP2, DELTAP3
+                    0x5d | 0x71 | 0x72 => try!(self.delta_points(opcode)),
+                    // SDB
+                    0x5e => self.state.delta_base = try!(self.pop()),
+                    // SDS
+                    0x5f => {
+                        let shift = try!(self.pop());
+                        if shift < 0 || shift > 6 {
+                            return Err(Error::HintingFailed)
+                        }
+                        self.state.delta_shift = shift
+                    }
+                    // ADD, SUB, DIV, MUL, MAX, MIN
+                    0x60 | 0x61 | 0x62 | 0x63 | 0x8b | 0x8c => {
+                        let (b, a) = (try!(self.pop()), try!(self.pop()));
+                        let result = match opcode {
+                            0x60 => a.wrapping_add(b),
+                            0x61 => a.wrapping_sub(b),
+                            0x62 => {
+                                if b == 0 {
+                                    return Err(Error::HintingFailed)
+                                }
+                                (a as i64 * 64 / b as i64) as i32
+                            }
+                            0x63 => mul_div(a, b, 64),
+                            0x8b => cmp::max(a, b),
+                            _ => cmp::min(a, b),
+                        };
+                        try!(self.push(result))
+                    }
+                    // ABS, NEG, FLOOR, CEILING
+                    0x64 | 0x65 | 0x66 | 0x67 => {
+                        let value = try!(self.pop());
+                        let result = match opcode {
+                            0x64 => value.wrapping_abs(),
+                            0x65 => value.wrapping_neg(),
+                            0x66 => value & !63,
+                            _ => value.wrapping_add(63) & !63,
+                        };
+                        try!(self.push(result))
+                    }
+                    // ROUND[ab]
+                    0x68 | 0x69 | 0x6a | 0x6b => {
+                        let value = try!(self.pop());
+                        let rounded = self.round(value);
+                        try!(self.push(rounded))
+                    }
+                    // NROUND[ab]: engine compensation is always zero, so this does nothing.
+                    0x6c | 0x6d | 0x6e | 0x6f => {}
+                    // WCVTF
+                    0x70 => {
+                        let value = try!(self.pop());
+                        let index = try!(self.pop());
+                        let value = util::mul_fix(value, self.scale);
+                        try!(self.write_cvt(index, value))
+                    }
+                    // DELTAC1, DELTAC2, DELTAC3
+                    0x73 | 0x74 | 0x75 => try!(self.delta_cvt(opcode)),
+                    // SROUND, S45ROUND
+                    0x76 | 0x77 => {
+                        let selector = try!(self.pop());
+                        self.set_super_round(selector, opcode == 0x77)
+                    }
+                    // JROT, JROF
+                    0x78 | 0x79 => {
+                        let condition = try!(self.pop()) != 0;
+                        let offset = try!(self.pop());
+                        if condition == (opcode == 0x78) {
+                            next_pc = try!(jump(code, pc, offset))
+                        }
+                    }
+                    // ROFF
+                    0x7a => self.state.round_state = RoundState::Off,
+                    // RUTG
+                    0x7c => self.state.round_state = RoundState::UpToGrid,
+                    // RDTG
+                    0x7d => self.state.round_state = RoundState::DownToGrid,
+                    // FLIPPT
+                    0x80 => {
+                        for _ in 0..self.take_loop_count() {
+                            let point = try!(self.pop_point(GLYPH_ZONE));
+                            let on_curve = &mut self.zones[GLYPH_ZONE].on_curve[point];
+                            *on_curve = !*on_curve
+                        }
+                    }
+                    // FLIPRGON, FLIPRGOFF
+                    0x81 | 0x82 => {
+                        let end = try!(self.pop_point(GLYPH_ZONE));
+                        let start = try!(self.pop_point(GLYPH_ZONE));
+                        for point in start..(end + 1) {
+                            self.zones[GLYPH_ZONE].on_curve[point] = opcode == 0x81
+                        }
+                    }
+                    // SDPVTL[a]
+                    0x86 | 0x87 => {
+                        self.state.dual_projection_vector =
+                            try!(self.vector_to_line(opcode, true));
+                        self.state.projection_vector = try!(self.vector_to_line(opcode, false));
+                    }
+                    // GETINFO
+                    0x88 => {
+                        let selector = try!(self.pop());
+                        let mut result = 0;
+                        if selector & 1 != 0 {
+                            result |= RASTERIZER_VERSION
+                        }
+                        if selector & 32 != 0 {
+                            result |= GETINFO_GRAYSCALE
+                        }
+                        try!(self.push(result))
+                    }
+                    // IDEF
+                    0x89 => {
+                        let defined_opcode = try!(self.pop()) as u8;
+                        let end = try!(find_end_of_definition(code, next_pc));
+                        let body = Arc::new(code[next_pc..end].to_vec());
+                        self.instruction_definitions.retain(|&(opcode, _)| {
+                            opcode != defined_opcode
+                        });
+                        self.instruction_definitions.push((defined_opcode, body));
+                        next_pc = end + 1
+                    }
+                    // ROLL
+                    0x8a => {
+                        if self.stack.len() < 3 {
+                            return Err(Error::HintingFailed)
+                        }
+                        let index = self.stack.len() - 3;
+                        let value = self.stack.remove(index);
+                        try!(self.push(value))
+                    }
+                    // INSTCTRL
+                    0x8e => {
+                        let selector = try!(self.pop());
+                        let value = try!(self.pop());
+                        if self.program == Program::ControlValue && selector >= 1 &&
+                                selector <= 3 {
+                            let flag = 1 << (selector - 1);
+                            self.state.instruct_control &= !flag;
+                            if value != 0 {
+                                self.state.instruct_control |= flag
+                            }
+                        }
+                    }
+                    // Instructions that the font defined itself with `IDEF`; anything else,
+                    // including a stray `ENDF`, is an error.
+                    _ => {
+                        let body = self.instruction_definitions
+                                       .iter()
+                                       .find(|&&(defined_opcode, _)| defined_opcode == opcode)
+                                       .map(|&(_, ref body)| body.clone());
+                        match body {
+                            Some(body) => try!(self.execute(&body, depth + 1)),
+                            None => return Err(Error::HintingFailed),
+                        }
+                    }
+                }
+            }
+
+            pc = next_pc
+        }
+
+        Ok(())
+    }
+
+    // Does the work of `MDRP[abcde]`.
+    fn move_direct_relative_point(&mut self, opcode: u8) -> Result<(), Error> {
+        let (reference_zone, zone) = (self.zone(0), self.zone(1));
+        let point = try!(self.pop_point(zone));
+        let reference_point = try!(self.reference_point(0, reference_zone));
+
+        let mut original_distance = self.original_distance(zone,
+                                                           point,
+                                                           reference_zone,
+                                                           reference_point);
+        original_distance = self.apply_single_width(original_distance);
+
+        let mut distance = if opcode & 4 != 0 {
+            self.round(original_distance)
+        } else {
+            original_distance
+        };
+        if opcode & 8 != 0 {
+            distance = self.apply_minimum_distance(original_distance, distance)
+        }
+
+        let current_distance = self.project(self.current(zone, point),
+                                            self.current(reference_zone, reference_point));
+        self.move_point(zone, point, distance.wrapping_sub(current_distance), true);
+
+        self.state.reference_points[1] = reference_point;
+        self.state.reference_points[2] = point;
+        if opcode & 16 != 0 {
+            self.state.reference_points[0] = point
+        }
+        Ok(())
+    }
+
+    // Does the work of `MIRP[abcde]`.
+    fn move_indirect_relative_point(&mut self, opcode: u8) -> Result<(), Error> {
+        let cvt_index = try!(self.pop());
+        let (reference_zone, zone) = (self.zone(0), self.zone(1));
+        let point = try!(self.pop_point(zone));
+        let reference_point = try!(self.reference_point(0, reference_zone));
+
+        // An index of -1 stands for a distance of zero.
+        let mut cvt_distance = if cvt_index == -1 {
+            0
+        } else {
+            try!(self.read_cvt(cvt_index))
+        };
+        cvt_distance = self.apply_single_width(cvt_distance);
+
+        // Like the Windows rasterizer, place a twilight point at the distance from the reference
+        // point in the original outline too.
+        if zone == TWILIGHT_ZONE {
+            let freedom_vector = self.state.freedom_vector;
+            let reference = self.zones[reference_zone].original[reference_point];
+            let position =
+                Point2D::new(reference.x.wrapping_add((cvt_distance as f32 *
+                                                       freedom_vector.x).round() as i32),
+                             reference.y.wrapping_add((cvt_distance as f32 *
+                                                       freedom_vector.y).round() as i32));
+            self.zones[zone].original[point] = position;
+            self.zones[zone].current[point] = position;
+        }
+
+        let (original, reference_original) = (self.zones[zone].original[point],
+                                              self.zones[reference_zone].original[reference_point]);
+        let original_distance =
+            self.state.dual_projection_vector.dot(original.x.wrapping_sub(reference_original.x),
+                                                  original.y.wrapping_sub(reference_original.y));
+        let current_distance = self.project(self.current(zone, point),
+                                            self.current(reference_zone, reference_point));
+
+        if self.state.auto_flip && (original_distance ^ cvt_distance) < 0 {
+            cvt_distance = cvt_distance.wrapping_neg()
+        }
+
+        let mut distance = if opcode & 4 != 0 {
+            // As in the Windows rasterizer, the cut-in only applies within one zone.
+            if reference_zone == zone &&
+                    (cvt_distance as i64 - original_distance as i64).abs() >
+                    self.state.control_value_cut_in as i64 {
+                cvt_distance = original_distance
+            }
+            self.round(cvt_distance)
+        } else {
+            cvt_distance
+        };
+        if opcode & 8 != 0 {
+            distance = self.apply_minimum_distance(original_distance, distance)
+        }
+
+        self.move_point(zone, point, distance.wrapping_sub(current_distance), true);
+
+        self.state.reference_points[1] = reference_point;
+        self.state.reference_points[2] = point;
+        if opcode & 16 != 0 {
+            self.state.reference_points[0] = point
+        }
+        Ok(())
+    }
+
+    // Replaces a distance with the single width value if it's within the single width cut-in of
+    // it.
+    fn apply_single_width(&self, distance: i32) -> i32 {
+        let single_width_value = self.state.single_width_value;
+        if (distance as i64 - single_width_value as i64).abs() >=
+                self.state.single_width_cut_in as i64 {
+            distance
+        } else if distance >= 0 {
+            single_width_value
+        } else {
+            single_width_value.wrapping_neg()
+        }
+    }
+
+    // Keeps a distance at least the minimum distance away from zero, on the side of the original
+    // distance.
+    fn apply_minimum_distance(&self, original_distance: i32, distance: i32) -> i32 {
+        let minimum_distance = self.state.minimum_distance;
+        if original_distance >= 0 {
+            cmp::max(distance, minimum_distance)
+        } else {
+            cmp::min(distance, minimum_distance.wrapping_neg())
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, value: i32) -> Result<(), Error> {
+        if self.stack.len() >= self.max_stack_depth {
+            return Err(Error::HintingFailed)
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Result<i32, Error> {
+        self.stack.pop().ok_or(Error::HintingFailed)
+    }
+
+    // Pops a nonnegative index.
+    #[inline]
+    fn pop_index(&mut self) -> Result<usize, Error> {
+        let index = try!(self.pop());
+        if index < 0 {
+            return Err(Error::HintingFailed)
+        }
+        Ok(index as usize)
+    }
+
+    // Pops a zone number for `SZP0` and friends.
+    fn pop_zone(&mut self) -> Result<usize, Error> {
+        match try!(self.pop()) {
+            0 => Ok(TWILIGHT_ZONE),
+            1 => Ok(GLYPH_ZONE),
+            _ => Err(Error::HintingFailed),
+        }
+    }
+
+    // Pops the number of a point in the given zone.
+    fn pop_point(&mut self, zone: usize) -> Result<usize, Error> {
+        let point = try!(self.pop_index());
+        if point >= self.zones[zone].current.len() {
+            return Err(Error::HintingFailed)
+        }
+        Ok(point)
+    }
+
+    // Pops the 1-based position of an element counted from the top of the stack, as `CINDEX` and
+    // `MINDEX` take, and returns its index in the stack.
+    fn stack_index(&mut self) -> Result<usize, Error> {
+        let position = try!(self.pop_index());
+        if position == 0 || position > self.stack.len() {
+            return Err(Error::HintingFailed)
+        }
+        Ok(self.stack.len() - position)
+    }
+
+    // Returns the zone that zone pointer `zp0`, `zp1`, or `zp2` refers to.
+    #[inline]
+    fn zone(&self, zone_pointer: usize) -> usize {
+        self.state.zone_pointers[zone_pointer]
+    }
+
+    // Returns reference point `rp0`, `rp1`, or `rp2`, checking that it's in the given zone.
+    fn reference_point(&self, index: usize, zone: usize) -> Result<usize, Error> {
+        let point = self.state.reference_points[index];
+        if point >= self.zones[zone].current.len() {
+            return Err(Error::HintingFailed)
+        }
+        Ok(point)
+    }
+
+    #[inline]
+    fn current(&self, zone: usize, point: usize) -> Point2D<i32> {
+        self.zones[zone].current[point]
+    }
+
+    fn function(&self, function: i32) -> Result<Arc<Vec<u8>>, Error> {
+        if function < 0 {
+            return Err(Error::HintingFailed)
+        }
+        match self.functions.get(function as usize) {
+            Some(&Some(ref body)) => Ok(body.clone()),
+            _ => Err(Error::HintingFailed),
+        }
+    }
+
+    fn read_cvt(&self, index: i32) -> Result<i32, Error> {
+        if index < 0 {
+            return Err(Error::HintingFailed)
+        }
+        self.cvt.get(index as usize).cloned().ok_or(Error::HintingFailed)
+    }
+
+    fn write_cvt(&mut self, index: i32, value: i32) -> Result<(), Error> {
+        if index < 0 {
+            return Err(Error::HintingFailed)
+        }
+        match self.cvt.get_mut(index as usize) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(Error::HintingFailed),
+        }
+    }
+
+    // Returns the loop count for an instruction that repeats, and resets it to 1. Like FreeType,
+    // an instruction without enough arguments on the stack for every repetition does nothing.
+    fn take_loop_count(&mut self) -> i32 {
+        let count = self.state.loop_count;
+        self.state.loop_count = 1;
+        if (self.stack.len() as i64) < count as i64 {
+            0
+        } else {
+            count
+        }
+    }
+
+    // Returns the distance from `b` to `a` along the projection vector.
+    #[inline]
+    fn project(&self, a: Point2D<i32>, b: Point2D<i32>) -> i32 {
+        self.state.projection_vector.dot(a.x.wrapping_sub(b.x), a.y.wrapping_sub(b.y))
+    }
+
+    // Returns the distance between two points in the original outline along the dual projection
+    // vector. Points in the glyph zone are measured in font units and scaled, as FreeType does.
+    fn original_distance(&self, zone_a: usize, a: usize, zone_b: usize, b: usize) -> i32 {
+        if zone_a == TWILIGHT_ZONE || zone_b == TWILIGHT_ZONE {
+            return self.dual_distance(false, zone_a, a, zone_b, b)
+        }
+        util::mul_fix(self.dual_distance(true, zone_a, a, zone_b, b), self.scale)
+    }
+
+    // Returns the distance between two points along the dual projection vector, in font units if
+    // `unscaled` is set, and in the scaled original outline otherwise.
+    fn dual_distance(&self, unscaled: bool, zone_a: usize, a: usize, zone_b: usize, b: usize)
+                     -> i32 {
+        let (a, b) = if unscaled {
+            (self.zones[zone_a].unscaled[a], self.zones[zone_b].unscaled[b])
+        } else {
+            (self.zones[zone_a].original[a], self.zones[zone_b].original[b])
+        };
+        self.state.dual_projection_vector.dot(a.x.wrapping_sub(b.x), a.y.wrapping_sub(b.y))
+    }
+
+    // Moves a point along the freedom vector so that its projection changes by `distance`.
+    fn move_point(&mut self, zone: usize, point: usize, distance: i32, touch: bool) {
+        let (dx, dy) = self.freedom_displacement(distance);
+        self.shift_point(zone, point, dx, dy, touch)
+    }
+
+    // Moves a point of the original outline, as `move_point()` does for the current outline.
+    fn move_original_point(&mut self, zone: usize, point: usize, distance: i32) {
+        let (dx, dy) = self.freedom_displacement(distance);
+        let position = &mut self.zones[zone].original[point];
+        position.x = position.x.wrapping_add(dx);
+        position.y = position.y.wrapping_add(dy);
+    }
+
+    // Returns the movement along the freedom vector that changes a point's projection by
+    // `distance`.
+    fn freedom_displacement(&self, distance: i32) -> (i32, i32) {
+        let (freedom_vector, projection_vector) = (self.state.freedom_vector,
+                                                   self.state.projection_vector);
+        let mut dot = freedom_vector.x * projection_vector.x +
+            freedom_vector.y * projection_vector.y;
+        if dot.abs() < 1.0 / 16.0 {
+            dot = 1.0
+        }
+        ((distance as f32 * freedom_vector.x / dot).round() as i32,
+         (distance as f32 * freedom_vector.y / dot).round() as i32)
+    }
+
+    // Moves a point by the given amount, touching it in the directions the freedom vector
+    // allows if `touch` is set.
+    fn shift_point(&mut self, zone: usize, point: usize, dx: i32, dy: i32, touch: bool) {
+        let freedom_vector = self.state.freedom_vector;
+        let zone = &mut self.zones[zone];
+        if freedom_vector.x != 0.0 {
+            zone.current[point].x = zone.current[point].x.wrapping_add(dx);
+            if touch {
+                zone.touched[point] |= TOUCHED_X
+            }
+        }
+        if freedom_vector.y != 0.0 {
+            zone.current[point].y = zone.current[point].y.wrapping_add(dy);
+            if touch {
+                zone.touched[point] |= TOUCHED_Y
+            }
+        }
+    }
+
+    // Rounds a distance according to the round state.
+    fn round(&self, distance: i32) -> i32 {
+        match self.state.round_state {
+            RoundState::Grid => round_to_grid(distance),
+            RoundState::HalfGrid => {
+                round_with(distance, |distance| (distance & !63).wrapping_add(32))
+            }
+            RoundState::DoubleGrid => {
+                round_with(distance, |distance| distance.wrapping_add(16) & !31)
+            }
+            RoundState::DownToGrid => round_with(distance, |distance| distance & !63),
+            RoundState::UpToGrid => {
+                round_with(distance, |distance| distance.wrapping_add(63) & !63)
+            }
+            RoundState::Off => distance,
+            RoundState::Super(period, phase, threshold) => {
+                // Work in 64 bits, since the distance may be anything that a program computes.
+                let (period, phase) = (period as i64, phase as i64);
+                let magnitude = (distance as i64).abs() - phase + threshold as i64;
+                let mut rounded = if magnitude >= 0 {
+                    magnitude / period * period
+                } else {
+                    -((-magnitude + period - 1) / period * period)
+                } + phase;
+                if rounded < 0 {
+                    rounded = phase
+                }
+                let rounded = if distance >= 0 { rounded } else { -rounded };
+                cmp::max(cmp::min(rounded, i32::MAX as i64), i32::MIN as i64) as i32
+            }
+        }
+    }
+
+    // Sets the round state for `SROUND` or, if `diagonal` is set, `S45ROUND`.
+    fn set_super_round(&mut self, selector: i32, diagonal: bool) {
+        let grid_period = if diagonal { 64.0 * 0.5f32.sqrt() } else { 64.0 };
+        let period = match (selector >> 6) & 3 {
+            0 => grid_period * 0.5,
+            2 => grid_period * 2.0,
+            _ => grid_period,
+        };
+        let phase = match (selector >> 4) & 3 {
+            0 => 0.0,
+            1 => period * 0.25,
+            2 => period * 0.5,
+            _ => period * 0.75,
+        };
+        let threshold = match selector & 15 {
+            0 => period - 1.0,
+            threshold => (threshold - 4) as f32 * period / 8.0,
+        };
+        self.state.round_state = RoundState::Super(cmp::max(period.round() as i32, 1),
+                                                   phase.round() as i32,
+                                                   threshold.round() as i32)
+    }
+
+    // Pops two points and returns the unit vector along or, for odd opcodes, perpendicular to
+    // the line from the second to the first, measured in the original outline if `original` is
+    // set.
+    fn vector_to_line(&mut self, opcode: u8, original: bool) -> Result<Vector, Error> {
+        let (zone_2, zone_1) = (self.zone(2), self.zone(1));
+        if self.stack.len() < 2 {
+            return Err(Error::HintingFailed)
+        }
+
+        // `SDPVTL` computes two vectors from the same points, so they stay on the stack until the
+        // second call.
+        let (point_2, point_1) = (self.stack[self.stack.len() - 1],
+                                  self.stack[self.stack.len() - 2]);
+        if !original {
+            self.stack.truncate(self.stack.len() - 2)
+        }
+        if point_1 < 0 || point_2 < 0 ||
+                point_1 as usize >= self.zones[zone_1].current.len() ||
+                point_2 as usize >= self.zones[zone_2].current.len() {
+            return Err(Error::HintingFailed)
+        }
+
+        let (a, b) = if original {
+            (self.zones[zone_1].original[point_1 as usize],
+             self.zones[zone_2].original[point_2 as usize])
+        } else {
+            (self.current(zone_1, point_1 as usize), self.current(zone_2, point_2 as usize))
+        };
+        let vector = if a == b {
+            X_AXIS
+        } else {
+            Vector::normalize(a.x as f64 - b.x as f64, a.y as f64 - b.y as f64)
+        };
+        if opcode & 1 != 0 {
+            Ok(vector.perpendicular())
+        } else {
+            Ok(vector)
+        }
+    }
+
+    // Returns the reference point for `SHP`, `SHC`, or `SHZ` and the displacement along the
+    // freedom vector that matches how far it has moved along the projection vector.
+    fn reference_displacement(&self, opcode: u8) -> Result<(usize, usize, i32, i32), Error> {
+        let (zone, point) = if opcode & 1 != 0 {
+            (self.zone(0), try!(self.reference_point(1, self.zone(0))))
+        } else {
+            (self.zone(1), try!(self.reference_point(2, self.zone(1))))
+        };
+        let distance = self.project(self.current(zone, point), self.zones[zone].original[point]);
+        let (dx, dy) = self.freedom_displacement(distance);
+        Ok((zone, point, dx, dy))
+    }
+
+    // Does the work of `ISECT`.
+    fn intersect(&mut self) -> Result<(), Error> {
+        let (zone_a, zone_b, zone) = (self.zone(1), self.zone(0), self.zone(2));
+        let b1 = try!(self.pop_point(zone_b));
+        let b0 = try!(self.pop_point(zone_b));
+        let a1 = try!(self.pop_point(zone_a));
+        let a0 = try!(self.pop_point(zone_a));
+        let point = try!(self.pop_point(zone));
+
+        let (a0, a1) = (self.current(zone_a, a0), self.current(zone_a, a1));
+        let (b0, b1) = (self.current(zone_b, b0), self.current(zone_b, b1));
+        // Work in floating point, since the points may be anywhere that a program moved them, and
+        // the products of their differences don't fit in 64 bits. Distances within the range of
+        // real outlines come out exact.
+        let (a0, a1) = (to_f64(&a0), to_f64(&a1));
+        let (b0, b1) = (to_f64(&b0), to_f64(&b1));
+        let (dax, day) = (a1.x - a0.x, a1.y - a0.y);
+        let (dbx, dby) = (b1.x - b0.x, b1.y - b0.y);
+        let (dx, dy) = (b0.x - a0.x, b0.y - a0.y);
+
+        let discriminant = dax * -dby + day * dbx;
+        let dot_product = dax * dbx + day * dby;
+
+        // Lines that are nearly parallel meet at the middle of the four points.
+        let position = if discriminant.abs() * 19.0 > dot_product.abs() {
+            let value = dx * -dby + dy * dbx;
+            Point2D::new(a0.x + (dax * value / discriminant).trunc(),
+                         a0.y + (day * value / discriminant).trunc())
+        } else {
+            Point2D::new(((a0.x + a1.x + b0.x + b1.x) / 4.0).trunc(),
+                         ((a0.y + a1.y + b0.y + b1.y) / 4.0).trunc())
+        };
+        let position = Point2D::new(position.x as i32, position.y as i32);
+
+        self.zones[zone].current[point] = position;
+        self.zones[zone].touched[point] |= TOUCHED_X | TOUCHED_Y;
+        Ok(())
+    }
+
+    // Does the work of `IP`.
+    fn interpolate_points(&mut self) -> Result<(), Error> {
+        let (zone_1, zone_2, zone) = (self.zone(0), self.zone(1), self.zone(2));
+        let reference_1 = try!(self.reference_point(1, zone_1));
+        let reference_2 = try!(self.reference_point(2, zone_2));
+
+        // The ratio of original distances is taken in font units, unless any zone is the twilight
+        // zone.
+        let unscaled = zone_1 != TWILIGHT_ZONE && zone_2 != TWILIGHT_ZONE && zone != TWILIGHT_ZONE;
+        let original_range = self.dual_distance(unscaled, zone_2, reference_2, zone_1, reference_1);
+        let reference_1_position = self.current(zone_1, reference_1);
+        let current_range = self.project(self.current(zone_2, reference_2),
+                                         reference_1_position);
+
+        for _ in 0..self.take_loop_count() {
+            let point = try!(self.pop_point(zone));
+            let original_distance = self.dual_distance(unscaled, zone, point, zone_1, reference_1);
+            let current_distance = self.project(self.current(zone, point), reference_1_position);
+            let new_distance = if original_distance == 0 {
+                0
+            } else if original_range == 0 {
+                current_distance
+            } else {
+                mul_div(original_distance, current_range, original_range)
+            };
+            self.move_point(zone, point, new_distance.wrapping_sub(current_distance), true)
+        }
+        Ok(())
+    }
+
+    // Does the work of `IUP`, interpolating the points of the glyph zone that haven't been
+    // touched in the given direction between those that have, contour by contour.
+    fn interpolate_untouched_points(&mut self, x_axis: bool) {
+        let flag = if x_axis { TOUCHED_X } else { TOUCHED_Y };
+        let zone = &mut self.zones[GLYPH_ZONE];
+        let coordinate = |point: Point2D<i32>| if x_axis { point.x } else { point.y };
+
+        let mut contour_start = 0;
+        for contour in 0..zone.contour_ends.len() {
+            let contour_end = cmp::min(zone.contour_ends[contour] as usize + 1,
+                                       zone.contour_point_count());
+            let range = contour_start..contour_end;
+            contour_start = contour_end;
+
+            let first_touched = match range.clone().find(|&point| {
+                zone.touched[point] & flag != 0
+            }) {
+                None => continue,
+                Some(point) => point,
+            };
+
+            let mut last_touched = first_touched;
+            for point in (first_touched + 1)..range.end {
+                if zone.touched[point] & flag != 0 {
+                    interpolate_range(zone, x_axis, last_touched + 1, point, last_touched, point);
+                    last_touched = point
+                }
+            }
+
+            if last_touched == first_touched {
+                // Only one point was touched, so the whole contour moves with it.
+                let delta = coordinate(zone.current[first_touched])
+                    .wrapping_sub(coordinate(zone.original[first_touched]));
+                for point in range {
+                    if point != first_touched {
+                        let position = &mut zone.current[point];
+                        if x_axis {
+                            position.x = position.x.wrapping_add(delta)
+                        } else {
+                            position.y = position.y.wrapping_add(delta)
+                        }
+                    }
+                }
+                continue
+            }
+
+            // Interpolate the points that wrap around from the last touched point to the first.
+            let (start, end) = (range.start, range.end);
+            interpolate_range(zone, x_axis, last_touched + 1, end, last_touched, first_touched);
+            interpolate_range(zone, x_axis, start, first_touched, last_touched, first_touched);
+        }
+    }
+
+    // Does the work of `DELTAP1`, `DELTAP2`, and `DELTAP3`.
+    fn delta_points(&mut self, opcode: u8) -> Result<(), Error> {
+        let count = try!(self.pop());
+        let zone = self.zone(0);
+        for _ in 0..cmp::max(count, 0) {
+            // Like FreeType, stop quietly if the stack runs out of pairs.
+            if self.stack.len() < 2 {
+                break
+            }
+            let point = try!(self.pop());
+            let argument = try!(self.pop());
+
+            // Points out of range are skipped, as in FreeType.
+            if point < 0 || point as usize >= self.zones[zone].current.len() {
+                continue
+            }
+            if let Some(distance) = self.delta_distance(opcode, 0x5d, argument) {
+                self.move_point(zone, point as usize, distance, true)
+            }
+        }
+        Ok(())
+    }
+
+    // Does the work of `DELTAC1`, `DELTAC2`, and `DELTAC3`.
+    fn delta_cvt(&mut self, opcode: u8) -> Result<(), Error> {
+        let count = try!(self.pop());
+        for _ in 0..cmp::max(count, 0) {
+            if self.stack.len() < 2 {
+                break
+            }
+            let index = try!(self.pop());
+            let argument = try!(self.pop());
+            // Entries out of range are skipped too.
+            if let Some(distance) = self.delta_distance(opcode, 0x73, argument) {
+                if let Ok(value) = self.read_cvt(index) {
+                    try!(self.write_cvt(index, value.wrapping_add(distance)))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the distance that a delta instruction's argument calls for at the current size, or
+    // `None` if it applies to another size. `first_opcode` is the opcode of the first of the
+    // three related instructions, which apply to successive ranges of 16 sizes.
+    fn delta_distance(&self, opcode: u8, first_opcode: u8, argument: i32) -> Option<i32> {
+        let range = match opcode - first_opcode {
+            0 => 0,
+            // `DELTAP2` and `DELTAP3` aren't next to `DELTAP1`.
+            _ if first_opcode == 0x5d => (opcode - 0x70) as i32,
+            offset => offset as i32,
+        };
+        let size = ((argument & 0xf0) >> 4) + range * 16;
+        if size.wrapping_add(self.state.delta_base) != self.pixels_per_em as i32 {
+            return None
+        }
+
+        let mut steps = (argument & 0xf) - 8;
+        if steps >= 0 {
+            steps += 1
+        }
+        Some(steps * 64 / (1 << self.state.delta_shift))
+    }
+}
+
+// Interpolates the coordinates of points `start` up to `end` in one direction between reference
+// points `reference_1` and `reference_2`, as `IUP` does.
+fn interpolate_range(zone: &mut Zone,
+                     x_axis: bool,
+                     start: usize,
+                     end: usize,
+                     reference_1: usize,
+                     reference_2: usize) {
+    if start >= end {
+        return
+    }
+
+    let coordinate = |point: Point2D<i32>| if x_axis { point.x } else { point.y };
+    let (mut reference_1, mut reference_2) = (reference_1, reference_2);
+    if coordinate(zone.unscaled[reference_1]) > coordinate(zone.unscaled[reference_2]) {
+        mem::swap(&mut reference_1, &mut reference_2)
+    }
+
+    let (unscaled_1, unscaled_2) = (coordinate(zone.unscaled[reference_1]),
+                                    coordinate(zone.unscaled[reference_2]));
+    let (original_1, original_2) = (coordinate(zone.original[reference_1]),
+                                    coordinate(zone.original[reference_2]));
+    let (current_1, current_2) = (coordinate(zone.current[reference_1]),
+                                  coordinate(zone.current[reference_2]));
+    let (delta_1, delta_2) = (current_1.wrapping_sub(original_1),
+                              current_2.wrapping_sub(original_2));
+    let scale = if current_1 == current_2 || unscaled_1 == unscaled_2 {
+        None
+    } else {
+        Some(util::div_fix(current_2.wrapping_sub(current_1), unscaled_2 - unscaled_1))
+    };
+
+    for point in start..end {
+        let original = coordinate(zone.original[point]);
+        let value = if original <= original_1 {
+            original.wrapping_add(delta_1)
+        } else if original >= original_2 {
+            original.wrapping_add(delta_2)
+        } else {
+            match scale {
+                None => current_1,
+                Some(scale) => {
+                    let unscaled = coordinate(zone.unscaled[point]);
+                    current_1.wrapping_add(util::mul_fix(unscaled - unscaled_1, scale))
+                }
+            }
+        };
+        if x_axis {
+            zone.current[point].x = value
+        } else {
+            zone.current[point].y = value
+        }
+    }
+}
+
+// Applies a rounding function to the magnitude of a distance, keeping its sign. A distance that
+// would round past zero rounds to zero instead.
+#[inline]
+fn round_with<F>(distance: i32, round: F) -> i32 where F: Fn(i32) -> i32 {
+    if distance >= 0 {
+        cmp::max(round(distance), 0)
+    } else {
+        cmp::max(round(distance.wrapping_neg()), 0).wrapping_neg()
+    }
+}
+
+#[inline]
+fn to_f64(point: &Point2D<i32>) -> Point2D<f64> {
+    Point2D::new(point.x as f64, point.y as f64)
+}
+
+// Computes `a * b / c`, rounding to nearest, as FreeType's `FT_MulDiv()` does.
+fn mul_div(a: i32, b: i32, c: i32) -> i32 {
+    if c == 0 {
+        return 0x7fffffff
+    }
+    let product = a as i64 * b as i64;
+    let negative = (product < 0) != (c < 0);
+    let (product, c) = (product.abs(), (c as i64).abs());
+    let quotient = ((product + c / 2) / c) as i32;
+    if negative {
+        quotient.wrapping_neg()
+    } else {
+        quotient
+    }
+}
+
+// Returns the length in bytes of the instruction at `pc`, including any data pushed with it.
+fn instruction_length(code: &[u8], pc: usize) -> Result<usize, Error> {
+    let opcode = code[pc];
+    Ok(match opcode {
+        // NPUSHB, NPUSHW
+        0x40 | 0x41 => {
+            let count = *try!(code.get(pc + 1).ok_or(Error::HintingFailed)) as usize;
+            if opcode == 0x40 {
+                2 + count
+            } else {
+                2 + count * 2
+            }
+        }
+        // PUSHB[abc], PUSHW[abc]
+        _ if opcode >= 0xb0 && opcode <= 0xb7 => 1 + (opcode - 0xb0) as usize + 1,
+        _ if opcode >= 0xb8 && opcode <= 0xbf => 1 + ((opcode - 0xb8) as usize + 1) * 2,
+        _ => 1,
+    })
+}
+
+// Returns the position of the end of a conditional branch that starts at `pc`: just past the
+// matching `EIF`, or just past the matching `ELSE` if `stop_at_else` is set.
+fn skip_conditional(code: &[u8], mut pc: usize, stop_at_else: bool) -> Result<usize, Error> {
+    let mut nesting = 0;
+    while pc < code.len() {
+        match code[pc] {
+            // IF
+            0x58 => nesting += 1,
+            // ELSE
+            0x1b if nesting == 0 && stop_at_else => return Ok(pc + 1),
+            // EIF
+            0x59 => {
+                if nesting == 0 {
+                    return Ok(pc + 1)
+                }
+                nesting -= 1
+            }
+            _ => {}
+        }
+        pc += try!(instruction_length(code, pc))
+    }
+    Err(Error::HintingFailed)
+}
+
+// Returns the position of the `ENDF` that ends the function or instruction definition whose body
+// starts at `pc`.
+fn find_end_of_definition(code: &[u8], mut pc: usize) -> Result<usize, Error> {
+    while pc < code.len() {
+        match code[pc] {
+            // ENDF
+            0x2d => return Ok(pc),
+            // FDEF, IDEF: definitions don't nest.
+            0x2c | 0x89 => return Err(Error::HintingFailed),
+            _ => pc += try!(instruction_length(code, pc)),
+        }
+    }
+    Err(Error::HintingFailed)
+}
+
+// Returns the position that a jump instruction at `pc` with the given offset goes to.
+fn jump(code: &[u8], pc: usize, offset: i32) -> Result<usize, Error> {
+    let target = pc as i64 + offset as i64;
+    if offset == 0 || target < 0 || target > code.len() as i64 {
+        return Err(Error::HintingFailed)
+    }
+    Ok(target as usize)
+}
@@ -39,7 +39,11 @@ impl<'a> HmtxTable<'a> {
             try!(reader.jump(mem::size_of::<u16>() * 2 *
                              (hhea_table.number_of_h_metrics - 1) as usize).map_err(Error::eof));
             advance_width = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
-            try!(reader.jump(mem::size_of::<i16>() * glyph_id as usize).map_err(Error::eof));
+
+            // Skip the left-side bearing of the last full record, then the bearings of the glyphs
+            // between it and this one in the trailing array.
+            let bearings_to_skip = 1 + (glyph_id - hhea_table.number_of_h_metrics) as usize;
+            try!(reader.jump(mem::size_of::<i16>() * bearings_to_skip).map_err(Error::eof));
         }
 
         // Read the left-side bearing.
@@ -0,0 +1,237 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The structures shared by the OpenType layout tables (`GSUB` and `GPOS`).
+//!
+//! See https://www.microsoft.com/typography/otspec/chapter2.htm.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable, Tag};
+use std::cmp::Ordering;
+use std::mem;
+use util::Jump;
+
+#[derive(Clone, Copy)]
+pub struct LayoutTable<'a> {
+    feature_list: &'a [u8],
+    lookup_list: &'a [u8],
+}
+
+/// A lookup, with any extension subtables resolved.
+pub struct Lookup<'a> {
+    pub lookup_type: u16,
+    pub subtables: Vec<&'a [u8]>,
+}
+
+impl<'a> LayoutTable<'a> {
+    pub fn new(table: FontTable) -> Result<LayoutTable, Error> {
+        let mut reader = table.bytes;
+        let major_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let _minor_version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if major_version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        let _script_list_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let feature_list_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let lookup_list_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        Ok(LayoutTable {
+            feature_list: try!(offset_slice(table.bytes, feature_list_offset as u32)),
+            lookup_list: try!(offset_slice(table.bytes, lookup_list_offset as u32)),
+        })
+    }
+
+    /// Returns the indices of the lookups that implement any of the given features, in the order
+    /// in which they must be applied.
+    ///
+    /// Features are gathered from all scripts and language systems.
+    pub fn lookup_indices_for_features(&self, features: &[Tag]) -> Result<Vec<u16>, Error> {
+        let mut reader = self.feature_list;
+        let feature_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        let mut lookup_indices = vec![];
+        for _ in 0..feature_count {
+            let feature_tag = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+            let feature_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if !features.contains(&feature_tag) {
+                continue
+            }
+
+            // Skip over the feature parameters offset.
+            let mut feature_reader = try!(offset_slice(self.feature_list, feature_offset as u32));
+            try!(feature_reader.jump(mem::size_of::<u16>()).map_err(Error::eof));
+
+            let lookup_index_count = try!(feature_reader.read_u16::<BigEndian>()
+                                                        .map_err(Error::eof));
+            for _ in 0..lookup_index_count {
+                lookup_indices.push(try!(feature_reader.read_u16::<BigEndian>()
+                                                       .map_err(Error::eof)))
+            }
+        }
+
+        // Lookups are applied in the order of the lookup list, once each.
+        lookup_indices.sort();
+        lookup_indices.dedup();
+        Ok(lookup_indices)
+    }
+
+    /// Returns the lookup with the given index.
+    ///
+    /// `extension_type` is the lookup type that this table uses for extension subtables, which
+    /// are replaced with the subtables they point to.
+    pub fn lookup(&self, lookup_index: u16, extension_type: u16) -> Result<Lookup<'a>, Error> {
+        let mut reader = self.lookup_list;
+        let lookup_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if lookup_index >= lookup_count {
+            return Err(Error::Failed)
+        }
+
+        try!(reader.jump(lookup_index as usize * mem::size_of::<u16>()).map_err(Error::eof));
+        let lookup_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let lookup_table = try!(offset_slice(self.lookup_list, lookup_offset as u32));
+
+        let mut reader = lookup_table;
+        let lookup_type = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let _lookup_flag = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        let subtable_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        let mut lookup = Lookup {
+            lookup_type: lookup_type,
+            subtables: vec![],
+        };
+        for _ in 0..subtable_count {
+            let subtable_offset = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let subtable = try!(offset_slice(lookup_table, subtable_offset as u32));
+            if lookup_type != extension_type {
+                lookup.subtables.push(subtable);
+                continue
+            }
+
+            let mut extension_reader = subtable;
+            let format = try!(extension_reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if format != 1 {
+                return Err(Error::UnknownFormat)
+            }
+            lookup.lookup_type = try!(extension_reader.read_u16::<BigEndian>()
+                                                      .map_err(Error::eof));
+            let extension_offset = try!(extension_reader.read_u32::<BigEndian>()
+                                                        .map_err(Error::eof));
+            lookup.subtables.push(try!(offset_slice(subtable, extension_offset)))
+        }
+
+        Ok(lookup)
+    }
+}
+
+/// Returns the index of the glyph in the given coverage table, or `None` if the table doesn't
+/// cover it.
+pub fn coverage_index(coverage: &[u8], glyph_id: u16) -> Result<Option<u16>, Error> {
+    let mut reader = coverage;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    let count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    match format {
+        1 => {
+            // The glyph array is sorted, so binary search it.
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = (low + high) / 2;
+                let mut glyph_reader = reader;
+                try!(glyph_reader.jump(mid as usize * mem::size_of::<u16>())
+                                 .map_err(Error::eof));
+                let covered_glyph_id = try!(glyph_reader.read_u16::<BigEndian>()
+                                                        .map_err(Error::eof));
+                match glyph_id.cmp(&covered_glyph_id) {
+                    Ordering::Less => high = mid,
+                    Ordering::Greater => low = mid + 1,
+                    Ordering::Equal => return Ok(Some(mid)),
+                }
+            }
+            Ok(None)
+        }
+        2 => {
+            // The ranges are sorted, so binary search them.
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = (low + high) / 2;
+                let mut range_reader = reader;
+                try!(range_reader.jump(mid as usize * mem::size_of::<[u16; 3]>())
+                                 .map_err(Error::eof));
+                let start = try!(range_reader.read_u16::<BigEndian>().map_err(Error::eof));
+                let end = try!(range_reader.read_u16::<BigEndian>().map_err(Error::eof));
+                let start_coverage_index = try!(range_reader.read_u16::<BigEndian>()
+                                                            .map_err(Error::eof));
+                if glyph_id < start {
+                    high = mid
+                } else if glyph_id > end {
+                    low = mid + 1
+                } else {
+                    // A malformed range can run past the largest coverage index.
+                    return match start_coverage_index.checked_add(glyph_id - start) {
+                        Some(coverage_index) => Ok(Some(coverage_index)),
+                        None => Err(Error::Failed),
+                    }
+                }
+            }
+            Ok(None)
+        }
+        _ => Err(Error::UnknownFormat),
+    }
+}
+
+/// Returns the part of `bytes` starting at `offset`.
+pub fn offset_slice(bytes: &[u8], offset: u32) -> Result<&[u8], Error> {
+    let mut reader = bytes;
+    try!(reader.jump(offset as usize).map_err(Error::eof));
+    Ok(reader)
+}
+
+/// Returns the class of the glyph in the given class definition table.
+///
+/// Glyphs that the table doesn't mention are in class 0.
+pub fn glyph_class(class_def: &[u8], glyph_id: u16) -> Result<u16, Error> {
+    let mut reader = class_def;
+    let format = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+    match format {
+        1 => {
+            let start_glyph_id = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let glyph_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            if glyph_id < start_glyph_id || glyph_id - start_glyph_id >= glyph_count {
+                return Ok(0)
+            }
+            try!(reader.jump((glyph_id - start_glyph_id) as usize * mem::size_of::<u16>())
+                       .map_err(Error::eof));
+            Ok(try!(reader.read_u16::<BigEndian>().map_err(Error::eof)))
+        }
+        2 => {
+            // The ranges are sorted, so binary search them.
+            let range_count = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+            let (mut low, mut high) = (0, range_count);
+            while low < high {
+                let mid = (low + high) / 2;
+                let mut range_reader = reader;
+                try!(range_reader.jump(mid as usize * mem::size_of::<[u16; 3]>())
+                                 .map_err(Error::eof));
+                let start = try!(range_reader.read_u16::<BigEndian>().map_err(Error::eof));
+                let end = try!(range_reader.read_u16::<BigEndian>().map_err(Error::eof));
+                let class = try!(range_reader.read_u16::<BigEndian>().map_err(Error::eof));
+                if glyph_id < start {
+                    high = mid
+                } else if glyph_id > end {
+                    low = mid + 1
+                } else {
+                    return Ok(class)
+                }
+            }
+            Ok(0)
+        }
+        _ => Err(Error::UnknownFormat),
+    }
+}
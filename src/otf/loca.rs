@@ -10,38 +10,57 @@
 
 use byteorder::{BigEndian, ReadBytesExt};
 use otf::head::HeadTable;
-use otf::{Error, FontTable};
+use otf::{Error, FontTable, LOCA};
 use util::Jump;
 
 pub struct LocaTable<'a> {
     table: FontTable<'a>,
+    // For fonts from a `FontReader`, the start and end of the data of each glyph read so far, or
+    // `None` for glyphs that haven't been read. The table itself is empty then.
+    loaded_ranges: Option<&'a [Option<(u32, u32)>]>,
 }
 
 impl<'a> LocaTable<'a> {
     pub fn new(loca_table: FontTable<'a>) -> Result<LocaTable<'a>, Error> {
         Ok(LocaTable {
             table: loca_table,
+            loaded_ranges: None,
         })
     }
 
+    pub fn from_loaded_ranges(loaded_ranges: &'a [Option<(u32, u32)>]) -> LocaTable<'a> {
+        LocaTable {
+            table: FontTable {
+                bytes: &[],
+            },
+            loaded_ranges: Some(loaded_ranges),
+        }
+    }
+
     pub fn location_of(&self, head_table: &HeadTable, glyph_id: u16)
                        -> Result<Option<u32>, Error> {
+        if let Some(loaded_ranges) = self.loaded_ranges {
+            return match try!(loaded_range(loaded_ranges, glyph_id)) {
+                (start, end) if start == end => Ok(None),
+                (start, _) => Ok(Some(start)),
+            }
+        }
+
         let mut reader = self.table.bytes;
         let (this_location, next_location) = match head_table.index_to_loc_format {
             0 => {
-                try!(reader.jump(glyph_id as usize * 2).map_err(Error::eof));
+                try!(reader.jump(glyph_id as usize * 2).map_err(eof));
                 let this_location =
-                    try!(reader.read_u16::<BigEndian>().map_err(Error::eof)) as u32 * 2;
-                let next_location = match reader.read_u16::<BigEndian>() {
-                    Ok(next_location) => Ok(next_location as u32 * 2),
-                    Err(_) => Err(Error::UnexpectedEof),
-                };
+                    try!(reader.read_u16::<BigEndian>().map_err(eof)) as u32 * 2;
+                let next_location = reader.read_u16::<BigEndian>()
+                                          .map(|next_location| next_location as u32 * 2)
+                                          .map_err(eof);
                 (this_location, next_location)
             }
             1 => {
-                try!(reader.jump(glyph_id as usize * 4).map_err(Error::eof));
-                let this_location = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
-                let next_location = reader.read_u32::<BigEndian>().map_err(Error::eof);
+                try!(reader.jump(glyph_id as usize * 4).map_err(eof));
+                let this_location = try!(reader.read_u32::<BigEndian>().map_err(eof));
+                let next_location = reader.read_u32::<BigEndian>().map_err(eof);
                 (this_location, next_location)
             }
             _ => return Err(Error::UnknownFormat),
@@ -53,5 +72,56 @@ impl<'a> LocaTable<'a> {
             Ok(Some(this_location))
         }
     }
+
+    /// Returns the start and end offsets of the given glyph's data in the `glyf` table, or `None`
+    /// if the glyph has no data.
+    pub fn range_of(&self, head_table: &HeadTable, glyph_id: u16)
+                    -> Result<Option<(u32, u32)>, Error> {
+        if let Some(loaded_ranges) = self.loaded_ranges {
+            return match try!(loaded_range(loaded_ranges, glyph_id)) {
+                (start, end) if end <= start => Ok(None),
+                range => Ok(Some(range)),
+            }
+        }
+
+        let mut reader = self.table.bytes;
+        let (this_location, next_location) = match head_table.index_to_loc_format {
+            0 => {
+                try!(reader.jump(glyph_id as usize * 2).map_err(eof));
+                let this_location = try!(reader.read_u16::<BigEndian>().map_err(eof));
+                let next_location = try!(reader.read_u16::<BigEndian>().map_err(eof));
+                (this_location as u32 * 2, next_location as u32 * 2)
+            }
+            1 => {
+                try!(reader.jump(glyph_id as usize * 4).map_err(eof));
+                let this_location = try!(reader.read_u32::<BigEndian>().map_err(eof));
+                let next_location = try!(reader.read_u32::<BigEndian>().map_err(eof));
+                (this_location, next_location)
+            }
+            _ => return Err(Error::UnknownFormat),
+        };
+
+        if next_location <= this_location {
+            Ok(None)
+        } else {
+            Ok(Some((this_location, next_location)))
+        }
+    }
 }
 
+// Returns the range of the data of the given glyph among the glyphs that a `FontReader` has read.
+// Glyph IDs past the end of the table are reported as they are for a `loca` table in the font.
+fn loaded_range(loaded_ranges: &[Option<(u32, u32)>], glyph_id: u16)
+                -> Result<(u32, u32), Error> {
+    match loaded_ranges.get(glyph_id as usize) {
+        Some(&Some(range)) => Ok(range),
+        Some(&None) => Err(Error::GlyphNotLoaded(glyph_id)),
+        None => Err(Error::TableTooShort(LOCA)),
+    }
+}
+
+// Reports that the `loca` table ended unexpectedly.
+#[inline]
+fn eof<T>(_: T) -> Error {
+    Error::TableTooShort(LOCA)
+}
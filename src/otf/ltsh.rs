@@ -0,0 +1,49 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+/// The linear threshold table, which tells at which size each glyph's hinted advance width
+/// starts scaling linearly.
+#[derive(Clone, Copy, Debug)]
+pub struct LtshTable<'a> {
+    table: FontTable<'a>,
+    num_glyphs: u16,
+}
+
+impl<'a> LtshTable<'a> {
+    pub fn new(table: FontTable) -> Result<LtshTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version != 0 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        let num_glyphs = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        Ok(LtshTable {
+            table: table,
+            num_glyphs: num_glyphs,
+        })
+    }
+
+    /// Returns the smallest size in pixels per em at and above which the given glyph's advance
+    /// width scales linearly, or `None` if the glyph isn't in the table.
+    pub fn linear_threshold(&self, glyph_id: u16) -> Result<Option<u8>, Error> {
+        if glyph_id >= self.num_glyphs {
+            return Ok(None)
+        }
+
+        let mut reader = self.table.bytes;
+        try!(reader.jump(4 + glyph_id as usize).map_err(Error::eof));
+        Ok(Some(try!(reader.read_u8().map_err(Error::eof))))
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+#[derive(Clone, Debug)]
+pub struct MaxpTable {
+    pub num_glyphs: u16,
+}
+
+impl MaxpTable {
+    pub fn new(table: FontTable) -> Result<MaxpTable, Error> {
+        let mut reader = table.bytes;
+
+        // Skip the version, which only determines which of the fields after this one exist.
+        try!(reader.jump(4).map_err(Error::eof));
+        let num_glyphs = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        Ok(MaxpTable {
+            num_glyphs: num_glyphs,
+        })
+    }
+}
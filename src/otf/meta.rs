@@ -0,0 +1,77 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable, Tag};
+use std::str;
+use util::Jump;
+
+/// The metadata table, which holds miscellaneous metadata about the font as a map from tags to
+/// data.
+#[derive(Clone, Copy, Debug)]
+pub struct MetaTable<'a> {
+    table: FontTable<'a>,
+    num_data_maps: u32,
+}
+
+impl<'a> MetaTable<'a> {
+    pub fn new(table: FontTable) -> Result<MetaTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        if version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Skip over the flags and the reserved field.
+        try!(reader.jump(8).map_err(Error::eof));
+        let num_data_maps = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        Ok(MetaTable {
+            table: table,
+            num_data_maps: num_data_maps,
+        })
+    }
+
+    /// Returns the data with the given tag, or `None` if there is none.
+    pub fn data(&self, tag: Tag) -> Result<Option<&'a [u8]>, Error> {
+        let mut reader = self.table.bytes;
+        try!(reader.jump(16).map_err(Error::eof));
+        for _ in 0..self.num_data_maps {
+            let data_tag = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+            let offset = try!(reader.read_u32::<BigEndian>().map_err(Error::eof)) as usize;
+            let length = try!(reader.read_u32::<BigEndian>().map_err(Error::eof)) as usize;
+            if data_tag != tag {
+                continue
+            }
+
+            return match self.table.bytes.get(offset..offset + length) {
+                Some(data) => Ok(Some(data)),
+                None => Err(Error::UnexpectedEof),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the language tags in the data with the given tag, which must be a comma-separated
+    /// list of ScriptLangTags, such as `dlng` or `slng`.
+    pub fn languages(&self, tag: Tag) -> Result<Vec<String>, Error> {
+        let data = match try!(self.data(tag)) {
+            None => return Ok(vec![]),
+            Some(data) => data,
+        };
+        let data = try!(str::from_utf8(data).map_err(|_| Error::Failed));
+
+        // Whitespace is allowed around each tag.
+        Ok(data.split(',')
+               .map(|language| language.trim())
+               .filter(|language| !language.is_empty())
+               .map(|language| language.to_owned())
+               .collect())
+    }
+}
@@ -10,38 +10,165 @@
 
 //! OpenType fonts.
 
-use byteorder::{BigEndian, ReadBytesExt};
-use charmap::{CodepointRange, GlyphMapping};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use charmap::{CodepointRange, CoverageSet, GlyphMapping};
+use euclid::Point2D;
+use flatten;
+use msdf;
+use otf::avar::AvarTable;
+use otf::base::BaseTable;
+use otf::cblc::CblcTable;
+use otf::cff2::Cff2Table;
 use otf::cmap::CmapTable;
+use otf::colr::ColrTable;
+use otf::cpal::CpalTable;
+use otf::fvar::FvarTable;
+use otf::gasp::GaspTable;
+use otf::gdef::GdefTable;
 use otf::glyf::{GlyfTable, Point};
+use otf::gpos::GposTable;
+use otf::gsub::GsubTable;
+use otf::hdmx::HdmxTable;
 use otf::head::HeadTable;
 use otf::hhea::HheaTable;
 use otf::hmtx::{HmtxTable, HorizontalMetrics};
 use otf::kern::KernTable;
 use otf::loca::LocaTable;
+use otf::ltsh::LtshTable;
+use otf::maxp::MaxpTable;
+use otf::meta::MetaTable;
 use otf::os_2::Os2Table;
-use outline::GlyphBounds;
+use otf::post::PostTable;
+use otf::sbix::SbixTable;
+use otf::svg::SvgTable;
+use outline::{GlyphBounds, GlyphFixedBounds, GlyphPixelBounds, GlyphSubpixelBounds};
+use overlap;
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, SeekFrom};
 use std::mem;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 use std::u16;
-use util::Jump;
+use std::u32;
+use triangulate;
+use util::{self, Jump};
 
+pub use otf::base::BaselineTag;
+pub use otf::fvar::VariationAxis;
+pub use otf::gasp::{GASP_DOGRAY, GASP_GRIDFIT, GASP_SYMMETRIC_GRIDFIT, GASP_SYMMETRIC_SMOOTHING};
+pub use otf::gasp::GaspBehavior;
+pub use otf::gdef::GlyphClass;
+pub use otf::gpos::GlyphAdjustment;
+#[cfg(feature = "hinting")]
+pub use otf::hint::Hinter;
+pub use otf::os_2::{Os2Range, UnicodeRanges};
+pub use otf::svg::SvgDocument;
+
+mod avar;
+mod base;
+mod cblc;
+mod cff2;
 mod cmap;
+mod colr;
+mod cpal;
+mod fvar;
+mod gasp;
+mod gdef;
 mod glyf;
+mod gpos;
+mod gsub;
+mod hdmx;
 mod head;
 mod hhea;
+#[cfg(feature = "hinting")]
+mod hint;
 mod hmtx;
 mod kern;
+mod layout;
 mod loca;
+mod ltsh;
+mod maxp;
+mod meta;
 mod os_2;
+mod post;
+mod sbix;
+mod svg;
 
+const AVAR: u32 = ((b'a' as u32) << 24) |
+                  ((b'v' as u32) << 16) |
+                  ((b'a' as u32) << 8)  |
+                   (b'r' as u32);
+const CBLC: u32 = ((b'C' as u32) << 24) |
+                  ((b'B' as u32) << 16) |
+                  ((b'L' as u32) << 8)  |
+                   (b'C' as u32);
+const CFF: u32 = ((b'C' as u32) << 24) |
+                 ((b'F' as u32) << 16) |
+                 ((b'F' as u32) << 8)  |
+                  (b' ' as u32);
+const CFF2: u32 = ((b'C' as u32) << 24) |
+                  ((b'F' as u32) << 16) |
+                  ((b'F' as u32) << 8)  |
+                   (b'2' as u32);
 const CMAP: u32 = ((b'c' as u32) << 24) |
                   ((b'm' as u32) << 16) |
                   ((b'a' as u32) << 8)  |
                    (b'p' as u32);
+const COLR: u32 = ((b'C' as u32) << 24) |
+                  ((b'O' as u32) << 16) |
+                  ((b'L' as u32) << 8)  |
+                   (b'R' as u32);
+const CPAL: u32 = ((b'C' as u32) << 24) |
+                  ((b'P' as u32) << 16) |
+                  ((b'A' as u32) << 8)  |
+                   (b'L' as u32);
+const CVT: u32 = ((b'c' as u32) << 24) |
+                 ((b'v' as u32) << 16) |
+                 ((b't' as u32) << 8)  |
+                  (b' ' as u32);
+const EBLC: u32 = ((b'E' as u32) << 24) |
+                  ((b'B' as u32) << 16) |
+                  ((b'L' as u32) << 8)  |
+                   (b'C' as u32);
+const FPGM: u32 = ((b'f' as u32) << 24) |
+                  ((b'p' as u32) << 16) |
+                  ((b'g' as u32) << 8)  |
+                   (b'm' as u32);
+const FVAR: u32 = ((b'f' as u32) << 24) |
+                  ((b'v' as u32) << 16) |
+                  ((b'a' as u32) << 8)  |
+                   (b'r' as u32);
+const GASP: u32 = ((b'g' as u32) << 24) |
+                  ((b'a' as u32) << 16) |
+                  ((b's' as u32) << 8)  |
+                   (b'p' as u32);
+const BASE: u32 = ((b'B' as u32) << 24) |
+                  ((b'A' as u32) << 16) |
+                  ((b'S' as u32) << 8)  |
+                   (b'E' as u32);
+const GDEF: u32 = ((b'G' as u32) << 24) |
+                  ((b'D' as u32) << 16) |
+                  ((b'E' as u32) << 8)  |
+                   (b'F' as u32);
 const GLYF: u32 = ((b'g' as u32) << 24) |
                   ((b'l' as u32) << 16) |
                   ((b'y' as u32) << 8)  |
                    (b'f' as u32);
+const GPOS: u32 = ((b'G' as u32) << 24) |
+                  ((b'P' as u32) << 16) |
+                  ((b'O' as u32) << 8)  |
+                   (b'S' as u32);
+const GSUB: u32 = ((b'G' as u32) << 24) |
+                  ((b'S' as u32) << 16) |
+                  ((b'U' as u32) << 8)  |
+                   (b'B' as u32);
+const HDMX: u32 = ((b'h' as u32) << 24) |
+                  ((b'd' as u32) << 16) |
+                  ((b'm' as u32) << 8)  |
+                   (b'x' as u32);
 const HEAD: u32 = ((b'h' as u32) << 24) |
                   ((b'e' as u32) << 16) |
                   ((b'a' as u32) << 8)  |
@@ -58,14 +185,42 @@ const KERN: u32 = ((b'k' as u32) << 24) |
                   ((b'e' as u32) << 16) |
                   ((b'r' as u32) << 8)  |
                    (b'n' as u32);
+const LTSH: u32 = ((b'L' as u32) << 24) |
+                  ((b'T' as u32) << 16) |
+                  ((b'S' as u32) << 8)  |
+                   (b'H' as u32);
+const META: u32 = ((b'm' as u32) << 24) |
+                  ((b'e' as u32) << 16) |
+                  ((b't' as u32) << 8)  |
+                   (b'a' as u32);
+const POST: u32 = ((b'p' as u32) << 24) |
+                  ((b'o' as u32) << 16) |
+                  ((b's' as u32) << 8)  |
+                   (b't' as u32);
+const PREP: u32 = ((b'p' as u32) << 24) |
+                  ((b'r' as u32) << 16) |
+                  ((b'e' as u32) << 8)  |
+                   (b'p' as u32);
 const LOCA: u32 = ((b'l' as u32) << 24) |
                   ((b'o' as u32) << 16) |
                   ((b'c' as u32) << 8)  |
                    (b'a' as u32);
+const MAXP: u32 = ((b'm' as u32) << 24) |
+                  ((b'a' as u32) << 16) |
+                  ((b'x' as u32) << 8)  |
+                   (b'p' as u32);
 const OS_2: u32 = ((b'O' as u32) << 24) |
                   ((b'S' as u32) << 16) |
                   ((b'/' as u32) << 8)  |
                    (b'2' as u32);
+const SBIX: u32 = ((b's' as u32) << 24) |
+                  ((b'b' as u32) << 16) |
+                  ((b'i' as u32) << 8)  |
+                   (b'x' as u32);
+const SVG: u32 = ((b'S' as u32) << 24) |
+                 ((b'V' as u32) << 16) |
+                 ((b'G' as u32) << 8)  |
+                  (b' ' as u32);
 const TTCF: u32 = ((b't' as u32) << 24) |
                   ((b't' as u32) << 16) |
                   ((b'c' as u32) << 8)  |
@@ -80,11 +235,279 @@ const SFNT: u32 = ((b's' as u32) << 24) |
                   ((b'n' as u32) << 8)  |
                    (b't' as u32);
 
+// The size of a table record in the table directory: a tag, a checksum, an offset, and a length.
+const TABLE_RECORD_SIZE: usize = 16;
+
+// The sum of the words of a font file whose `checksumAdjustment` is correct.
+const CHECKSUM_MAGIC: u32 = 0xb1b0afba;
+
+// The parameters of the 64-bit FNV-1a hash that `Font::id()` uses.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
 static SFNT_VERSIONS: [u32; 2] = [
     0x10000,
     ((b't' as u32) << 24) | ((b'r' as u32) << 16) | ((b'u' as u32) << 8) | (b'e' as u32),
 ];
 
+/// A four-byte OpenType tag, such as the name of a table or a layout feature.
+///
+/// Tags are stored big-endian, so `b"liga"` is `0x6c696761`. Use `tag()` to make one.
+pub type Tag = u32;
+
+/// Returns the tag with the given four-character name, such as `b"liga"`.
+#[inline]
+pub fn tag(name: &[u8; 4]) -> Tag {
+    ((name[0] as u32) << 24) | ((name[1] as u32) << 16) | ((name[2] as u32) << 8) | (name[3] as u32)
+}
+
+/// A font read from a seekable stream, such as a file or a network resource, that reads glyph
+/// outlines only when they're needed.
+///
+/// `Font` borrows a buffer holding the whole file. A `FontReader` owns the stream instead: it
+/// reads the table directory and the tables that `Font` needs up front, except for the TrueType
+/// outlines in the `glyf` and `loca` tables, which are usually most of the file. The outlines are
+/// read glyph by glyph with `load_glyphs()`, seeking to each glyph's entry in `loca` and then to
+/// its data in `glyf`. Tables that `Font` doesn't read, such as `name`, `DSIG`, and the bitmap
+/// data of color fonts, aren't read at all.
+///
+/// The outlines of fonts with a `CFF2` table are read up front with the other tables, since
+/// charstrings share subroutines. Only standalone fonts are supported: collections return
+/// `Error::UnknownFormat`, and CFF fonts return `Error::UnsupportedOutlineFormat`. Errors reading
+/// from the stream are reported as `Error::UnexpectedEof`, or as `Error::TableTooShort` if a
+/// table is cut off.
+pub struct FontReader<R> {
+    reader: R,
+    limits: ParseLimits,
+    file_length: u64,
+
+    // A font file holding the tables that `Font` reads, other than `glyf` and `loca`.
+    bytes: Vec<u8>,
+
+    // The offsets and lengths of the `glyf` and `loca` tables in the stream.
+    glyf_range: Option<(u64, u64)>,
+    loca_range: Option<(u64, u64)>,
+    index_to_loc_format: i16,
+
+    // The data of the glyphs read so far, back to back, and the start and end of each glyph's
+    // data within it, or `None` if the glyph hasn't been read.
+    glyph_data: Vec<u8>,
+    glyph_ranges: Vec<Option<(u32, u32)>>,
+}
+
+impl<R> FontReader<R> where R: Read + Seek {
+    /// Reads the table directory of the font in the given stream and the tables that `Font`
+    /// needs, except for the glyph outlines.
+    #[inline]
+    pub fn new(reader: R) -> Result<FontReader<R>, Error> {
+        FontReader::with_limits(reader, ParseLimits::default())
+    }
+
+    /// Reads a font from the given stream, as `new()` does, but fails if the font exceeds any of
+    /// the given limits, as `Font::from_bytes_limited()` does.
+    ///
+    /// Tables are checked against `ParseLimits::max_table_size` before they're read, so a
+    /// malicious stream can't make the reader allocate more than the limits allow.
+    pub fn with_limits(mut reader: R, limits: ParseLimits) -> Result<FontReader<R>, Error> {
+        let file_length = try!(reader.seek(SeekFrom::End(0)).map_err(Error::eof));
+        try!(reader.seek(SeekFrom::Start(0)).map_err(Error::eof));
+
+        let mut bytes = vec![0; 12];
+        try!(reader.read_exact(&mut bytes).map_err(Error::eof));
+        let magic_number = BigEndian::read_u32(&bytes[0..4]);
+        if magic_number != OTTO && !SFNT_VERSIONS.contains(&magic_number) {
+            return Err(Error::UnknownFormat)
+        }
+
+        let num_tables = BigEndian::read_u16(&bytes[4..6]) as usize;
+        let mut table_records = vec![0; num_tables * TABLE_RECORD_SIZE];
+        try!(reader.read_exact(&mut table_records).map_err(Error::eof));
+
+        // Keep the tables that `Font` reads. The outlines are left in the stream.
+        let (mut glyf_range, mut loca_range) = (None, None);
+        let mut slots = TableSlots::default();
+        let mut kept_records = vec![];
+        for record in table_records.chunks(TABLE_RECORD_SIZE) {
+            let table_id = BigEndian::read_u32(&record[0..4]);
+            let offset = BigEndian::read_u32(&record[8..12]) as u64;
+            let length = BigEndian::read_u32(&record[12..16]);
+            if length > limits.max_table_size {
+                return Err(Error::TableTooLarge(table_id))
+            }
+            match table_id {
+                GLYF => glyf_range = Some((offset, length as u64)),
+                LOCA => loca_range = Some((offset, length as u64)),
+                _ if slots.slot(table_id).is_some() => kept_records.push(record),
+                _ => {}
+            }
+        }
+
+        // Don't read the rest of a font in the CFF container format unless its outlines are in
+        // CFF2, which is the only kind of cubic outline that `Font` reads.
+        let has_cff2 = kept_records.iter().any(|record| BigEndian::read_u32(&record[0..4]) == CFF2);
+        if magic_number == OTTO && !has_cff2 {
+            return Err(Error::UnsupportedOutlineFormat(OutlineFormat::Cff))
+        }
+
+        // Rewrite the offset table for the tables that are kept.
+        let num_tables = kept_records.len();
+        let entry_selector = if num_tables == 0 {
+            0
+        } else {
+            31 - (num_tables as u32).leading_zeros()
+        };
+        let search_range = (1 << entry_selector) * TABLE_RECORD_SIZE;
+        BigEndian::write_u16(&mut bytes[4..6], num_tables as u16);
+        BigEndian::write_u16(&mut bytes[6..8], search_range as u16);
+        BigEndian::write_u16(&mut bytes[8..10], entry_selector as u16);
+        BigEndian::write_u16(&mut bytes[10..12],
+                             (num_tables * TABLE_RECORD_SIZE).saturating_sub(search_range) as u16);
+
+        // The tables follow the records, each starting on a 4-byte boundary.
+        let tables_offset = bytes.len() + num_tables * TABLE_RECORD_SIZE;
+        let mut tables = vec![];
+        for record in &kept_records {
+            let table_id = BigEndian::read_u32(&record[0..4]);
+            let offset = BigEndian::read_u32(&record[8..12]);
+            let length = BigEndian::read_u32(&record[12..16]);
+            let start = tables.len();
+            try!(read_range(&mut reader, offset as u64, length as u64, &mut tables)
+                     .map_err(|err| err.in_table(table_id)));
+            while tables.len() % 4 != 0 {
+                tables.push(0)
+            }
+
+            let mut new_offset = [0; 4];
+            BigEndian::write_u32(&mut new_offset, (tables_offset + start) as u32);
+            bytes.extend_from_slice(&record[0..8]);
+            bytes.extend_from_slice(&new_offset);
+            bytes.extend_from_slice(&record[12..16]);
+        }
+        bytes.extend_from_slice(&tables);
+
+        let (index_to_loc_format, glyph_count) = {
+            let font = try!(Font::from_otf(&bytes, 0, &limits));
+            (font.head.index_to_loc_format, font.glyph_count)
+        };
+
+        Ok(FontReader {
+            reader: reader,
+            limits: limits,
+            file_length: file_length,
+
+            bytes: bytes,
+
+            glyf_range: glyf_range,
+            loca_range: loca_range,
+            index_to_loc_format: index_to_loc_format,
+
+            glyph_data: vec![],
+            glyph_ranges: vec![None; glyph_count as usize],
+        })
+    }
+
+    /// Reads the outlines of the glyphs with the given IDs from the stream, along with the
+    /// components of composite glyphs, so that the font returned by `font()` can use them.
+    ///
+    /// Glyphs that have been read already are skipped, so this is cheap to call before each use
+    /// of a glyph. Glyph IDs past the end of the font are skipped too; the font reports them as
+    /// it would if it had been loaded from a buffer. If an error occurs, the glyphs read before it
+    /// stay read. Fonts without TrueType outlines have nothing to read.
+    pub fn load_glyphs(&mut self, glyph_ids: &[u16]) -> Result<(), Error> {
+        let (glyf_range, loca_range) = match (self.glyf_range, self.loca_range) {
+            (Some(glyf_range), Some(loca_range)) => (glyf_range, loca_range),
+            _ => return Ok(()),
+        };
+        let loca_entry_size = match self.index_to_loc_format {
+            0 => mem::size_of::<u16>() as u64,
+            1 => mem::size_of::<u32>() as u64,
+            _ => return Err(Error::UnknownFormat),
+        };
+
+        let mut pending_glyph_ids = glyph_ids.to_vec();
+        while let Some(glyph_id) = pending_glyph_ids.pop() {
+            match self.glyph_ranges.get(glyph_id as usize) {
+                Some(&None) => {}
+                Some(&Some(_)) | None => continue,
+            }
+
+            // The glyph's entry in `loca` and the next one give the range of its data.
+            let mut entries = vec![];
+            let entry_offset = glyph_id as u64 * loca_entry_size;
+            if entry_offset + loca_entry_size * 2 > loca_range.1 {
+                return Err(Error::TableTooShort(LOCA))
+            }
+            try!(read_range(&mut self.reader,
+                            loca_range.0 + entry_offset,
+                            loca_entry_size * 2,
+                            &mut entries).map_err(|err| err.in_table(LOCA)));
+            let (start, end) = if loca_entry_size == 2 {
+                (BigEndian::read_u16(&entries[0..2]) as u64 * 2,
+                 BigEndian::read_u16(&entries[2..4]) as u64 * 2)
+            } else {
+                (BigEndian::read_u32(&entries[0..4]) as u64,
+                 BigEndian::read_u32(&entries[4..8]) as u64)
+            };
+
+            let data_start = self.glyph_data.len();
+            if end > start {
+                if end > glyf_range.1 {
+                    return Err(Error::TableTooShort(GLYF))
+                }
+                if let Err(err) = read_range(&mut self.reader,
+                                             glyf_range.0 + start,
+                                             end - start,
+                                             &mut self.glyph_data) {
+                    self.glyph_data.truncate(data_start);
+                    return Err(err.in_table(GLYF))
+                }
+
+                // Read the components of composite glyphs too. If they can't be found, the font
+                // reports the malformed glyph when it's used.
+                if let Ok(component_glyph_ids) =
+                        glyf::component_glyph_ids(&self.glyph_data[data_start..]) {
+                    pending_glyph_ids.extend_from_slice(&component_glyph_ids)
+                }
+            }
+            self.glyph_ranges[glyph_id as usize] = Some((data_start as u32,
+                                                         self.glyph_data.len() as u32));
+        }
+        Ok(())
+    }
+
+    /// Returns the font, with the outlines of the glyphs read so far.
+    ///
+    /// Everything that doesn't need outlines works for every glyph, such as `glyph_for_char()`
+    /// and `metrics_for_glyph()`. Methods that read the outline of a glyph that hasn't been read
+    /// with `load_glyphs()`, such as `for_each_point()` and `OutlineBuilder::add_glyph()`, return
+    /// `Error::GlyphNotLoaded`. `Font::table()` finds the tables that were read, which don't
+    /// include `glyf` and `loca`.
+    pub fn font(&self) -> Result<Font, Error> {
+        let mut font = try!(Font::from_otf(&self.bytes, 0, &self.limits));
+        if self.glyf_range.is_some() {
+            font.glyf = Some(GlyfTable::new(FontTable { bytes: &self.glyph_data }, &self.limits));
+            font.outline_format = OutlineFormat::TrueType;
+        }
+        if self.loca_range.is_some() {
+            font.loca = Some(LocaTable::from_loaded_ranges(&self.glyph_ranges));
+        }
+        font.file_length = Some(self.file_length);
+        Ok(font)
+    }
+}
+
+// Appends `length` bytes starting at `offset` in the stream to the buffer.
+fn read_range<R>(reader: &mut R, offset: u64, length: u64, buffer: &mut Vec<u8>)
+                 -> Result<(), Error> where R: Read + Seek {
+    try!(reader.seek(SeekFrom::Start(offset)).map_err(Error::eof));
+    let start = buffer.len();
+    try!(reader.by_ref().take(length).read_to_end(buffer).map_err(Error::eof));
+    if ((buffer.len() - start) as u64) < length {
+        return Err(Error::UnexpectedEof)
+    }
+    Ok(())
+}
+
 /// A handle to a font backed by a byte buffer containing the contents of the file (`.ttf`,
 /// `.otf`), etc.
 ///
@@ -92,15 +515,47 @@ static SFNT_VERSIONS: [u32; 2] = [
 pub struct Font<'a> {
     pub bytes: &'a [u8],
 
+    // The table records of the table directory, 16 bytes each.
+    table_records: &'a [u8],
+
     cmap: CmapTable<'a>,
     head: HeadTable,
     hhea: HheaTable,
     hmtx: HmtxTable<'a>,
     os_2: Os2Table,
+    post: Option<PostTable>,
 
     glyf: Option<GlyfTable<'a>>,
     loca: Option<LocaTable<'a>>,
+    cff2: Option<Cff2Table<'a>>,
+    fvar: Option<FvarTable<'a>>,
+    avar: Option<AvarTable<'a>>,
     kern: Option<KernTable<'a>>,
+    gsub: Option<GsubTable<'a>>,
+    gpos: Option<GposTable<'a>>,
+    gdef: Option<GdefTable<'a>>,
+    base: Option<BaseTable<'a>>,
+    hdmx: Option<HdmxTable<'a>>,
+    ltsh: Option<LtshTable<'a>>,
+    gasp: Option<GaspTable<'a>>,
+    meta: Option<MetaTable<'a>>,
+    colr: Option<ColrTable<'a>>,
+    cpal: Option<CpalTable<'a>>,
+    svg: Option<SvgTable<'a>>,
+    cblc: Option<CblcTable<'a>>,
+    sbix: Option<SbixTable<'a>>,
+
+    outline_format: OutlineFormat,
+    glyph_count: u16,
+    // The length of the font file if `bytes` holds copies of some of its tables rather than the
+    // file itself, as for fonts from a `FontReader`.
+    file_length: Option<u64>,
+    // The normalized coordinates of the instance of a variable font chosen with
+    // `set_variations()`, one per axis. Axes past the end are at their defaults.
+    variation_coordinates: Vec<f32>,
+
+    // Flattened contours keyed by glyph ID and the bits of the tolerance, if caching is enabled.
+    flatten_cache: Option<Mutex<HashMap<(u16, u32), Arc<Vec<Vec<Point2D<f32>>>>>>>,
 }
 
 #[doc(hidden)]
@@ -109,6 +564,84 @@ pub struct FontTable<'a> {
     pub bytes: &'a [u8],
 }
 
+// The tables that `Font` parses or looks up by tag, as found in the table directory. These are
+// the tables that `FontReader` reads, other than `glyf` and `loca`.
+#[derive(Default)]
+struct TableSlots<'a> {
+    avar: Option<FontTable<'a>>,
+    base: Option<FontTable<'a>>,
+    cblc: Option<FontTable<'a>>,
+    cff: Option<FontTable<'a>>,
+    cff2: Option<FontTable<'a>>,
+    cmap: Option<FontTable<'a>>,
+    colr: Option<FontTable<'a>>,
+    cpal: Option<FontTable<'a>>,
+    cvt: Option<FontTable<'a>>,
+    eblc: Option<FontTable<'a>>,
+    fpgm: Option<FontTable<'a>>,
+    fvar: Option<FontTable<'a>>,
+    gasp: Option<FontTable<'a>>,
+    gdef: Option<FontTable<'a>>,
+    glyf: Option<FontTable<'a>>,
+    gpos: Option<FontTable<'a>>,
+    gsub: Option<FontTable<'a>>,
+    hdmx: Option<FontTable<'a>>,
+    head: Option<FontTable<'a>>,
+    hhea: Option<FontTable<'a>>,
+    hmtx: Option<FontTable<'a>>,
+    kern: Option<FontTable<'a>>,
+    loca: Option<FontTable<'a>>,
+    ltsh: Option<FontTable<'a>>,
+    maxp: Option<FontTable<'a>>,
+    meta: Option<FontTable<'a>>,
+    os_2: Option<FontTable<'a>>,
+    post: Option<FontTable<'a>>,
+    prep: Option<FontTable<'a>>,
+    sbix: Option<FontTable<'a>>,
+    svg: Option<FontTable<'a>>,
+}
+
+impl<'a> TableSlots<'a> {
+    // Returns the slot for the table with the given tag, or `None` if `Font` doesn't use the
+    // table.
+    fn slot(&mut self, table_id: u32) -> Option<&mut Option<FontTable<'a>>> {
+        Some(match table_id {
+            AVAR => &mut self.avar,
+            BASE => &mut self.base,
+            CBLC => &mut self.cblc,
+            CFF => &mut self.cff,
+            CFF2 => &mut self.cff2,
+            CMAP => &mut self.cmap,
+            COLR => &mut self.colr,
+            CPAL => &mut self.cpal,
+            CVT => &mut self.cvt,
+            EBLC => &mut self.eblc,
+            FPGM => &mut self.fpgm,
+            FVAR => &mut self.fvar,
+            GASP => &mut self.gasp,
+            GDEF => &mut self.gdef,
+            GLYF => &mut self.glyf,
+            GPOS => &mut self.gpos,
+            GSUB => &mut self.gsub,
+            HDMX => &mut self.hdmx,
+            HEAD => &mut self.head,
+            HHEA => &mut self.hhea,
+            HMTX => &mut self.hmtx,
+            KERN => &mut self.kern,
+            LOCA => &mut self.loca,
+            LTSH => &mut self.ltsh,
+            MAXP => &mut self.maxp,
+            META => &mut self.meta,
+            OS_2 => &mut self.os_2,
+            POST => &mut self.post,
+            PREP => &mut self.prep,
+            SBIX => &mut self.sbix,
+            SVG => &mut self.svg,
+            _ => return None,
+        })
+    }
+}
+
 impl<'a> Font<'a> {
     /// Creates a new font from a byte buffer containing the contents of a file or font collection
     /// (`.ttf`, `.ttc`, `.otf`, etc.)
@@ -128,6 +661,27 @@ impl<'a> Font<'a> {
     ///
     /// Returns the font on success or an error on failure.
     pub fn from_collection_index<'b>(bytes: &'b [u8], index: u32) -> Result<Font<'b>, Error> {
+        Font::from_collection_index_limited(bytes, index, &ParseLimits::default())
+    }
+
+    /// Creates a new font from a byte buffer, as `new()` does, but fails if the font exceeds any
+    /// of the given limits.
+    ///
+    /// Use this for fonts from untrusted sources, such as user uploads, to bound the work that a
+    /// malicious font can cause. The glyph count and table sizes are checked here; the limits on
+    /// points and composite glyphs are checked whenever a glyph's outline is read, so methods such
+    /// as `for_each_point()` fail with the corresponding error for glyphs that exceed them.
+    ///
+    /// Every read is bounds-checked regardless of the limits, so a malformed font never causes a
+    /// panic or an out-of-bounds read; the limits guard against fonts that are well-formed but
+    /// huge.
+    pub fn from_bytes_limited<'b>(bytes: &'b [u8], limits: ParseLimits)
+                                  -> Result<Font<'b>, Error> {
+        Font::from_collection_index_limited(bytes, 0, &limits)
+    }
+
+    fn from_collection_index_limited<'b>(bytes: &'b [u8], index: u32, limits: &ParseLimits)
+                                         -> Result<Font<'b>, Error> {
         // Check magic number.
         let mut reader = bytes;
         let mut magic_number = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
@@ -149,40 +703,34 @@ impl<'a> Font<'a> {
 
                 try!(reader.jump(index as usize * mem::size_of::<u32>()).map_err(Error::eof));
                 let table_offset = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
-                Font::from_otf(&bytes, table_offset)
+                Font::from_otf(&bytes, table_offset, limits)
             }
-            magic_number if SFNT_VERSIONS.contains(&magic_number) => Font::from_otf(bytes, 0),
-            0x0100 => Font::from_dfont_index(bytes, index),
-            OTTO => {
-                // TODO(pcwalton): Support CFF outlines.
-                Err(Error::UnsupportedCffOutlines)
+            magic_number if magic_number == OTTO || SFNT_VERSIONS.contains(&magic_number) => {
+                Font::from_otf(bytes, 0, limits)
             }
+            0x0100 => Font::from_dfont_index(bytes, index, limits),
             _ => Err(Error::UnknownFormat),
         }
     }
 
-    fn from_otf<'b>(bytes: &'b [u8], offset: u32) -> Result<Font<'b>, Error> {
+    fn from_otf<'b>(bytes: &'b [u8], offset: u32, limits: &ParseLimits)
+                    -> Result<Font<'b>, Error> {
         let mut reader = bytes;
         try!(reader.jump(offset as usize).map_err(Error::eof));
 
         let mut magic_number = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
 
         // Check version.
-        if magic_number == OTTO {
-            // TODO(pcwalton): Support CFF outlines.
-            return Err(Error::UnsupportedCffOutlines)
-        } else if !SFNT_VERSIONS.contains(&magic_number) {
+        if magic_number != OTTO && !SFNT_VERSIONS.contains(&magic_number) {
             return Err(Error::UnknownFormat)
         }
 
         let num_tables = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
         try!(reader.jump(mem::size_of::<u16>() * 3).map_err(Error::eof));
 
-        let (mut cmap_table, mut head_table) = (None, None);
-        let (mut hhea_table, mut hmtx_table) = (None, None);
-        let (mut glyf_table, mut kern_table) = (None, None);
-        let (mut loca_table, mut os_2_table) = (None, None);
+        let mut tables = TableSlots::default();
 
+        let table_records = reader;
         for _ in 0..num_tables {
             let table_id = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
 
@@ -191,17 +739,13 @@ impl<'a> Font<'a> {
 
             let offset = try!(reader.read_u32::<BigEndian>().map_err(Error::eof)) as usize;
             let length = try!(reader.read_u32::<BigEndian>().map_err(Error::eof)) as usize;
+            if length > limits.max_table_size as usize {
+                return Err(Error::TableTooLarge(table_id))
+            }
 
-            let mut slot = match table_id {
-                CMAP => &mut cmap_table,
-                HEAD => &mut head_table,
-                HHEA => &mut hhea_table,
-                HMTX => &mut hmtx_table,
-                GLYF => &mut glyf_table,
-                KERN => &mut kern_table,
-                LOCA => &mut loca_table,
-                OS_2 => &mut os_2_table,
-                _ => continue,
+            let slot = match tables.slot(table_id) {
+                None => continue,
+                Some(slot) => slot,
             };
 
             // Make sure there isn't more than one copy of the table.
@@ -209,33 +753,146 @@ impl<'a> Font<'a> {
                 return Err(Error::Failed)
             }
 
-            *slot = Some(FontTable {
-                bytes: &bytes[offset..offset + length],
-            })
+            *slot = match bytes.get(offset..offset.saturating_add(length)) {
+                Some(table_bytes) => Some(FontTable { bytes: table_bytes }),
+                None => return Err(Error::TableTooShort(table_id)),
+            }
+        }
+
+        // TODO(pcwalton): Support CFF outlines. Fonts in the CFF container format can only be read
+        // if their outlines are in CFF2.
+        if magic_number == OTTO && tables.cff2.is_none() {
+            return Err(Error::UnsupportedOutlineFormat(OutlineFormat::Cff))
         }
 
+        let table_records = &table_records[0..num_tables as usize * TABLE_RECORD_SIZE];
+        Font::from_table_slots(bytes, table_records, tables, limits)
+    }
+
+    // Parses the tables found in the table directory of a font in the given buffer.
+    fn from_table_slots<'b>(bytes: &'b [u8],
+                            table_records: &'b [u8],
+                            tables: TableSlots<'b>,
+                            limits: &ParseLimits)
+                            -> Result<Font<'b>, Error> {
+        let TableSlots {
+            avar: avar_table,
+            base: base_table,
+            cblc: cblc_table,
+            cff: cff_table,
+            cff2: cff2_table,
+            cmap: cmap_table,
+            colr: colr_table,
+            cpal: cpal_table,
+            eblc: eblc_table,
+            fvar: fvar_table,
+            gasp: gasp_table,
+            gdef: gdef_table,
+            glyf: glyf_table,
+            gpos: gpos_table,
+            gsub: gsub_table,
+            hdmx: hdmx_table,
+            head: head_table,
+            hhea: hhea_table,
+            hmtx: hmtx_table,
+            kern: kern_table,
+            loca: loca_table,
+            ltsh: ltsh_table,
+            maxp: maxp_table,
+            meta: meta_table,
+            os_2: os_2_table,
+            post: post_table,
+            sbix: sbix_table,
+            svg: svg_table,
+            ..
+        } = tables;
+
         let loca_table = match loca_table {
             None => None,
             Some(loca_table) => Some(try!(LocaTable::new(loca_table))),
         };
 
+        // The `hdmx` table can only be read if we know the number of glyphs.
+        let maxp_table = maxp_table.and_then(|table| MaxpTable::new(table).ok());
+        if let Some(ref maxp_table) = maxp_table {
+            if maxp_table.num_glyphs as u32 > limits.max_glyph_count {
+                return Err(Error::TooManyGlyphs)
+            }
+        }
+        let glyph_count = maxp_table.as_ref().map_or(0, |maxp_table| maxp_table.num_glyphs);
+        let hdmx_table = match (hdmx_table, maxp_table) {
+            (Some(hdmx_table), Some(maxp_table)) => {
+                HdmxTable::new(hdmx_table, maxp_table.num_glyphs).ok()
+            }
+            _ => None,
+        };
+
+        let outline_format = if glyf_table.is_some() {
+            OutlineFormat::TrueType
+        } else if cff2_table.is_some() {
+            OutlineFormat::Cff2
+        } else if cff_table.is_some() {
+            OutlineFormat::Cff
+        } else {
+            OutlineFormat::BitmapOnly
+        };
+
+        // A malformed `CFF2` table makes every outline unreadable, as a malformed `loca` table
+        // does.
+        let cff2_table = match cff2_table {
+            Some(cff2_table) if outline_format == OutlineFormat::Cff2 => {
+                Some(try!(Cff2Table::new(cff2_table, limits).map_err(|err| err.in_table(CFF2))))
+            }
+            _ => None,
+        };
+
         Ok(Font {
             bytes: bytes,
 
-            cmap: CmapTable::new(try!(cmap_table.ok_or(Error::RequiredTableMissing))),
-            head: try!(HeadTable::new(try!(head_table.ok_or(Error::RequiredTableMissing)))),
-            hhea: try!(HheaTable::new(try!(hhea_table.ok_or(Error::RequiredTableMissing)))),
-            hmtx: HmtxTable::new(try!(hmtx_table.ok_or(Error::RequiredTableMissing))),
-            os_2: try!(Os2Table::new(try!(os_2_table.ok_or(Error::RequiredTableMissing)))),
+            table_records: table_records,
+
+            cmap: CmapTable::new(try!(cmap_table.ok_or(Error::MissingTable(CMAP)))),
+            head: try!(HeadTable::new(try!(head_table.ok_or(Error::MissingTable(HEAD))))
+                                 .map_err(|err| err.in_table(HEAD))),
+            hhea: try!(HheaTable::new(try!(hhea_table.ok_or(Error::MissingTable(HHEA))))
+                                 .map_err(|err| err.in_table(HHEA))),
+            hmtx: HmtxTable::new(try!(hmtx_table.ok_or(Error::MissingTable(HMTX)))),
+            os_2: try!(Os2Table::new(try!(os_2_table.ok_or(Error::MissingTable(OS_2))))
+                                 .map_err(|err| err.in_table(OS_2))),
+            post: post_table.and_then(|table| PostTable::new(table).ok()),
 
-            glyf: glyf_table.map(GlyfTable::new),
+            glyf: glyf_table.map(|table| GlyfTable::new(table, limits)),
             loca: loca_table,
+            cff2: cff2_table,
+            fvar: fvar_table.and_then(|table| FvarTable::new(table).ok()),
+            avar: avar_table.and_then(|table| AvarTable::new(table).ok()),
             kern: kern_table.and_then(|table| KernTable::new(table).ok()),
+            gsub: gsub_table.and_then(|table| GsubTable::new(table).ok()),
+            gpos: gpos_table.and_then(|table| GposTable::new(table).ok()),
+            gdef: gdef_table.and_then(|table| GdefTable::new(table).ok()),
+            base: base_table.and_then(|table| BaseTable::new(table).ok()),
+            hdmx: hdmx_table,
+            ltsh: ltsh_table.and_then(|table| LtshTable::new(table).ok()),
+            gasp: gasp_table.and_then(|table| GaspTable::new(table).ok()),
+            meta: meta_table.and_then(|table| MetaTable::new(table).ok()),
+            colr: colr_table.and_then(|table| ColrTable::new(table).ok()),
+            cpal: cpal_table.and_then(|table| CpalTable::new(table).ok()),
+            svg: svg_table.and_then(|table| SvgTable::new(table).ok()),
+            cblc: cblc_table.or(eblc_table).and_then(|table| CblcTable::new(table).ok()),
+            sbix: sbix_table.and_then(|table| SbixTable::new(table).ok()),
+
+            outline_format: outline_format,
+            glyph_count: glyph_count,
+            file_length: None,
+            variation_coordinates: vec![],
+
+            flatten_cache: None,
         })
     }
 
     /// https://github.com/kreativekorp/ksfl/wiki/Macintosh-Resource-File-Format
-    fn from_dfont_index<'b>(bytes: &'b [u8], index: u32) -> Result<Font<'b>, Error> {
+    fn from_dfont_index<'b>(bytes: &'b [u8], index: u32, limits: &ParseLimits)
+                            -> Result<Font<'b>, Error> {
         let mut reader = bytes;
 
         // Read the Mac resource file header.
@@ -259,7 +916,8 @@ impl<'a> Font<'a> {
                    .map_err(Error::eof));
 
         // Find the 'sfnt' type.
-        let type_count = (try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) + 1) as usize;
+        let type_count = try!(reader.read_i16::<BigEndian>().map_err(Error::eof)) as i32 + 1;
+        let type_count = cmp::max(type_count, 0) as usize;
         let mut resource_count_and_list_offset = None;
         for type_index in 0..type_count {
             let type_id = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
@@ -302,7 +960,53 @@ impl<'a> Font<'a> {
         try!(reader.jump(resource_data_offset as usize + sfnt_data_offset as usize)
                    .map_err(Error::eof));
         let sfnt_size = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
-        Font::from_otf(&reader[0..sfnt_size as usize], 0)
+        let sfnt = try!(reader.get(..sfnt_size as usize).ok_or(Error::UnexpectedEof));
+        Font::from_otf(sfnt, 0, limits)
+    }
+
+    /// Returns the technology that this font uses to describe the shapes of its glyphs.
+    ///
+    /// Only `OutlineFormat::TrueType` and `OutlineFormat::Cff2` fonts can be added to an
+    /// `OutlineBuilder`; check this first to report an unsupported font early.
+    #[inline]
+    pub fn outline_format(&self) -> OutlineFormat {
+        self.outline_format
+    }
+
+    /// Returns the axes along which the glyphs of this variable font vary, such as weight or
+    /// width, or an empty list if this isn't a variable font.
+    pub fn variation_axes(&self) -> Vec<VariationAxis> {
+        match self.fvar {
+            None => vec![],
+            Some(ref fvar) => fvar.axes().unwrap_or(vec![]),
+        }
+    }
+
+    /// Chooses the instance of this variable font that outlines are read from, given as values
+    /// along its axes in the units of `variation_axes()`, such as `(otf::tag(b"wght"), 700.0)`.
+    ///
+    /// Axes that aren't given take their default values, values are clamped to the range of their
+    /// axis, and tags that name no axis are ignored, so an empty list restores the default
+    /// instance. The values are normalized as the font's `fvar` and `avar` tables say. If either
+    /// table is malformed, the error is returned and the instance stays as it was.
+    ///
+    /// The instance applies to the blend operators of CFF2 outlines. Variations of TrueType
+    /// outlines in a `gvar` table aren't applied, and metrics are those of the default instance.
+    /// Contours cached by `flattened_contours()` are discarded.
+    pub fn set_variations(&mut self, variations: &[(Tag, f32)]) -> Result<(), Error> {
+        let mut coordinates = match self.fvar {
+            None => vec![],
+            Some(ref fvar) => {
+                try!(fvar.normalized_coordinates(variations).map_err(|err| err.in_table(FVAR)))
+            }
+        };
+        if let Some(ref avar) = self.avar {
+            try!(avar.map(&mut coordinates).map_err(|err| err.in_table(AVAR)))
+        }
+
+        self.variation_coordinates = coordinates;
+        self.clear_flatten_cache();
+        Ok(())
     }
 
     /// Returns the glyph IDs that map to the given ranges of Unicode codepoints.
@@ -311,158 +1015,1468 @@ impl<'a> Font<'a> {
     #[inline]
     pub fn glyph_mapping_for_codepoint_ranges(&self, codepoint_ranges: &[CodepointRange])
                                               -> Result<GlyphMapping, Error> {
-        self.cmap.glyph_mapping_for_codepoint_ranges(codepoint_ranges)
+        self.cmap
+            .glyph_mapping_for_codepoint_ranges(codepoint_ranges)
+            .map_err(|err| err.in_table(CMAP))
     }
 
-    /// Calls the given callback for each point in the supplied glyph's contour.
+    /// Returns the glyph ID for the given Unicode character, or `None` if the font doesn't map
+    /// the character to a glyph.
     ///
-    /// This function is the primary method for accessing a glyph's outline.
-    #[inline]
-    pub fn for_each_point<F>(&self, glyph_id: u16, callback: F) -> Result<(), Error>
-                             where F: FnMut(&Point) {
-        match self.glyf {
-            Some(glyf) => {
-                let loca = match self.loca {
-                    Some(ref loca) => loca,
-                    None => return Err(Error::RequiredTableMissing),
-                };
-
-                glyf.for_each_point(&self.head, loca, glyph_id, callback)
+    /// To look up many characters at once, `glyph_mapping_for_codepoint_ranges()` is faster.
+    pub fn glyph_for_char(&self, character: char) -> Option<u16> {
+        let codepoint_ranges = [CodepointRange::new(character as u32, character as u32)];
+        match self.cmap.glyph_mapping_for_codepoint_ranges(&codepoint_ranges) {
+            Ok(glyph_mapping) => {
+                match glyph_mapping.glyph_for(character as u32) {
+                    Some(0) | None => None,
+                    Some(glyph_id) => Some(glyph_id),
+                }
             }
-            None => Ok(()),
+            Err(_) => None,
         }
     }
 
-    /// Returns the boundaries of the given glyph in font units.
+    /// Returns the glyph ID for the given Unicode character, or the ID of the `.notdef` glyph if
+    /// the font doesn't map the character to a glyph.
+    ///
+    /// This suits layout code that always needs a glyph to draw. To fall back to other fonts
+    /// instead, use `glyph_for_char()`.
     #[inline]
-    pub fn glyph_bounds(&self, glyph_id: u16) -> Result<GlyphBounds, Error> {
-        match self.glyf {
-            Some(glyf) => {
-                let loca = match self.loca {
-                    Some(ref loca) => loca,
-                    None => return Err(Error::RequiredTableMissing),
-                };
+    pub fn glyph_or_notdef(&self, character: char) -> u16 {
+        self.glyph_for_char(character).unwrap_or(self.notdef_glyph())
+    }
 
-                glyf.glyph_bounds(&self.head, loca, glyph_id)
-            }
-            None => Err(Error::RequiredTableMissing),
-        }
+    /// Returns the set of characters that this font maps to glyphs.
+    ///
+    /// This reads the whole character map, so it's much slower than a single call to
+    /// `glyph_for_char()`, but testing whether the set contains a character is fast. It suits
+    /// font fallback, where many characters are tested against many fonts. Characters mapped to
+    /// the `.notdef` glyph aren't in the set. If the character map can't be read, the set is
+    /// empty, just as `glyph_for_char()` returns `None` in that case.
+    pub fn coverage_set(&self) -> CoverageSet {
+        CoverageSet::new(self.cmap.coverage().unwrap_or(vec![]))
     }
 
-    /// Returns the minimum shelf height that an atlas containing glyphs from this font will need.
+    /// Returns the ID of the `.notdef` glyph, which fonts display for characters they don't
+    /// support.
+    ///
+    /// The OpenType specification requires this to be glyph 0. Most fonts draw it as a box, often
+    /// called "tofu", though some leave it empty.
     #[inline]
-    pub fn shelf_height(&self, point_size: f32) -> u32 {
-        // Add 2 to account for the border.
-        self.head
-            .max_glyph_bounds
-            .subpixel_bounds(self.head.units_per_em, point_size)
-            .round_out()
-            .size()
-            .height as u32 + 2
+    pub fn notdef_glyph(&self) -> u16 {
+        0
     }
 
-    /// Returns the number of font units per em.
-    ///
-    /// An em is traditionally the width of the lowercase letter "m". A typical point size of a
-    /// font is expressed in number of pixels per em. Thus, in order to convert font units to
-    /// pixels, you can use an expression like `units * font_size / font.units_per_em()`.
+    /// Returns the number of glyphs in the font, as recorded in its `maxp` table, or 0 if it has
+    /// none.
     #[inline]
-    pub fn units_per_em(&self) -> u16 {
-        self.head.units_per_em
+    pub fn glyph_count(&self) -> u16 {
+        self.glyph_count
     }
 
-    /// Returns the horizontal metrics for the glyph with the given ID.
+    /// Returns an iterator over the ID of every glyph in the font, from 0 up to `glyph_count()`.
     ///
-    /// Horizontal metrics are important for text shaping, as they specify the number of units to
-    /// advance the pen after typesetting a glyph.
+    /// This includes glyphs with no outline, such as spaces. Use `non_empty_glyph_ids()` to skip
+    /// them when rendering every glyph of the font.
     #[inline]
-    pub fn metrics_for_glyph(&self, glyph_id: u16) -> Result<HorizontalMetrics, Error> {
-        self.hmtx.metrics_for_glyph(&self.hhea, glyph_id)
+    pub fn glyph_ids(&self) -> Range<u16> {
+        0..self.glyph_count
     }
 
-    /// Returns the kerning between the given two glyph IDs in font units.
-    ///
-    /// Positive values move glyphs farther apart; negative values move glyphs closer together.
+    /// Returns an iterator over the ID of every glyph in the font that has an outline, in
+    /// increasing order.
     ///
-    /// Zero is returned if no kerning is available in the font.
+    /// Glyphs whose entries in the `loca` table are empty, such as spaces, are skipped without
+    /// reading their outlines. Fonts without TrueType outlines yield no glyphs.
     #[inline]
-    pub fn kerning_for_glyph_pair(&self, left_glyph_id: u16, right_glyph_id: u16) -> i16 {
-        match self.kern {
-            None => 0,
-            Some(kern) => kern.kerning_for_glyph_pair(left_glyph_id, right_glyph_id).unwrap_or(0),
+    pub fn non_empty_glyph_ids<'b>(&'b self) -> NonEmptyGlyphIds<'b> {
+        NonEmptyGlyphIds {
+            font: self,
+            glyph_ids: self.glyph_ids(),
         }
     }
 
-    /// Returns the distance from the baseline to the top of the text box in font units.
+    /// Returns the glyph ID for the given character followed by the given variation selector,
+    /// such as U+FE0F to request emoji presentation or one of U+E0100 to U+E01EF to request an
+    /// ideographic variant.
     ///
-    /// The following expression computes the baseline-to-baseline height:
-    /// `font.ascender() - font.descender() + font.line_gap()`.
-    #[inline]
-    pub fn ascender(&self) -> i16 {
-        self.os_2.typo_ascender
+    /// This consults the Unicode Variation Sequences subtable of the character map. If the font
+    /// has no special glyph for the sequence, this returns the glyph for `base` alone, as
+    /// `glyph_for_char()` does.
+    pub fn glyph_for_char_variation(&self, base: char, selector: char) -> Option<u16> {
+        match self.cmap.glyph_for_variation_sequence(base as u32, selector as u32) {
+            Ok(Some(glyph_id)) if glyph_id != 0 => Some(glyph_id),
+            _ => self.glyph_for_char(base),
+        }
     }
 
-    /// Returns the distance from the baseline to the bottom of the text box in font units.
+    /// Calls the given callback for each point in the supplied glyph's contour.
     ///
-    /// The following expression computes the baseline-to-baseline height:
-    /// `font.ascender() - font.descender() + font.line_gap()`.
+    /// This function is the primary method for accessing a glyph's outline. Glyphs of fonts
+    /// without outlines, such as bitmap-only fonts, have no points.
+    ///
+    /// The points describe lines and quadratic curves, as in TrueType outlines. The cubic curves
+    /// of CFF2 outlines are approximated with quadratic curves to within a quarter of a font unit
+    /// and their points are rounded to whole font units; use `walk_glyph()` for the exact curves.
+    /// CFF2 outlines are drawn at the instance chosen with `set_variations()`. Fonts with CFF
+    /// outlines aren't supported yet and return `Error::UnsupportedOutlineFormat`.
     #[inline]
-    pub fn descender(&self) -> i16 {
-        self.os_2.typo_descender
+    pub fn for_each_point<F>(&self, glyph_id: u16, callback: F) -> Result<(), Error>
+                             where F: FnMut(&Point) {
+        if let Some(ref cff2) = self.cff2 {
+            return cff2.for_each_point(glyph_id, &self.variation_coordinates, callback)
+                       .map_err(|err| err.in_table(CFF2))
+        }
+
+        let (glyf, loca) = match self.glyf_and_loca() {
+            Ok(tables) => tables,
+            Err(Error::MissingTable(GLYF)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        glyf.for_each_point(&self.head, loca, glyph_id, callback)
+            .map_err(|err| err.in_table(GLYF))
     }
 
-    /// Returns the recommended extra gap between lines in font units.
+    /// Returns true if the font has TrueType hinting instructions: a font program (`fpgm`), a
+    /// control value program (`prep`), or a control value table (`cvt `).
     ///
-    /// The following expression computes the baseline-to-baseline height:
-    /// `font.ascender() - font.descender() + font.line_gap()`.
-    #[inline]
-    pub fn line_gap(&self) -> i16 {
-        self.os_2.typo_line_gap
+    /// Glyphs may also carry instructions of their own, but they're rarely useful without these
+    /// tables. With the `hinting` feature, use `hinter()` to run the instructions.
+    pub fn has_hinting(&self) -> bool {
+        self.glyf.is_some() &&
+            (self.table(FPGM).is_some() || self.table(PREP).is_some() || self.table(CVT).is_some())
     }
-}
 
-/// Errors that can occur when parsing OpenType fonts.
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub enum Error {
-    /// A miscellaneous error occurred.
-    Failed,
-    /// The file ended unexpectedly.
-    UnexpectedEof,
-    /// There is no font with this index in this font collection.
-    FontIndexOutOfBounds,
-    /// The file declared that it was in a version of the format we don't support.
-    UnsupportedVersion,
-    /// The file was of a format we don't support.
-    UnknownFormat,
-    /// The font has CFF outlines, which we don't yet support.
-    UnsupportedCffOutlines,
-    /// The font had a glyph format we don't support.
-    UnsupportedGlyphFormat,
-    /// We don't support the declared version of the font's character map.
-    UnsupportedCmapVersion,
-    /// The font character map has an unsupported platform/encoding ID.
-    UnsupportedCmapEncoding,
-    /// The font character map has an unsupported format.
-    UnsupportedCmapFormat,
-    /// We don't support the declared version of the font header.
-    UnsupportedHeadVersion,
-    /// We don't support the declared version of the font's horizontal metrics.
-    UnsupportedHheaVersion,
-    /// We don't support the declared version of the font's OS/2 and Windows table.
-    UnsupportedOs2Version,
-    /// A required table is missing.
-    RequiredTableMissing,
-    /// The glyph is a composite glyph.
+    /// Runs the font's font program and control value program for the given size in pixels per
+    /// em, which is rounded to a whole number, and returns a `Hinter` that grid-fits glyphs at
+    /// that size.
     ///
-    /// TODO(pcwalton): Support these.
-    CompositeGlyph,
-}
+    /// Fonts without hinting instructions get a hinter that leaves outlines alone, apart from
+    /// rounding the points to the nearest 1/64 pixel. Fails with `Error::HintingFailed` if the
+    /// programs are malformed or don't finish.
+    ///
+    /// This is only available with the `hinting` feature.
+    #[cfg(feature = "hinting")]
+    pub fn hinter(&self, point_size: f32) -> Result<Hinter, Error> {
+        try!(self.glyf_and_loca());
+        hint::new(self.table(FPGM).unwrap_or(&[]),
+                  self.table(PREP).unwrap_or(&[]),
+                  self.table(CVT).unwrap_or(&[]),
+                  self.table(MAXP).unwrap_or(&[]),
+                  self.head.units_per_em,
+                  point_size)
+    }
 
-impl Error {
-    #[doc(hidden)]
-    #[inline]
-    pub fn eof<T>(_: T) -> Error {
-        Error::UnexpectedEof
+    /// Calls the given callback with each point of the given glyph after grid-fitting it with the
+    /// given hinter, in font units, as `for_each_point()` does for the original outline.
+    ///
+    /// The hinter must come from this font. Each glyph is hinted from the state that the font's
+    /// programs left in the hinter, so the result doesn't depend on which glyphs were hinted
+    /// before. If the glyph's own instructions fail, its original outline is used. The components
+    /// of composite glyphs are hinted separately; the instructions of the composite glyph itself
+    /// aren't run.
+    ///
+    /// Points come back in font units so that they can go wherever unhinted points go, but they
+    /// only land on the pixel grid when drawn at `hinter.pixels_per_em()` pixels per em, with the
+    /// glyph's origin on a pixel boundary.
+    ///
+    /// This is only available with the `hinting` feature.
+    #[cfg(feature = "hinting")]
+    pub fn for_each_hinted_point<F>(&self, hinter: &Hinter, glyph_id: u16, callback: F)
+                                    -> Result<(), Error> where F: FnMut(&Point) {
+        let (glyf, loca) = match self.glyf_and_loca() {
+            Ok(tables) => tables,
+            Err(Error::MissingTable(GLYF)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        // The phantom points mark the glyph's origin and advance on the baseline, and the top and
+        // bottom of its line, for the instructions to fit.
+        let (ascender, descender) = (self.os_2.typo_ascender as i32,
+                                     self.os_2.typo_descender as i32);
+        let phantom_points = |glyph_id: u16, x_min: i16| {
+            let metrics = try!(self.metrics_for_glyph(glyph_id));
+            let origin = x_min as i32 - metrics.lsb as i32;
+            Ok([
+                Point2D::new(origin, 0),
+                Point2D::new(origin + metrics.advance_width as i32, 0),
+                Point2D::new(0, ascender),
+                Point2D::new(0, descender),
+            ])
+        };
+
+        glyf.for_each_hinted_point(&self.head, loca, hinter, phantom_points, glyph_id, callback)
+            .map_err(|err| err.in_table(GLYF))
     }
-}
 
+    // Returns the tables that TrueType outlines are read from, or an error explaining why this
+    // font's outlines can't be read. Don't pretend that glyphs with cubic outlines are empty.
+    fn glyf_and_loca(&self) -> Result<(GlyfTable<'a>, &LocaTable<'a>), Error> {
+        match (self.glyf, self.loca.as_ref()) {
+            (Some(glyf), Some(loca)) => Ok((glyf, loca)),
+            (Some(_), None) => Err(Error::MissingTable(LOCA)),
+            (None, _) => {
+                match self.outline_format {
+                    OutlineFormat::Cff | OutlineFormat::Cff2 => {
+                        Err(Error::UnsupportedOutlineFormat(self.outline_format))
+                    }
+                    OutlineFormat::TrueType | OutlineFormat::BitmapOnly => {
+                        Err(Error::MissingTable(GLYF))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walks the outline of the given glyph, calling the visitor for each contour and segment in
+    /// font units.
+    ///
+    /// Each contour begins with `move_to()` and ends with `close()`. The last segment of each
+    /// contour ends at the contour's starting point. TrueType outlines consist of lines and
+    /// quadratic curves only, so `cubic_to()` is never called for them, and CFF2 outlines consist
+    /// of lines and cubic curves only, at the instance chosen with `set_variations()`.
+    pub fn walk_glyph(&self, glyph_id: u16, visitor: &mut OutlineVisitor) -> Result<(), Error> {
+        if let Some(ref cff2) = self.cff2 {
+            return cff2.walk_glyph(glyph_id, &self.variation_coordinates, visitor)
+                       .map_err(|err| err.in_table(CFF2))
+        }
+
+        let (mut contour_open, mut control_point) = (false, None);
+        try!(self.for_each_point(glyph_id, |point| {
+            let position = Point2D::new(point.position.x as f32, point.position.y as f32);
+
+            // A new contour starts with an on-curve point.
+            if point.index_in_contour == 0 {
+                if contour_open {
+                    visitor.close()
+                }
+                visitor.move_to(&position);
+                contour_open = true;
+                control_point = None;
+                return
+            }
+
+            if !point.on_curve {
+                control_point = Some(position);
+                return
+            }
+
+            match control_point.take() {
+                None => visitor.line_to(&position),
+                Some(control_point) => visitor.quad_to(&control_point, &position),
+            }
+        }));
+
+        if contour_open {
+            visitor.close()
+        }
+        Ok(())
+    }
+
+    /// Returns the point on the outline of the given glyph that is nearest to `query`, along with
+    /// its distance from `query`, all in font units.
+    ///
+    /// Curves are approximated with line segments to within a quarter of a font unit. Returns
+    /// `Error::Failed` if the glyph has no outline.
+    #[inline]
+    pub fn nearest_outline_point(&self, glyph_id: u16, query: Point2D<f32>)
+                                 -> Result<(Point2D<f32>, f32), Error> {
+        self.nearest_outline_point_with_tolerance(glyph_id, query, 0.25)
+    }
+
+    /// Returns the point on the outline of the given glyph that is nearest to `query`, along with
+    /// its distance from `query`, with curves approximated by line segments to within
+    /// `tolerance` font units.
+    ///
+    /// See `curve_tolerance()` for choosing the tolerance. Otherwise, this is the same as
+    /// `nearest_outline_point()`.
+    pub fn nearest_outline_point_with_tolerance(&self,
+                                                glyph_id: u16,
+                                                query: Point2D<f32>,
+                                                tolerance: f32)
+                                                -> Result<(Point2D<f32>, f32), Error> {
+        let mut nearest: Option<(Point2D<f32>, f32)> = None;
+        for contour in try!(self.flattened_contours(glyph_id, tolerance)).iter() {
+            for line in contour.windows(2) {
+                let point = flatten::nearest_point_on_line_segment(&line[0], &line[1], &query);
+                let vector = point - query;
+                let distance_squared = vector.dot(vector);
+                match nearest {
+                    Some((_, nearest_distance_squared)) if nearest_distance_squared <=
+                        distance_squared => {}
+                    _ => nearest = Some((point, distance_squared)),
+                }
+            }
+        }
+
+        match nearest {
+            None => Err(Error::Failed),
+            Some((point, distance_squared)) => Ok((point, distance_squared.sqrt())),
+        }
+    }
+
+    /// Returns the curve tolerance in font units that keeps flattened outlines within
+    /// `pixel_error` pixels of the true curves when rendered at `point_size`.
+    ///
+    /// The CPU flattening methods, `flattened_contours()`, `triangulate_glyph()`, and
+    /// `nearest_outline_point_with_tolerance()`, take their tolerance in font units, so that one
+    /// flattening can serve every size up to the one it was computed for. As elsewhere in
+    /// Pathfinder, `point_size` is in pixels per em, so a font unit is
+    /// `point_size / units_per_em()` pixels and the tolerance shrinks as the text grows. For a
+    /// size given in typographic points, pass `points * dpi / 72.0`. An error of a quarter of a
+    /// pixel or less is invisible in antialiased text; a tenth of a pixel is safe even for
+    /// stroked outlines.
+    ///
+    /// The GPU rasterizer doesn't use this tolerance; it subdivides curves as the
+    /// `AntialiasMode` passed to `Rasterizer::draw_atlas_with_antialiasing()` demands.
+    #[inline]
+    pub fn curve_tolerance(&self, point_size: f32, pixel_error: f32) -> f32 {
+        pixel_error / self.pixels_per_unit(point_size)
+    }
+
+    /// Returns the contours of the given glyph made of lines and quadratic curves only, in font
+    /// units.
+    ///
+    /// Cubic curves are approximated with quadratic curves to within `tolerance` font units; see
+    /// `curve_tolerance()` for choosing it. This lets engines that only handle quadratic curves
+    /// draw any outline. TrueType outlines are already quadratic, so they come back exactly as
+    /// `walk_glyph()` describes them. Fonts with CFF outlines can't be read yet and return
+    /// `Error::UnsupportedOutlineFormat`, as with `for_each_point()`.
+    pub fn glyph_quadratic_outline(&self, glyph_id: u16, tolerance: f32)
+                                   -> Result<Vec<Contour>, Error> {
+        flatten::quadratic_contours(self, glyph_id, tolerance)
+    }
+
+    /// Triangulates the filled interior of the given glyph on the CPU.
+    ///
+    /// Curves are approximated with line segments to within `tolerance` font units. Returns the
+    /// vertices in font units and the indices of the triangles, three per triangle, wound
+    /// counterclockwise. Triangles of overlapping contours may overlap; this is harmless for
+    /// opaque fills but will double-blend translucent ones.
+    ///
+    /// See `curve_tolerance()` for choosing the tolerance.
+    ///
+    /// This is useful as a software fallback when GPU rasterization is unavailable.
+    pub fn triangulate_glyph(&self, glyph_id: u16, tolerance: f32)
+                             -> Result<(Vec<Point2D<f32>>, Vec<u32>), Error> {
+        let contours = try!(self.flattened_contours(glyph_id, tolerance));
+        Ok(triangulate::triangulate(&contours))
+    }
+
+    /// Returns true if the contours of the given glyph overlap one another or themselves.
+    ///
+    /// Contours overlap if some region is enclosed by more than one of them under the nonzero
+    /// winding rule, as where two strokes of a hand-converted font cross, or if filled regions
+    /// wound in opposite directions meet, as where a contour crosses itself. Holes cut out of the
+    /// contour around them don't count, nor do separate contours that are merely wound in
+    /// different directions. Curves are approximated with line segments to within a quarter of a
+    /// font unit, so curves that only graze each other may be missed.
+    ///
+    /// Overlapping contours can show seams when rasterized; see
+    /// `OutlineBuilder::set_overlap_removal_enabled()`.
+    pub fn has_overlapping_contours(&self, glyph_id: u16) -> Result<bool, Error> {
+        let contours = try!(self.flattened_contours(glyph_id, 0.25));
+        Ok(overlap::has_overlaps(&contours))
+    }
+
+    /// Returns the closed polygons that approximate each contour of the given glyph, in font
+    /// units.
+    ///
+    /// Curves are subdivided so that no point on the curve is more than `tolerance` font units
+    /// away from the polygons. The last point of each polygon is the same as its first.
+    ///
+    /// See `curve_tolerance()` for choosing the tolerance.
+    ///
+    /// If the flattening cache is enabled with `set_flatten_cache_enabled()`, the result is
+    /// remembered for each glyph and tolerance, so that `nearest_outline_point()`,
+    /// `triangulate_glyph()`, and repeated calls to this method on the same glyph don't parse its
+    /// outline again.
+    pub fn flattened_contours(&self, glyph_id: u16, tolerance: f32)
+                              -> Result<Arc<Vec<Vec<Point2D<f32>>>>, Error> {
+        let cache = match self.flatten_cache {
+            None => return Ok(Arc::new(try!(flatten::flatten_contours(self, glyph_id, tolerance)))),
+            Some(ref cache) => cache,
+        };
+
+        let key = (glyph_id, tolerance.to_bits());
+        if let Some(contours) = cache.lock().unwrap().get(&key) {
+            return Ok(contours.clone())
+        }
+
+        // Don't hold the lock while flattening, so that other threads can use the cache.
+        let contours = Arc::new(try!(flatten::flatten_contours(self, glyph_id, tolerance)));
+        cache.lock().unwrap().insert(key, contours.clone());
+        Ok(contours)
+    }
+
+    /// Enables or disables the cache of flattened contours used by `flattened_contours()`.
+    ///
+    /// The cache is disabled by default. Disabling it frees all cached contours. The cache grows
+    /// with every glyph and tolerance flattened; call `clear_flatten_cache()` to bound its size.
+    pub fn set_flatten_cache_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.flatten_cache = None
+        } else if self.flatten_cache.is_none() {
+            self.flatten_cache = Some(Mutex::new(HashMap::new()))
+        }
+    }
+
+    /// Frees all contours in the flattening cache, leaving it enabled if it was.
+    pub fn clear_flatten_cache(&self) {
+        if let Some(ref cache) = self.flatten_cache {
+            cache.lock().unwrap().clear()
+        }
+    }
+
+    /// Returns the boundaries of the given glyph in font units.
+    ///
+    /// CFF2 outlines don't store their bounds, so for fonts with them, this returns
+    /// `computed_glyph_bounds()` for the current instance.
+    #[inline]
+    pub fn glyph_bounds(&self, glyph_id: u16) -> Result<GlyphBounds, Error> {
+        if self.cff2.is_some() {
+            return self.computed_glyph_bounds(glyph_id)
+        }
+
+        let (glyf, loca) = try!(self.glyf_and_loca());
+        glyf.glyph_bounds(&self.head, loca, glyph_id).map_err(|err| err.in_table(GLYF))
+    }
+
+    /// Returns the boundaries of the given glyph in font units, computed from its outline instead
+    /// of read from its header.
+    ///
+    /// `glyph_bounds()` trusts the bounding box that the font stores for each glyph, which some
+    /// fonts get wrong. This instead takes the bounding box of all the glyph's points, including
+    /// off-curve control points. Each curve lies inside the triangle formed by its control point
+    /// and endpoints, so the result always contains the whole outline, though it may be slightly
+    /// loose around curves. A glyph with no outline has empty bounds at the origin.
+    ///
+    /// This is slower than `glyph_bounds()`, since it reads every point of the glyph.
+    pub fn computed_glyph_bounds(&self, glyph_id: u16) -> Result<GlyphBounds, Error> {
+        let mut bounds: Option<GlyphBounds> = None;
+        try!(self.for_each_point(glyph_id, |point| {
+            let (x, y) = (point.position.x as i32, point.position.y as i32);
+            bounds = Some(match bounds {
+                None => GlyphBounds { left: x, bottom: y, right: x, top: y },
+                Some(bounds) => {
+                    GlyphBounds {
+                        left: cmp::min(bounds.left, x),
+                        bottom: cmp::min(bounds.bottom, y),
+                        right: cmp::max(bounds.right, x),
+                        top: cmp::max(bounds.top, y),
+                    }
+                }
+            })
+        }));
+
+        Ok(bounds.unwrap_or(GlyphBounds { left: 0, bottom: 0, right: 0, top: 0 }))
+    }
+
+    /// Returns the tight bounding box of the ink of the given glyph in fractional pixels at the
+    /// given point size, or `None` if the glyph has no outline, as for spaces.
+    ///
+    /// Unlike the layout box, which runs from the origin to the advance width, this excludes the
+    /// side bearings, and unlike `computed_glyph_bounds()`, it touches the curves themselves
+    /// rather than their off-curve control points. This is the box to center when aligning a
+    /// glyph optically, such as an icon in a button. As elsewhere, `point_size` is in pixels per
+    /// em, and the y axis points up. Also returns `None` if the glyph's outline can't be read.
+    pub fn ink_extents(&self, glyph_id: u16, point_size: f32) -> Option<GlyphSubpixelBounds> {
+        let (lower_left, upper_right) = match flatten::ink_bounds(self, glyph_id) {
+            Ok(Some(bounds)) => bounds,
+            Ok(None) | Err(_) => return None,
+        };
+
+        let pixels_per_unit = self.pixels_per_unit(point_size);
+        Some(GlyphSubpixelBounds {
+            left: lower_left.x * pixels_per_unit,
+            bottom: lower_left.y * pixels_per_unit,
+            right: upper_right.x * pixels_per_unit,
+            top: upper_right.y * pixels_per_unit,
+        })
+    }
+
+    /// Generates a multi-channel signed distance field (MSDF) for the given glyph on the CPU.
+    ///
+    /// Unlike a single-channel distance field, which rounds off sharp corners when magnified, an
+    /// MSDF stores three distances per pixel: the edges of each contour are colored so that the
+    /// two edges meeting at each corner share only one channel, and each channel holds the
+    /// distance to the nearest edge of its color. Sampling the field and taking the median of the
+    /// three channels, then thresholding at one half, redraws the glyph with its corners intact.
+    ///
+    /// Each channel maps signed distances from `-range / 2` to `range / 2` pixels onto 0 to 255,
+    /// with values above 127 inside the glyph. `range` must be positive. The pixels cover
+    /// `glyph_msdf_bounds()`, in rows from top to bottom with no padding between them. A glyph
+    /// with no outline has no pixels.
+    pub fn glyph_msdf(&self, glyph_id: u16, point_size: f32, range: f32)
+                      -> Result<Vec<[u8; 3]>, Error> {
+        msdf::generate(self, glyph_id, point_size, range)
+    }
+
+    /// Returns the pixel bounds of the field that `glyph_msdf()` generates for the given glyph.
+    ///
+    /// These are the glyph's pixel bounds at the given point size, grown by half of `range`,
+    /// rounded up, on every side so that the field falls off to zero around the outline.
+    pub fn glyph_msdf_bounds(&self, glyph_id: u16, point_size: f32, range: f32)
+                             -> Result<GlyphPixelBounds, Error> {
+        msdf::bounds(self, glyph_id, point_size, range)
+    }
+
+    /// Returns the raw bytes of the given glyph's entry in the `glyf` table, exactly as they
+    /// appear in the font.
+    ///
+    /// This is a zero-copy slice of the font data, useful for subsetting, re-embedding, or
+    /// comparing glyphs. Returns `None` if the glyph has no data (as is usual for spaces), if the
+    /// glyph ID is out of range, or if the font has no `glyf` and `loca` tables.
+    pub fn glyph_data(&self, glyph_id: u16) -> Option<&'a [u8]> {
+        let (glyf, loca) = match (self.glyf, self.loca.as_ref()) {
+            (Some(glyf), Some(loca)) => (glyf, loca),
+            _ => return None,
+        };
+
+        match loca.range_of(&self.head, glyph_id) {
+            Ok(Some((start, end))) => glyf.table.bytes.get(start as usize..end as usize),
+            Ok(None) | Err(_) => None,
+        }
+    }
+
+    /// Returns the minimum shelf height that an atlas containing glyphs from this font will need.
+    #[inline]
+    pub fn shelf_height(&self, point_size: f32) -> u32 {
+        // Add 2 to account for the border.
+        self.head
+            .max_glyph_bounds
+            .subpixel_bounds(self.head.units_per_em, point_size)
+            .round_out()
+            .size()
+            .height as u32 + 2
+    }
+
+    /// Returns the number of font units per em.
+    ///
+    /// An em is traditionally the width of the lowercase letter "m". A typical point size of a
+    /// font is expressed in number of pixels per em. Thus, in order to convert font units to
+    /// pixels, multiply them by `pixels_per_unit()`.
+    ///
+    /// This is never zero or greater than 16384; fonts declaring such values are rejected with
+    /// `Error::InvalidUnitsPerEm` when they are loaded.
+    #[inline]
+    pub fn units_per_em(&self) -> u16 {
+        self.head.units_per_em
+    }
+
+    /// Returns the number of pixels per font unit at the given point size, in pixels per em.
+    ///
+    /// Multiply font units by this to convert them to pixels; this is the scale that
+    /// `GlyphBounds::subpixel_bounds()` and the rasterizer apply.
+    #[inline]
+    pub fn pixels_per_unit(&self, point_size: f32) -> f32 {
+        point_size / self.head.units_per_em as f32
+    }
+
+    /// Returns the number of pixels per font unit that makes the em box `pixel_height` pixels
+    /// tall.
+    ///
+    /// This is the scale that designers mean when they give an em height in pixels. Since point
+    /// sizes in Pathfinder are in pixels per em, the matching point size is `pixel_height`
+    /// itself. Unlike stb_truetype's function of the same name, which fits the ascender and
+    /// descender into the height, this uses the em box alone; use
+    /// `pixel_height / (font.ascender() - font.descender()) as f32` for that behavior.
+    #[inline]
+    pub fn scale_for_pixel_height(&self, pixel_height: f32) -> f32 {
+        self.pixels_per_unit(pixel_height)
+    }
+
+    /// Returns the number of pixels per font unit that makes capital letters `pixel_height`
+    /// pixels tall, or `None` if the font's cap height is unknown.
+    ///
+    /// Multiply the result by `units_per_em()` to get the point size. See `cap_height()` for
+    /// where the cap height comes from.
+    pub fn scale_for_cap_height(&self, pixel_height: f32) -> Option<f32> {
+        self.cap_height().map(|cap_height| pixel_height / cap_height as f32)
+    }
+
+    /// Returns the height of capital letters above the baseline in font units, or `None` if it's
+    /// unknown.
+    ///
+    /// This is the `sCapHeight` field of the `OS/2` table, which fonts with a table of version 2
+    /// or later have. For older fonts, it's the top of the glyph for "H", as the OpenType
+    /// specification suggests. Nonpositive heights are treated as unknown.
+    pub fn cap_height(&self) -> Option<i16> {
+        let cap_height = match self.os_2.cap_height {
+            Some(cap_height) => cap_height,
+            None => {
+                match self.glyph_for_char('H').map(|glyph_id| self.glyph_bounds(glyph_id)) {
+                    Some(Ok(bounds)) => bounds.top as i16,
+                    Some(Err(_)) | None => return None,
+                }
+            }
+        };
+        if cap_height > 0 { Some(cap_height) } else { None }
+    }
+
+    /// Returns the number of pixels per font unit for text of the given size in typographic
+    /// points, at 1/72 of an inch each, on a display with the given number of pixels per inch.
+    ///
+    /// This is `pixels_per_unit(point_size * dpi / 72.0)`. Elsewhere in Pathfinder, point sizes
+    /// are in pixels per em, which is the same thing at 72 DPI.
+    #[inline]
+    pub fn scale_factor(&self, point_size: f32, dpi: f32) -> f32 {
+        self.pixels_per_unit(point_size * dpi / 72.0)
+    }
+
+    /// Returns the horizontal metrics for the glyph with the given ID.
+    ///
+    /// Horizontal metrics are important for text shaping, as they specify the number of units to
+    /// advance the pen after typesetting a glyph.
+    #[inline]
+    pub fn metrics_for_glyph(&self, glyph_id: u16) -> Result<HorizontalMetrics, Error> {
+        self.hmtx.metrics_for_glyph(&self.hhea, glyph_id).map_err(|err| err.in_table(HMTX))
+    }
+
+    /// Returns the horizontal metrics and bounds of each of the given glyphs, in font units.
+    ///
+    /// This is equivalent to calling `metrics_for_glyph()` and `glyph_bounds()` on each glyph,
+    /// but looks up the tables only once and returns the results in a single array, which is
+    /// faster when laying out long runs of text.
+    pub fn metrics_for(&self, glyph_ids: &[u16]) -> Result<Vec<GlyphMetrics>, Error> {
+        let (glyf, loca) = try!(self.glyf_and_loca());
+
+        let mut metrics = Vec::with_capacity(glyph_ids.len());
+        for &glyph_id in glyph_ids {
+            let horizontal_metrics = try!(self.metrics_for_glyph(glyph_id));
+            metrics.push(GlyphMetrics {
+                advance_width: horizontal_metrics.advance_width,
+                lsb: horizontal_metrics.lsb,
+                bounds: try!(glyf.glyph_bounds(&self.head, loca, glyph_id)
+                                 .map_err(|err| err.in_table(GLYF))),
+            })
+        }
+        Ok(metrics)
+    }
+
+    /// Returns the advance width of the glyph with the given ID in 26.6 fixed-point pixels (that
+    /// is, 1/64ths of a pixel) at the given point size.
+    ///
+    /// The result is bit-for-bit identical to the `advance.x` that FreeType reports for this glyph
+    /// after `FT_Set_Char_Size()` at 72 DPI and `FT_Load_Glyph()` with `FT_LOAD_NO_HINTING`. This
+    /// accounts for FreeType's rounding of the point size to 1/64 of a point, of the scale factor
+    /// to 16.16 fixed point, and of the glyph's origin relative to its outline. Pathfinder doesn't
+    /// hint, so it doesn't attempt to reproduce hinted advances.
+    pub fn advance_width_26_6(&self, glyph_id: u16, point_size: f32) -> Result<i32, Error> {
+        let metrics = try!(self.metrics_for_glyph(glyph_id));
+        let origin_x = try!(self.outline_origin_x(glyph_id, &metrics));
+        let scale = util::scale_26_6(self.head.units_per_em, point_size);
+        Ok(util::mul_fix(origin_x + metrics.advance_width as i32, scale) -
+           util::mul_fix(origin_x, scale))
+    }
+
+    /// Returns the advance width of the glyph with the given ID in pixels at the given point size.
+    ///
+    /// This is the advance width from `metrics_for_glyph()` times `pixels_per_unit(point_size)`,
+    /// unrounded. Layout code that accumulates pen positions should add up these values and round
+    /// only where glyphs are placed, rather than rounding each advance, which drifts along a line.
+    pub fn advance_pixels(&self, glyph_id: u16, point_size: f32) -> Result<f32, Error> {
+        let metrics = try!(self.metrics_for_glyph(glyph_id));
+        Ok(metrics.advance_width as f32 * self.pixels_per_unit(point_size))
+    }
+
+    /// Returns the advance width of the glyph with the given ID in whole pixels at the given point
+    /// size.
+    ///
+    /// The exact advance is rounded to the nearest pixel, with halves rounded up, so 10.5 pixels
+    /// becomes 11. The product is computed in double precision first so that advances that are
+    /// exactly halfway always round the same way, whereas `advance_pixels().round()` may not.
+    pub fn advance_pixels_rounded(&self, glyph_id: u16, point_size: f32) -> Result<i32, Error> {
+        let metrics = try!(self.metrics_for_glyph(glyph_id));
+        let advance = metrics.advance_width as f64 * point_size as f64 /
+            self.head.units_per_em as f64;
+        Ok((advance + 0.5).floor() as i32)
+    }
+
+    /// Returns the width of a run of glyphs in pixels at the given point size: the sum of their
+    /// advance widths plus the kerning between each adjacent pair, as `kerning_for_glyph_pair()`
+    /// reports it.
+    ///
+    /// This is the distance the pen moves across the run, which is what centering and
+    /// right-aligning text need. It includes the whole advance of the last glyph rather than
+    /// stopping at the right edge of its ink, so a run measures the same whether or not more text
+    /// follows it; use `glyph_bounds()` on the last glyph to find where its ink ends. The sum is
+    /// taken in font units and scaled once, unrounded, as in `advance_pixels()`. An empty run is
+    /// 0 pixels wide.
+    pub fn run_width(&self, glyphs: &[u16], point_size: f32) -> Result<f32, Error> {
+        let mut width = 0;
+        for (index, &glyph_id) in glyphs.iter().enumerate() {
+            width += try!(self.metrics_for_glyph(glyph_id)).advance_width as i32;
+            if let Some(&next_glyph_id) = glyphs.get(index + 1) {
+                width += self.kerning_for_glyph_pair(glyph_id, next_glyph_id) as i32
+            }
+        }
+        Ok(width as f32 * self.pixels_per_unit(point_size))
+    }
+
+    /// Returns the distance between the baselines of successive lines in pixels at the given point
+    /// size.
+    ///
+    /// This is the same as `line_height()`, unrounded. Round it with the same rule as
+    /// `advance_pixels_rounded()`, `(x + 0.5).floor()`, to keep lines on whole pixels.
+    #[inline]
+    pub fn line_advance_pixels(&self, point_size: f32) -> f32 {
+        self.line_height(point_size)
+    }
+
+    /// Returns the recommended distance between the baselines of successive lines in pixels at
+    /// the given point size.
+    ///
+    /// This is `(ascender() - descender() + line_gap()) * point_size / units_per_em()`. Like those
+    /// accessors, it uses the typographic metrics of the `OS/2` table if the font sets the
+    /// `USE_TYPO_METRICS` flag and the metrics of the `hhea` table otherwise.
+    pub fn line_height(&self, point_size: f32) -> f32 {
+        let (ascent, descent, line_gap) = self.vertical_metrics();
+        let line_height = ascent as i32 - descent as i32 + line_gap as i32;
+        line_height as f32 * self.pixels_per_unit(point_size)
+    }
+
+    // Returns the ascent, descent, and line gap in font units. If the font sets the
+    // `USE_TYPO_METRICS` flag in its `OS/2` table, these are the typographic metrics. Otherwise
+    // they come from the `hhea` table, as on macOS and in most browsers, since older fonts often
+    // leave their typographic metrics too tight for the glyphs they contain.
+    fn vertical_metrics(&self) -> (i16, i16, i16) {
+        const FS_SELECTION_USE_TYPO_METRICS: u16 = 1 << 7;
+        if self.os_2.fs_selection & FS_SELECTION_USE_TYPO_METRICS != 0 {
+            (self.os_2.typo_ascender, self.os_2.typo_descender, self.os_2.typo_line_gap)
+        } else {
+            (self.hhea.ascender, self.hhea.descender, self.hhea.line_gap)
+        }
+    }
+
+    /// Returns the control box of the glyph with the given ID in 26.6 fixed-point pixels at the
+    /// given point size, relative to the glyph's origin on the baseline.
+    ///
+    /// The result is bit-for-bit identical to what `FT_Outline_Get_CBox()` returns for the outline
+    /// FreeType loads under the conditions described in `advance_width_26_6()`. Unlike
+    /// `glyph_bounds()`, which returns the bounding box stored in the font, this is computed from
+    /// the glyph's points, so it's correct even if the stored bounding box is stale. Glyphs with no
+    /// outline have empty bounds at the origin. Use `GlyphFixedBounds::round_out()` to find the
+    /// pixel bounds of the bitmap FreeType would render.
+    pub fn glyph_bounds_26_6(&self, glyph_id: u16, point_size: f32)
+                             -> Result<GlyphFixedBounds, Error> {
+        let (glyf, loca) = try!(self.glyf_and_loca());
+
+        let scale = util::scale_26_6(self.head.units_per_em, point_size);
+        match try!(glyf.fixed_point_bounds(&self.head, loca, glyph_id, scale)
+                       .map_err(|err| err.in_table(GLYF))) {
+            None => Ok(GlyphFixedBounds { left: 0, bottom: 0, right: 0, top: 0 }),
+            Some(bounds) => {
+                // FreeType moves the outline horizontally so that the origin is at zero.
+                let metrics = try!(self.metrics_for_glyph(glyph_id));
+                let origin_x = try!(self.outline_origin_x(glyph_id, &metrics));
+                let shift = util::mul_fix(origin_x, scale);
+                Ok(GlyphFixedBounds {
+                    left: bounds.left - shift,
+                    right: bounds.right - shift,
+                    ..bounds
+                })
+            }
+        }
+    }
+
+    // Returns the horizontal position of the glyph's origin in the coordinate system of its
+    // outline, in font units. This is the first phantom point in TrueType terminology.
+    fn outline_origin_x(&self, glyph_id: u16, metrics: &HorizontalMetrics) -> Result<i32, Error> {
+        match self.glyf {
+            None => Ok(0),
+            Some(_) => Ok(try!(self.glyph_bounds(glyph_id)).left - metrics.lsb as i32),
+        }
+    }
+
+    /// Returns the hinted advance width in whole pixels of the glyph with the given ID at the given
+    /// size in pixels per em, as recorded in the font's `hdmx` table.
+    ///
+    /// Fonts designed for hinting record these so that text laid out by GDI and similar
+    /// rasterizers can be matched exactly. Returns `None` if the font has no `hdmx` table or the
+    /// table has no record for this size, in which case callers should fall back to scaling the
+    /// advance width from `metrics_for_glyph()`. See also `linear_threshold()`.
+    pub fn device_advance(&self, glyph_id: u16, ppem: u8) -> Option<u8> {
+        match self.hdmx {
+            None => None,
+            Some(hdmx) => hdmx.device_advance(glyph_id, ppem).unwrap_or(None),
+        }
+    }
+
+    /// Returns the smallest size in pixels per em at and above which the hinted advance width of
+    /// the glyph with the given ID is its linearly scaled advance width, as recorded in the font's
+    /// `LTSH` table.
+    ///
+    /// Below this size, hinting may change the advance width, so it must be taken from
+    /// `device_advance()` if available. Returns `None` if the font has no `LTSH` table.
+    pub fn linear_threshold(&self, glyph_id: u16) -> Option<u8> {
+        match self.ltsh {
+            None => None,
+            Some(ltsh) => ltsh.linear_threshold(glyph_id).unwrap_or(None),
+        }
+    }
+
+    /// Returns how the font's `gasp` table says glyphs should be rendered at the given size in
+    /// pixels per em.
+    ///
+    /// Many fonts turn off antialiasing at small sizes, where their hinting instructions snap
+    /// stems to whole pixels, and turn it back on at larger sizes. A renderer can use this to
+    /// choose an `AntialiasMode` for each size, such as `AntialiasMode::None` where `GASP_DOGRAY`
+    /// is absent, and, with the `hinting` feature, whether to hint outlines at all, such as only
+    /// where `GASP_GRIDFIT` is set. Fonts without a `gasp` table, or whose table doesn't cover the
+    /// size, get `GASP_DOGRAY`: grayscale antialiasing at all sizes. The symmetric
+    /// flags are only set by version 1 tables.
+    pub fn gasp_behavior(&self, ppem: u16) -> GaspBehavior {
+        let behavior = match self.gasp {
+            None => None,
+            Some(gasp) => gasp.behavior(ppem).unwrap_or(None),
+        };
+        behavior.unwrap_or(GASP_DOGRAY)
+    }
+
+    /// Returns the number of color palettes in the font's `CPAL` table, or 0 if it has none.
+    ///
+    /// Fonts may offer several palettes for their color glyphs, for example one suited to light
+    /// backgrounds and one suited to dark backgrounds. Pass the index of the one you want to
+    /// `color_layers_with_palette()`.
+    #[inline]
+    pub fn palette_count(&self) -> u16 {
+        match self.cpal {
+            None => 0,
+            Some(cpal) => cpal.num_palettes,
+        }
+    }
+
+    /// Returns the layers that make up the given color glyph, from bottom to top, colored with
+    /// the font's first palette.
+    ///
+    /// See `color_layers_with_palette()` for details.
+    #[inline]
+    pub fn color_layers(&self, glyph_id: u16) -> Vec<ColorLayer> {
+        self.color_layers_with_palette(glyph_id, 0)
+    }
+
+    /// Returns the layers that make up the given color glyph, from bottom to top, colored with
+    /// the palette with the given index.
+    ///
+    /// A color glyph is drawn by drawing the glyph of each layer in that layer's color, in order,
+    /// over the ones before it. If `palette_index` is out of range, the first palette is used.
+    /// Returns an empty list if the glyph isn't a color glyph or the font has no `COLR` and
+    /// `CPAL` tables, in which case the glyph should be drawn normally.
+    pub fn color_layers_with_palette(&self, glyph_id: u16, palette_index: u16)
+                                     -> Vec<ColorLayer> {
+        let (colr, cpal) = match (self.colr, self.cpal) {
+            (Some(colr), Some(cpal)) => (colr, cpal),
+            _ => return vec![],
+        };
+        let palette_index = if palette_index < cpal.num_palettes {
+            palette_index
+        } else {
+            0
+        };
+
+        let mut layers = vec![];
+        let result = colr.for_each_layer(glyph_id, |layer_glyph_id, palette_entry_index| {
+            layers.push(ColorLayer {
+                glyph_id: layer_glyph_id,
+                color: palette_entry_index.and_then(|palette_entry_index| {
+                    cpal.color(palette_index, palette_entry_index).unwrap_or(None)
+                }),
+            })
+        });
+
+        match result {
+            Ok(()) => layers,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Returns the SVG document that describes the given color glyph, or `None` if there isn't
+    /// one.
+    ///
+    /// Documents come from the `SVG ` table. One document may describe a whole range of glyphs;
+    /// render the element for this glyph, as described in `SvgDocument`. Returns `None` for
+    /// glyphs without a document and for fonts with no `SVG ` table or a malformed one, in which
+    /// case the glyph should be drawn from its outline or its `color_layers()`.
+    pub fn svg_document(&self, glyph_id: u16) -> Option<SvgDocument<'a>> {
+        match self.svg {
+            None => None,
+            Some(svg) => svg.document(glyph_id).unwrap_or(None),
+        }
+    }
+
+    /// Returns the sizes in pixels per em of the bitmap strikes that the font embeds, in
+    /// ascending order without duplicates.
+    ///
+    /// Strikes come from the `sbix` table, as in Apple's color emoji fonts, and from the `CBLC`
+    /// table, or the `EBLC` table if there's no `CBLC` table. A renderer can pick the strike
+    /// nearest to the size it draws at and scale its bitmaps, or draw the outlines instead if the
+    /// list is empty. Malformed tables contribute no strikes.
+    pub fn bitmap_strikes(&self) -> Vec<u16> {
+        let mut strikes = vec![];
+        if let Some(sbix) = self.sbix {
+            strikes.extend(sbix.strike_sizes().unwrap_or(vec![]))
+        }
+        if let Some(cblc) = self.cblc {
+            strikes.extend(cblc.strike_sizes().unwrap_or(vec![]))
+        }
+        strikes.sort();
+        strikes.dedup();
+        strikes
+    }
+
+    /// Returns the kerning between the given two glyph IDs in font units.
+    ///
+    /// Positive values move glyphs farther apart; negative values move glyphs closer together.
+    ///
+    /// Zero is returned if no kerning is available in the font.
+    #[inline]
+    pub fn kerning_for_glyph_pair(&self, left_glyph_id: u16, right_glyph_id: u16) -> i16 {
+        match self.kern {
+            None => 0,
+            Some(kern) => kern.kerning_for_glyph_pair(left_glyph_id, right_glyph_id).unwrap_or(0),
+        }
+    }
+
+    /// Applies the glyph substitutions for the given layout features, such as `tag(b"liga")` for
+    /// standard ligatures, to a sequence of glyph IDs.
+    ///
+    /// Single (type 1) and ligature (type 4) substitutions from the `GSUB` table are supported.
+    /// Lookups of other types leave the glyphs unchanged, as do fonts without a `GSUB` table or
+    /// with a malformed one. The result can be passed to `OutlineBuilder::add_glyph()` as usual.
+    pub fn substitute(&self, glyphs: &[u16], features: &[Tag]) -> Vec<u16> {
+        match self.gsub {
+            None => glyphs.to_vec(),
+            Some(gsub) => gsub.substitute(glyphs, features).unwrap_or_else(|_| glyphs.to_vec()),
+        }
+    }
+
+    /// Computes the positioning adjustments for the given layout features, such as `tag(b"kern")`
+    /// and `tag(b"mark")`, for a sequence of glyph IDs.
+    ///
+    /// Returns one adjustment per glyph, in font units. Pair adjustment (type 2) and mark-to-base
+    /// attachment (type 4) lookups from the `GPOS` table are supported. Lookups of other types
+    /// contribute nothing, and fonts without a `GPOS` table or with a malformed one get zero
+    /// adjustments. Unlike `kerning_for_glyph_pair()`, this doesn't consult the legacy `kern`
+    /// table.
+    pub fn position(&self, glyphs: &[u16], features: &[Tag]) -> Vec<GlyphAdjustment> {
+        let no_adjustments = vec![GlyphAdjustment::default(); glyphs.len()];
+        let gpos = match self.gpos {
+            None => return no_adjustments,
+            Some(gpos) => gpos,
+        };
+
+        let mut advances = Vec::with_capacity(glyphs.len());
+        for &glyph_id in glyphs {
+            match self.metrics_for_glyph(glyph_id) {
+                Ok(metrics) => advances.push(metrics.advance_width as i32),
+                Err(_) => return no_adjustments,
+            }
+        }
+
+        gpos.position(glyphs, &advances, features).unwrap_or(no_adjustments)
+    }
+
+    /// Returns the angle that the font's upright strokes lean at, in degrees counterclockwise
+    /// from vertical, as the `post` table declares it.
+    ///
+    /// Italic and oblique faces that lean forward have negative angles, such as -12. Upright
+    /// faces, and fonts without a `post` table, have an angle of zero.
+    #[inline]
+    pub fn italic_angle(&self) -> f32 {
+        self.post.as_ref().map_or(0.0, |post| post.italic_angle)
+    }
+
+    /// Returns true if the font declares itself italic or oblique.
+    ///
+    /// This checks the italic and oblique bits of `fsSelection` in the `OS/2` table and the
+    /// italic bit of `macStyle` in the `head` table; any of them makes the face italic. A
+    /// renderer synthesizing italics should skip its shear for such faces, which are already
+    /// slanted by `italic_angle()`.
+    pub fn is_italic(&self) -> bool {
+        const FS_SELECTION_ITALIC: u16 = 1 << 0;
+        const FS_SELECTION_OBLIQUE: u16 = 1 << 9;
+        const MAC_STYLE_ITALIC: u16 = 1 << 1;
+        self.os_2.fs_selection & (FS_SELECTION_ITALIC | FS_SELECTION_OBLIQUE) != 0 ||
+            self.head.mac_style & MAC_STYLE_ITALIC != 0
+    }
+
+    /// Returns the class of the given glyph from the font's `GDEF` table: whether it's a base
+    /// glyph, a ligature, a combining mark, or a component of a character.
+    ///
+    /// Returns `GlyphClass::Unassigned` for glyphs that the table doesn't classify and for fonts
+    /// without a `GDEF` table, without glyph classes, or with a malformed table.
+    pub fn glyph_class(&self, glyph_id: u16) -> GlyphClass {
+        match self.gdef {
+            None => GlyphClass::Unassigned,
+            Some(gdef) => gdef.glyph_class(glyph_id).unwrap_or(GlyphClass::Unassigned),
+        }
+    }
+
+    /// Returns true if the `GDEF` table classifies the given glyph as a combining mark.
+    ///
+    /// A renderer without full `GPOS` support can use this to stack diacritics: a mark following
+    /// a base glyph takes no advance of its own and is drawn over the base, at its mark
+    /// attachment anchor if `position()` supplies one. Glyphs in fonts without glyph classes are
+    /// never marks.
+    #[inline]
+    pub fn is_mark_glyph(&self, glyph_id: u16) -> bool {
+        self.glyph_class(glyph_id) == GlyphClass::Mark
+    }
+
+    /// Returns the position of the given baseline for the given script, such as
+    /// `tag(b"hani")` for CJK ideographs, in font units above the baseline that the font's
+    /// glyphs are drawn on, as the `BASE` table records it for horizontal text.
+    ///
+    /// A layout engine can align runs of different scripts on one line by shifting each run
+    /// vertically so that the baseline of the line's dominant script coincides. For example, to
+    /// set ideographs on the alphabetic baseline of a Latin line, raise them by the `romn` offset
+    /// for `hani` minus their own `ideo` offset, if those are what the design calls for.
+    /// If the table has no entry for the script, its `DFLT` entry is used. Returns `None` if the
+    /// font has no `BASE` table, the table doesn't list the baseline for the script, or the
+    /// table is malformed; callers should then fall back to the alphabetic baseline, which is
+    /// usually at zero. Device and variation adjustments are ignored.
+    pub fn baseline_offset(&self, baseline: BaselineTag, script: Tag) -> Option<i16> {
+        match self.base {
+            None => None,
+            Some(base) => base.baseline_offset(baseline, script).unwrap_or(None),
+        }
+    }
+
+    /// Returns the distance from the baseline to the top of the text box in font units.
+    ///
+    /// These are the `OS/2` table's typographic metrics if the font sets the `USE_TYPO_METRICS`
+    /// flag and the `hhea` table's metrics otherwise. The following expression computes the
+    /// baseline-to-baseline height, as `line_height()` does:
+    /// `font.ascender() - font.descender() + font.line_gap()`.
+    #[inline]
+    pub fn ascender(&self) -> i16 {
+        self.vertical_metrics().0
+    }
+
+    /// Returns the distance from the baseline to the bottom of the text box in font units.
+    ///
+    /// These are the `OS/2` table's typographic metrics if the font sets the `USE_TYPO_METRICS`
+    /// flag and the `hhea` table's metrics otherwise. The following expression computes the
+    /// baseline-to-baseline height, as `line_height()` does:
+    /// `font.ascender() - font.descender() + font.line_gap()`.
+    #[inline]
+    pub fn descender(&self) -> i16 {
+        self.vertical_metrics().1
+    }
+
+    /// Returns the recommended extra gap between lines in font units.
+    ///
+    /// These are the `OS/2` table's typographic metrics if the font sets the `USE_TYPO_METRICS`
+    /// flag and the `hhea` table's metrics otherwise. The following expression computes the
+    /// baseline-to-baseline height, as `line_height()` does:
+    /// `font.ascender() - font.descender() + font.line_gap()`.
+    #[inline]
+    pub fn line_gap(&self) -> i16 {
+        self.vertical_metrics().2
+    }
+
+    /// Returns the languages that the font was designed for, as recorded in the `dlng` entry of
+    /// its `meta` table.
+    ///
+    /// Each language is a ScriptLangTag: a BCP 47 language tag such as `"en-Latn"`, or just a
+    /// script such as `"Latn"` or `"Jpan"`, naming the writing systems whose style the design
+    /// follows. Returns an empty list if the font has no `meta` table, no such entry, or a
+    /// malformed one.
+    pub fn design_languages(&self) -> Vec<String> {
+        self.meta_languages(tag(b"dlng"))
+    }
+
+    /// Returns the languages that the font can render, as recorded in the `slng` entry of its
+    /// `meta` table.
+    ///
+    /// These are ScriptLangTags, as in `design_languages()`, and may include languages that the
+    /// font supports but wasn't designed for. Returns an empty list if the font has no `meta`
+    /// table, no such entry, or a malformed one; `unicode_ranges()` and `coverage_set()` give a
+    /// rougher idea of support in that case.
+    pub fn supported_languages(&self) -> Vec<String> {
+        self.meta_languages(tag(b"slng"))
+    }
+
+    fn meta_languages(&self, tag: Tag) -> Vec<String> {
+        match self.meta {
+            None => vec![],
+            Some(meta) => meta.languages(tag).unwrap_or(vec![]),
+        }
+    }
+
+    /// Returns the Unicode ranges that the font claims to support in its `OS/2` table.
+    ///
+    /// This is much cheaper than `coverage_set()`, but also much coarser; see `UnicodeRanges`.
+    #[inline]
+    pub fn unicode_ranges(&self) -> UnicodeRanges {
+        self.os_2.unicode_ranges
+    }
+
+    /// Returns the raw bytes of the table with the given tag, or `None` if the font has no such
+    /// table.
+    ///
+    /// This works for any table in the table directory, including those that Pathfinder doesn't
+    /// parse, such as `MATH` or `SVG `, so that they can be read without changes to this crate.
+    /// Use `tag()` to make the tag, as in `font.table(otf::tag(b"MATH"))`. The bytes are borrowed
+    /// from the font's buffer, not copied.
+    pub fn table(&self, tag: Tag) -> Option<&'a [u8]> {
+        for record in self.table_records.chunks(TABLE_RECORD_SIZE) {
+            if BigEndian::read_u32(&record[0..4]) != tag {
+                continue
+            }
+
+            let offset = BigEndian::read_u32(&record[8..12]) as usize;
+            let length = BigEndian::read_u32(&record[12..16]) as usize;
+            return match offset.checked_add(length) {
+                Some(end) => self.bytes.get(offset..end),
+                None => None,
+            }
+        }
+        None
+    }
+
+    /// Returns a cheap identifier for this font, suitable as a key for caches of its glyphs.
+    ///
+    /// This is a hash of the `checksumAdjustment` field of the `head` table, the length of the
+    /// font's file, and the position of the font within it, so it's the same every time the
+    /// same file is loaded, whether from a buffer or through a `FontReader`, and fonts of one
+    /// collection get different identifiers. The hash is
+    /// 64-bit FNV-1a over the big-endian bytes of the three, which doesn't change between builds
+    /// of Pathfinder, so identifiers may be persisted. Computing it doesn't read the rest of the
+    /// font. Different fonts that agree on all three collide; to tell such fonts apart, as when
+    /// deduplicating fonts from untrusted sources, use `content_hash()`.
+    pub fn id(&self) -> u64 {
+        let mut fields = [0; 20];
+        BigEndian::write_u32(&mut fields[0..4], self.head.checksum_adjustment);
+        let file_length = self.file_length.unwrap_or(self.bytes.len() as u64);
+        BigEndian::write_u64(&mut fields[4..12], file_length);
+        BigEndian::write_u64(&mut fields[12..20], self.table_directory_offset() as u64);
+        fields.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Returns a hash of the entire contents of the font's buffer and the position of the font
+    /// within it.
+    ///
+    /// Unlike `id()`, this reads every byte, so it takes time proportional to the size of the
+    /// file. Like `id()`, it's the same every time the same file is loaded by the same build of
+    /// Pathfinder, but it shouldn't be persisted, since the hash function may change.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(self.bytes);
+        (self.table_directory_offset() as u64).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Checks the font's checksums against its contents, to catch truncated or corrupted files
+    /// before they cause confusing errors when glyphs are read.
+    ///
+    /// The checksum of every table in the table directory is recomputed, whether or not Pathfinder
+    /// reads the table, with the `checksumAdjustment` field of the `head` table counted as zero.
+    /// Then, if the font is the whole buffer rather than one font of a collection, the checksum of
+    /// the whole file is checked against `checksumAdjustment`. A digital signature in a `DSIG`
+    /// table isn't verified; its table is checked like any other. Returns the first mismatch
+    /// found, in the order of the table directory.
+    ///
+    /// A font from a `FontReader` only holds the tables that were read, other than `glyf` and
+    /// `loca`, so only those tables are checked.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        for record in self.table_records.chunks(TABLE_RECORD_SIZE) {
+            let tag = BigEndian::read_u32(&record[0..4]);
+            let expected = BigEndian::read_u32(&record[4..8]);
+            let offset = BigEndian::read_u32(&record[8..12]) as usize;
+            let length = BigEndian::read_u32(&record[12..16]) as usize;
+            let table = match self.bytes.get(offset..offset.saturating_add(length)) {
+                Some(table) => table,
+                None => return Err(ValidationError::TableOutOfBounds(tag)),
+            };
+
+            let mut actual = checksum(table);
+            if tag == HEAD && table.len() >= 12 {
+                actual = actual.wrapping_sub(BigEndian::read_u32(&table[8..12]))
+            }
+            if actual != expected {
+                return Err(ValidationError::TableChecksumMismatch {
+                    tag: tag,
+                    expected: expected,
+                    actual: actual,
+                })
+            }
+        }
+
+        // The table records follow the 12-byte offset table, which is at the start of a
+        // standalone font.
+        if self.table_directory_offset() != 12 || self.file_length.is_some() {
+            return Ok(())
+        }
+
+        // The whole file, `checksumAdjustment` included, sums to this magic number.
+        let actual = CHECKSUM_MAGIC.wrapping_sub(checksum(self.bytes))
+                                   .wrapping_add(self.head.checksum_adjustment);
+        if actual != self.head.checksum_adjustment {
+            return Err(ValidationError::ChecksumAdjustmentMismatch {
+                expected: self.head.checksum_adjustment,
+                actual: actual,
+            })
+        }
+        Ok(())
+    }
+
+    // Returns the offset of this font's table records within its buffer, which tells the fonts
+    // of a collection apart.
+    fn table_directory_offset(&self) -> usize {
+        self.table_records.as_ptr() as usize - self.bytes.as_ptr() as usize
+    }
+}
+
+/// Limits on the fonts that `Font::from_bytes_limited()` accepts.
+///
+/// The default limits are those that `Font::new()` applies: composite glyphs may nest up to 8
+/// levels deep, and nothing else is limited. For fonts from untrusted sources, lower them to suit
+/// the fonts you expect; for example, few legitimate fonts have more than 65,535 glyphs, 10,000
+/// points in a glyph, 4 levels of composite glyphs, or tables larger than 16 MB.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ParseLimits {
+    /// The maximum number of glyphs, as declared in the `maxp` table. Fonts with more fail to
+    /// load with `Error::TooManyGlyphs`.
+    pub max_glyph_count: u32,
+    /// The maximum number of points in the outline of a glyph, counting the points of all the
+    /// components of a composite glyph together. Reading a larger outline fails with
+    /// `Error::TooManyPoints`.
+    pub max_points_per_glyph: u32,
+    /// The maximum nesting depth of composite glyphs. A composite glyph made of simple glyphs has
+    /// a depth of 1, and a limit of 0 rejects composite glyphs entirely. Reading a deeper glyph
+    /// fails with `Error::ComponentsTooDeep`.
+    pub max_component_depth: u32,
+    /// The maximum size of any table, in bytes. Fonts with a larger table fail to load with
+    /// `Error::TableTooLarge`.
+    pub max_table_size: u32,
+}
+
+impl Default for ParseLimits {
+    fn default() -> ParseLimits {
+        ParseLimits {
+            max_glyph_count: u32::MAX,
+            max_points_per_glyph: u32::MAX,
+            max_component_depth: glyf::MAX_COMPONENT_DEPTH,
+            max_table_size: u32::MAX,
+        }
+    }
+}
+
+/// An iterator over the IDs of the glyphs of a font that have outlines, as returned by
+/// `Font::non_empty_glyph_ids()`.
+pub struct NonEmptyGlyphIds<'a> {
+    font: &'a Font<'a>,
+    glyph_ids: Range<u16>,
+}
+
+impl<'a> Iterator for NonEmptyGlyphIds<'a> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        while let Some(glyph_id) = self.glyph_ids.next() {
+            if self.font.glyph_data(glyph_id).is_some() {
+                return Some(glyph_id)
+            }
+        }
+        None
+    }
+}
+
+/// The horizontal metrics and bounds of a glyph in font units, as returned by
+/// `Font::metrics_for()`.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphMetrics {
+    /// The distance to advance the pen after drawing the glyph.
+    pub advance_width: u16,
+    /// The left side bearing: the distance from the pen position to the left edge of the glyph.
+    pub lsb: i16,
+    /// The boundaries of the glyph.
+    pub bounds: GlyphBounds,
+}
+
+/// One layer of a color glyph, as returned by `Font::color_layers_with_palette()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ColorLayer {
+    /// The glyph whose outline forms this layer.
+    pub glyph_id: u16,
+    /// The color of this layer in straight (non-premultiplied) RGBA order, or `None` if the
+    /// layer should be drawn in the text color.
+    ///
+    /// Colors are in the same form that `CoverageBuffer::new_premultiplied()` takes.
+    pub color: Option<[u8; 4]>,
+}
+
+/// A closed contour of a glyph outline made of lines and quadratic curves only, as returned by
+/// `Font::glyph_quadratic_outline()`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Contour {
+    /// The points of the contour, in the manner of TrueType outlines.
+    ///
+    /// The first point is on the curve. An off-curve point is the control point of the quadratic
+    /// curve between the on-curve points on either side of it; two on-curve points in a row are
+    /// joined by a line. The contour closes back to its first point.
+    pub points: Vec<OutlinePoint>,
+}
+
+/// A point of a `Contour`, in font units.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OutlinePoint {
+    /// The position of the point.
+    pub position: Point2D<f32>,
+    /// Whether the point is on the curve, rather than a control point.
+    pub on_curve: bool,
+}
+
+/// The technology that a font uses to describe the shapes of its glyphs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutlineFormat {
+    /// Quadratic outlines in the `glyf` table.
+    TrueType,
+    /// Cubic outlines in the `CFF ` table.
+    ///
+    /// Note that fonts in the OpenType CFF container format (`.otf` files beginning with `OTTO`)
+    /// are rejected with `Error::UnsupportedOutlineFormat` when they are loaded, unless they have
+    /// a `CFF2` table.
+    Cff,
+    /// Cubic outlines in the `CFF2` table, as used by variable OpenType fonts.
+    ///
+    /// The blend operators of the charstrings follow the instance chosen with
+    /// `Font::set_variations()`.
+    Cff2,
+    /// No outlines, as in color emoji fonts that only have bitmaps in a `CBDT` table.
+    BitmapOnly,
+}
+
+/// Receives the contours of a glyph outline from `Font::walk_glyph()`.
+///
+/// All points are in font units.
+pub trait OutlineVisitor {
+    /// Starts a new contour at the given point.
+    fn move_to(&mut self, to: &Point2D<f32>);
+    /// Adds a line segment from the current point to the given point.
+    fn line_to(&mut self, to: &Point2D<f32>);
+    /// Adds a quadratic Bézier curve from the current point to the given point.
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>);
+    /// Adds a cubic Bézier curve from the current point to the given point.
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>);
+    /// Ends the current contour.
+    fn close(&mut self);
+}
+
+/// Errors that can occur when parsing OpenType fonts.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Error {
+    /// A miscellaneous error occurred.
+    Failed,
+    /// The file ended unexpectedly.
+    UnexpectedEof,
+    /// The table with the given tag ended unexpectedly, or extends past the end of the file.
+    ///
+    /// Compare the tag against `otf::tag()`, as in `otf::tag(b"glyf")`.
+    TableTooShort(Tag),
+    /// There is no font with this index in this font collection.
+    FontIndexOutOfBounds,
+    /// The file declared that it was in a version of the format we don't support.
+    UnsupportedVersion,
+    /// The file was of a format we don't support.
+    UnknownFormat,
+    /// The font's glyphs are described in the given format, which we don't yet support. This is
+    /// currently the case for CFF outlines, and for hinting anything but TrueType outlines.
+    UnsupportedOutlineFormat(OutlineFormat),
+    /// The font had a glyph format we don't support.
+    UnsupportedGlyphFormat,
+    /// We don't support the declared version of the font's character map.
+    UnsupportedCmapVersion,
+    /// The font character map has an unsupported platform/encoding ID.
+    UnsupportedCmapEncoding,
+    /// The font character map has an unsupported format.
+    UnsupportedCmapFormat,
+    /// We don't support the declared version of the font header.
+    UnsupportedHeadVersion,
+    /// We don't support the declared version of the font's horizontal metrics.
+    UnsupportedHheaVersion,
+    /// We don't support the declared version of the font's OS/2 and Windows table.
+    UnsupportedOs2Version,
+    /// The table with the given tag is missing, but this operation or every font requires it.
+    MissingTable(Tag),
+    /// The font header declares an invalid number of font units per em: either zero or more than
+    /// 16384, the maximum that the OpenType specification allows.
+    InvalidUnitsPerEm,
+    /// The glyph is a composite glyph where only a simple glyph can appear.
+    CompositeGlyph,
+    /// The glyph's outline data is inconsistent: the end points of its contours decrease, so they
+    /// don't describe the points that follow.
+    MalformedGlyph,
+    /// The table with the given tag is larger than `ParseLimits::max_table_size`.
+    TableTooLarge(Tag),
+    /// The font has more glyphs than `ParseLimits::max_glyph_count`.
+    TooManyGlyphs,
+    /// The glyph has more points than `ParseLimits::max_points_per_glyph`.
+    TooManyPoints,
+    /// The composite glyph nests more deeply than `ParseLimits::max_component_depth`.
+    ComponentsTooDeep,
+    /// The outline of the glyph with the given ID hasn't been read from the stream of the
+    /// `FontReader` that the font came from; see `FontReader::load_glyphs()`.
+    GlyphNotLoaded(u16),
+    /// The font's hinting instructions are malformed, use instructions that aren't supported, or
+    /// run for too long.
+    HintingFailed,
+}
+
+impl Error {
+    #[doc(hidden)]
+    #[inline]
+    pub fn eof<T>(_: T) -> Error {
+        Error::UnexpectedEof
+    }
+
+    // Attributes an unexpected end of data to the table with the given tag.
+    #[inline]
+    fn in_table(self, tag: Tag) -> Error {
+        match self {
+            Error::UnexpectedEof => Error::TableTooShort(tag),
+            error => error,
+        }
+    }
+}
+
+/// A disagreement between a font's checksums and its contents, as found by `Font::validate()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ValidationError {
+    /// The table with the given tag extends past the end of the buffer, as in a truncated file.
+    TableOutOfBounds(Tag),
+    /// The checksum of the table with the given tag, `actual`, differs from the one recorded in
+    /// the table directory, `expected`.
+    TableChecksumMismatch {
+        tag: Tag,
+        expected: u32,
+        actual: u32,
+    },
+    /// The `checksumAdjustment` field of the `head` table, `expected`, differs from the value
+    /// computed from the whole file, `actual`.
+    ChecksumAdjustmentMismatch {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+// Returns the OpenType checksum of the given bytes: the sum of their big-endian 32-bit words,
+// with the last word padded with zeros.
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut sum = 0u32;
+    for word in bytes.chunks(4) {
+        let mut padded_word = [0; 4];
+        padded_word[..word.len()].copy_from_slice(word);
+        sum = sum.wrapping_add(BigEndian::read_u32(&padded_word))
+    }
+    sum
+}
@@ -18,6 +18,9 @@ pub struct Os2Table {
     pub typo_ascender: i16,
     pub typo_descender: i16,
     pub typo_line_gap: i16,
+    pub unicode_ranges: UnicodeRanges,
+    pub fs_selection: u16,
+    pub cap_height: Option<i16>,
 }
 
 impl Os2Table {
@@ -28,27 +31,238 @@ impl Os2Table {
         // Postel's law and hope for the best.
         let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
 
-        // Skip to the line gap.
+        // Skip to the Unicode ranges.
         try!(reader.jump(mem::size_of::<u16>() * 15).map_err(Error::eof));
         try!(reader.jump(10).map_err(Error::eof));
-        if version == 0 {
-            try!(reader.jump(mem::size_of::<u32>() * 2).map_err(Error::eof));
-        } else {
-            try!(reader.jump(mem::size_of::<u32>() * 5).map_err(Error::eof));
+
+        // Read the Unicode ranges, which every version has, and skip the vendor ID.
+        let mut unicode_ranges = UnicodeRanges::default();
+        for bits in &mut unicode_ranges.bits {
+            *bits = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
         }
-        try!(reader.jump(mem::size_of::<u16>() * 3).map_err(Error::eof));
+        try!(reader.jump(mem::size_of::<u32>()).map_err(Error::eof));
+
+        // Read the selection flags, and skip the first and last character indices.
+        let fs_selection = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        try!(reader.jump(mem::size_of::<u16>() * 2).map_err(Error::eof));
 
         // Read the line spacing information.
         let typo_ascender = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
         let typo_descender = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
         let typo_line_gap = try!(reader.read_i16::<BigEndian>().map_err(Error::eof));
 
+        // Version 2 added the cap height after the Windows metrics, the code page ranges, and the
+        // x-height. Tolerate tables that are too short to have it.
+        let mut cap_height = None;
+        if version >= 2 &&
+                reader.jump(mem::size_of::<u16>() * 2 + mem::size_of::<u32>() * 2 +
+                            mem::size_of::<i16>()).is_ok() {
+            cap_height = reader.read_i16::<BigEndian>().ok()
+        }
+
         Ok(Os2Table {
             typo_ascender: typo_ascender,
             typo_descender: typo_descender,
             typo_line_gap: typo_line_gap,
+            unicode_ranges: unicode_ranges,
+            fs_selection: fs_selection,
+            cap_height: cap_height,
         })
     }
 }
 
+/// The Unicode ranges that a font claims to support, from the `ulUnicodeRange1` through
+/// `ulUnicodeRange4` fields of its `OS/2` table.
+///
+/// These are set by the font's designer and cover whole blocks at a time, so they're much coarser
+/// than the character map, and a font may claim a range it only partly covers. They're cheap to
+/// test, though, which makes them useful to rule out fonts before consulting
+/// `Font::coverage_set()`.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct UnicodeRanges {
+    bits: [u32; 4],
+}
+
+impl UnicodeRanges {
+    /// Returns true if the font claims to support the given range.
+    #[inline]
+    pub fn contains_range(&self, range: Os2Range) -> bool {
+        let bit = range as u32;
+        (self.bits[(bit / 32) as usize] >> (bit % 32)) & 1 != 0
+    }
+
+    /// Returns true if the font claims to support no ranges at all, as is the case for many old
+    /// fonts, which leave the fields zero.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bits == [0; 4]
+    }
+
+    /// Returns the raw bits, with `ulUnicodeRange1` first and bit 0 of each word standing for
+    /// the lowest-numbered range in it.
+    #[inline]
+    pub fn bits(&self) -> [u32; 4] {
+        self.bits
+    }
+}
+
+/// A Unicode range that a font can claim in its `OS/2` table, numbered by its bit.
+///
+/// Some bits stand for several Unicode blocks at once, as noted.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Os2Range {
+    BasicLatin = 0,
+    Latin1Supplement = 1,
+    LatinExtendedA = 2,
+    LatinExtendedB = 3,
+    /// IPA Extensions, Phonetic Extensions, and Phonetic Extensions Supplement.
+    IpaExtensions = 4,
+    /// Spacing Modifier Letters and Modifier Tone Letters.
+    SpacingModifierLetters = 5,
+    /// Combining Diacritical Marks and Combining Diacritical Marks Supplement.
+    CombiningDiacriticalMarks = 6,
+    GreekAndCoptic = 7,
+    Coptic = 8,
+    /// Cyrillic, Cyrillic Supplement, Cyrillic Extended-A, and Cyrillic Extended-B.
+    Cyrillic = 9,
+    Armenian = 10,
+    Hebrew = 11,
+    Vai = 12,
+    /// Arabic and Arabic Supplement.
+    Arabic = 13,
+    NKo = 14,
+    Devanagari = 15,
+    Bengali = 16,
+    Gurmukhi = 17,
+    Gujarati = 18,
+    Oriya = 19,
+    Tamil = 20,
+    Telugu = 21,
+    Kannada = 22,
+    Malayalam = 23,
+    Thai = 24,
+    Lao = 25,
+    /// Georgian and Georgian Supplement.
+    Georgian = 26,
+    Balinese = 27,
+    HangulJamo = 28,
+    /// Latin Extended Additional, Latin Extended-C, and Latin Extended-D.
+    LatinExtendedAdditional = 29,
+    GreekExtended = 30,
+    /// General Punctuation and Supplemental Punctuation.
+    GeneralPunctuation = 31,
+    SuperscriptsAndSubscripts = 32,
+    CurrencySymbols = 33,
+    CombiningDiacriticalMarksForSymbols = 34,
+    LetterlikeSymbols = 35,
+    NumberForms = 36,
+    /// Arrows, Supplemental Arrows-A, Supplemental Arrows-B, and Miscellaneous Symbols and
+    /// Arrows.
+    Arrows = 37,
+    /// Mathematical Operators, Supplemental Mathematical Operators, and Miscellaneous
+    /// Mathematical Symbols-A and -B.
+    MathematicalOperators = 38,
+    MiscellaneousTechnical = 39,
+    ControlPictures = 40,
+    OpticalCharacterRecognition = 41,
+    EnclosedAlphanumerics = 42,
+    BoxDrawing = 43,
+    BlockElements = 44,
+    GeometricShapes = 45,
+    MiscellaneousSymbols = 46,
+    Dingbats = 47,
+    CjkSymbolsAndPunctuation = 48,
+    Hiragana = 49,
+    /// Katakana and Katakana Phonetic Extensions.
+    Katakana = 50,
+    /// Bopomofo and Bopomofo Extended.
+    Bopomofo = 51,
+    HangulCompatibilityJamo = 52,
+    PhagsPa = 53,
+    EnclosedCjkLettersAndMonths = 54,
+    CjkCompatibility = 55,
+    HangulSyllables = 56,
+    /// Any character outside the Basic Multilingual Plane.
+    NonPlane0 = 57,
+    Phoenician = 58,
+    /// CJK Unified Ideographs, CJK Radicals Supplement, Kangxi Radicals, Ideographic
+    /// Description Characters, CJK Unified Ideographs Extensions A and B, and Kanbun.
+    CjkUnifiedIdeographs = 59,
+    /// The Private Use Area of the Basic Multilingual Plane.
+    PrivateUseArea = 60,
+    CjkStrokes = 61,
+    AlphabeticPresentationForms = 62,
+    ArabicPresentationFormsA = 63,
+    CombiningHalfMarks = 64,
+    /// Vertical Forms and CJK Compatibility Forms.
+    VerticalForms = 65,
+    SmallFormVariants = 66,
+    ArabicPresentationFormsB = 67,
+    HalfwidthAndFullwidthForms = 68,
+    Specials = 69,
+    Tibetan = 70,
+    Syriac = 71,
+    Thaana = 72,
+    Sinhala = 73,
+    Myanmar = 74,
+    /// Ethiopic, Ethiopic Supplement, and Ethiopic Extended.
+    Ethiopic = 75,
+    Cherokee = 76,
+    UnifiedCanadianAboriginalSyllabics = 77,
+    Ogham = 78,
+    Runic = 79,
+    /// Khmer and Khmer Symbols.
+    Khmer = 80,
+    Mongolian = 81,
+    BraillePatterns = 82,
+    /// Yi Syllables and Yi Radicals.
+    YiSyllables = 83,
+    /// Tagalog, Hanunoo, Buhid, and Tagbanwa.
+    Tagalog = 84,
+    OldItalic = 85,
+    Gothic = 86,
+    Deseret = 87,
+    /// Byzantine Musical Symbols, Musical Symbols, and Ancient Greek Musical Notation.
+    ByzantineMusicalSymbols = 88,
+    MathematicalAlphanumericSymbols = 89,
+    /// The Supplementary Private Use Areas A and B.
+    SupplementaryPrivateUseArea = 90,
+    /// Variation Selectors and Variation Selectors Supplement.
+    VariationSelectors = 91,
+    Tags = 92,
+    Limbu = 93,
+    TaiLe = 94,
+    NewTaiLue = 95,
+    Buginese = 96,
+    Glagolitic = 97,
+    Tifinagh = 98,
+    YijingHexagramSymbols = 99,
+    SylotiNagri = 100,
+    /// Linear B Syllabary, Linear B Ideograms, and Aegean Numbers.
+    LinearBSyllabary = 101,
+    AncientGreekNumbers = 102,
+    Ugaritic = 103,
+    OldPersian = 104,
+    Shavian = 105,
+    Osmanya = 106,
+    CypriotSyllabary = 107,
+    Kharoshthi = 108,
+    TaiXuanJingSymbols = 109,
+    /// Cuneiform and Cuneiform Numbers and Punctuation.
+    Cuneiform = 110,
+    CountingRodNumerals = 111,
+    Sundanese = 112,
+    Lepcha = 113,
+    OlChiki = 114,
+    Saurashtra = 115,
+    KayahLi = 116,
+    Rejang = 117,
+    Cham = 118,
+    AncientSymbols = 119,
+    PhaistosDisc = 120,
+    /// Carian, Lycian, and Lydian.
+    Carian = 121,
+    /// Domino Tiles and Mahjong Tiles.
+    DominoTiles = 122,
+}
 
@@ -0,0 +1,32 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+
+/// The PostScript table, of which only the header is read.
+#[derive(Clone, Debug)]
+pub struct PostTable {
+    pub italic_angle: f32,
+}
+
+impl PostTable {
+    pub fn new(table: FontTable) -> Result<PostTable, Error> {
+        // Every version has the same header, so skip the version.
+        let mut reader = table.bytes;
+        try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+
+        // The italic angle is a 16.16 fixed-point number of degrees.
+        let italic_angle = try!(reader.read_i32::<BigEndian>().map_err(Error::eof));
+        Ok(PostTable {
+            italic_angle: italic_angle as f32 / 65536.0,
+        })
+    }
+}
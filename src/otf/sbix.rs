@@ -0,0 +1,55 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+/// The standard bitmap graphics table, which stores color glyph images such as PNGs in strikes of
+/// a fixed size.
+#[derive(Clone, Copy, Debug)]
+pub struct SbixTable<'a> {
+    table: FontTable<'a>,
+    num_strikes: u32,
+}
+
+impl<'a> SbixTable<'a> {
+    pub fn new(table: FontTable) -> Result<SbixTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version != 1 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        // Skip over the flags.
+        try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        let num_strikes = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        Ok(SbixTable {
+            table: table,
+            num_strikes: num_strikes,
+        })
+    }
+
+    /// Returns the size of each strike in pixels per em, in the order they're stored.
+    pub fn strike_sizes(&self) -> Result<Vec<u16>, Error> {
+        let mut offsets = self.table.bytes;
+        try!(offsets.jump(8).map_err(Error::eof));
+
+        let mut sizes = vec![];
+        for _ in 0..self.num_strikes {
+            let offset = try!(offsets.read_u32::<BigEndian>().map_err(Error::eof));
+            let mut strike = self.table.bytes;
+            try!(strike.jump(offset as usize).map_err(Error::eof));
+            sizes.push(try!(strike.read_u16::<BigEndian>().map_err(Error::eof)))
+        }
+        Ok(sizes)
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use otf::{Error, FontTable};
+use util::Jump;
+
+// The size of an SVG document record.
+const DOCUMENT_RECORD_SIZE: usize = 12;
+
+// The first three bytes of every gzip stream.
+static GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+
+/// The SVG table, which describes color glyphs as SVG documents.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgTable<'a> {
+    table: FontTable<'a>,
+    document_list_offset: u32,
+    num_entries: u16,
+}
+
+impl<'a> SvgTable<'a> {
+    pub fn new(table: FontTable) -> Result<SvgTable, Error> {
+        let mut reader = table.bytes;
+        let version = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+        if version != 0 {
+            return Err(Error::UnsupportedVersion)
+        }
+
+        let document_list_offset = try!(reader.read_u32::<BigEndian>().map_err(Error::eof));
+        let mut reader = table.bytes;
+        try!(reader.jump(document_list_offset as usize).map_err(Error::eof));
+        let num_entries = try!(reader.read_u16::<BigEndian>().map_err(Error::eof));
+
+        Ok(SvgTable {
+            table: table,
+            document_list_offset: document_list_offset,
+            num_entries: num_entries,
+        })
+    }
+
+    /// Returns the SVG document that describes the given glyph, or `None` if there isn't one.
+    pub fn document(&self, glyph_id: u16) -> Result<Option<SvgDocument<'a>>, Error> {
+        // Binary search for the document record. These are sorted by glyph ID, and their glyph ID
+        // ranges don't overlap.
+        let (mut low, mut high) = (0, self.num_entries as usize);
+        while low < high {
+            let mid = (low + high) / 2;
+
+            let mut record = self.table.bytes;
+            try!(record.jump(self.document_list_offset as usize + 2 +
+                             mid * DOCUMENT_RECORD_SIZE).map_err(Error::eof));
+            let start_glyph_id = try!(record.read_u16::<BigEndian>().map_err(Error::eof));
+            let end_glyph_id = try!(record.read_u16::<BigEndian>().map_err(Error::eof));
+            if glyph_id < start_glyph_id {
+                high = mid
+            } else if glyph_id > end_glyph_id {
+                low = mid + 1
+            } else {
+                // The document offset is relative to the start of the document list.
+                let offset = try!(record.read_u32::<BigEndian>().map_err(Error::eof)) as usize;
+                let length = try!(record.read_u32::<BigEndian>().map_err(Error::eof)) as usize;
+                let start = self.document_list_offset as usize + offset;
+                let bytes = match self.table.bytes.get(start..start + length) {
+                    Some(bytes) => bytes,
+                    None => return Err(Error::UnexpectedEof),
+                };
+
+                return Ok(Some(SvgDocument {
+                    bytes: bytes,
+                    compressed: bytes.starts_with(&GZIP_MAGIC),
+                    start_glyph_id: start_glyph_id,
+                    end_glyph_id: end_glyph_id,
+                }))
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// An SVG document that describes one or more color glyphs, as returned by
+/// `Font::svg_document()`.
+///
+/// Within the document, the element describing each glyph has the ID `glyph` followed by the
+/// glyph ID, such as `glyph42`. Its coordinates are in font units with the y axis pointing down,
+/// and the origin at the glyph origin on the baseline.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SvgDocument<'a> {
+    /// The bytes of the document, compressed if `compressed` is true.
+    pub bytes: &'a [u8],
+    /// Whether the document is compressed with gzip.
+    ///
+    /// Pathfinder doesn't decompress documents, so pass compressed ones through a gzip decoder,
+    /// such as the one in the `flate2` crate, before handing them to an SVG renderer.
+    pub compressed: bool,
+    /// The first glyph ID that this document describes.
+    pub start_glyph_id: u16,
+    /// The last glyph ID that this document describes, inclusive.
+    pub end_glyph_id: u16,
+}
@@ -10,13 +10,53 @@
 
 //! Glyph vectors, uploaded in a resolution-independent manner to the GPU.
 
-use error::GlError;
-use euclid::Size2D;
-use gl::types::{GLsizeiptr, GLuint};
+#[cfg(feature = "gpu")]
+use atlas::Atlas;
+#[cfg(feature = "gpu")]
+use coverage::CoverageBuffer;
+#[cfg(feature = "gpu")]
+use error::{GlError, RasterError};
+use error::OutlineError;
+use euclid::{Matrix2D, Point2D, Rect, Size2D};
+#[cfg(feature = "gpu")]
+use gl::types::{GLintptr, GLsizeiptr, GLsync, GLuint};
+#[cfg(feature = "gpu")]
 use gl;
 use otf::{self, Font};
+#[cfg(feature = "hinting")]
+use otf::Hinter;
+use overlap;
+#[cfg(feature = "gpu")]
+use rasterizer::{DrawAtlasOptions, Rasterizer};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use shaper::ShapedGlyph;
+use simplify::{self, ContourPoint};
+#[cfg(feature = "gpu")]
+use std::fmt;
+use std::cmp;
+use std::collections::HashMap;
+use std::i16;
 use std::mem;
+use std::ops::Range;
+#[cfg(feature = "gpu")]
 use std::os::raw::c_void;
+#[cfg(feature = "gpu")]
+use std::ptr;
+#[cfg(feature = "metrics")]
+use time;
+use tofu;
+use winding;
+
+/// The index of the placeholder vertex at the start of the vertex buffer, which serves as the
+/// middle vertex of every line segment.
+///
+/// Each segment of an outline is drawn as a patch of three vertices. The built-in draw shaders
+/// rely on this invariant: a patch whose middle vertex is the placeholder, as `gl_VertexID`
+/// reports it, is a straight line from its first vertex to its last, and any other patch is a
+/// quadratic curve whose middle vertex is the control point. The placeholder's position is never
+/// used. To build outlines without it, see `OutlineOptions::dummy_vertex`.
+pub const DUMMY_VERTEX_INDEX: u32 = 0;
 
 static DUMMY_VERTEX: Vertex = Vertex {
     x: 0,
@@ -24,11 +64,52 @@ static DUMMY_VERTEX: Vertex = Vertex {
     glyph_index: 0,
 };
 
+/// Options that control how an `OutlineBuilder` lays out vertices and indices.
+#[derive(Clone, Copy, Debug)]
+pub struct OutlineOptions {
+    /// Whether the vertex buffer begins with the placeholder vertex at `DUMMY_VERTEX_INDEX`.
+    ///
+    /// The built-in draw shaders need the placeholder, so the default is true. Without it, the
+    /// first glyph's points start at index 0, and the middle vertex of each line segment is the
+    /// first point of its contour instead, so the line segments of each contour form a triangle
+    /// fan around that point. Either way, a segment is a quadratic curve if its middle index is
+    /// one more than its first, and a line otherwise.
+    ///
+    /// Turn this off for shaders of your own that number contours differently. Outlines built
+    /// without the placeholder can't be drawn by the `Rasterizer`, which returns
+    /// `RasterError::MissingDummyVertex` for them.
+    pub dummy_vertex: bool,
+}
+
+impl Default for OutlineOptions {
+    fn default() -> OutlineOptions {
+        OutlineOptions {
+            dummy_vertex: true,
+        }
+    }
+}
+
 /// Packs up outlines for glyphs into a format that the GPU can process.
+///
+/// Glyphs are accumulated on the CPU, and only `create_buffers()` touches OpenGL, so a builder
+/// can be filled and inspected without an OpenGL context. `create_buffers()` is only available
+/// with the `gpu` feature.
+///
+/// The output is deterministic: vertices are stored in the order the font lists the glyph's
+/// points, and each segment's triangle is emitted when its end point is reached, so adding the
+/// same glyphs from the same font in the same order always produces byte-for-byte identical
+/// vertices, indices, and descriptors. Nothing in the builder depends on hash map iteration order
+/// or on caches, so the output is safe to cache and compare across runs.
 pub struct OutlineBuilder {
     vertices: Vec<Vertex>,
     indices: Vec<u32>,
     descriptors: Vec<GlyphDescriptor>,
+    transform: Option<Matrix2D<f32>>,
+    remove_overlaps: bool,
+    normalize_winding: bool,
+    dummy_vertex: bool,
+    #[cfg(feature = "metrics")]
+    stats: Stats,
 }
 
 impl OutlineBuilder {
@@ -39,12 +120,429 @@ impl OutlineBuilder {
             vertices: vec![DUMMY_VERTEX],
             indices: vec![],
             descriptors: vec![],
+            transform: None,
+            remove_overlaps: false,
+            normalize_winding: false,
+            dummy_vertex: true,
+            #[cfg(feature = "metrics")]
+            stats: Stats::default(),
+        }
+    }
+
+    /// Creates a new empty set of outlines laid out as `options` specifies.
+    ///
+    /// `OutlineBuilder::new()` is the same as this with the default options.
+    #[inline]
+    pub fn with_options(options: OutlineOptions) -> OutlineBuilder {
+        OutlineBuilder {
+            vertices: if options.dummy_vertex { vec![DUMMY_VERTEX] } else { vec![] },
+            dummy_vertex: options.dummy_vertex,
+            ..OutlineBuilder::new()
+        }
+    }
+
+    /// Returns the options that this builder lays out vertices and indices with.
+    #[inline]
+    pub fn options(&self) -> OutlineOptions {
+        OutlineOptions {
+            dummy_vertex: self.dummy_vertex,
+        }
+    }
+
+    /// Creates a new empty set of outlines that applies `transform` to the points of every glyph
+    /// added to it.
+    ///
+    /// The transform maps font units to font units, and transformed points are rounded to the
+    /// nearest unit. The bounds in each glyph's descriptor are the bounds of the transformed
+    /// glyph, so atlases are packed correctly. A transform that mirrors the glyph (one with a
+    /// negative determinant) also reverses the direction of each contour, so that the glyph keeps
+    /// the winding that the rasterizer expects.
+    ///
+    /// This is useful for transforms, such as a flip or a fixed scale, that should be baked into
+    /// the outlines rather than applied when drawing. To apply several transforms, compose them
+    /// into one matrix first with `Matrix2D::pre_mul()` or `Matrix2D::post_mul()`.
+    #[inline]
+    pub fn with_transform(transform: &Matrix2D<f32>) -> OutlineBuilder {
+        OutlineBuilder {
+            transform: Some(*transform),
+            ..OutlineBuilder::new()
         }
     }
 
+    /// Returns the transform applied to glyphs added to this builder, or the identity if there is
+    /// none.
+    #[inline]
+    pub fn transform(&self) -> Matrix2D<f32> {
+        self.transform.unwrap_or(Matrix2D::identity())
+    }
+
+    /// Enables or disables merging the overlapping contours of glyphs added with `add_glyph()`.
+    ///
+    /// Removal is disabled by default. When it's enabled, each glyph whose contours overlap, as
+    /// `Font::has_overlapping_contours()` determines, is replaced by the union of its contours,
+    /// which covers the same area without overlaps, so the overlaps can't double-count coverage
+    /// or show seams. The union is built from line segments within a quarter of a font unit of
+    /// the curves, so merged glyphs lose their curves and have many more vertices; glyphs without
+    /// overlaps are added unchanged. Finding the overlaps takes time quadratic in the number of
+    /// segments, so this is best left off for fonts known to be clean.
+    pub fn set_overlap_removal_enabled(&mut self, enabled: bool) {
+        self.remove_overlaps = enabled
+    }
+
+    /// Enables or disables normalizing the direction of the contours of glyphs added with
+    /// `add_glyph()`.
+    ///
+    /// Normalization is disabled by default. When it's enabled, contours are reversed where needed
+    /// so that outer contours wind clockwise and holes counterclockwise in font units, which are
+    /// y-up, as the TrueType specification requires; on a y-down surface, outer contours appear
+    /// counterclockwise. A contour is a hole if it lies entirely within an odd number of the
+    /// glyph's other contours; contours that cross another are left as they are, since there's no
+    /// telling which way they were meant to wind. This fixes fonts whose contours wind
+    /// inconsistently, such as some converted from other formats, at the cost of comparing every
+    /// pair of contours in each glyph. Use `add_glyph_counting_reversals()` to find out which
+    /// glyphs needed fixing.
+    pub fn set_winding_normalization_enabled(&mut self, enabled: bool) {
+        self.normalize_winding = enabled
+    }
+
     /// Adds a new glyph to the outline builder. Returns the glyph index, which is useful for later
     /// calls to `Atlas::pack_glyph()`.
+    ///
+    /// The `.notdef` glyph (`Font::notdef_glyph()`) is added like any other, so if the font draws
+    /// it as a box, the box is what gets rendered. If overlap removal is enabled with
+    /// `set_overlap_removal_enabled()`, overlapping contours are merged first, and if winding
+    /// normalization is enabled with `set_winding_normalization_enabled()`, contours that wind the
+    /// wrong way are then reversed.
+    #[inline]
     pub fn add_glyph(&mut self, font: &Font, glyph_id: u16) -> Result<u16, otf::Error> {
+        self.add_glyph_counting_reversals(font, glyph_id).map(|(glyph_index, _)| glyph_index)
+    }
+
+    /// Adds a new glyph to the outline builder, as `add_glyph()` does, and returns its glyph index
+    /// along with the number of contours that winding normalization reversed.
+    ///
+    /// The count is always 0 unless normalization is enabled with
+    /// `set_winding_normalization_enabled()`. A nonzero count flags a glyph whose contours the
+    /// font winds inconsistently, which is worth noting in a font validation report.
+    pub fn add_glyph_counting_reversals(&mut self, font: &Font, glyph_id: u16)
+                                        -> Result<(u16, u32), otf::Error> {
+        let mut contours: Option<Vec<Vec<ContourPoint>>> = None;
+        if self.remove_overlaps {
+            let flattened_contours = try!(font.flattened_contours(glyph_id, 0.25));
+            contours = overlap::remove_overlaps(&flattened_contours).map(|merged_contours| {
+                merged_contours.iter().map(|contour| {
+                    contour.iter().map(|&position| {
+                        ContourPoint {
+                            position: position,
+                            on_curve: true,
+                        }
+                    }).collect()
+                }).collect()
+            })
+        }
+
+        if self.normalize_winding && contours.is_none() {
+            contours = Some(try!(glyph_contours(font, glyph_id)))
+        }
+
+        let mut contours = match contours {
+            Some(contours) => contours,
+            None => {
+                let glyph_index = try!(self.add_glyph_points(font, glyph_id, |callback| {
+                    font.for_each_point(glyph_id, |point| {
+                        callback(&point.position, point.on_curve, point.index_in_contour)
+                    })
+                }));
+                return Ok((glyph_index, 0))
+            }
+        };
+
+        let reversed_count = if self.normalize_winding {
+            winding::normalize(&mut contours)
+        } else {
+            0
+        };
+
+        let glyph_index = try!(self.add_glyph_points(font, glyph_id, |callback| {
+            for contour in &contours {
+                for (index_in_contour, point) in contour.iter().enumerate() {
+                    callback(&point.position, point.on_curve, index_in_contour as u16)
+                }
+            }
+            Ok(())
+        }));
+        Ok((glyph_index, reversed_count))
+    }
+
+    /// Adds the glyphs of a run positioned by an external shaper, such as HarfBuzz, and returns
+    /// where each one is placed.
+    ///
+    /// Each distinct glyph ID is added once, as `add_glyph()` adds it, no matter how often it
+    /// appears in the run; the placements of repeated glyphs share a glyph index. The placements
+    /// are in the same order as `glyphs`. The run starts with the pen at the origin, each glyph is
+    /// placed at the pen position moved by its offsets, and the pen then moves right by the
+    /// glyph's advance. Placements are in font units and aren't affected by this builder's
+    /// transform.
+    ///
+    /// If a glyph fails to load, its error is returned, and the glyphs before it stay added.
+    pub fn add_shaped_run(&mut self, font: &Font, glyphs: &[ShapedGlyph])
+                          -> Result<Vec<PlacedGlyph>, otf::Error> {
+        let mut glyph_indices: HashMap<u16, u16> = HashMap::new();
+        let mut placed_glyphs = Vec::with_capacity(glyphs.len());
+        let mut pen_x = 0.0;
+        for glyph in glyphs {
+            let glyph_index = match glyph_indices.get(&glyph.glyph_id) {
+                Some(&glyph_index) => glyph_index,
+                None => {
+                    let glyph_index = try!(self.add_glyph(font, glyph.glyph_id));
+                    glyph_indices.insert(glyph.glyph_id, glyph_index);
+                    glyph_index
+                }
+            };
+
+            placed_glyphs.push(PlacedGlyph {
+                glyph_index: glyph_index,
+                origin: Point2D::new(pen_x + glyph.x_offset, glyph.y_offset),
+            });
+            pen_x += glyph.x_advance
+        }
+        Ok(placed_glyphs)
+    }
+
+    /// Adds a new glyph to the outline builder after grid-fitting it with the given hinter, as
+    /// `Font::for_each_hinted_point()` does. Returns the glyph index, as `add_glyph()` does.
+    ///
+    /// The glyph's bounds are widened to whole pixels at the hinter's size, so that the pixels of
+    /// the atlas line up with the grid that the outline was fitted to. Draw the glyph at
+    /// `hinter.pixels_per_em()` pixels per em; at other sizes, or under a transform given to
+    /// `with_transform()`, the fitted edges fall between pixels again.
+    ///
+    /// This is only available with the `hinting` feature.
+    #[cfg(feature = "hinting")]
+    pub fn add_hinted_glyph(&mut self, font: &Font, hinter: &Hinter, glyph_id: u16)
+                            -> Result<u16, otf::Error> {
+        let mut points = vec![];
+        try!(font.for_each_hinted_point(hinter, glyph_id, |point| {
+            points.push((point.position, point.on_curve, point.index_in_contour))
+        }));
+
+        let units_per_em = font.units_per_em();
+        let bounds = pixel_aligned_bounds(points.iter().map(|&(position, _, _)| position),
+                                          units_per_em,
+                                          hinter.pixels_per_em());
+        self.add_points(units_per_em, glyph_id, bounds, |callback| {
+            for &(position, on_curve, index_in_contour) in &points {
+                callback(&position, on_curve, index_in_contour)
+            }
+            Ok(())
+        })
+    }
+
+    /// Adds the given glyphs to the outline builder, reading and tessellating them in parallel on
+    /// Rayon's thread pool. Returns their glyph indices.
+    ///
+    /// The result is exactly what calling `add_glyph()` for each glyph in turn would produce: the
+    /// glyph indices, vertices, indices, and descriptors are identical. Each glyph is built into
+    /// a scratch builder of its own, and the scratch builders are then merged in order. If any
+    /// glyph fails to load, no glyphs are added, and the error for the earliest such glyph is
+    /// returned.
+    ///
+    /// This is only available with the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn par_add_glyphs(&mut self, font: &Font, glyph_ids: &[u16])
+                          -> Result<Vec<u16>, otf::Error> {
+        let (transform, remove_overlaps) = (self.transform, self.remove_overlaps);
+        let (normalize_winding, options) = (self.normalize_winding, self.options());
+        let builders: Vec<Result<OutlineBuilder, otf::Error>> =
+            glyph_ids.par_iter().map(|&glyph_id| {
+                let mut builder = OutlineBuilder {
+                    transform: transform,
+                    remove_overlaps: remove_overlaps,
+                    normalize_winding: normalize_winding,
+                    ..OutlineBuilder::with_options(options)
+                };
+                try!(builder.add_glyph(font, glyph_id));
+                Ok(builder)
+            }).collect();
+        let builders: Vec<OutlineBuilder> = try!(builders.into_iter().collect());
+
+        let mut glyph_indices = Vec::with_capacity(glyph_ids.len());
+        for builder in builders {
+            glyph_indices.extend(self.append(builder))
+        }
+        Ok(glyph_indices)
+    }
+
+    /// Adds a new glyph to the outline builder after removing points that move its outline by no
+    /// more than `tolerance` font units.
+    ///
+    /// Nearly colinear points along straight edges are dropped, and curves that are flat to
+    /// within the tolerance become straight lines. The endpoints of the remaining curves are
+    /// always kept, so curves are never distorted. This reduces the size of the vertex and index
+    /// buffers for fonts with needlessly dense contours.
+    ///
+    /// Returns the glyph index, as `add_glyph()` does, along with the number of points before and
+    /// after simplification, which is useful for tuning the tolerance.
+    pub fn add_simplified_glyph(&mut self, font: &Font, glyph_id: u16, tolerance: f32)
+                                -> Result<(u16, SimplificationStats), otf::Error> {
+        let contours = try!(glyph_contours(font, glyph_id));
+        let original_point_count = contours.iter().map(|contour| contour.len()).sum();
+        let contours: Vec<_> = contours.iter().map(|contour| {
+            simplify::simplify_contour(contour, tolerance)
+        }).collect();
+        let point_count = contours.iter().map(|contour| contour.len()).sum();
+
+        let glyph_index = try!(self.add_glyph_points(font, glyph_id, |callback| {
+            for contour in &contours {
+                for (index_in_contour, point) in contour.iter().enumerate() {
+                    callback(&point.position, point.on_curve, index_in_contour as u16)
+                }
+            }
+            Ok(())
+        }));
+
+        Ok((glyph_index, SimplificationStats {
+            original_point_count: original_point_count,
+            point_count: point_count,
+        }))
+    }
+
+    /// Adds a placeholder "tofu" box that doesn't come from any font, for characters that no
+    /// available font can draw. Returns its glyph index, as `add_glyph()` does.
+    ///
+    /// The box is a hollow square drawn on an em of `em_size` units, which becomes the glyph's
+    /// units per em; pass the units per em of the surrounding text's font to match its scale. The
+    /// box sits on the baseline, is 0.7 em tall, and leaves 0.1 em on either side within an
+    /// advance of 0.8 em. The glyph's ID, as `Outlines::glyph_id()` reports it, is 0, which is
+    /// `.notdef` in every font. This builder's transform applies to the box as to any glyph.
+    ///
+    /// To show which character is missing, use `add_tofu_box_for_char()` instead.
+    pub fn add_tofu_box(&mut self, em_size: u16) -> u16 {
+        self.add_tofu_contours(em_size, None)
+    }
+
+    /// Adds a placeholder "tofu" box, as `add_tofu_box()` does, with the code point of the given
+    /// character written inside it in hexadecimal digits. Returns its glyph index.
+    ///
+    /// Characters in the Basic Multilingual Plane get four digits and all others get six, on two
+    /// rows of simple seven-segment digits. The digits need an em of a few hundred units or more
+    /// to be legible.
+    pub fn add_tofu_box_for_char(&mut self, em_size: u16, character: char) -> u16 {
+        self.add_tofu_contours(em_size, Some(character))
+    }
+
+    fn add_tofu_contours(&mut self, em_size: u16, character: Option<char>) -> u16 {
+        // Keep to the units per em that fonts may declare, so that the box fits in 16 bits.
+        let em_size = cmp::max(cmp::min(em_size, tofu::MAX_EM_SIZE), 1);
+        let contours = tofu::contours(em_size, character);
+
+        // The points come from memory, so adding them can't fail.
+        self.add_points(em_size, 0, tofu::bounds(em_size), |callback| {
+            for contour in &contours {
+                for (index_in_contour, position) in contour.iter().enumerate() {
+                    callback(position, true, index_in_contour as u16)
+                }
+            }
+            Ok(())
+        }).unwrap()
+    }
+
+    // Adds the points that `for_each_point` supplies, given as position, whether the point is on
+    // the curve, and index in its contour, as a new glyph, applying this builder's transform.
+    fn add_glyph_points<F>(&mut self, font: &Font, glyph_id: u16, for_each_point: F)
+                           -> Result<u16, otf::Error>
+                           where F: FnOnce(&mut FnMut(&Point2D<i16>, bool, u16))
+                                           -> Result<(), otf::Error> {
+        let bounds = try!(font.glyph_bounds(glyph_id));
+        self.add_points(font.units_per_em(), glyph_id, bounds, for_each_point)
+    }
+
+    // Does the work of `add_glyph_points()` once the glyph's bounds are known.
+    fn add_points<F>(&mut self,
+                     units_per_em: u16,
+                     glyph_id: u16,
+                     bounds: GlyphBounds,
+                     for_each_point: F)
+                     -> Result<u16, otf::Error>
+                     where F: FnOnce(&mut FnMut(&Point2D<i16>, bool, u16))
+                                     -> Result<(), otf::Error> {
+        #[cfg(feature = "metrics")]
+        let start_time = time::precise_time_ns();
+
+        let glyph_index = try!(self.transform_glyph_points(units_per_em,
+                                                           glyph_id,
+                                                           bounds,
+                                                           for_each_point));
+
+        #[cfg(feature = "metrics")]
+        self.record_tessellation(glyph_index, start_time);
+        Ok(glyph_index)
+    }
+
+    // Records the tessellation of the last glyph added, which started at `start_time`.
+    #[cfg(feature = "metrics")]
+    fn record_tessellation(&mut self, glyph_index: u16, start_time: u64) {
+        let descriptor = &self.descriptors[glyph_index as usize];
+        self.stats.glyphs_tessellated += 1;
+        self.stats.vertices_emitted += self.vertices.len() as u64 - descriptor.start_point as u64;
+        self.stats.indices_emitted += self.indices.len() as u64 - descriptor.start_index as u64;
+        self.stats.tessellation_time_ns += time::precise_time_ns() - start_time
+    }
+
+    // Does the work of `add_points()`.
+    fn transform_glyph_points<F>(&mut self,
+                                 units_per_em: u16,
+                                 glyph_id: u16,
+                                 bounds: GlyphBounds,
+                                 for_each_point: F)
+                                 -> Result<u16, otf::Error>
+                                 where F: FnOnce(&mut FnMut(&Point2D<i16>, bool, u16))
+                                                 -> Result<(), otf::Error> {
+        let transform = match self.transform {
+            None => {
+                return self.push_glyph_points(units_per_em, glyph_id, bounds, for_each_point)
+            }
+            Some(transform) => transform,
+        };
+
+        let mut contours: Vec<Vec<(Point2D<i16>, bool)>> = vec![];
+        try!(for_each_point(&mut |position, on_curve, index_in_contour| {
+            if index_in_contour == 0 {
+                contours.push(vec![])
+            }
+            if let Some(contour) = contours.last_mut() {
+                contour.push((transform_position(&transform, position), on_curve))
+            }
+        }));
+
+        // Each contour begins and ends on the curve, so reversing it yields a valid contour.
+        if transform.determinant() < 0.0 {
+            for contour in &mut contours {
+                contour.reverse()
+            }
+        }
+
+        let bounds = transform_bounds(&transform, &bounds);
+        self.push_glyph_points(units_per_em, glyph_id, bounds, |callback| {
+            for contour in &contours {
+                for (index_in_contour, &(position, on_curve)) in contour.iter().enumerate() {
+                    callback(&position, on_curve, index_in_contour as u16)
+                }
+            }
+            Ok(())
+        })
+    }
+
+    // Adds the points that `for_each_point` supplies as a new glyph with the given bounds,
+    // without transforming them.
+    fn push_glyph_points<F>(&mut self,
+                            units_per_em: u16,
+                            glyph_id: u16,
+                            bounds: GlyphBounds,
+                            for_each_point: F)
+                            -> Result<u16, otf::Error>
+                            where F: FnOnce(&mut FnMut(&Point2D<i16>, bool, u16))
+                                            -> Result<(), otf::Error> {
         let glyph_index = self.descriptors.len() as u16;
 
         let mut point_index = self.vertices.len() as u32;
@@ -52,30 +550,39 @@ impl OutlineBuilder {
         let start_point = point_index;
         let mut last_point_on_curve = true;
 
-        try!(font.for_each_point(glyph_id, |point| {
+        // Line segments pivot on the placeholder vertex if there is one, and otherwise on the
+        // first point of their contour.
+        let dummy_vertex = self.dummy_vertex;
+        let mut contour_start = point_index;
+
+        try!(for_each_point(&mut |position, on_curve, index_in_contour| {
             self.vertices.push(Vertex {
-                x: point.position.x,
-                y: point.position.y,
+                x: position.x,
+                y: position.y,
                 glyph_index: glyph_index,
             });
 
-            if point.index_in_contour > 0 && point.on_curve {
+            if index_in_contour == 0 {
+                contour_start = point_index
+            } else if on_curve {
                 let indices = if !last_point_on_curve {
                     [point_index - 2, point_index - 1, point_index]
+                } else if dummy_vertex {
+                    [point_index - 1, DUMMY_VERTEX_INDEX, point_index]
                 } else {
-                    [point_index - 1, 0, point_index]
+                    [point_index - 1, contour_start, point_index]
                 };
                 self.indices.extend(indices.iter().cloned());
             }
 
             point_index += 1;
-            last_point_on_curve = point.on_curve
+            last_point_on_curve = on_curve
         }));
 
         // Add a glyph descriptor.
         self.descriptors.push(GlyphDescriptor {
-            bounds: try!(font.glyph_bounds(glyph_id)),
-            units_per_em: font.units_per_em() as u32,
+            bounds: bounds,
+            units_per_em: units_per_em as u32,
             start_point: start_point as u32,
             start_index: start_index,
             glyph_id: glyph_id,
@@ -84,66 +591,1013 @@ impl OutlineBuilder {
         Ok(glyph_index)
     }
 
+    /// Appends all glyphs from another outline builder to this one, as though they had been added
+    /// to this builder directly.
+    ///
+    /// This allows outlines to be built on several threads and then uploaded together. Returns a
+    /// table mapping each glyph index in `other` to the glyph's new index in this builder.
+    ///
+    /// The glyphs in `other` keep the transform of the builder they were added to; this builder's
+    /// transform isn't applied to them. With the `metrics` feature, `other`'s statistics are added
+    /// to this builder's.
+    ///
+    /// Fails with `OutlineError::DummyVertexMismatch`, adding nothing, if the builders were
+    /// created with different `OutlineOptions::dummy_vertex` settings.
+    pub fn merge(&mut self, other: OutlineBuilder) -> Result<Vec<u16>, OutlineError> {
+        if self.dummy_vertex != other.dummy_vertex {
+            return Err(OutlineError::DummyVertexMismatch)
+        }
+        Ok(self.append(other))
+    }
+
+    // Appends the glyphs of a builder with the same layout, as `merge()` does.
+    fn append(&mut self, other: OutlineBuilder) -> Vec<u16> {
+        #[cfg(feature = "metrics")]
+        self.stats.add(&other.stats);
+
+        let glyph_index_offset = self.descriptors.len() as u16;
+        let start_index_offset = self.indices.len() as u32;
+
+        // If both builders begin with the placeholder vertex, `other`'s copy is dropped and the
+        // indices that refer to it are left alone.
+        let dummy_vertex = self.dummy_vertex;
+        let skipped_points = if dummy_vertex { 1 } else { 0 };
+        let point_offset = self.vertices.len() as u32 - skipped_points;
+
+        self.vertices.extend(other.vertices[skipped_points as usize..].iter().map(|vertex| {
+            Vertex {
+                x: vertex.x,
+                y: vertex.y,
+                glyph_index: vertex.glyph_index + glyph_index_offset,
+            }
+        }));
+        self.indices.extend(other.indices.iter().map(|&index| {
+            if dummy_vertex && index == DUMMY_VERTEX_INDEX {
+                DUMMY_VERTEX_INDEX
+            } else {
+                index + point_offset
+            }
+        }));
+        self.descriptors.extend(other.descriptors.iter().map(|descriptor| {
+            GlyphDescriptor {
+                start_point: descriptor.start_point + point_offset,
+                start_index: descriptor.start_index + start_index_offset,
+                ..*descriptor
+            }
+        }));
+
+        (0..other.descriptors.len() as u16).map(|glyph_index| {
+            glyph_index + glyph_index_offset
+        }).collect()
+    }
+
+    /// Returns a copy of the tessellated outline of the glyph with the given index, which can be
+    /// added to this or another builder later with `add_cached_glyph()` without reading the font
+    /// again. Returns `None` if there is no such glyph.
+    pub fn cached_glyph(&self, glyph_index: u16) -> Option<CachedGlyph> {
+        let descriptor = match self.descriptors.get(glyph_index as usize) {
+            None => return None,
+            Some(descriptor) => *descriptor,
+        };
+        let (end_point, end_index) = match self.descriptors.get(glyph_index as usize + 1) {
+            None => (self.vertices.len() as u32, self.indices.len() as u32),
+            Some(next) => (next.start_point, next.start_index),
+        };
+
+        let vertices = &self.vertices[descriptor.start_point as usize..end_point as usize];
+        let indices = &self.indices[descriptor.start_index as usize..end_index as usize];
+        Some(CachedGlyph {
+            vertices: vertices.iter().map(|vertex| {
+                Vertex {
+                    glyph_index: 0,
+                    ..*vertex
+                }
+            }).collect(),
+            indices: indices.iter().map(|&index| {
+                if self.dummy_vertex && index == DUMMY_VERTEX_INDEX {
+                    0
+                } else {
+                    index - descriptor.start_point + 1
+                }
+            }).collect(),
+            descriptor: GlyphDescriptor {
+                start_point: 1,
+                start_index: 0,
+                ..descriptor
+            },
+            dummy_vertex: self.dummy_vertex,
+        })
+    }
+
+    /// Appends a glyph tessellated earlier, as returned by `cached_glyph()`. Returns the new glyph
+    /// index, as `add_glyph()` does.
+    ///
+    /// Cached and freshly added glyphs can be mixed freely in one builder. The cached glyph keeps
+    /// the transform of the builder it was first added to; this builder's transform isn't applied
+    /// to it.
+    ///
+    /// Fails with `OutlineError::DummyVertexMismatch`, adding nothing, if the glyph was cached
+    /// from a builder with a different `OutlineOptions::dummy_vertex` setting.
+    pub fn add_cached_glyph(&mut self, cached: &CachedGlyph) -> Result<u16, OutlineError> {
+        if self.dummy_vertex != cached.dummy_vertex {
+            return Err(OutlineError::DummyVertexMismatch)
+        }
+
+        let glyph_index = self.descriptors.len() as u16;
+        let start_point = self.vertices.len() as u32;
+        let start_index = self.indices.len() as u32;
+
+        // The cached indices number the glyph's first point 1, as though it followed only the
+        // placeholder vertex, which keeps index 0 here too. Without the placeholder, 0 never
+        // appears, and the glyph's first point may go at index 0 of an empty builder.
+        self.vertices.extend(cached.vertices.iter().map(|vertex| {
+            Vertex {
+                glyph_index: glyph_index,
+                ..*vertex
+            }
+        }));
+        let dummy_vertex = self.dummy_vertex;
+        self.indices.extend(cached.indices.iter().map(|&index| {
+            if dummy_vertex && index == 0 {
+                DUMMY_VERTEX_INDEX
+            } else {
+                start_point + (index - 1)
+            }
+        }));
+        self.descriptors.push(GlyphDescriptor {
+            start_point: start_point,
+            start_index: start_index,
+            ..cached.descriptor
+        });
+
+        Ok(glyph_index)
+    }
+
+    /// Returns the points of all glyphs added so far, in the order they'll be uploaded.
+    ///
+    /// Unless `OutlineOptions::dummy_vertex` was turned off, the first vertex is a placeholder at
+    /// the origin, which serves as the middle vertex of the triangle emitted for each line
+    /// segment. See `DUMMY_VERTEX_INDEX`.
+    #[inline]
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// Returns the vertex indices of all glyphs added so far, three per segment.
+    ///
+    /// A quadratic curve is emitted as its start point, control point, and end point. A line
+    /// segment is emitted as its start point, the placeholder vertex 0, and its end point, or, if
+    /// `OutlineOptions::dummy_vertex` was turned off, the first point of its contour in place of
+    /// the placeholder.
+    #[inline]
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Returns the descriptors of all glyphs added so far, indexed by glyph index.
+    #[inline]
+    pub fn descriptors(&self) -> &[GlyphDescriptor] {
+        &self.descriptors
+    }
+
+    /// Returns the statistics collected while adding glyphs to this builder.
+    ///
+    /// This is only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
     /// Uploads the outlines to the GPU.
+    ///
+    /// This is the only method of `OutlineBuilder` that requires an OpenGL context.
+    ///
+    /// The upload is followed by a fence and a `glFlush()`, but not by a `glFinish()`, so this
+    /// returns without waiting for the data to reach the GPU. See `Outlines::fence()`.
+    ///
+    /// The glyph descriptors go in a uniform buffer. To use a shader storage buffer, use
+    /// `create_buffers_with_storage()`.
+    #[cfg(feature = "gpu")]
+    #[inline]
     pub fn create_buffers(self) -> Result<Outlines, GlError> {
-        // TODO(pcwalton): Try using `glMapBuffer` here. Requires precomputing contour types and
-        // counts.
+        self.create_buffers_with_storage(StorageKind::Ubo)
+    }
+
+    /// Uploads the outlines to the GPU, putting the glyph descriptors in the given kind of
+    /// buffer.
+    ///
+    /// Uniform buffers are limited to `GL_MAX_UNIFORM_BLOCK_SIZE` bytes, often 64 KB, and the draw
+    /// shader reads at most 2048 descriptors from one. Shader storage buffers lift both limits,
+    /// so choose `StorageKind::Ssbo` for outlines with more glyphs. Atlases still keep their image
+    /// descriptors in a uniform buffer, so only glyphs with indices below 2048 can be drawn with
+    /// `Rasterizer::draw_atlas()`; draw the rest with `Rasterizer::draw_instances()`, which
+    /// passes the image descriptors as vertex attributes. Storage buffers need OpenGL 4.3 or the
+    /// `ARB_shader_storage_buffer_object` extension; check
+    /// `Rasterizer::supports_storage_buffers()` first, since drawing such outlines otherwise fails
+    /// with `RasterError::StorageBuffersUnsupported`. Otherwise, this is the same as
+    /// `create_buffers()`.
+    #[cfg(feature = "gpu")]
+    #[inline]
+    pub fn create_buffers_with_storage(self, storage: StorageKind) -> Result<Outlines, GlError> {
+        self.into_data().create_buffers(storage)
+    }
+
+    /// Finishes the outlines without uploading them, returning the vertices, indices, and
+    /// descriptors as an `OutlineData` that can be uploaded any number of times.
+    ///
+    /// This doesn't require an OpenGL context.
+    #[inline]
+    pub fn into_data(self) -> OutlineData {
+        OutlineData {
+            vertices: self.vertices,
+            indices: self.indices,
+            descriptors: self.descriptors,
+            dummy_vertex: self.dummy_vertex,
+            #[cfg(feature = "metrics")]
+            stats: self.stats,
+        }
+    }
+}
+
+/// Finished outlines held on the CPU, ready to be uploaded to the GPU once or many times.
+///
+/// Glyph descriptors are resolution-independent, so the same data can back `Outlines` in several
+/// OpenGL contexts that don't share objects, such as those of two windows, without the glyphs
+/// being read and tessellated again for each. Build the data once with
+/// `OutlineBuilder::into_data()`, then call `upload()` with each context current in turn. The
+/// data is `Send` and `Sync`, so it can be built on one thread and shared with the others, for
+/// example in an `Arc`.
+///
+/// Each upload copies the data into the `Outlines` it returns, which keep it for `recreate()`.
+#[derive(Clone, Debug)]
+pub struct OutlineData {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    descriptors: Vec<GlyphDescriptor>,
+    dummy_vertex: bool,
+    #[cfg(feature = "metrics")]
+    stats: Stats,
+}
+
+impl OutlineData {
+    /// Uploads the outlines to new OpenGL buffers in the current context, as
+    /// `OutlineBuilder::create_buffers()` does, leaving this data intact for further uploads.
+    ///
+    /// This is only available with the `gpu` feature.
+    #[cfg(feature = "gpu")]
+    #[inline]
+    pub fn upload(&self) -> Result<Outlines, GlError> {
+        self.upload_with_storage(StorageKind::Ubo)
+    }
+
+    /// Uploads the outlines to new OpenGL buffers in the current context, putting the glyph
+    /// descriptors in the given kind of buffer, as `OutlineBuilder::create_buffers_with_storage()`
+    /// does.
+    ///
+    /// This is only available with the `gpu` feature.
+    #[cfg(feature = "gpu")]
+    #[inline]
+    pub fn upload_with_storage(&self, storage: StorageKind) -> Result<Outlines, GlError> {
+        self.clone().create_buffers(storage)
+    }
+
+    /// Returns the points of all glyphs, as `OutlineBuilder::vertices()` does.
+    #[inline]
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// Returns the vertex indices of all glyphs, as `OutlineBuilder::indices()` does.
+    #[inline]
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Returns the descriptors of all glyphs, indexed by glyph index.
+    #[inline]
+    pub fn descriptors(&self) -> &[GlyphDescriptor] {
+        &self.descriptors
+    }
+
+    /// Returns the statistics collected while building the outlines. Every `Outlines` uploaded
+    /// from this data starts with these and adds the time of its own upload.
+    ///
+    /// This is only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    // Uploads the outlines, handing the data over to the `Outlines` to keep.
+    #[cfg(feature = "gpu")]
+    fn create_buffers(self, storage: StorageKind) -> Result<Outlines, GlError> {
+        let mut outlines = Outlines {
+            vertices_buffer: 0,
+            indices_buffer: 0,
+            descriptors_buffer: 0,
+            fence: ptr::null(),
+            vertex_count: self.vertices.len(),
+            index_count: self.indices.len(),
+            vertices: self.vertices,
+            indices: self.indices,
+            retained: true,
+            descriptors: self.descriptors,
+            storage: storage,
+            dummy_vertex: self.dummy_vertex,
+            #[cfg(feature = "metrics")]
+            stats: self.stats,
+        };
+
+        #[cfg(feature = "metrics")]
+        let start_time = time::precise_time_ns();
+
+        try!(outlines.recreate());
+
+        #[cfg(feature = "metrics")]
+        {
+            outlines.stats.upload_time_ns += time::precise_time_ns() - start_time;
+        }
+        Ok(outlines)
+    }
+}
+
+/// Builds outlines like `OutlineBuilder`, but uploads them to the GPU in chunks as glyphs are
+/// added, so that only the latest chunk is held on the CPU.
+///
+/// Use this for fonts so large that holding all of their vertices and indices in memory at once,
+/// as `OutlineBuilder::create_buffers()` does, is a problem. Every `glyphs_per_flush` glyphs, the
+/// vertices and indices accumulated since the last chunk are copied into the OpenGL buffers with
+/// `glBufferSubData()` and dropped. Only the glyph descriptors, 32 bytes per glyph, are kept
+/// until `finish()`, which uploads them and returns the `Outlines`.
+///
+/// The buffers are allocated up front from estimates of the number of glyphs and points to be
+/// added. If the glyphs need more room, the buffers are doubled in size and their contents copied
+/// on the GPU, which costs time and briefly needs both copies, so overestimate rather than
+/// underestimate.
+///
+/// The resulting outlines are the same as those that `OutlineBuilder` would produce from the same
+/// glyphs, except that they keep no CPU-side copy of their vertices and indices. They therefore
+/// can't be recreated after the OpenGL context is lost, and `Outlines::glyph_vertices()` and
+/// `Outlines::update_glyph()` don't work on them.
+///
+/// Every method, including `new()`, requires a current OpenGL context. This is only available
+/// with the `gpu` feature.
+#[cfg(feature = "gpu")]
+pub struct StreamingOutlineBuilder {
+    pending: OutlineBuilder,
+    descriptors: Vec<GlyphDescriptor>,
+    glyphs_per_flush: usize,
+    vertices_buffer: GLuint,
+    indices_buffer: GLuint,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    vertex_count: usize,
+    index_count: usize,
+    #[cfg(feature = "metrics")]
+    stats: Stats,
+}
+
+#[cfg(feature = "gpu")]
+impl Drop for StreamingOutlineBuilder {
+    fn drop(&mut self) {
+        // `finish()` hands the buffers over to the outlines and zeroes them, and deleting buffer
+        // 0 is a no-op.
         unsafe {
-            let (mut vertices, mut indices, mut descriptors) = (0, 0, 0);
-            gl::GenBuffers(1, &mut vertices);
-            gl::GenBuffers(1, &mut indices);
-            gl::GenBuffers(1, &mut descriptors);
+            gl::DeleteBuffers(1, &mut self.indices_buffer);
+            gl::DeleteBuffers(1, &mut self.vertices_buffer);
+        }
+    }
+}
 
-            gl::BindBuffer(gl::ARRAY_BUFFER, vertices);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                           (self.vertices.len() * mem::size_of::<Vertex>()) as GLsizeiptr,
-                           self.vertices.as_ptr() as *const Vertex as *const c_void,
-                           gl::STATIC_DRAW);
+#[cfg(feature = "gpu")]
+impl StreamingOutlineBuilder {
+    /// Creates a new streaming outline builder, allocating OpenGL buffers for about
+    /// `estimated_glyph_count` glyphs with `estimated_point_count` points among them.
+    ///
+    /// The accumulated glyphs are uploaded every `glyphs_per_flush` glyphs; a few hundred glyphs
+    /// is a reasonable chunk. Zero is treated as 1.
+    pub fn new(estimated_glyph_count: usize,
+               estimated_point_count: usize,
+               glyphs_per_flush: usize)
+               -> StreamingOutlineBuilder {
+        // The placeholder vertex comes first, and each point ends at most one segment of three
+        // indices.
+        let vertex_capacity = estimated_point_count + 1;
+        let index_capacity = estimated_point_count * 3;
 
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, indices);
-            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER,
-                           (self.indices.len() * mem::size_of::<u32>()) as GLsizeiptr,
-                           self.indices.as_ptr() as *const u32 as *const c_void,
-                           gl::STATIC_DRAW);
+        let mut builder = StreamingOutlineBuilder {
+            pending: OutlineBuilder::new(),
+            descriptors: Vec::with_capacity(estimated_glyph_count),
+            glyphs_per_flush: cmp::max(glyphs_per_flush, 1),
+            vertices_buffer: 0,
+            indices_buffer: 0,
+            vertex_capacity: vertex_capacity,
+            index_capacity: index_capacity,
+            vertex_count: 1,
+            index_count: 0,
+            #[cfg(feature = "metrics")]
+            stats: Stats::default(),
+        };
 
-            let length = self.descriptors.len() * mem::size_of::<GlyphDescriptor>();
-            gl::BindBuffer(gl::UNIFORM_BUFFER, descriptors);
-            gl::BufferData(gl::UNIFORM_BUFFER,
-                           length as GLsizeiptr,
-                           self.descriptors.as_ptr() as *const GlyphDescriptor as *const c_void,
-                           gl::STATIC_DRAW);
+        unsafe {
+            builder.vertices_buffer = create_buffer(vertex_capacity * mem::size_of::<Vertex>());
+            builder.indices_buffer = create_buffer(index_capacity * mem::size_of::<u32>());
+            upload_to_buffer(builder.vertices_buffer, 0, &[DUMMY_VERTEX]);
+        }
+        builder
+    }
+
+    /// Adds a new glyph, as `OutlineBuilder::add_glyph()` does, and uploads the pending glyphs if
+    /// there are `glyphs_per_flush` of them. Returns the glyph index.
+    pub fn add_glyph(&mut self, font: &Font, glyph_id: u16) -> Result<u16, otf::Error> {
+        let pending_index = try!(self.pending.add_glyph(font, glyph_id));
+        Ok(self.added(pending_index))
+    }
+
+    /// Appends a glyph tessellated earlier, as `OutlineBuilder::add_cached_glyph()` does, and
+    /// uploads the pending glyphs if there are `glyphs_per_flush` of them. Returns the glyph
+    /// index.
+    pub fn add_cached_glyph(&mut self, cached: &CachedGlyph) -> Result<u16, OutlineError> {
+        let pending_index = try!(self.pending.add_cached_glyph(cached));
+        Ok(self.added(pending_index))
+    }
+
+    /// Returns the descriptors of all glyphs added so far, indexed by glyph index.
+    ///
+    /// Only the descriptors of glyphs that have been uploaded are included; call `flush()` first
+    /// to include every glyph.
+    #[inline]
+    pub fn descriptors(&self) -> &[GlyphDescriptor] {
+        &self.descriptors
+    }
+
+    /// Returns the statistics collected while adding and uploading the glyphs that have been
+    /// uploaded so far.
+    ///
+    /// This is only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Uploads the glyphs added since the last upload and drops the CPU-side copies of their
+    /// vertices and indices.
+    ///
+    /// This happens automatically every `glyphs_per_flush` glyphs and in `finish()`, so there's
+    /// usually no need to call it.
+    pub fn flush(&mut self) {
+        let mut pending = mem::replace(&mut self.pending, OutlineBuilder::new());
+        if pending.descriptors.is_empty() {
+            return
+        }
+
+        #[cfg(feature = "metrics")]
+        let start_time = time::precise_time_ns();
+
+        // Renumber the glyphs and points as `OutlineBuilder::merge()` does. The pending builder
+        // has a placeholder vertex of its own, which isn't uploaded again.
+        let glyph_index_offset = self.descriptors.len() as u16;
+        let point_offset = self.vertex_count as u32 - 1;
+        let start_index_offset = self.index_count as u32;
+        for vertex in &mut pending.vertices[1..] {
+            vertex.glyph_index += glyph_index_offset
+        }
+        for index in &mut pending.indices {
+            if *index != 0 {
+                *index += point_offset
+            }
+        }
+        self.descriptors.extend(pending.descriptors.iter().map(|descriptor| {
+            GlyphDescriptor {
+                start_point: descriptor.start_point + point_offset,
+                start_index: descriptor.start_index + start_index_offset,
+                ..*descriptor
+            }
+        }));
+
+        let vertices = &pending.vertices[1..];
+        unsafe {
+            reserve_buffer(&mut self.vertices_buffer,
+                           &mut self.vertex_capacity,
+                           self.vertex_count,
+                           vertices.len(),
+                           mem::size_of::<Vertex>());
+            reserve_buffer(&mut self.indices_buffer,
+                           &mut self.index_capacity,
+                           self.index_count,
+                           pending.indices.len(),
+                           mem::size_of::<u32>());
+            upload_to_buffer(self.vertices_buffer, self.vertex_count, vertices);
+            upload_to_buffer(self.indices_buffer, self.index_count, &pending.indices);
+        }
+        self.vertex_count += vertices.len();
+        self.index_count += pending.indices.len();
+
+        #[cfg(feature = "metrics")]
+        {
+            self.stats.add(&pending.stats);
+            self.stats.upload_time_ns += time::precise_time_ns() - start_time;
+        }
+    }
+
+    /// Uploads the remaining glyphs and their descriptors, putting the descriptors in a uniform
+    /// buffer, and returns the outlines.
+    ///
+    /// As with `OutlineBuilder::create_buffers()`, the upload is followed by a fence and a
+    /// `glFlush()`. To use a shader storage buffer, use `finish_with_storage()`.
+    #[inline]
+    pub fn finish(self) -> Result<Outlines, GlError> {
+        self.finish_with_storage(StorageKind::Ubo)
+    }
+
+    /// Uploads the remaining glyphs and their descriptors, putting the descriptors in the given
+    /// kind of buffer, and returns the outlines. See
+    /// `OutlineBuilder::create_buffers_with_storage()`.
+    pub fn finish_with_storage(mut self, storage: StorageKind) -> Result<Outlines, GlError> {
+        self.flush();
+
+        let mut outlines = Outlines {
+            vertices_buffer: self.vertices_buffer,
+            indices_buffer: self.indices_buffer,
+            descriptors_buffer: 0,
+            fence: ptr::null(),
+            vertex_count: self.vertex_count,
+            index_count: self.index_count,
+            vertices: vec![],
+            indices: vec![],
+            retained: false,
+            descriptors: mem::replace(&mut self.descriptors, vec![]),
+            storage: storage,
+            dummy_vertex: true,
+            #[cfg(feature = "metrics")]
+            stats: self.stats,
+        };
+        self.vertices_buffer = 0;
+        self.indices_buffer = 0;
+
+        #[cfg(feature = "metrics")]
+        let start_time = time::precise_time_ns();
+
+        outlines.upload_descriptors();
+
+        #[cfg(feature = "metrics")]
+        {
+            outlines.stats.upload_time_ns += time::precise_time_ns() - start_time;
+        }
+        Ok(outlines)
+    }
 
-            Ok(Outlines {
-                vertices_buffer: vertices,
-                indices_buffer: indices,
-                descriptors_buffer: descriptors,
-                descriptors: self.descriptors,
-                indices_count: self.indices.len(),
+    // Turns the index of a glyph just added to the pending builder into its glyph index, and
+    // uploads the pending glyphs if there are enough of them.
+    fn added(&mut self, pending_index: u16) -> u16 {
+        let glyph_index = self.descriptors.len() as u16 + pending_index;
+        if self.pending.descriptors.len() >= self.glyphs_per_flush {
+            self.flush()
+        }
+        glyph_index
+    }
+}
+
+// Creates a buffer of `size` bytes with undefined contents.
+#[cfg(feature = "gpu")]
+unsafe fn create_buffer(size: usize) -> GLuint {
+    let mut buffer = 0;
+    gl::GenBuffers(1, &mut buffer);
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer);
+    gl::BufferData(gl::COPY_WRITE_BUFFER, size as GLsizeiptr, ptr::null(), gl::STATIC_DRAW);
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+    buffer
+}
+
+// Copies `data` into `buffer`, starting at element `start`.
+#[cfg(feature = "gpu")]
+unsafe fn upload_to_buffer<T>(buffer: GLuint, start: usize, data: &[T]) {
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer);
+    gl::BufferSubData(gl::COPY_WRITE_BUFFER,
+                      (start * mem::size_of::<T>()) as GLintptr,
+                      (data.len() * mem::size_of::<T>()) as GLsizeiptr,
+                      data.as_ptr() as *const c_void);
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+}
+
+// Makes room for `additional` more elements of `element_size` bytes in `buffer`, which holds
+// `count` elements and has room for `capacity`, by copying its contents into a new buffer of at
+// least twice the capacity if necessary.
+#[cfg(feature = "gpu")]
+unsafe fn reserve_buffer(buffer: &mut GLuint,
+                         capacity: &mut usize,
+                         count: usize,
+                         additional: usize,
+                         element_size: usize) {
+    if count + additional <= *capacity {
+        return
+    }
+
+    let new_capacity = cmp::max(count + additional, *capacity * 2);
+    let new_buffer = create_buffer(new_capacity * element_size);
+    gl::BindBuffer(gl::COPY_READ_BUFFER, *buffer);
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, new_buffer);
+    gl::CopyBufferSubData(gl::COPY_READ_BUFFER,
+                          gl::COPY_WRITE_BUFFER,
+                          0,
+                          0,
+                          (count * element_size) as GLsizeiptr);
+    gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+    gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+    gl::DeleteBuffers(1, buffer);
+
+    *buffer = new_buffer;
+    *capacity = new_capacity;
+}
+
+// Returns the contours of the given glyph, as `Font::for_each_point()` supplies them.
+fn glyph_contours(font: &Font, glyph_id: u16) -> Result<Vec<Vec<ContourPoint>>, otf::Error> {
+    let mut contours: Vec<Vec<ContourPoint>> = vec![];
+    try!(font.for_each_point(glyph_id, |point| {
+        if point.index_in_contour == 0 {
+            contours.push(vec![])
+        }
+        if let Some(contour) = contours.last_mut() {
+            contour.push(ContourPoint {
+                position: point.position,
+                on_curve: point.on_curve,
             })
         }
+    }));
+    Ok(contours)
+}
+
+// Applies a transform to a point in font units, rounding to the nearest unit.
+fn transform_position(transform: &Matrix2D<f32>, position: &Point2D<i16>) -> Point2D<i16> {
+    let position = transform.transform_point(&Point2D::new(position.x as f32,
+                                                           position.y as f32));
+    Point2D::new(clamp_to_i16(position.x.round()), clamp_to_i16(position.y.round()))
+}
+
+#[inline]
+fn clamp_to_i16(value: f32) -> i16 {
+    value.max(i16::MIN as f32).min(i16::MAX as f32) as i16
+}
+
+// Returns the bounds of the given glyph bounds after transformation, rounded out to whole units.
+fn transform_bounds(transform: &Matrix2D<f32>, bounds: &GlyphBounds) -> GlyphBounds {
+    let rect = Rect::new(Point2D::new(bounds.left as f32, bounds.bottom as f32),
+                         Size2D::new((bounds.right - bounds.left) as f32,
+                                     (bounds.top - bounds.bottom) as f32));
+    let rect = transform.transform_rect(&rect);
+    GlyphBounds {
+        left: rect.origin.x.floor() as i32,
+        bottom: rect.origin.y.floor() as i32,
+        right: rect.max_x().ceil() as i32,
+        top: rect.max_y().ceil() as i32,
+    }
+}
+
+// Returns the bounds of the given points in font units, rounded out to whole pixels at the given
+// size in pixels per em.
+#[cfg(feature = "hinting")]
+fn pixel_aligned_bounds<I>(points: I, units_per_em: u16, pixels_per_em: u16) -> GlyphBounds
+                           where I: Iterator<Item = Point2D<i16>> {
+    let mut bounds: Option<(Point2D<i16>, Point2D<i16>)> = None;
+    for point in points {
+        bounds = Some(match bounds {
+            None => (point, point),
+            Some((min, max)) => {
+                (Point2D::new(cmp::min(min.x, point.x), cmp::min(min.y, point.y)),
+                 Point2D::new(cmp::max(max.x, point.x), cmp::max(max.y, point.y)))
+            }
+        })
+    }
+    let (min, max) = match bounds {
+        None => return GlyphBounds { left: 0, bottom: 0, right: 0, top: 0 },
+        Some(bounds) => bounds,
+    };
+
+    let units_per_pixel = units_per_em as f32 / pixels_per_em as f32;
+    let to_pixels = |value: i16| value as f32 / units_per_pixel;
+    let to_units = |value: f32| (value * units_per_pixel).round() as i32;
+    GlyphBounds {
+        left: to_units(to_pixels(min.x).floor()),
+        bottom: to_units(to_pixels(min.y).floor()),
+        right: to_units(to_pixels(max.x).ceil()),
+        top: to_units(to_pixels(max.y).ceil()),
+    }
+}
+
+/// The tessellated outline of one glyph, copied out of an `OutlineBuilder` with
+/// `OutlineBuilder::cached_glyph()` so that it can be added again with
+/// `OutlineBuilder::add_cached_glyph()`.
+#[derive(Clone, Debug)]
+pub struct CachedGlyph {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    descriptor: GlyphDescriptor,
+    dummy_vertex: bool,
+}
+
+impl CachedGlyph {
+    /// Returns the glyph ID of this glyph in its font.
+    #[inline]
+    pub fn glyph_id(&self) -> u16 {
+        self.descriptor.glyph_id
+    }
+
+    /// Returns the descriptor of this glyph.
+    ///
+    /// Its start point and start index are those the glyph would have in an otherwise empty
+    /// builder.
+    #[inline]
+    pub fn descriptor(&self) -> &GlyphDescriptor {
+        &self.descriptor
+    }
+
+    /// Returns the points of this glyph.
+    #[inline]
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// Returns the vertex indices of this glyph, three per segment, numbered as in a builder
+    /// containing only this glyph: 0 is the placeholder vertex and 1 is the first point.
+    #[inline]
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+}
+
+/// Where a glyph of a shaped run goes, as `OutlineBuilder::add_shaped_run()` returns it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlacedGlyph {
+    /// The glyph index of the glyph's outline in the builder.
+    pub glyph_index: u16,
+    /// The position of the glyph's origin relative to the start of the run, in font units, with
+    /// y pointing up.
+    pub origin: Point2D<f32>,
+}
+
+/// The number of points in a glyph's outline before and after simplification by
+/// `OutlineBuilder::add_simplified_glyph()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SimplificationStats {
+    /// The number of points in the glyph's original outline.
+    pub original_point_count: usize,
+    /// The number of points that were added to the builder.
+    pub point_count: usize,
+}
+
+/// The kind of OpenGL buffer that glyph descriptors are uploaded to, as chosen with
+/// `OutlineBuilder::create_buffers_with_storage()`.
+#[cfg(feature = "gpu")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StorageKind {
+    /// A uniform buffer (`GL_UNIFORM_BUFFER`), which every supported OpenGL version has. This is
+    /// the default.
+    Ubo,
+    /// A shader storage buffer (`GL_SHADER_STORAGE_BUFFER`), which holds any number of
+    /// descriptors but needs OpenGL 4.3 or `ARB_shader_storage_buffer_object`.
+    Ssbo,
+}
+
+#[cfg(feature = "gpu")]
+impl Default for StorageKind {
+    #[inline]
+    fn default() -> StorageKind {
+        StorageKind::Ubo
+    }
+}
+
+/// Counters and timings collected by an `OutlineBuilder` for profiling, as returned by
+/// `OutlineBuilder::stats()` and `Outlines::stats()`.
+///
+/// Times are wall-clock nanoseconds. Glyphs added by `par_add_glyphs()` are timed on their own
+/// threads, so the tessellation time is the total across threads rather than the time that
+/// elapsed. Glyphs added with `add_cached_glyph()` aren't tessellated and aren't counted.
+///
+/// This is only available with the `metrics` feature.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Stats {
+    /// The number of glyphs tessellated.
+    pub glyphs_tessellated: u64,
+    /// The number of vertices emitted for those glyphs.
+    pub vertices_emitted: u64,
+    /// The number of vertex indices emitted for those glyphs, three per segment.
+    pub indices_emitted: u64,
+    /// The time spent reading and tessellating those glyphs.
+    pub tessellation_time_ns: u64,
+    /// The time spent in `OutlineBuilder::create_buffers()` creating and filling the GPU buffers.
+    ///
+    /// This is the time taken to submit the upload, which returns without waiting for the data to
+    /// reach the GPU.
+    pub upload_time_ns: u64,
+}
+
+#[cfg(feature = "metrics")]
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.glyphs_tessellated += other.glyphs_tessellated;
+        self.vertices_emitted += other.vertices_emitted;
+        self.indices_emitted += other.indices_emitted;
+        self.tessellation_time_ns += other.tessellation_time_ns;
+        self.upload_time_ns += other.upload_time_ns
     }
 }
 
 /// Resolution-independent glyph vectors uploaded to the GPU.
+///
+/// The vertices, indices, and descriptors are also retained on the CPU so that the buffers can be
+/// recreated if the OpenGL context is lost.
+#[cfg(feature = "gpu")]
 pub struct Outlines {
     vertices_buffer: GLuint,
     indices_buffer: GLuint,
     descriptors_buffer: GLuint,
+    fence: GLsync,
+    vertex_count: usize,
+    index_count: usize,
+    // The CPU-side copies of the vertices and indices, which are empty if the outlines were
+    // streamed to the GPU.
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    // Whether `vertices` and `indices` hold copies of everything uploaded. Outlines without the
+    // placeholder vertex can be retained and still have no vertices.
+    retained: bool,
     descriptors: Vec<GlyphDescriptor>,
-    indices_count: usize,
+    storage: StorageKind,
+    dummy_vertex: bool,
+    #[cfg(feature = "metrics")]
+    stats: Stats,
+}
+
+#[cfg(feature = "gpu")]
+impl fmt::Debug for Outlines {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Outlines")
+                 .field("vertices_buffer", &self.vertices_buffer)
+                 .field("indices_buffer", &self.indices_buffer)
+                 .field("descriptors_buffer", &self.descriptors_buffer)
+                 .field("storage", &self.storage)
+                 .field("descriptor_count", &self.descriptors.len())
+                 .field("indices_count", &self.index_count)
+                 .finish()
+    }
 }
 
+// Like buffer names, sync objects may be used from any context in the share group, so the raw
+// `GLsync` pointer doesn't tie the outlines to one thread.
+#[cfg(feature = "gpu")]
+unsafe impl Send for Outlines {}
+#[cfg(feature = "gpu")]
+unsafe impl Sync for Outlines {}
+
+#[cfg(feature = "gpu")]
 impl Drop for Outlines {
+    #[inline]
     fn drop(&mut self) {
+        self.delete_buffers()
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl Outlines {
+    /// Forgets the OpenGL buffers without deleting them.
+    ///
+    /// Call this after the OpenGL context has been lost, since the names of the buffers may refer
+    /// to unrelated objects in a new context. Dropping the outlines afterward won't delete
+    /// anything. The outlines can't be drawn until `recreate()` is called.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.vertices_buffer = 0;
+        self.indices_buffer = 0;
+        self.descriptors_buffer = 0;
+        self.fence = ptr::null();
+    }
+
+    /// Deletes the OpenGL buffers immediately, consuming the outlines.
+    ///
+    /// Dropping the outlines deletes the buffers too, but only whenever and wherever the value
+    /// happens to be dropped. Call this instead to release them at a known point while the
+    /// OpenGL context that created them is still current, such as during an engine's shutdown.
+    #[inline]
+    pub fn destroy(mut self) {
+        self.delete_buffers()
+    }
+
+    // Deletes the OpenGL buffers and forgets them, so that deleting them again does nothing.
+    fn delete_buffers(&mut self) {
+        // Deleting buffer 0 is a no-op, so invalidated buffers are skipped.
         unsafe {
+            if !self.fence.is_null() {
+                gl::DeleteSync(self.fence);
+            }
             gl::DeleteBuffers(1, &mut self.descriptors_buffer);
             gl::DeleteBuffers(1, &mut self.indices_buffer);
             gl::DeleteBuffers(1, &mut self.vertices_buffer);
         }
+        self.invalidate()
+    }
+
+    /// Uploads the outlines to new OpenGL buffers in the current context.
+    ///
+    /// Call this after the OpenGL context has been lost and a new one made current. The old
+    /// buffers are forgotten without being deleted, as with `invalidate()`. Atlases refer to
+    /// glyphs by index, so they remain valid, but must themselves be recreated in the new context.
+    ///
+    /// Outlines built with a `StreamingOutlineBuilder` have no CPU-side copy to upload from, so
+    /// this returns `GlError(GL_INVALID_OPERATION)` for them without changing anything. Build them
+    /// again instead.
+    pub fn recreate(&mut self) -> Result<(), GlError> {
+        if !self.is_retained() {
+            return Err(GlError(gl::INVALID_OPERATION))
+        }
+
+        self.invalidate();
+
+        // TODO(pcwalton): Try using `glMapBuffer` here. Requires precomputing contour types and
+        // counts.
+        unsafe {
+            gl::GenBuffers(1, &mut self.vertices_buffer);
+            gl::GenBuffers(1, &mut self.indices_buffer);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertices_buffer);
+            gl::BufferData(gl::ARRAY_BUFFER,
+                           (self.vertices.len() * mem::size_of::<Vertex>()) as GLsizeiptr,
+                           self.vertices.as_ptr() as *const Vertex as *const c_void,
+                           gl::STATIC_DRAW);
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.indices_buffer);
+            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER,
+                           (self.indices.len() * mem::size_of::<u32>()) as GLsizeiptr,
+                           self.indices.as_ptr() as *const u32 as *const c_void,
+                           gl::STATIC_DRAW);
+        }
+
+        self.upload_descriptors();
+        Ok(())
+    }
+
+    // Uploads the descriptors to a new buffer, then sets and flushes the fence.
+    fn upload_descriptors(&mut self) {
+        unsafe {
+            gl::GenBuffers(1, &mut self.descriptors_buffer);
+
+            let target = match self.storage {
+                StorageKind::Ubo => gl::UNIFORM_BUFFER,
+                StorageKind::Ssbo => gl::SHADER_STORAGE_BUFFER,
+            };
+            let length = self.descriptors.len() * mem::size_of::<GlyphDescriptor>();
+            gl::BindBuffer(target, self.descriptors_buffer);
+            gl::BufferData(target,
+                           length as GLsizeiptr,
+                           self.descriptors.as_ptr() as *const GlyphDescriptor as *const c_void,
+                           gl::STATIC_DRAW);
+
+            // Flush so that the fence is visible to other contexts in the share group.
+            self.fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            gl::Flush();
+        }
+    }
+
+    // Returns true if the CPU-side copies of the vertices and indices were kept, which they are
+    // unless the outlines were streamed.
+    #[inline]
+    fn is_retained(&self) -> bool {
+        self.retained
+    }
+
+    /// Returns the fence that is signaled once the upload in `create_buffers()` or `recreate()`
+    /// has completed, or `None` if the outlines have been invalidated.
+    ///
+    /// The fence has already been flushed, so it may be waited on from another context that
+    /// shares objects with the uploading one, for example with `glWaitSync()` or
+    /// `glClientWaitSync()`. It remains owned by the outlines and is deleted along with them.
+    #[inline]
+    pub fn fence(&self) -> Option<GLsync> {
+        if self.fence.is_null() {
+            None
+        } else {
+            Some(self.fence)
+        }
+    }
+
+    /// Makes the GPU wait for the upload of these outlines to complete before executing any
+    /// further commands issued in the current context.
+    ///
+    /// This is a server-side wait (`glWaitSync()`): it returns immediately without blocking the
+    /// CPU, unlike `glFinish()`. Call it on the drawing context before the first draw when the
+    /// outlines were uploaded from another context. Within the uploading context, OpenGL already
+    /// orders the upload before subsequent draws, so this is unnecessary there.
+    pub fn wait_uploaded(&self) {
+        if let Some(fence) = self.fence() {
+            unsafe {
+                gl::WaitSync(fence, 0, gl::TIMEOUT_IGNORED)
+            }
+        }
     }
-}
 
-impl Outlines {
     #[doc(hidden)]
     #[inline]
     pub fn vertices_buffer(&self) -> GLuint {
@@ -162,16 +1616,139 @@ impl Outlines {
         self.descriptors_buffer
     }
 
+    /// Returns the kind of buffer that the glyph descriptors were uploaded to.
+    #[inline]
+    pub fn storage(&self) -> StorageKind {
+        self.storage
+    }
+
+    /// Returns true if the vertex buffer begins with the placeholder vertex at
+    /// `DUMMY_VERTEX_INDEX`, which the `Rasterizer` needs to draw the outlines.
+    ///
+    /// See `OutlineOptions::dummy_vertex`.
+    #[inline]
+    pub fn has_dummy_vertex(&self) -> bool {
+        self.dummy_vertex
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn descriptor(&self, glyph_index: u16) -> Option<&GlyphDescriptor> {
         self.descriptors.get(glyph_index as usize)
     }
 
+    /// Returns the descriptors of all glyphs in these outlines, indexed by glyph index.
+    ///
+    /// These are the CPU-side copies of the contents of `descriptors_buffer()`, so they can be
+    /// inspected without reading the buffer back from the GPU.
+    #[inline]
+    pub fn descriptor_slice(&self) -> &[GlyphDescriptor] {
+        &self.descriptors
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn indices_count(&self) -> usize {
-        self.indices_count
+        self.index_count
+    }
+
+    /// Returns the statistics of the builder that these outlines were created from, including
+    /// the time spent uploading them.
+    ///
+    /// This is only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Draws the edges of the glyphs in `atlas` into `coverage_buffer`, without accumulating them
+    /// into an image.
+    ///
+    /// This is the first half of `Rasterizer::draw_atlas_with_options()`: it binds the
+    /// rasterizer's vertex array and program, these outlines' buffers, and the coverage buffer's
+    /// framebuffer, clears the part of the coverage buffer that the atlas covers, and draws the
+    /// atlas, restoring the viewport and scissor state afterward. `size` is the size of the atlas,
+    /// and the coverage buffer or `options.coverage_region` must be at least that large.
+    /// Afterward, the coverage buffer's image holds the signed coverage deltas described in
+    /// `CoverageBuffer::image()`, ready for a resolve pass of your own.
+    ///
+    /// Use the raw buffer handles, such as `vertices_buffer()`, only if you need to draw the
+    /// outlines with shaders of your own.
+    pub fn rasterize(&self,
+                     rasterizer: &Rasterizer,
+                     coverage_buffer: &CoverageBuffer,
+                     atlas: &Atlas,
+                     size: &Size2D<u32>,
+                     options: &DrawAtlasOptions)
+                     -> Result<(), RasterError> {
+        let rect = Rect::new(Point2D::zero(), *size);
+        rasterizer.draw_atlas_coverage(&rect, atlas, self, coverage_buffer, options).map(|_| ())
+    }
+
+    /// Returns the points of the glyph with the given index, or `None` if there is no such glyph.
+    ///
+    /// These are CPU-side copies of the glyph's part of `vertices_buffer()`, reflecting any
+    /// changes made with `update_glyph()`. Outlines built with a `StreamingOutlineBuilder` have
+    /// no such copies, so this always returns `None` for them.
+    pub fn glyph_vertices(&self, glyph_index: u16) -> Option<&[Vertex]> {
+        self.vertex_range(glyph_index).and_then(|(start, end)| self.vertices.get(start..end))
+    }
+
+    /// Replaces the points of the glyph with the given index, uploading only that glyph's part of
+    /// the vertex buffer with `glBufferSubData()`.
+    ///
+    /// This is cheap enough to do every frame, so it suits glyphs whose outlines are animated by
+    /// moving their points. `new_vertices` must have exactly as many points as the glyph had
+    /// originally, in the same order, so that the index buffer stays valid; only their positions
+    /// are used. Returns `GlError(GL_INVALID_VALUE)` without changing anything if the count
+    /// differs or there is no such glyph.
+    ///
+    /// The glyph's bounds aren't updated, so atlases still size the glyph by its original bounds.
+    /// Keep the points within them, or leave room around the glyph with `AtlasOptions::margin`.
+    ///
+    /// Outlines built with a `StreamingOutlineBuilder` can't be updated, since they keep no
+    /// CPU-side copy of their points; this returns `GlError(GL_INVALID_OPERATION)` for them.
+    pub fn update_glyph(&mut self, glyph_index: u16, new_vertices: &[Vertex])
+                        -> Result<(), GlError> {
+        if !self.is_retained() {
+            return Err(GlError(gl::INVALID_OPERATION))
+        }
+
+        let (start, end) = match self.vertex_range(glyph_index) {
+            Some(range) if range.1 - range.0 == new_vertices.len() => range,
+            _ => return Err(GlError(gl::INVALID_VALUE)),
+        };
+
+        for (vertex, new_vertex) in self.vertices[start..end].iter_mut().zip(new_vertices) {
+            vertex.x = new_vertex.x;
+            vertex.y = new_vertex.y;
+        }
+
+        unsafe {
+            let vertices = &self.vertices[start..end];
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertices_buffer);
+            gl::BufferSubData(gl::ARRAY_BUFFER,
+                              (start * mem::size_of::<Vertex>()) as GLintptr,
+                              (vertices.len() * mem::size_of::<Vertex>()) as GLsizeiptr,
+                              vertices.as_ptr() as *const Vertex as *const c_void);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Ok(())
+    }
+
+    // Returns the start and end of the given glyph's points in the vertex buffer.
+    fn vertex_range(&self, glyph_index: u16) -> Option<(usize, usize)> {
+        let start = match self.descriptors.get(glyph_index as usize) {
+            None => return None,
+            Some(descriptor) => descriptor.start_point as usize,
+        };
+        let end = match self.descriptors.get(glyph_index as usize + 1) {
+            None => self.vertex_count,
+            Some(descriptor) => descriptor.start_point as usize,
+        };
+        Some((start, end))
     }
 
     /// Returns the glyph rectangle in font units.
@@ -187,11 +1764,35 @@ impl Outlines {
     }
 
     /// Returns the boundaries of the glyph, rounded out to the nearest pixel.
+    ///
+    /// These bounds don't include the transparent gutter that an atlas leaves around each glyph;
+    /// use `glyph_padded_pixel_bounds()` for the space the glyph takes up in an atlas.
     #[inline]
     pub fn glyph_pixel_bounds(&self, glyph_index: u16, point_size: f32) -> GlyphPixelBounds {
         self.descriptors[glyph_index as usize].subpixel_bounds(point_size).round_out()
     }
 
+    /// Returns the boundaries of the glyph, rounded out to the nearest pixel and grown by
+    /// `padding` pixels on every side.
+    ///
+    /// With the padding of an atlas (`Atlas::padding()`), these bounds cover the glyph and its
+    /// gutter, which is the area that can be sampled under linear filtering without picking up
+    /// neighboring glyphs.
+    #[inline]
+    pub fn glyph_padded_pixel_bounds(&self, glyph_index: u16, point_size: f32, padding: u32)
+                                     -> GlyphPixelBounds {
+        self.glyph_pixel_bounds(glyph_index, point_size).expanded(padding as i32)
+    }
+
+    /// Returns the offset from the pen position on the baseline to the top-left corner of the
+    /// glyph's pixel bounds, in y-down pixels.
+    ///
+    /// See `GlyphPixelBounds::draw_offset()`.
+    #[inline]
+    pub fn glyph_draw_offset(&self, glyph_index: u16, point_size: f32) -> Point2D<i32> {
+        self.glyph_pixel_bounds(glyph_index, point_size).draw_offset()
+    }
+
     /// Returns the ID of the glyph with the given index.
     #[inline]
     pub fn glyph_id(&self, glyph_index: u16) -> u16 {
@@ -199,7 +1800,29 @@ impl Outlines {
     }
 }
 
-#[doc(hidden)]
+/// Per-glyph information uploaded to the GPU in the buffer returned by
+/// `Outlines::descriptors_buffer()`.
+///
+/// The layout of this structure is part of the public interface so that custom shaders can read
+/// it. It is 32 bytes long, 4-byte aligned, and laid out as follows (all values little-endian):
+///
+/// | Offset | Size | Field          | Type    |
+/// | ------ | ---- | -------------- | ------- |
+/// | 0      | 4    | `bounds.left`  | `i32`   |
+/// | 4      | 4    | `bounds.bottom`| `i32`   |
+/// | 8      | 4    | `bounds.right` | `i32`   |
+/// | 12     | 4    | `bounds.top`   | `i32`   |
+/// | 16     | 4    | `units_per_em` | `u32`   |
+/// | 20     | 4    | `start_point`  | `u32`   |
+/// | 24     | 4    | `start_index`  | `u32`   |
+/// | 28     | 2    | `glyph_id`     | `u16`   |
+/// | 30     | 2    | (padding)      |         |
+///
+/// Under `std140` rules this is exactly an `ivec4` followed by a `uvec4`, so the array stride in
+/// a uniform block is also 32 bytes. See `GLSL_STRUCT` for the matching GLSL declaration. Note
+/// that the upper 16 bits of the last component contain padding and must be masked off.
+///
+/// If this layout ever changes, `LAYOUT_VERSION` will be incremented.
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct GlyphDescriptor {
@@ -210,22 +1833,77 @@ pub struct GlyphDescriptor {
     glyph_id: u16,
 }
 
+// Fails to compile if the size of `GlyphDescriptor` drifts from the `std140` stride.
+#[allow(dead_code)]
+const GLYPH_DESCRIPTOR_SIZE_CHECK: [(); GlyphDescriptor::UBO_STRIDE] =
+    [(); mem::size_of::<GlyphDescriptor>()];
+
 impl GlyphDescriptor {
+    /// The version of the memory layout of this structure.
+    pub const LAYOUT_VERSION: u32 = 1;
+
+    /// The size in bytes of each element of the descriptor array in a `std140` uniform block.
+    pub const UBO_STRIDE: usize = 32;
+
+    /// The GLSL declaration matching the memory layout of this structure.
+    ///
+    /// Use it inside a `layout(std140)` uniform block.
+    pub const GLSL_STRUCT: &'static str = "\
+struct GlyphDescriptor {
+    // x: left, y: bottom, z: right, w: top, in font units.
+    ivec4 extents;
+    // x: units per em, y: start point, z: start index, w: glyph ID (low 16 bits only).
+    uvec4 misc;
+};
+";
+
+    /// Returns the number of font units per em of the glyph's font.
+    ///
+    /// This is stored as a `u32` so that it fills a `uvec4` component on the GPU, but it always
+    /// fits in a `u16`, like `Font::units_per_em()`.
+    #[inline]
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em as u16
+    }
+
+    /// Returns the boundaries of the glyph in font units, after any transform that the outline
+    /// builder applied to it.
+    #[inline]
+    pub fn bounds(&self) -> GlyphBounds {
+        self.bounds
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn start_index(&self) -> u32 {
         self.start_index
     }
 
-    #[doc(hidden)]
+    #[cfg(feature = "gpu")]
     #[inline]
     fn subpixel_bounds(&self, point_size: f32) -> GlyphSubpixelBounds {
-        self.bounds.subpixel_bounds(self.units_per_em as u16, point_size)
+        self.bounds.subpixel_bounds(self.units_per_em(), point_size)
     }
 }
 
+// Returns the part of the index buffer that draws the glyph with the given index, or `None` if
+// there's no such glyph. Each glyph's indices run up to the first index of the next glyph.
 #[doc(hidden)]
-#[derive(Copy, Clone, Debug)]
+pub fn glyph_index_range(descriptors: &[GlyphDescriptor], indices_count: usize, glyph_index: u16)
+                         -> Option<Range<u32>> {
+    let start_index = match descriptors.get(glyph_index as usize) {
+        None => return None,
+        Some(descriptor) => descriptor.start_index,
+    };
+    let end_index = match descriptors.get(glyph_index as usize + 1) {
+        None => indices_count as u32,
+        Some(descriptor) => descriptor.start_index,
+    };
+    Some(start_index..end_index)
+}
+
+/// A point of a glyph outline, as stored in the vertex buffer.
+#[derive(Copy, Clone, PartialEq, Debug)]
 #[repr(C)]
 pub struct Vertex {
     x: i16,
@@ -233,6 +1911,31 @@ pub struct Vertex {
     glyph_index: u16,
 }
 
+impl Vertex {
+    /// Creates a point of the glyph with the given index at the given position in font units.
+    #[inline]
+    pub fn new(position: &Point2D<i16>, glyph_index: u16) -> Vertex {
+        Vertex {
+            x: position.x,
+            y: position.y,
+            glyph_index: glyph_index,
+        }
+    }
+
+    /// Returns the position of this point in font units.
+    #[inline]
+    pub fn position(&self) -> Point2D<i16> {
+        Point2D::new(self.x, self.y)
+    }
+
+    /// Returns the index of the glyph that this point belongs to, as returned by
+    /// `OutlineBuilder::add_glyph()`.
+    #[inline]
+    pub fn glyph_index(&self) -> u16 {
+        self.glyph_index
+    }
+}
+
 /// The boundaries of the glyph in fractional pixels.
 #[derive(Copy, Clone, Debug)]
 pub struct GlyphSubpixelBounds {
@@ -259,6 +1962,85 @@ impl GlyphSubpixelBounds {
     pub fn size(&self) -> Size2D<f32> {
         Size2D::new(self.right - self.left, self.top - self.bottom)
     }
+
+    /// Returns these bounds as a rectangle in the same y-up coordinate system.
+    ///
+    /// The origin of the rectangle is the bottom-left corner `(left, bottom)`, and its size is
+    /// `size()`. To get a rectangle for a y-down surface, use `to_y_down()` instead.
+    #[inline]
+    pub fn to_rect(&self) -> Rect<f32> {
+        Rect::new(Point2D::new(self.left, self.bottom), self.size())
+    }
+
+    /// Returns these bounds as a rectangle in y-down coordinates whose origin is `ascent` pixels
+    /// above the baseline, directly above the pen position.
+    ///
+    /// Pass the ascent of the line to get coordinates relative to the top of the line box, or 0
+    /// to get coordinates relative to the pen position. The rectangle's origin is the glyph's
+    /// top-left corner, `(left, ascent - top)`; its bottom edge, `max_y()`, is at
+    /// `ascent - bottom`. Its size is `size()`, as for `to_rect()`.
+    #[inline]
+    pub fn to_y_down(&self, ascent: f32) -> Rect<f32> {
+        Rect::new(Point2D::new(self.left, ascent - self.top), self.size())
+    }
+
+    /// Converts these bounds to 26.6 fixed-point pixels by rounding each edge to the nearest 1/64
+    /// of a pixel.
+    ///
+    /// Because these bounds were computed in floating point from the bounding box stored in the
+    /// font, the result often differs by 1/64 from FreeType's. For exact agreement with FreeType,
+    /// use `Font::glyph_bounds_26_6()` instead.
+    #[inline]
+    pub fn to_26_6(&self) -> GlyphFixedBounds {
+        GlyphFixedBounds {
+            left: (self.left * 64.0).round() as i32,
+            bottom: (self.bottom * 64.0).round() as i32,
+            right: (self.right * 64.0).round() as i32,
+            top: (self.top * 64.0).round() as i32,
+        }
+    }
+
+    /// Returns these bounds moved by `dx` pixels to the right and `dy` pixels up, as when placing
+    /// the glyph with the pen at `(dx, dy)`.
+    #[inline]
+    pub fn translated(&self, dx: f32, dy: f32) -> GlyphSubpixelBounds {
+        GlyphSubpixelBounds {
+            left: self.left + dx,
+            bottom: self.bottom + dy,
+            right: self.right + dx,
+            top: self.top + dy,
+        }
+    }
+
+    /// Returns which of `buckets` equal divisions of a pixel the left edge of these bounds is
+    /// nearest to, from 0 to `buckets - 1`.
+    ///
+    /// The atlas keeps the fractional part of the left edge when it places a glyph, so a glyph
+    /// rasterized once per bucket can stand in for every horizontal position: pen positions whose
+    /// translated bounds fall in the same bucket differ only by whole pixels, or by at most half a
+    /// bucket. Left edges within half a bucket below a whole pixel wrap around to bucket 0. A
+    /// `buckets` of 0 is treated as 1. See `GlyphCacheKey::for_pen_position()` for the matching
+    /// cache key.
+    #[inline]
+    pub fn subpixel_bucket(&self, buckets: u32) -> u32 {
+        let buckets = buckets.max(1);
+        let fraction = self.left - self.left.floor();
+        (fraction * buckets as f32).round() as u32 % buckets
+    }
+
+    /// Returns the bounds of the glyph after rotating it 90° clockwise about the origin, which
+    /// maps each point (x, y) to (y, -x).
+    ///
+    /// This is the rotation the atlas applies to rotated glyphs.
+    #[inline]
+    pub fn rotated(&self) -> GlyphSubpixelBounds {
+        GlyphSubpixelBounds {
+            left: self.bottom,
+            bottom: -self.right,
+            right: self.top,
+            top: -self.left,
+        }
+    }
 }
 
 /// The boundaries of the glyph, rounded out to the nearest pixel.
@@ -276,6 +2058,103 @@ impl GlyphPixelBounds {
     pub fn size(&self) -> Size2D<i32> {
         Size2D::new(self.right - self.left, self.top - self.bottom)
     }
+
+    /// Returns these bounds as a rectangle in the same y-up coordinate system.
+    ///
+    /// The origin of the rectangle is the bottom-left corner `(left, bottom)`, and its size is
+    /// `size()`. For a rectangle on a y-down surface, use `to_y_down()` instead.
+    #[inline]
+    pub fn to_rect(&self) -> Rect<i32> {
+        Rect::new(Point2D::new(self.left, self.bottom), self.size())
+    }
+
+    /// Returns these bounds as a rectangle in y-down coordinates whose origin is `ascent` pixels
+    /// above the baseline, directly above the pen position.
+    ///
+    /// The rectangle's origin is the glyph's top-left corner, `(left, ascent - top)`, and its
+    /// bottom edge, `max_y()`, is at `ascent - bottom`. With an ascent of 0, the origin is
+    /// `draw_offset()`. See `GlyphSubpixelBounds::to_y_down()`.
+    #[inline]
+    pub fn to_y_down(&self, ascent: i32) -> Rect<i32> {
+        Rect::new(Point2D::new(self.left, ascent - self.top), self.size())
+    }
+
+    /// Returns these bounds grown by `margin` pixels on every side.
+    ///
+    /// A negative margin shrinks the bounds.
+    #[inline]
+    pub fn expanded(&self, margin: i32) -> GlyphPixelBounds {
+        GlyphPixelBounds {
+            left: self.left - margin,
+            bottom: self.bottom - margin,
+            right: self.right + margin,
+            top: self.top + margin,
+        }
+    }
+
+    /// Returns the offset from the pen position on the baseline to the top-left corner of these
+    /// bounds, in y-down pixels: that is, `(left, -top)`.
+    ///
+    /// When drawing the glyph's atlas rectangle to a y-down surface with the pen at a whole pixel
+    /// position, the quad's top-left corner goes at the pen position plus this offset and its
+    /// size is `size()`. Since these bounds are rounded out, the quad covers the whole glyph.
+    #[inline]
+    pub fn draw_offset(&self) -> Point2D<i32> {
+        Point2D::new(self.left, -self.top)
+    }
+}
+
+/// The boundaries of the glyph in 26.6 fixed-point pixels: that is, in 1/64ths of a pixel.
+///
+/// This is the representation that FreeType uses for metrics such as the control box returned by
+/// `FT_Outline_Get_CBox()`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GlyphFixedBounds {
+    pub left: i32,
+    pub bottom: i32,
+    pub right: i32,
+    pub top: i32,
+}
+
+impl GlyphFixedBounds {
+    /// Rounds these bounds out to whole pixels the way FreeType's `FT_PIX_FLOOR()` and
+    /// `FT_PIX_CEIL()` do, as when computing the size of a bitmap to render into.
+    #[inline]
+    pub fn round_out(&self) -> GlyphPixelBounds {
+        GlyphPixelBounds {
+            left: self.left >> 6,
+            bottom: self.bottom >> 6,
+            right: (self.right + 63) >> 6,
+            top: (self.top + 63) >> 6,
+        }
+    }
+
+    /// Converts these bounds to fractional pixels.
+    #[inline]
+    pub fn to_subpixel(&self) -> GlyphSubpixelBounds {
+        GlyphSubpixelBounds {
+            left: self.left as f32 / 64.0,
+            bottom: self.bottom as f32 / 64.0,
+            right: self.right as f32 / 64.0,
+            top: self.top as f32 / 64.0,
+        }
+    }
+}
+
+impl From<GlyphSubpixelBounds> for Rect<f32> {
+    /// See `GlyphSubpixelBounds::to_rect()`.
+    #[inline]
+    fn from(bounds: GlyphSubpixelBounds) -> Rect<f32> {
+        bounds.to_rect()
+    }
+}
+
+impl From<GlyphPixelBounds> for Rect<i32> {
+    /// See `GlyphPixelBounds::to_rect()`.
+    #[inline]
+    fn from(bounds: GlyphPixelBounds) -> Rect<i32> {
+        bounds.to_rect()
+    }
 }
 
 /// The boundaries of a glyph in font units.
@@ -290,8 +2169,21 @@ pub struct GlyphBounds {
 impl GlyphBounds {
     /// Given the units per em of the font and the point size, returns the fractional boundaries of
     /// this glyph.
+    ///
+    /// If the point size is not positive or the units per em is zero, the result is an empty
+    /// rectangle at the origin.
     #[inline]
     pub fn subpixel_bounds(&self, units_per_em: u16, point_size: f32) -> GlyphSubpixelBounds {
+        // Don't let NaNs or infinities leak into the pixel bounds.
+        if !(point_size > 0.0) || units_per_em == 0 {
+            return GlyphSubpixelBounds {
+                left: 0.0,
+                bottom: 0.0,
+                right: 0.0,
+                top: 0.0,
+            }
+        }
+
         let pixels_per_unit = point_size / units_per_em as f32;
         GlyphSubpixelBounds {
             left: self.left as f32 * pixels_per_unit,
@@ -306,5 +2198,16 @@ impl GlyphBounds {
     pub fn size(&self) -> Size2D<i32> {
         Size2D::new(self.right - self.left, self.top - self.bottom)
     }
+
+    /// Returns these bounds as a rectangle in y-down font units whose origin is `ascent` units
+    /// above the baseline, directly above the glyph origin.
+    ///
+    /// The rectangle's origin is the glyph's top-left corner, `(left, ascent - top)`, and its
+    /// bottom edge, `max_y()`, is at `ascent - bottom`. Passing `Font::ascender()` gives
+    /// coordinates relative to the top of the line box. See `GlyphSubpixelBounds::to_y_down()`.
+    #[inline]
+    pub fn to_y_down(&self, ascent: i32) -> Rect<i32> {
+        Rect::new(Point2D::new(self.left, ascent - self.top), self.size())
+    }
 }
 
@@ -0,0 +1,307 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Detection and removal of overlaps between glyph contours by polygon union.
+
+use euclid::Point2D;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::i16;
+
+// How far to either side of an edge the winding number is sampled, in font units. This is far
+// smaller than a font unit, but far larger than the rounding error of the intersections.
+const SAMPLE_OFFSET: f64 = 1.0 / 256.0;
+
+// How close the endpoints of two edges must be for the edges to meet, in font units. Edges split
+// at the same intersection end at points that differ by rounding error.
+const MEETING_DISTANCE: f64 = SAMPLE_OFFSET / 16.0;
+
+// A piece of a polygon's edge that meets other edges only at its endpoints, with the winding
+// numbers of the regions to its left and right.
+struct Edge {
+    from: Point2D<f64>,
+    to: Point2D<f64>,
+    left_winding: i32,
+    right_winding: i32,
+}
+
+/// Returns true if the given closed polygons overlap.
+///
+/// Polygons overlap if some region is enclosed more than once under the nonzero winding rule, or
+/// if filled regions wound in opposite directions meet, as where a contour crosses itself. Holes
+/// that lie within the polygon that they cut out of don't count, nor do polygons wound the same
+/// way that only touch, nor separate polygons that are merely wound in different directions.
+pub fn has_overlaps(polygons: &[Vec<Point2D<f32>>]) -> bool {
+    overlapping(&split_edges(polygons))
+}
+
+/// Returns the union of the given closed polygons under the nonzero winding rule as polygons that
+/// don't overlap, or `None` if the polygons don't overlap already.
+///
+/// The result is in whole font units. Each polygon ends with its first point, and outer edges are
+/// wound clockwise and holes counterclockwise, as in TrueType outlines. Collinear points are
+/// kept.
+pub fn remove_overlaps(polygons: &[Vec<Point2D<f32>>]) -> Option<Vec<Vec<Point2D<i16>>>> {
+    let edges = split_edges(polygons);
+    if !overlapping(&edges) {
+        return None
+    }
+
+    // Keep the edges that separate filled regions from empty ones, directed so that the filled
+    // region is on the right. Edges that coincide once rounded are kept only once.
+    let mut boundary: Vec<(Point2D<i16>, Point2D<i16>)> = vec![];
+    let mut outgoing: HashMap<Point2D<i16>, Vec<usize>> = HashMap::new();
+    for edge in &edges {
+        let (from, to) = match (edge.left_winding != 0, edge.right_winding != 0) {
+            (false, true) => (round(&edge.from), round(&edge.to)),
+            (true, false) => (round(&edge.to), round(&edge.from)),
+            _ => continue,
+        };
+        if from == to {
+            continue
+        }
+        let edge_indices = match outgoing.entry(from) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(vec![]),
+        };
+        if edge_indices.iter().all(|&edge_index| boundary[edge_index].1 != to) {
+            edge_indices.push(boundary.len());
+            boundary.push((from, to))
+        }
+    }
+
+    // Link the edges into closed polygons. Where several edges leave a point, any choice yields
+    // the same filled region. Chains that don't close, which rounding can leave behind around
+    // slivers, are dropped.
+    let mut used = vec![false; boundary.len()];
+    let mut polygons = vec![];
+    for first_edge_index in 0..boundary.len() {
+        if used[first_edge_index] {
+            continue
+        }
+
+        let start = boundary[first_edge_index].0;
+        let mut polygon = vec![start];
+        let mut edge_index = first_edge_index;
+        loop {
+            used[edge_index] = true;
+            let to = boundary[edge_index].1;
+            polygon.push(to);
+            if to == start {
+                break
+            }
+            let next_index = outgoing.get(&to).and_then(|edge_indices| {
+                edge_indices.iter().cloned().find(|&next_index| !used[next_index])
+            });
+            match next_index {
+                Some(next_index) => edge_index = next_index,
+                None => {
+                    polygon.clear();
+                    break
+                }
+            }
+        }
+
+        if polygon.len() > 3 {
+            polygons.push(polygon)
+        }
+    }
+    Some(polygons)
+}
+
+// Returns true if the winding numbers around the edges show an overlap, as `has_overlaps()`
+// describes.
+fn overlapping(edges: &[Edge]) -> bool {
+    // Group the edges of filled regions that meet, so that each group bounds regions that are
+    // connected, if only at a point. Separate contours fall into separate groups.
+    let filled = |edge: &Edge| edge.left_winding != 0 || edge.right_winding != 0;
+    let mut groups: Vec<usize> = (0..edges.len()).collect();
+    for index in 0..edges.len() {
+        if !filled(&edges[index]) {
+            continue
+        }
+        for other_index in (index + 1)..edges.len() {
+            if filled(&edges[other_index]) && meet(&edges[index], &edges[other_index]) {
+                let group = find_group(&mut groups, index);
+                let other_group = find_group(&mut groups, other_index);
+                groups[other_group] = group
+            }
+        }
+    }
+
+    // Every filled region of a group must be wound the same way, and no region more than once.
+    let mut fill_windings = vec![0; edges.len()];
+    for (index, edge) in edges.iter().enumerate() {
+        let group = find_group(&mut groups, index);
+        for &winding in &[edge.left_winding, edge.right_winding] {
+            if winding == 0 {
+                continue
+            }
+            let fill_winding = fill_windings[group];
+            if winding.abs() > 1 || (fill_winding != 0 && winding != fill_winding) {
+                return true
+            }
+            fill_windings[group] = winding
+        }
+    }
+    false
+}
+
+// Returns true if the edges share an endpoint.
+fn meet(edge: &Edge, other: &Edge) -> bool {
+    let near = |a: &Point2D<f64>, b: &Point2D<f64>| {
+        (a.x - b.x).abs() <= MEETING_DISTANCE && (a.y - b.y).abs() <= MEETING_DISTANCE
+    };
+    near(&edge.from, &other.from) || near(&edge.from, &other.to) ||
+        near(&edge.to, &other.from) || near(&edge.to, &other.to)
+}
+
+// Returns the representative of the group of the edge with the given index, shortening the path
+// to it along the way.
+fn find_group(groups: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while groups[root] != root {
+        root = groups[root]
+    }
+    let mut index = index;
+    while groups[index] != root {
+        let next_index = groups[index];
+        groups[index] = root;
+        index = next_index
+    }
+    root
+}
+
+// Splits the edges of the polygons wherever they cross or touch, so that edges only meet at their
+// endpoints, and finds the winding numbers on either side of each piece.
+fn split_edges(polygons: &[Vec<Point2D<f32>>]) -> Vec<Edge> {
+    let mut lines = vec![];
+    for polygon in polygons {
+        let (first, last) = match (polygon.first(), polygon.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        let closing_line = if first != last { Some((*last, *first)) } else { None };
+        let polygon_lines = polygon.windows(2).map(|line| (line[0], line[1])).chain(closing_line);
+        for (from, to) in polygon_lines {
+            if from != to {
+                lines.push((to_f64(&from), to_f64(&to)))
+            }
+        }
+    }
+
+    // Find where along each line it meets the others, as a fraction of its length.
+    let mut splits: Vec<Vec<f64>> = vec![vec![]; lines.len()];
+    for line_index in 0..lines.len() {
+        for other_index in (line_index + 1)..lines.len() {
+            let (line, other) = (&lines[line_index], &lines[other_index]);
+            for &(t, u) in &intersections(line, other) {
+                if t > 0.0 && t < 1.0 {
+                    splits[line_index].push(t)
+                }
+                if u > 0.0 && u < 1.0 {
+                    splits[other_index].push(u)
+                }
+            }
+        }
+    }
+
+    let mut pieces = vec![];
+    for (&(from, to), splits) in lines.iter().zip(splits.iter_mut()) {
+        splits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut piece_from = from;
+        for &t in splits.iter().chain(Some(1.0).iter()) {
+            let piece_to = if t == 1.0 { to } else { from + (to - from) * t };
+            if piece_to != piece_from {
+                pieces.push((piece_from, piece_to))
+            }
+            piece_from = piece_to
+        }
+    }
+
+    pieces.iter().map(|&(from, to)| {
+        let vector = to - from;
+        let length = vector.dot(vector).sqrt();
+        let offset = Point2D::new(-vector.y, vector.x) * (SAMPLE_OFFSET / length);
+        let middle = (from + to) * 0.5;
+        Edge {
+            from: from,
+            to: to,
+            left_winding: winding_number(&pieces, &(middle + offset)),
+            right_winding: winding_number(&pieces, &(middle - offset)),
+        }
+    }).collect()
+}
+
+// Returns the fractions along each of two lines at which they meet: one point if they cross or
+// touch, or the endpoints of the shared stretch if they're collinear and overlap.
+fn intersections(line: &(Point2D<f64>, Point2D<f64>), other: &(Point2D<f64>, Point2D<f64>))
+                 -> Vec<(f64, f64)> {
+    let (from, vector) = (line.0, line.1 - line.0);
+    let (other_from, other_vector) = (other.0, other.1 - other.0);
+    let offset = other_from - from;
+    let denominator = cross(&vector, &other_vector);
+
+    if denominator != 0.0 {
+        let t = cross(&offset, &other_vector) / denominator;
+        let u = cross(&offset, &vector) / denominator;
+        return if t >= 0.0 && t <= 1.0 && u >= 0.0 && u <= 1.0 { vec![(t, u)] } else { vec![] }
+    }
+
+    // The lines are parallel. If they're collinear, each is split where the other ends.
+    if cross(&offset, &vector) != 0.0 {
+        return vec![]
+    }
+    let length_squared = vector.dot(vector);
+    let other_length_squared = other_vector.dot(other_vector);
+    let mut intersections = vec![];
+    for &endpoint in &[other.0, other.1] {
+        let t = (endpoint - from).dot(vector) / length_squared;
+        if t > 0.0 && t < 1.0 {
+            intersections.push((t, -1.0))
+        }
+    }
+    for &endpoint in &[line.0, line.1] {
+        let u = (endpoint - other_from).dot(other_vector) / other_length_squared;
+        if u > 0.0 && u < 1.0 {
+            intersections.push((-1.0, u))
+        }
+    }
+    intersections
+}
+
+// Returns the number of times the edges wind counterclockwise around the point.
+fn winding_number(edges: &[(Point2D<f64>, Point2D<f64>)], point: &Point2D<f64>) -> i32 {
+    let mut winding = 0;
+    for &(from, to) in edges {
+        let side = cross(&(to - from), &(*point - from));
+        if from.y <= point.y {
+            if to.y > point.y && side > 0.0 {
+                winding += 1
+            }
+        } else if to.y <= point.y && side < 0.0 {
+            winding -= 1
+        }
+    }
+    winding
+}
+
+fn cross(a: &Point2D<f64>, b: &Point2D<f64>) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+fn to_f64(point: &Point2D<f32>) -> Point2D<f64> {
+    Point2D::new(point.x as f64, point.y as f64)
+}
+
+fn round(point: &Point2D<f64>) -> Point2D<i16> {
+    let clamp = |value: f64| value.round().max(i16::MIN as f64).min(i16::MAX as f64) as i16;
+    Point2D::new(clamp(point.x), clamp(point.y))
+}
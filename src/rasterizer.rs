@@ -10,20 +10,25 @@
 
 //! A GPU rasterizer for glyphs.
 
-use atlas::Atlas;
+use atlas::{Atlas, AtlasBuilder, GlyphInstanceBuffer};
+use compute_shader::buffer::Protection;
 use compute_shader::device::Device;
-use compute_shader::image::{Format, Image};
+use compute_shader::image::{Color, ExternalImage, Format, Image};
 use compute_shader::instance::{Instance, ShadingLanguage};
 use compute_shader::profile_event::ProfileEvent;
 use compute_shader::program::Program;
 use compute_shader::queue::{Queue, Uniform};
-use coverage::CoverageBuffer;
+use coverage::{AccumulationMode, ColorSpace, CoverageBuffer};
 use error::{InitError, RasterError};
 use euclid::rect::Rect;
+use euclid::{Point2D, Size2D};
 use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint, GLvoid};
 use gl;
-use outline::{Outlines, Vertex};
+use otf::Font;
+use outline::{OutlineBuilder, Outlines, StorageKind, Vertex};
+use rect_packer;
 use std::ascii::AsciiExt;
+use std::cmp;
 use std::env;
 use std::fs::File;
 use std::io::Read;
@@ -50,11 +55,17 @@ pub struct Rasterizer {
     draw_program: GLuint,
     accum_program_r8: Program,
     accum_program_rgba8: Program,
+    accum_program_r32f: Program,
     draw_vertex_array: GLuint,
     draw_position_attribute: GLint,
     draw_glyph_index_attribute: GLint,
+    draw_instance_image_attribute: GLint,
     draw_atlas_size_uniform: GLint,
+    draw_instanced_uniform: GLint,
+    draw_storage_buffer_uniform: GLint,
+    draw_curve_tolerance_uniform: GLint,
     draw_glyph_descriptors_uniform: GLuint,
+    draw_glyph_descriptors_storage_block: Option<GLuint>,
     draw_image_descriptors_uniform: GLuint,
     draw_query: GLuint,
     options: RasterizerOptions,
@@ -88,8 +99,10 @@ impl Rasterizer {
     pub fn new(instance: &Instance, device: Device, queue: Queue, options: RasterizerOptions)
                -> Result<Rasterizer, InitError> {
         let (draw_program, draw_position_attribute, draw_glyph_index_attribute);
+        let draw_instance_image_attribute;
         let (draw_glyph_descriptors_uniform, draw_image_descriptors_uniform);
-        let draw_atlas_size_uniform;
+        let (draw_atlas_size_uniform, draw_instanced_uniform, draw_curve_tolerance_uniform);
+        let (draw_storage_buffer_uniform, draw_glyph_descriptors_storage_block);
         let (mut draw_vertex_array, mut draw_query) = (0, 0);
         unsafe {
             draw_program = gl::CreateProgram();
@@ -138,9 +151,20 @@ impl Rasterizer {
                 gl::GetAttribLocation(draw_program, b"aPosition\0".as_ptr() as *const GLchar);
             draw_glyph_index_attribute =
                 gl::GetAttribLocation(draw_program, b"aGlyphIndex\0".as_ptr() as *const GLchar);
+            draw_instance_image_attribute =
+                gl::GetAttribLocation(draw_program,
+                                      b"aInstanceImage\0".as_ptr() as *const GLchar);
 
             draw_atlas_size_uniform =
                 gl::GetUniformLocation(draw_program, b"uAtlasSize\0".as_ptr() as *const GLchar);
+            draw_instanced_uniform =
+                gl::GetUniformLocation(draw_program, b"uInstanced\0".as_ptr() as *const GLchar);
+            draw_storage_buffer_uniform =
+                gl::GetUniformLocation(draw_program,
+                                       b"uStorageBuffer\0".as_ptr() as *const GLchar);
+            draw_curve_tolerance_uniform =
+                gl::GetUniformLocation(draw_program,
+                                       b"uCurveTolerance\0".as_ptr() as *const GLchar);
             draw_glyph_descriptors_uniform =
                 gl::GetUniformBlockIndex(draw_program,
                                          b"ubGlyphDescriptors\0".as_ptr() as *const GLchar);
@@ -148,6 +172,24 @@ impl Rasterizer {
                 gl::GetUniformBlockIndex(draw_program,
                                          b"ubImageDescriptors\0".as_ptr() as *const GLchar);
 
+            // The draw shader only declares the storage block if the driver supports storage
+            // buffers, and the functions to bind it are only loaded in that case too.
+            draw_glyph_descriptors_storage_block =
+                if gl::GetProgramResourceIndex::is_loaded() &&
+                        gl::ShaderStorageBlockBinding::is_loaded() {
+                    let index = gl::GetProgramResourceIndex(draw_program,
+                                                            gl::SHADER_STORAGE_BLOCK,
+                                                            b"bGlyphDescriptors\0".as_ptr() as
+                                                            *const GLchar);
+                    if index != gl::INVALID_INDEX {
+                        Some(index)
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                };
+
             gl::GenQueries(1, &mut draw_query)
         }
 
@@ -194,11 +236,16 @@ impl Rasterizer {
         let accum_source_rgba8 = format!("{}\n#define IMAGE_FORMAT rgba8\n{}",
                                          compute_preamble_source,
                                          accum_source);
+        let accum_source_r32f = format!("{}\n#define IMAGE_FORMAT r32f\n{}",
+                                        compute_preamble_source,
+                                        accum_source);
 
         let accum_program_r8 = try!(device.create_program(&accum_source_r8)
                                           .map_err(InitError::ComputeError));
         let accum_program_rgba8 = try!(device.create_program(&accum_source_rgba8)
                                              .map_err(InitError::ComputeError));
+        let accum_program_r32f = try!(device.create_program(&accum_source_r32f)
+                                            .map_err(InitError::ComputeError));
 
         Ok(Rasterizer {
             device: device,
@@ -207,11 +254,17 @@ impl Rasterizer {
             draw_program: draw_program,
             accum_program_r8: accum_program_r8,
             accum_program_rgba8: accum_program_rgba8,
+            accum_program_r32f: accum_program_r32f,
             draw_vertex_array: draw_vertex_array,
             draw_position_attribute: draw_position_attribute,
             draw_glyph_index_attribute: draw_glyph_index_attribute,
+            draw_instance_image_attribute: draw_instance_image_attribute,
             draw_atlas_size_uniform: draw_atlas_size_uniform,
+            draw_instanced_uniform: draw_instanced_uniform,
+            draw_storage_buffer_uniform: draw_storage_buffer_uniform,
+            draw_curve_tolerance_uniform: draw_curve_tolerance_uniform,
             draw_glyph_descriptors_uniform: draw_glyph_descriptors_uniform,
+            draw_glyph_descriptors_storage_block: draw_glyph_descriptors_storage_block,
             draw_image_descriptors_uniform: draw_image_descriptors_uniform,
             draw_query: draw_query,
             options: options,
@@ -229,7 +282,12 @@ impl Rasterizer {
     /// * `outlines` specifies the outlines for the font associated with that atlas.
     ///
     /// * `coverage_buffer` is a coverage buffer to use (see `CoverageBuffer`). This can be reused
-    ///   from call to call. It must be at least as large as the atlas.
+    ///   from call to call. It must be at least as large as `rect`; if it isn't, nothing is drawn
+    ///   and `RasterError::CoverageTooSmall` is returned.
+    ///
+    /// Glyphs are antialiased with `AntialiasMode::Grayscale`. To choose another mode, use
+    /// `draw_atlas_with_antialiasing()`.
+    #[inline]
     pub fn draw_atlas(&self,
                       image: &Image,
                       rect: &Rect<u32>,
@@ -237,14 +295,178 @@ impl Rasterizer {
                       outlines: &Outlines,
                       coverage_buffer: &CoverageBuffer)
                       -> Result<DrawAtlasProfilingEvents, RasterError> {
+        self.draw_atlas_with_antialiasing(image,
+                                          rect,
+                                          atlas,
+                                          outlines,
+                                          coverage_buffer,
+                                          AntialiasMode::Grayscale)
+    }
+
+    /// Draws the supplied font atlas into the texture image at the given location, antialiasing
+    /// the glyphs as `antialias_mode` specifies.
+    ///
+    /// The other arguments are as in `draw_atlas()`.
+    #[inline]
+    pub fn draw_atlas_with_antialiasing(&self,
+                                        image: &Image,
+                                        rect: &Rect<u32>,
+                                        atlas: &Atlas,
+                                        outlines: &Outlines,
+                                        coverage_buffer: &CoverageBuffer,
+                                        antialias_mode: AntialiasMode)
+                                        -> Result<DrawAtlasProfilingEvents, RasterError> {
+        let options = DrawAtlasOptions {
+            antialias_mode: antialias_mode,
+            ..DrawAtlasOptions::default()
+        };
+        self.draw_atlas_with_options(image, rect, atlas, outlines, coverage_buffer, &options)
+    }
+
+    /// Draws the supplied font atlas into the texture image at the given location, as `options`
+    /// specifies.
+    ///
+    /// The other arguments are as in `draw_atlas()`, except that if `options.coverage_region` is
+    /// set, only that region of `coverage_buffer` needs to be at least as large as `rect`.
+    pub fn draw_atlas_with_options(&self,
+                                   image: &Image,
+                                   rect: &Rect<u32>,
+                                   atlas: &Atlas,
+                                   outlines: &Outlines,
+                                   coverage_buffer: &CoverageBuffer,
+                                   options: &DrawAtlasOptions)
+                                   -> Result<DrawAtlasProfilingEvents, RasterError> {
+        let coverage_region = try!(self.draw_atlas_coverage(rect,
+                                                            atlas,
+                                                            outlines,
+                                                            coverage_buffer,
+                                                            options));
+        self.accumulate(image,
+                        rect,
+                        atlas.shelf_height(),
+                        atlas.shelf_columns(),
+                        coverage_buffer,
+                        &coverage_region,
+                        options)
+    }
+
+    // Draws the edges of the glyphs in the atlas into the coverage buffer, without accumulating
+    // them. Returns the region of the coverage buffer drawn into. See `Outlines::rasterize()`.
+    #[doc(hidden)]
+    pub fn draw_atlas_coverage(&self,
+                               rect: &Rect<u32>,
+                               atlas: &Atlas,
+                               outlines: &Outlines,
+                               coverage_buffer: &CoverageBuffer,
+                               options: &DrawAtlasOptions)
+                               -> Result<Rect<u32>, RasterError> {
+        let coverage_region = try!(validate_coverage_region(rect, coverage_buffer, options));
+        try!(self.draw_coverage(rect,
+                                outlines,
+                                coverage_buffer,
+                                &coverage_region,
+                                options,
+                                None,
+                                |primitive| unsafe { atlas.draw(primitive) }));
+        Ok(coverage_region)
+    }
+
+    /// Draws every glyph instance in `instances` into the texture image at the given location,
+    /// with one draw call per distinct glyph.
+    ///
+    /// This is an alternative to drawing an atlas for large amounts of text, such as a paragraph
+    /// laid out directly into `image`, where the same glyphs appear many times. Each instance's
+    /// origin is relative to the top left of `rect`, and parts of glyphs outside `rect` are
+    /// clipped. The other arguments are as in `draw_atlas_with_options()`.
+    ///
+    /// The instanced path accumulates coverage down whole columns of `rect` rather than shelf by
+    /// shelf, so it parallelizes less well than an atlas of the same size on the GPU.
+    pub fn draw_instances(&self,
+                          image: &Image,
+                          rect: &Rect<u32>,
+                          instances: &GlyphInstanceBuffer,
+                          outlines: &Outlines,
+                          coverage_buffer: &CoverageBuffer,
+                          options: &DrawAtlasOptions)
+                          -> Result<DrawAtlasProfilingEvents, RasterError> {
+        let coverage_region = try!(validate_coverage_region(rect, coverage_buffer, options));
+        let instance_attribute = self.draw_instance_image_attribute as GLuint;
+        try!(self.draw_coverage(rect,
+                                outlines,
+                                coverage_buffer,
+                                &coverage_region,
+                                options,
+                                Some(instance_attribute),
+                                |primitive| unsafe {
+                                    instances.draw(primitive, instance_attribute)
+                                }));
+
+        // The instances may be anywhere in the rectangle, so treat it as one tall shelf.
+        self.accumulate(image,
+                        rect,
+                        rect.size.height,
+                        rect.size.width,
+                        coverage_buffer,
+                        &coverage_region,
+                        options)
+    }
+
+    // Draws the glyph edges into the coverage buffer with `draw`, which receives the primitive
+    // type, and times it with the draw query. With an instance attribute, the draw shaders read
+    // each glyph's image descriptor from that attribute instead of from the atlas's uniform
+    // buffer. Drawing is clipped to `options.clip_rect`, if any.
+    fn draw_coverage<F>(&self,
+                        rect: &Rect<u32>,
+                        outlines: &Outlines,
+                        coverage_buffer: &CoverageBuffer,
+                        coverage_region: &Rect<u32>,
+                        options: &DrawAtlasOptions,
+                        instance_attribute: Option<GLuint>,
+                        draw: F)
+                        -> Result<(), RasterError>
+                        where F: FnOnce(GLenum) {
+        if !outlines.has_dummy_vertex() {
+            return Err(RasterError::MissingDummyVertex)
+        }
+
+        let storage_block = match (outlines.storage(), self.draw_glyph_descriptors_storage_block) {
+            (StorageKind::Ubo, _) => None,
+            (StorageKind::Ssbo, Some(storage_block)) => Some(storage_block),
+            (StorageKind::Ssbo, None) => return Err(RasterError::StorageBuffersUnsupported),
+        };
+
         unsafe {
+            // Save the viewport and scissor state so that we can put them back afterward.
+            let mut old_viewport = [0; 4];
+            let mut old_scissor_box = [0; 4];
+            gl::GetIntegerv(gl::VIEWPORT, old_viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::SCISSOR_BOX, old_scissor_box.as_mut_ptr());
+            let old_scissor_test = gl::IsEnabled(gl::SCISSOR_TEST);
+
             gl::BindFramebuffer(gl::FRAMEBUFFER, coverage_buffer.framebuffer());
-            gl::Viewport(0, 0, rect.size.width as GLint, rect.size.height as GLint);
 
-            // TODO(pcwalton): Scissor to the image rect to clear faster?
+            // Restrict both drawing and clearing to the part of the coverage region that the
+            // atlas covers, leaving the rest of the coverage buffer untouched.
+            let (x, y) = (coverage_region.origin.x as GLint, coverage_region.origin.y as GLint);
+            let (width, height) = (rect.size.width as GLsizei, rect.size.height as GLsizei);
+            gl::Viewport(x, y, width, height);
+            gl::Scissor(x, y, width, height);
+            gl::Enable(gl::SCISSOR_TEST);
+
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
+            // Clip drawing, but not the clear above, so that the coverage outside the clip
+            // rectangle is zero rather than left over from an earlier draw.
+            if let Some(ref clip_rect) = options.clip_rect {
+                let clip_rect = clip_rect.intersection(&Rect::new(Point2D::zero(), rect.size))
+                                         .unwrap_or(Rect::zero());
+                gl::Scissor(x + clip_rect.origin.x as GLint,
+                            y + clip_rect.origin.y as GLint,
+                            clip_rect.size.width as GLsizei,
+                            clip_rect.size.height as GLsizei);
+            }
+
             gl::BindVertexArray(self.draw_vertex_array);
             gl::UseProgram(self.draw_program);
 
@@ -265,13 +487,41 @@ impl Rasterizer {
 
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, outlines.indices_buffer());
 
+            // The instance buffer binds itself to the instance attribute as it draws.
+            match instance_attribute {
+                None => {
+                    gl::DisableVertexAttribArray(self.draw_instance_image_attribute as GLuint);
+                    gl::Uniform1i(self.draw_instanced_uniform, 0);
+                }
+                Some(instance_attribute) => {
+                    gl::EnableVertexAttribArray(instance_attribute);
+                    gl::VertexAttribDivisor(instance_attribute, 1);
+                    gl::Uniform1i(self.draw_instanced_uniform, 1);
+                }
+            }
+
             // Don't bind the atlas uniform buffers (binding point 2) here; the batches will do
             // that on their own.
             gl::BindBufferBase(gl::UNIFORM_BUFFER, 1, outlines.descriptors_buffer());
             gl::UniformBlockBinding(self.draw_program, self.draw_glyph_descriptors_uniform, 1);
             gl::UniformBlockBinding(self.draw_program, self.draw_image_descriptors_uniform, 2);
 
+            // The uniform block stays active even when the descriptors come from the storage
+            // block, so it's left backed by the same buffer.
+            match storage_block {
+                None => gl::Uniform1i(self.draw_storage_buffer_uniform, 0),
+                Some(storage_block) => {
+                    gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER,
+                                       1,
+                                       outlines.descriptors_buffer());
+                    gl::ShaderStorageBlockBinding(self.draw_program, storage_block, 1);
+                    gl::Uniform1i(self.draw_storage_buffer_uniform, 1);
+                }
+            }
+
             gl::Uniform2ui(self.draw_atlas_size_uniform, rect.size.width, rect.size.height);
+            gl::Uniform1f(self.draw_curve_tolerance_uniform,
+                          options.antialias_mode.curve_tolerance());
 
             gl::PatchParameteri(gl::PATCH_VERTICES, 3);
 
@@ -296,12 +546,26 @@ impl Rasterizer {
 
             // Now draw the glyph ranges.
             gl::BeginQuery(gl::TIME_ELAPSED, self.draw_query);
-            atlas.draw(primitive);
+            draw(primitive);
             gl::EndQuery(gl::TIME_ELAPSED);
 
+            if let Some(instance_attribute) = instance_attribute {
+                gl::VertexAttribDivisor(instance_attribute, 0);
+                gl::DisableVertexAttribArray(instance_attribute);
+            }
+
             gl::Disable(gl::CULL_FACE);
             gl::Disable(gl::BLEND);
 
+            gl::Viewport(old_viewport[0], old_viewport[1], old_viewport[2], old_viewport[3]);
+            gl::Scissor(old_scissor_box[0],
+                        old_scissor_box[1],
+                        old_scissor_box[2],
+                        old_scissor_box[3]);
+            if old_scissor_test == gl::FALSE {
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
 
             // FIXME(pcwalton): We should have some better synchronization here if we're using
@@ -314,24 +578,70 @@ impl Rasterizer {
             }
         }
 
+        Ok(())
+    }
+
+    // Sums the coverage deltas down each column of each shelf and writes the result into
+    // `image`. `shelf_columns` is the number of columns of all shelves together.
+    fn accumulate(&self,
+                  image: &Image,
+                  rect: &Rect<u32>,
+                  shelf_height: u32,
+                  shelf_columns: u32,
+                  coverage_buffer: &CoverageBuffer,
+                  coverage_region: &Rect<u32>,
+                  options: &DrawAtlasOptions)
+                  -> Result<DrawAtlasProfilingEvents, RasterError> {
+        let signed_area = coverage_buffer.accumulation_mode() == AccumulationMode::SignedArea;
+        let premultiplied_color = coverage_buffer.premultiplied_color();
+        let color = premultiplied_color.unwrap_or([0; 4]);
+        let color = [color[0] as u32, color[1] as u32, color[2] as u32, color[3] as u32];
+        let antialias = options.antialias_mode != AntialiasMode::None;
+
+        // The coverage floor goes to the shader in 16-bit fixed point, since there are no float
+        // uniforms.
+        let min_coverage = (options.min_coverage.max(0.0).min(1.0) * 65535.0).round() as u32;
+
+        // Without layer images, bind placeholders that the shader never touches, since OpenCL
+        // requires every kernel argument to be set.
+        let layer_images = coverage_buffer.layer_images_to_composite();
+        let composite = layer_images.is_some();
+        let (layers_in, layers_out) = match layer_images {
+            Some((layers_in, layers_out)) => (layers_in, layers_out),
+            None => (coverage_buffer.image(), image),
+        };
         let accum_uniforms = [
             (0, Uniform::Image(image)),
             (1, Uniform::Image(coverage_buffer.image())),
             (2, Uniform::UVec4([rect.origin.x, rect.origin.y, rect.max_x(), rect.max_y()])),
-            (3, Uniform::U32(atlas.shelf_height())),
+            (3, Uniform::U32(shelf_height)),
+            (4, Uniform::UVec4(color)),
+            (5, Uniform::U32(premultiplied_color.is_some() as u32)),
+            (6, Uniform::U32(antialias as u32)),
+            (7, Uniform::UVec4([coverage_region.origin.x, coverage_region.origin.y, 0, 0])),
+            (8, Uniform::U32((coverage_buffer.color_space() == ColorSpace::Srgb) as u32)),
+            (9, Uniform::U32(signed_area as u32)),
+            (10, Uniform::Image(layers_in)),
+            (11, Uniform::Image(layers_out)),
+            (12, Uniform::U32(composite as u32)),
+            (13, Uniform::U32(min_coverage)),
         ];
 
-        let accum_program = match image.format() {
-            Ok(Format::R8) => &self.accum_program_r8,
-            Ok(Format::RGBA8) => &self.accum_program_rgba8,
-            Ok(_) => return Err(RasterError::UnsupportedImageFormat),
-            Err(err) => return Err(RasterError::ComputeError(err)),
+        let accum_program = match (image.format(), premultiplied_color, signed_area, composite) {
+            (Ok(Format::R32F), _, true, false) => &self.accum_program_r32f,
+            (Ok(Format::R8), None, false, false) => &self.accum_program_r8,
+            (Ok(Format::RGBA8), _, false, _) => &self.accum_program_rgba8,
+            (Ok(_), _, _, _) => return Err(RasterError::UnsupportedImageFormat),
+            (Err(err), _, _, _) => return Err(RasterError::ComputeError(err)),
         };
 
         let accum_event = try!(self.queue.submit_compute(accum_program,
-                                                         &[atlas.shelf_columns()],
+                                                         &[shelf_columns],
                                                          &accum_uniforms,
                                                          &[]).map_err(RasterError::ComputeError));
+        if composite {
+            coverage_buffer.swap_layer_images()
+        }
 
         Ok(DrawAtlasProfilingEvents {
             draw: self.draw_query,
@@ -339,6 +649,144 @@ impl Rasterizer {
         })
     }
 
+    /// Rasterizes a single glyph and reads its coverage back to the CPU.
+    ///
+    /// This is a convenience for batch uses such as thumbnails and test baselines; it sets up and
+    /// tears down its own outlines, atlas, and coverage buffer, so it's much slower per glyph than
+    /// drawing an atlas. Returns the size of the glyph's pixel bounds and its 8-bit coverage, one
+    /// byte per pixel, in rows from top to bottom with no padding between them. A glyph with no
+    /// outline has an empty size and no pixels. A glyph too large at this size for any coverage
+    /// buffer yields `RasterError::SizeTooLarge`.
+    pub fn rasterize_glyph(&self, font: &Font, glyph_id: u16, point_size: f32)
+                           -> Result<(Size2D<u32>, Vec<u8>), RasterError> {
+        let mut outline_builder = OutlineBuilder::new();
+        let glyph_index = try!(outline_builder.add_glyph(font, glyph_id)
+                                              .map_err(RasterError::FontError));
+        let outlines = try!(outline_builder.create_buffers().map_err(RasterError::GlError));
+
+        let glyph_size = outlines.glyph_pixel_bounds(glyph_index, point_size).size();
+        let glyph_size = Size2D::new(glyph_size.width.max(0) as u32,
+                                     glyph_size.height.max(0) as u32);
+        if glyph_size.width == 0 || glyph_size.height == 0 {
+            return Ok((glyph_size, vec![]))
+        }
+
+        // Make an atlas just big enough for the glyph and its gutter. The glyph always fits.
+        let padding = rect_packer::DEFAULT_PADDING;
+        let atlas_size = glyph_size + Size2D::new(padding * 2, padding * 2);
+        let mut atlas_builder = AtlasBuilder::new(atlas_size.width, atlas_size.height);
+        atlas_builder.pack_glyph(&outlines, glyph_index, point_size).unwrap();
+        let atlas = try!(atlas_builder.create_atlas().map_err(RasterError::GlError));
+
+        let coverage_buffer = match CoverageBuffer::new(&self.device, &atlas_size) {
+            Ok(coverage_buffer) => coverage_buffer,
+            Err(InitError::GlError(err)) => return Err(RasterError::GlError(err)),
+            Err(InitError::ComputeError(err)) => return Err(RasterError::ComputeError(err)),
+            Err(InitError::SizeTooLarge { requested, max }) => {
+                return Err(RasterError::SizeTooLarge {
+                    requested: requested,
+                    max: max,
+                })
+            }
+            // Creating a coverage buffer loads no shaders and reads no settings.
+            Err(InitError::ShaderUnreadable(_)) |
+            Err(InitError::CompileFailed(..)) |
+            Err(InitError::LinkFailed(_)) |
+            Err(InitError::InvalidSetting) => unreachable!(),
+        };
+        let image = try!(self.device
+                             .create_image(Format::R8, Protection::ReadWrite, &atlas_size)
+                             .map_err(RasterError::ComputeError));
+        try!(self.queue.submit_clear(&image, &Color::UInt(0, 0, 0, 0), &[])
+                       .map_err(RasterError::ComputeError));
+
+        let rect = Rect::new(Point2D::new(0, 0), atlas_size);
+        try!(self.draw_atlas(&image, &rect, &atlas, &outlines, &coverage_buffer));
+
+        // Read the glyph back without the gutter.
+        let glyph_rect = Rect::new(Point2D::new(padding, padding), glyph_size);
+        let pixels = try!(self.read_image(&image, &glyph_rect));
+        Ok((glyph_size, pixels))
+    }
+
+    /// Waits for drawing to finish and reads the given rectangle of an image back to the CPU.
+    ///
+    /// The image must have the format `Format::R8`, which gives one byte per pixel, or
+    /// `Format::RGBA8`, which gives four; other formats return
+    /// `RasterError::UnsupportedImageFormat`. The pixels are returned in rows from top to bottom
+    /// with no padding between them; parts of the rectangle that lie outside the image are zero.
+    /// This goes through an OpenGL texture and reads the whole image, so it's meant for tools and
+    /// tests rather than for every frame.
+    pub fn read_image(&self, image: &Image, rect: &Rect<u32>) -> Result<Vec<u8>, RasterError> {
+        let (gl_format, bytes_per_pixel) = match image.format() {
+            Ok(Format::R8) => (gl::RED, 1),
+            Ok(Format::RGBA8) => (gl::RGBA, 4),
+            Ok(_) => return Err(RasterError::UnsupportedImageFormat),
+            Err(err) => return Err(RasterError::ComputeError(err)),
+        };
+        try!(self.queue.finish().map_err(RasterError::ComputeError));
+
+        let mut image_pixels = vec![];
+        let image_width;
+        unsafe {
+            if self.shading_language == ShadingLanguage::Glsl {
+                gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT |
+                                  gl::TEXTURE_UPDATE_BARRIER_BIT);
+            }
+
+            let mut gl_texture = 0;
+            gl::GenTextures(1, &mut gl_texture);
+            let result = image.bind_to(&ExternalImage::GlTexture(gl_texture));
+            let (mut width, mut height) = (0, 0);
+            if result.is_ok() {
+                gl::BindTexture(gl::TEXTURE_RECTANGLE, gl_texture);
+                gl::GetTexLevelParameteriv(gl::TEXTURE_RECTANGLE, 0, gl::TEXTURE_WIDTH, &mut width);
+                gl::GetTexLevelParameteriv(gl::TEXTURE_RECTANGLE,
+                                           0,
+                                           gl::TEXTURE_HEIGHT,
+                                           &mut height);
+                image_pixels = vec![0; width as usize * height as usize * bytes_per_pixel];
+                gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl::GetTexImage(gl::TEXTURE_RECTANGLE,
+                                0,
+                                gl_format,
+                                gl::UNSIGNED_BYTE,
+                                image_pixels.as_mut_ptr() as *mut GLvoid);
+                gl::BindTexture(gl::TEXTURE_RECTANGLE, 0);
+            }
+            gl::DeleteTextures(1, &mut gl_texture);
+            try!(result.map_err(RasterError::ComputeError));
+            image_width = width as usize;
+        }
+
+        // Crop out the rectangle. Any part of it beyond the edges of the image reads as zero, so
+        // every row is `rect.size.width` pixels wide.
+        let image_height = image_pixels.len() / cmp::max(image_width * bytes_per_pixel, 1);
+        let (width, height) = (rect.size.width as usize, rect.size.height as usize);
+        let (left, top) = (rect.origin.x as usize, rect.origin.y as usize);
+        let right = cmp::min(left.saturating_add(width), image_width);
+        let bottom = cmp::min(top.saturating_add(height), image_height);
+        let left = cmp::min(left, right);
+        let mut pixels = vec![0; width * height * bytes_per_pixel];
+        for y in top..bottom {
+            let row_start = (y * image_width + left) * bytes_per_pixel;
+            let row_end = (y * image_width + right) * bytes_per_pixel;
+            let dest_start = (y - top) * width * bytes_per_pixel;
+            pixels[dest_start..(dest_start + row_end - row_start)]
+                .copy_from_slice(&image_pixels[row_start..row_end]);
+        }
+        Ok(pixels)
+    }
+
+    /// Returns true if this rasterizer can draw outlines whose glyph descriptors were uploaded
+    /// with `StorageKind::Ssbo`.
+    ///
+    /// This needs OpenGL 4.3 or the `ARB_shader_storage_buffer_object` extension.
+    #[inline]
+    pub fn supports_storage_buffers(&self) -> bool {
+        self.draw_glyph_descriptors_storage_block.is_some()
+    }
+
     /// Returns the GPU compute device that this rasterizer is using.
     #[inline]
     pub fn device(&self) -> &Device {
@@ -352,6 +800,32 @@ impl Rasterizer {
     }
 }
 
+// Returns the region of the coverage buffer to draw into, or an error if the buffer or region is
+// too small for `rect`.
+fn validate_coverage_region(rect: &Rect<u32>,
+                            coverage_buffer: &CoverageBuffer,
+                            options: &DrawAtlasOptions)
+                            -> Result<Rect<u32>, RasterError> {
+    let coverage_size = coverage_buffer.size();
+    let coverage_region = options.coverage_region
+                                 .unwrap_or(Rect::new(Point2D::zero(), coverage_size));
+    if coverage_region.max_x() > coverage_size.width ||
+            coverage_region.max_y() > coverage_size.height {
+        return Err(RasterError::CoverageTooSmall {
+            required: Size2D::new(coverage_region.max_x(), coverage_region.max_y()),
+            actual: coverage_size,
+        })
+    }
+    if rect.size.width > coverage_region.size.width ||
+            rect.size.height > coverage_region.size.height {
+        return Err(RasterError::CoverageTooSmall {
+            required: rect.size,
+            actual: coverage_region.size,
+        })
+    }
+    Ok(coverage_region)
+}
+
 fn compile_gl_shader(shader_type: GLuint,
                      description: &'static str,
                      filename: &str,
@@ -409,6 +883,94 @@ fn check_gl_object_status(object: GLuint,
     }
 }
 
+/// How the edges of glyphs are antialiased by `Rasterizer::draw_atlas_with_antialiasing()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AntialiasMode {
+    /// No antialiasing. Each pixel is either fully covered or empty, depending on whether at least
+    /// half of its area is inside the glyph.
+    ///
+    /// This is useful for bitmap-style rendering and for tests that compare exact pixel values.
+    None,
+    /// Exact fractional area coverage. This is the default.
+    Grayscale,
+    /// Exact fractional area coverage, with curves approximated by about twice as many line
+    /// segments as `Grayscale` uses.
+    ///
+    /// The coverage of each pixel is already the exact area of the pixel inside the outline,
+    /// which is what box-filtered supersampling converges to, so supersampling wouldn't improve
+    /// it. The remaining error comes from flattening curves into lines, which this mode reduces
+    /// at some cost in speed, mostly noticeable at large sizes.
+    HighQuality,
+}
+
+impl Default for AntialiasMode {
+    #[inline]
+    fn default() -> AntialiasMode {
+        AntialiasMode::Grayscale
+    }
+}
+
+impl AntialiasMode {
+    // The value of `uCurveTolerance` in the draw shaders. The number of lines that a curve is
+    // divided into grows with the fourth root of this value.
+    #[inline]
+    fn curve_tolerance(self) -> f32 {
+        match self {
+            AntialiasMode::None | AntialiasMode::Grayscale => 3.0,
+            AntialiasMode::HighQuality => 48.0,
+        }
+    }
+}
+
+/// Per-draw options for `Rasterizer::draw_atlas_with_options()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DrawAtlasOptions {
+    /// How the edges of glyphs are antialiased. The default is `AntialiasMode::Grayscale`.
+    pub antialias_mode: AntialiasMode,
+    /// The region of the coverage buffer to render into, in coverage buffer pixels.
+    ///
+    /// Coverage is written only to the top-left part of this region that is as large as the
+    /// destination rect; the rest of the coverage buffer is neither cleared nor drawn to. This
+    /// lets several atlases share one large coverage buffer. The region must lie inside the
+    /// coverage buffer and be at least as large as the destination rect.
+    ///
+    /// The default, `None`, uses the whole coverage buffer.
+    pub coverage_region: Option<Rect<u32>>,
+    /// The rectangle to clip drawing to, in pixels relative to the top left of the destination
+    /// rect: that is, in the same coordinates as the positions of glyphs in the atlas.
+    ///
+    /// This is applied with a scissor rectangle in the coverage buffer as each atlas is drawn,
+    /// so no coverage is written outside it, and the pixels of the destination rect outside it
+    /// come out empty. The clip rectangle is intersected with the destination rect, so it may
+    /// extend past it; one that lies entirely outside clips everything. This suits text in
+    /// scrolling regions, where glyphs straddle the edge of the visible area.
+    ///
+    /// The default, `None`, doesn't clip.
+    pub clip_rect: Option<Rect<u32>>,
+    /// The least coverage, from 0.0 to 1.0, that a pixel touched by a glyph is drawn with.
+    ///
+    /// Pixels whose coverage is visible but below this floor are raised to it, so that hairlines
+    /// and thin stems that cover only a sliver of each pixel don't fade away at small sizes. This
+    /// is a crude form of stem darkening: it thickens faint edges of every glyph too, so values
+    /// around 0.25 to 0.5 work best. Pixels with no coverage stay empty. The floor has no effect
+    /// with `AntialiasMode::None` or with a coverage buffer in `AccumulationMode::SignedArea`.
+    ///
+    /// The default, 0.0, leaves coverage as it is.
+    pub min_coverage: f32,
+}
+
+impl Default for DrawAtlasOptions {
+    #[inline]
+    fn default() -> DrawAtlasOptions {
+        DrawAtlasOptions {
+            antialias_mode: AntialiasMode::default(),
+            coverage_region: None,
+            clip_rect: None,
+            min_coverage: 0.0,
+        }
+    }
+}
+
 /// Options that control Pathfinder's behavior.
 #[derive(Clone, Debug)]
 pub struct RasterizerOptions {
@@ -8,41 +8,201 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use error::AtlasError;
 use euclid::{Point2D, Rect, Size2D};
+use std::cmp;
+use std::u32;
+
+/// The default size of the transparent border around each rectangle, in pixels.
+pub const DEFAULT_PADDING: u32 = 1;
+
+/// The algorithm used to place glyphs within each shelf of an atlas.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PackingMode {
+    /// Splits free space into rectangles guillotine-style and places each glyph in the smallest
+    /// free rectangle that fits it. This is fast and works well when glyphs are of similar
+    /// heights.
+    Guillotine,
+    /// Tracks the height profile ("skyline") of each shelf and places each glyph at the position
+    /// that keeps it highest, breaking ties by the least wasted space underneath. This packs
+    /// glyphs of mixed heights more tightly, especially if they're packed tallest first.
+    Skyline,
+}
 
 pub struct RectPacker {
+    mode: PackingMode,
     free_rects: Vec<Rect<u32>>,
+    skylines: Vec<Vec<SkylineSegment>>,
+    /// Rectangles, including padding, that the caller placed itself with `reserve()`.
+    reserved_rects: Vec<Rect<u32>>,
     available_width: u32,
+    available_height: u32,
     shelf_height: u32,
+    padding: u32,
     shelf_count: u32,
     /// The amount of horizontal space allocated in the last shelf.
     width_of_last_shelf: u32,
+    /// The total area of all rectangles packed so far, not including padding.
+    used_area: u64,
 }
 
 impl RectPacker {
+    /// Creates a rect packer that surrounds every rectangle with `padding` pixels of empty space.
+    ///
+    /// The packer's height is unlimited; use `set_available_height()` to limit it.
     #[inline]
-    pub fn new(available_width: u32, shelf_height: u32) -> RectPacker {
+    pub fn new(available_width: u32, shelf_height: u32, padding: u32, mode: PackingMode)
+               -> RectPacker {
         RectPacker {
+            mode: mode,
             free_rects: vec![],
+            skylines: vec![],
+            reserved_rects: vec![],
             available_width: available_width,
+            available_height: u32::MAX,
             shelf_height: shelf_height,
+            padding: padding,
             shelf_count: 0,
             width_of_last_shelf: 0,
+            used_area: 0,
         }
     }
 
+    /// Limits the total height of all shelves.
+    #[inline]
+    pub fn set_available_height(&mut self, available_height: u32) {
+        self.available_height = available_height
+    }
+
     /// Packs a rectangle of the given size.
     ///
     /// Returns the top-left position of the rectangle or an error if there is no space left.
-    pub fn pack(&mut self, size: &Size2D<u32>) -> Result<Point2D<u32>, ()> {
-        // Add a one-pixel border to prevent bleed.
-        let alloc_size = *size + Size2D::new(2, 2);
+    pub fn pack(&mut self, size: &Size2D<u32>) -> Result<Point2D<u32>, AtlasError> {
+        let alloc_size = try!(self.check_size(size));
+
+        // Space that turns out to overlap a reserved rectangle is given up on, so this always
+        // makes progress toward a free spot or a new shelf.
+        let mut chosen_rect;
+        loop {
+            chosen_rect = match self.mode {
+                PackingMode::Guillotine => try!(self.pack_guillotine(&alloc_size)),
+                PackingMode::Skyline => try!(self.pack_skyline(&alloc_size)),
+            };
+            if !self.reserved_rects.iter().any(|reserved| reserved.intersects(&chosen_rect)) {
+                break
+            }
+        }
+
+        // Update width of last shelf if necessary.
+        let on_last_shelf = chosen_rect.origin.y >= self.shelf_height * (self.shelf_count - 1);
+        if on_last_shelf && self.width_of_last_shelf < chosen_rect.max_x() {
+            self.width_of_last_shelf = chosen_rect.max_x()
+        }
 
-        // If the allocation size is less than our shelf height, we will always fail.
-        if alloc_size.height > self.shelf_height {
-            return Err(())
+        self.used_area += size.width as u64 * size.height as u64;
+
+        let object_origin = chosen_rect.origin + Point2D::new(self.padding, self.padding);
+        Ok(object_origin)
+    }
+
+    /// Marks a rectangle at a position of the caller's choosing as allocated, so that `pack()`
+    /// never places anything on top of it.
+    ///
+    /// `rect` includes the padding on every side. Shelves are added as needed to reach it. Returns
+    /// `AtlasError::OutOfBounds` if the rectangle extends past the available width or height or
+    /// from one shelf into the next. The packer doesn't keep track of the rectangles it packed, so
+    /// it's up to the caller not to reserve space that overlaps them.
+    pub fn reserve(&mut self, rect: &Rect<u32>) -> Result<(), AtlasError> {
+        let shelf_index = rect.origin.y / self.shelf_height;
+        let shelf_bottom = (shelf_index as u64 + 1) * self.shelf_height as u64;
+        if rect.max_x() as u64 > self.available_width as u64 ||
+                rect.max_y() as u64 > shelf_bottom ||
+                shelf_bottom > self.available_height as u64 {
+            return Err(AtlasError::OutOfBounds {
+                rect: *rect,
+            })
         }
 
+        while self.shelf_count <= shelf_index {
+            let shelf_rect = try!(self.add_shelf(&rect.size));
+            if self.mode == PackingMode::Guillotine {
+                self.free_rects.push(shelf_rect)
+            }
+        }
+
+        if shelf_index == self.shelf_count - 1 && self.width_of_last_shelf < rect.max_x() {
+            self.width_of_last_shelf = rect.max_x()
+        }
+
+        let padding = self.padding * 2;
+        self.used_area += rect.size.width.saturating_sub(padding) as u64 *
+            rect.size.height.saturating_sub(padding) as u64;
+        self.reserved_rects.push(*rect);
+        Ok(())
+    }
+
+    /// Returns true if a rectangle of the given size could be packed right now.
+    pub fn can_pack(&self, size: &Size2D<u32>) -> bool {
+        let alloc_size = match self.check_size(size) {
+            Ok(alloc_size) => alloc_size,
+            Err(_) => return false,
+        };
+
+        if self.can_add_shelf() {
+            return true
+        }
+
+        match self.mode {
+            PackingMode::Guillotine => {
+                self.free_rects.iter().any(|rect| {
+                    alloc_size.width <= rect.size.width && alloc_size.height <= rect.size.height
+                })
+            }
+            PackingMode::Skyline => {
+                self.skylines.iter().any(|skyline| {
+                    (0..skyline.len()).any(|segment_index| {
+                        fit_skyline(skyline,
+                                    segment_index,
+                                    &alloc_size,
+                                    self.available_width,
+                                    self.shelf_height).is_some()
+                    })
+                })
+            }
+        }
+    }
+
+    /// Makes the space occupied by a rectangle that `pack()` returned available again.
+    ///
+    /// `rect` is the packed rectangle, not including padding. Freed space is only reused in
+    /// guillotine mode; in skyline mode, this just updates the occupancy.
+    pub fn free(&mut self, rect: &Rect<u32>) {
+        self.used_area -= rect.size.width as u64 * rect.size.height as u64;
+
+        if self.mode == PackingMode::Guillotine {
+            let padding = Point2D::new(self.padding, self.padding);
+            let padding_size = Size2D::new(self.padding * 2, self.padding * 2);
+            self.free_rects.push(Rect::new(rect.origin - padding, rect.size + padding_size))
+        }
+    }
+
+    // Adds the border to prevent bleed and checks that the result fits within a shelf.
+    fn check_size(&self, size: &Size2D<u32>) -> Result<Size2D<u32>, AtlasError> {
+        let alloc_size = *size + Size2D::new(self.padding * 2, self.padding * 2);
+
+        // If the allocation size is more than our shelf size, we will always fail.
+        if alloc_size.height > self.shelf_height || alloc_size.width > self.available_width {
+            return Err(AtlasError::GlyphTooLarge {
+                needed: alloc_size,
+                shelf_size: Size2D::new(self.available_width, self.shelf_height),
+            })
+        }
+
+        Ok(alloc_size)
+    }
+
+    // Returns the allocated rectangle, including padding.
+    fn pack_guillotine(&mut self, alloc_size: &Size2D<u32>) -> Result<Rect<u32>, AtlasError> {
         let chosen_index_and_rect =
             self.free_rects
                 .iter()
@@ -57,10 +217,7 @@ impl RectPacker {
         match chosen_index_and_rect {
             None => {
                 // Make a new shelf.
-                chosen_rect = Rect::new(Point2D::new(0, self.shelf_height * self.shelf_count),
-                                        Size2D::new(self.available_width, self.shelf_height));
-                self.shelf_count += 1;
-                self.width_of_last_shelf = 0
+                chosen_rect = try!(self.add_shelf(alloc_size));
             }
             Some((index, rect)) => {
                 self.free_rects.swap_remove(index);
@@ -85,14 +242,73 @@ impl RectPacker {
             self.free_rects.push(free_to_right);
         }
 
-        // Update width of last shelf if necessary.
-        let on_last_shelf = chosen_rect.max_y() >= self.shelf_height * (self.shelf_count - 1);
-        if on_last_shelf && self.width_of_last_shelf < chosen_rect.max_x() {
-            self.width_of_last_shelf = chosen_rect.max_x()
+        Ok(Rect::new(chosen_rect.origin, *alloc_size))
+    }
+
+    // Returns the allocated rectangle, including padding.
+    fn pack_skyline(&mut self, alloc_size: &Size2D<u32>) -> Result<Rect<u32>, AtlasError> {
+        // Find the best position across all shelves: the one with the smallest `y` within its
+        // shelf, then the least wasted area beneath the rectangle, then the earliest shelf.
+        let mut best = None;
+        for (shelf_index, skyline) in self.skylines.iter().enumerate() {
+            for segment_index in 0..skyline.len() {
+                let (y, waste) = match fit_skyline(skyline,
+                                                   segment_index,
+                                                   alloc_size,
+                                                   self.available_width,
+                                                   self.shelf_height) {
+                    None => continue,
+                    Some(fit) => fit,
+                };
+                let is_better = match best {
+                    None => true,
+                    Some((_, _, best_y, best_waste)) => (y, waste) < (best_y, best_waste),
+                };
+                if is_better {
+                    best = Some((shelf_index, segment_index, y, waste))
+                }
+            }
         }
 
-        let object_origin = chosen_rect.origin + Point2D::new(1, 1);
-        Ok(object_origin)
+        let (shelf_index, segment_index, y) = match best {
+            Some((shelf_index, segment_index, y, _)) => (shelf_index, segment_index, y),
+            None => {
+                try!(self.add_shelf(alloc_size));
+                (self.skylines.len() - 1, 0, 0)
+            }
+        };
+
+        let x = self.skylines[shelf_index][segment_index].x;
+        add_to_skyline(&mut self.skylines[shelf_index], segment_index, alloc_size, y);
+
+        Ok(Rect::new(Point2D::new(x, shelf_index as u32 * self.shelf_height + y), *alloc_size))
+    }
+
+    #[inline]
+    fn can_add_shelf(&self) -> bool {
+        (self.shelf_count as u64 + 1) * self.shelf_height as u64 <= self.available_height as u64
+    }
+
+    // Makes a new shelf to hold a rectangle of the given size and returns the shelf's rectangle.
+    fn add_shelf(&mut self, alloc_size: &Size2D<u32>) -> Result<Rect<u32>, AtlasError> {
+        if !self.can_add_shelf() {
+            let used_height = self.shelf_count * self.shelf_height;
+            return Err(AtlasError::OutOfSpace {
+                needed: *alloc_size,
+                remaining: Size2D::new(self.available_width, self.available_height - used_height),
+            })
+        }
+
+        let shelf_rect = Rect::new(Point2D::new(0, self.shelf_height * self.shelf_count),
+                                   Size2D::new(self.available_width, self.shelf_height));
+        self.skylines.push(vec![SkylineSegment {
+            x: 0,
+            width: self.available_width,
+            y: 0,
+        }]);
+        self.shelf_count += 1;
+        self.width_of_last_shelf = 0;
+        Ok(shelf_rect)
     }
 
     #[inline]
@@ -100,6 +316,11 @@ impl RectPacker {
         self.shelf_height
     }
 
+    #[inline]
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
     #[inline]
     pub fn shelf_columns(&self) -> u32 {
         let full_shelf_count = if self.shelf_count == 0 {
@@ -110,10 +331,115 @@ impl RectPacker {
 
         full_shelf_count * self.available_width + self.width_of_last_shelf
     }
+
+    /// Returns the fraction of the area of all shelves allocated so far that is covered by packed
+    /// rectangles, not counting padding.
+    ///
+    /// Returns 0.0 if nothing has been packed.
+    pub fn occupancy(&self) -> f32 {
+        let total_area = self.shelf_count as u64 * self.shelf_height as u64 *
+            self.available_width as u64;
+        if total_area == 0 {
+            0.0
+        } else {
+            (self.used_area as f64 / total_area as f64) as f32
+        }
+    }
+}
+
+/// A horizontal span of a shelf's skyline. `y` is the lowest free row, relative to the top of the
+/// shelf.
+#[derive(Clone, Copy, Debug)]
+struct SkylineSegment {
+    x: u32,
+    width: u32,
+    y: u32,
+}
+
+// Determines whether a rectangle fits with its left edge at the start of the given segment.
+// Returns the `y` position it would occupy and the area wasted underneath it if so.
+fn fit_skyline(skyline: &[SkylineSegment],
+               segment_index: usize,
+               size: &Size2D<u32>,
+               available_width: u32,
+               shelf_height: u32)
+               -> Option<(u32, u64)> {
+    let x = skyline[segment_index].x;
+    if x + size.width > available_width {
+        return None
+    }
+
+    // The rectangle rests on the highest segment it spans.
+    let mut y = 0;
+    let mut width_left = size.width;
+    for segment in &skyline[segment_index..] {
+        if width_left == 0 {
+            break
+        }
+        y = cmp::max(y, segment.y);
+        width_left -= cmp::min(width_left, segment.width);
+    }
+    if y + size.height > shelf_height {
+        return None
+    }
+
+    let mut waste = 0;
+    let mut width_left = size.width;
+    for segment in &skyline[segment_index..] {
+        if width_left == 0 {
+            break
+        }
+        let spanned = cmp::min(width_left, segment.width);
+        waste += (y - segment.y) as u64 * spanned as u64;
+        width_left -= spanned;
+    }
+
+    Some((y, waste))
+}
+
+// Raises the skyline to account for a rectangle placed at the start of the given segment.
+fn add_to_skyline(skyline: &mut Vec<SkylineSegment>,
+                  segment_index: usize,
+                  size: &Size2D<u32>,
+                  y: u32) {
+    let x = skyline[segment_index].x;
+    let new_segment = SkylineSegment {
+        x: x,
+        width: size.width,
+        y: y + size.height,
+    };
+    skyline.insert(segment_index, new_segment);
+
+    // Shrink or remove the segments that the new one now covers.
+    let right = x + size.width;
+    let index = segment_index + 1;
+    while index < skyline.len() {
+        if skyline[index].x >= right {
+            break
+        }
+        let segment_right = skyline[index].x + skyline[index].width;
+        if segment_right <= right {
+            skyline.remove(index);
+        } else {
+            skyline[index].width = segment_right - right;
+            skyline[index].x = right;
+            break
+        }
+    }
+
+    // Merge adjacent segments of the same height.
+    let mut index = 0;
+    while index + 1 < skyline.len() {
+        if skyline[index].y == skyline[index + 1].y {
+            skyline[index].width += skyline[index + 1].width;
+            skyline.remove(index + 1);
+        } else {
+            index += 1
+        }
+    }
 }
 
 #[inline]
 fn area(rect: &Rect<u32>) -> u32 {
     rect.size.width * rect.size.height
 }
-
@@ -12,7 +12,8 @@
 //!
 //! Do not use this for international or high-quality text. This shaper does not do kerning,
 //! ligation, or advanced typography features (`GSUB`, `GPOS`, text morphing). Consider HarfBuzz or
-//! the system shaper instead.
+//! the system shaper instead, and pass their output to `OutlineBuilder::add_shaped_run()` as
+//! `ShapedGlyph`s.
 
 use charmap::GlyphMapping;
 use otf::Font;
@@ -54,6 +55,64 @@ pub fn shape_text(font: &Font, glyph_mapping: &GlyphMapping, string: &str) -> Ve
     result
 }
 
+/// Shapes a run of Unicode text written in one direction, returning each glyph with its pen
+/// position.
+///
+/// The glyphs are returned in visual order, from left to right. For `Direction::LeftToRight`, the
+/// run starts at its left edge, at position 0, and the pen moves right. For
+/// `Direction::RightToLeft`, the run starts at its right edge, at position 0, and the pen moves
+/// left, so every glyph has a negative position; the first character of `string` is rightmost.
+/// Kerning is applied between glyphs that are adjacent on screen.
+///
+/// This does no bidirectional analysis; split mixed-direction text into runs of a single
+/// direction first. Like `shape_text()`, it does no contextual shaping, so Arabic letters take
+/// their isolated forms. See the description of this module for other caveats.
+pub fn shape_run(font: &Font, glyph_mapping: &GlyphMapping, string: &str, direction: Direction)
+                 -> Vec<PositionedGlyph> {
+    let glyph_positions = match direction {
+        Direction::LeftToRight => shape_text(font, glyph_mapping, string),
+        Direction::RightToLeft => {
+            let visual_string: String = string.chars().rev().collect();
+            shape_text(font, glyph_mapping, &visual_string)
+        }
+    };
+
+    let mut x = match direction {
+        Direction::LeftToRight => 0,
+        Direction::RightToLeft => {
+            -glyph_positions.iter().map(|glyph_pos| glyph_pos.advance as i32).sum::<i32>()
+        }
+    };
+
+    glyph_positions.iter().map(|glyph_pos| {
+        let positioned_glyph = PositionedGlyph {
+            glyph_id: glyph_pos.glyph_id,
+            x: x,
+        };
+        x += glyph_pos.advance as i32;
+        positioned_glyph
+    }).collect()
+}
+
+/// The direction in which the characters of a run of text are written.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    /// Left to right, as in Latin or Cyrillic text.
+    LeftToRight,
+    /// Right to left, as in Arabic or Hebrew text.
+    RightToLeft,
+}
+
+/// A glyph placed by `shape_run()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PositionedGlyph {
+    /// The glyph ID to emit.
+    pub glyph_id: u16,
+    /// The horizontal position of the glyph's origin relative to the start of the run, in font
+    /// units.
+    pub x: i32,
+}
+
 /// The position of a glyph after shaping.
 #[derive(Clone, Copy, Debug)]
 pub struct GlyphPos {
@@ -63,3 +122,23 @@ pub struct GlyphPos {
     pub advance: i16,
 }
 
+/// A glyph positioned by an external shaper such as HarfBuzz, as `OutlineBuilder::add_shaped_run()`
+/// takes it.
+///
+/// The fields correspond to HarfBuzz's `hb_glyph_info_t::codepoint`, which holds the glyph ID
+/// after shaping, and to the `x_offset`, `y_offset`, and `x_advance` fields of its
+/// `hb_glyph_position_t`. All distances are in font units, which is what HarfBuzz produces when
+/// the font's scale is left at its units per em; divide by 64 first if the scale is in 26.6 fixed
+/// point. The offsets move the glyph without moving the pen, and the advance moves the pen after
+/// the glyph is drawn. Vertical advances aren't supported.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShapedGlyph {
+    /// The glyph ID to emit.
+    pub glyph_id: u16,
+    /// How far to move the glyph right of the pen position.
+    pub x_offset: f32,
+    /// How far to move the glyph up from the baseline.
+    pub y_offset: f32,
+    /// How far to move the pen right after emitting this glyph.
+    pub x_advance: f32,
+}
@@ -0,0 +1,106 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Simplification of glyph contours to reduce the number of vertices.
+
+use euclid::Point2D;
+use flatten;
+
+/// A point on a TrueType contour, in font units.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ContourPoint {
+    pub position: Point2D<i16>,
+    pub on_curve: bool,
+}
+
+/// Removes points from a closed contour that don't move its outline by more than `tolerance` font
+/// units.
+///
+/// The contour must be in the form that `Font::for_each_point()` produces: it starts and ends with
+/// the same on-curve point, and every off-curve point lies between two on-curve points.
+///
+/// Quadratic curves whose control point lies within `tolerance` of the line between the curve's
+/// endpoints become straight lines. Then runs of straight lines are simplified with the
+/// Douglas–Peucker algorithm. The endpoints of the remaining curves are always kept, so curves are
+/// never distorted.
+pub fn simplify_contour(contour: &[ContourPoint], tolerance: f32) -> Vec<ContourPoint> {
+    // Straighten curves that are flat enough.
+    let mut points: Vec<ContourPoint> = Vec::with_capacity(contour.len());
+    for (index, point) in contour.iter().enumerate() {
+        if !point.on_curve && index > 0 && index + 1 < contour.len() {
+            let (from, to) = (&contour[index - 1], &contour[index + 1]);
+            if from.on_curve && to.on_curve &&
+                    distance_to_line_segment(point, from, to) <= tolerance {
+                continue
+            }
+        }
+        points.push(*point)
+    }
+
+    // Anchor the endpoints of the contour and of each remaining curve.
+    let mut keep = vec![false; points.len()];
+    for (index, point) in points.iter().enumerate() {
+        if index == 0 || index + 1 == points.len() || !point.on_curve {
+            keep[index] = true
+        } else if !points[index - 1].on_curve || !points[index + 1].on_curve {
+            keep[index] = true
+        }
+    }
+
+    // Simplify the straight runs between anchors.
+    let mut run_start = 0;
+    for index in 1..points.len() {
+        if keep[index] {
+            if points[run_start].on_curve && points[index].on_curve {
+                simplify_run(&points, run_start, index, tolerance, &mut keep)
+            }
+            run_start = index
+        }
+    }
+
+    points.iter().zip(keep.iter()).filter(|&(_, &keep)| keep).map(|(point, _)| *point).collect()
+}
+
+// Marks the points between `start` and `end` that the Douglas–Peucker algorithm keeps.
+fn simplify_run(points: &[ContourPoint],
+                start: usize,
+                end: usize,
+                tolerance: f32,
+                keep: &mut [bool]) {
+    let mut stack = vec![(start, end)];
+    while let Some((start, end)) = stack.pop() {
+        let mut farthest = None;
+        let mut max_distance = tolerance;
+        for index in (start + 1)..end {
+            let distance = distance_to_line_segment(&points[index], &points[start], &points[end]);
+            if distance > max_distance {
+                farthest = Some(index);
+                max_distance = distance
+            }
+        }
+
+        if let Some(farthest) = farthest {
+            keep[farthest] = true;
+            stack.push((start, farthest));
+            stack.push((farthest, end))
+        }
+    }
+}
+
+fn distance_to_line_segment(point: &ContourPoint, from: &ContourPoint, to: &ContourPoint) -> f32 {
+    let point = to_f32(point);
+    let nearest = flatten::nearest_point_on_line_segment(&to_f32(from), &to_f32(to), &point);
+    let vector = point - nearest;
+    vector.dot(vector).sqrt()
+}
+
+fn to_f32(point: &ContourPoint) -> Point2D<f32> {
+    Point2D::new(point.position.x as f32, point.position.y as f32)
+}
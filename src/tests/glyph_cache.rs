@@ -0,0 +1,65 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+use error::AtlasError;
+use euclid::Size2D;
+use glyph_cache::{GlyphCache, GlyphCacheKey};
+
+#[test]
+fn cache_evicts_least_recently_used_glyph() {
+    // Room for exactly four 10x10 glyphs, since each one takes 12x12 with padding.
+    let mut cache = GlyphCache::new(24, 24, 12);
+    let size = Size2D::new(10, 10);
+    let keys: Vec<_> = (0..5).map(|glyph_id| GlyphCacheKey::new(0, glyph_id, 12.0, 0.0)).collect();
+
+    let mut rasterized = 0;
+    let mut rects = vec![];
+    for key in &keys[0..4] {
+        rects.push(cache.get_or_insert(*key, &size, |_| rasterized += 1).unwrap());
+    }
+    assert_eq!(rasterized, 4);
+
+    // Hits don't rasterize again.
+    assert_eq!(cache.get_or_insert(keys[0], &size, |_| rasterized += 1).unwrap(), rects[0]);
+    assert_eq!(rasterized, 4);
+
+    // Glyph 1 is now the least recently used, so it makes room for glyph 4.
+    let rect = cache.get_or_insert(keys[4], &size, |_| rasterized += 1).unwrap();
+    assert_eq!(rasterized, 5);
+    assert_eq!(rect, rects[1]);
+    assert_eq!(cache.len(), 4);
+    assert!(cache.get(&keys[1]).is_none());
+    assert_eq!(cache.get(&keys[0]), Some(rects[0]));
+
+    // A glyph that can never fit doesn't evict anything.
+    let key = GlyphCacheKey::new(0, 5, 12.0, 0.0);
+    match cache.get_or_insert(key, &Size2D::new(30, 10), |_| rasterized += 1) {
+        Err(AtlasError::GlyphTooLarge { .. }) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(cache.len(), 4);
+}
+
+#[test]
+fn cache_keys_quantize_sizes_and_offsets() {
+    assert_eq!(GlyphCacheKey::new(1, 2, 12.0, 0.25), GlyphCacheKey::new(1, 2, 12.001, 0.251));
+    assert!(GlyphCacheKey::new(1, 2, 12.0, 0.25) != GlyphCacheKey::new(1, 2, 12.0, 0.5));
+    assert!(GlyphCacheKey::new(1, 2, 12.0, 0.25) != GlyphCacheKey::new(2, 2, 12.0, 0.25));
+}
+
+#[test]
+fn pen_positions_share_subpixel_buckets() {
+    let (key, x) = GlyphCacheKey::for_pen_position(0, 7, 12.0, 10.3, 4);
+    assert_eq!((key.subpixel_offset(), x), (0.25, 10));
+    assert_eq!(GlyphCacheKey::for_pen_position(0, 7, 12.0, 3.2, 4), (key, 3));
+
+    // Positions just below a whole pixel round up to the next one.
+    let (key, x) = GlyphCacheKey::for_pen_position(0, 7, 12.0, 0.9, 4);
+    assert_eq!((key.subpixel_offset(), x), (0.0, 1));
+    let (key, x) = GlyphCacheKey::for_pen_position(0, 7, 12.0, -0.3, 4);
+    assert_eq!((key.subpixel_offset(), x), (0.75, -1));
+
+    // A single bucket snaps to whole pixels.
+    let (key, x) = GlyphCacheKey::for_pen_position(0, 7, 12.0, 5.4, 0);
+    assert_eq!((key.subpixel_offset(), x), (0.0, 5));
+}
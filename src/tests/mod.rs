@@ -9,5 +9,9 @@
 // except according to those terms.
 
 mod buffers;
+mod glyph_cache;
+mod otf;
+mod outline;
 mod rect_packer;
+mod shaper;
 
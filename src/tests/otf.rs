@@ -0,0 +1,1944 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use euclid::{Point2D, Size2D};
+use flatten;
+use font_stack::FontStack;
+use memmap::{Mmap, Protection};
+use msdf::{self, Segment};
+use otf::{self, Font, GlyphAdjustment, GlyphClass, Os2Range, OutlineFormat, OutlineVisitor};
+use otf::{BaselineTag, FontReader, ParseLimits};
+use outline::{GlyphFixedBounds, OutlineBuilder};
+use std::cell::Cell;
+use std::char;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::u16;
+
+static TEST_FONT_PATH: &'static str = "resources/tests/nimbus-sans/NimbusSanL-Regu.ttf";
+
+#[test]
+fn glyph_for_char_falls_back_through_stack() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let glyph_id = font.glyph_for_char('A').expect("No glyph for 'A'");
+        assert!(font.glyph_for_char('\u{4e00}').is_none());
+
+        let font_stack = FontStack::new(vec![Font::new(file.as_slice()).unwrap(), font]);
+        assert_eq!(font_stack.glyph_for_char('A'), Some((0, glyph_id)));
+        assert_eq!(font_stack.glyph_for_char('\u{4e00}'), None);
+    }
+}
+
+#[test]
+fn glyph_for_char_falls_back_to_later_fonts() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let (glyph_a, glyph_lowercase_a) = (font.glyph_for_char('A').unwrap(),
+                                        font.glyph_for_char('a').unwrap());
+
+    // A copy of the font that only maps "A" to "Z".
+    let mut cmap = vec![];
+    for &value in &[12, 0] {
+        cmap.write_u16::<BigEndian>(value).unwrap();
+    }
+    for &value in &[28, 0, 1, 0x41, 0x5a, glyph_a as u32] {
+        cmap.write_u32::<BigEndian>(value).unwrap();
+    }
+    let bytes = with_cmap(unsafe { file.as_slice() }, &cmap);
+    let uppercase_font = Font::new(&bytes).unwrap();
+    assert_eq!(uppercase_font.glyph_for_char('A'), Some(glyph_a));
+    assert_eq!(uppercase_font.glyph_for_char('a'), None);
+
+    let font_stack = FontStack::new(vec![uppercase_font, font]);
+    assert_eq!(font_stack.glyph_for_char('A'), Some((0, glyph_a)));
+    assert_eq!(font_stack.glyph_for_char('a'), Some((1, glyph_lowercase_a)));
+    assert_eq!(font_stack.glyph_for_char('\u{4e00}'), None);
+}
+
+#[test]
+fn glyph_or_notdef_falls_back_to_notdef() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        assert_eq!(font.glyph_or_notdef('A'), font.glyph_for_char('A').unwrap());
+        assert_eq!(font.glyph_or_notdef('\u{4e00}'), font.notdef_glyph());
+    }
+}
+
+#[test]
+fn substitute_passes_glyphs_through_without_gsub() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let glyph_ids: Vec<u16> = "ffi".chars().map(|c| font.glyph_for_char(c).unwrap()).collect();
+        assert_eq!(font.substitute(&glyph_ids, &[otf::tag(b"liga")]), glyph_ids);
+    }
+}
+
+#[test]
+fn position_returns_zero_adjustments_without_gpos() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let glyph_ids: Vec<u16> = "AV".chars().map(|c| font.glyph_for_char(c).unwrap()).collect();
+        assert_eq!(font.position(&glyph_ids, &[otf::tag(b"kern")]),
+                   vec![GlyphAdjustment::default(); 2]);
+    }
+}
+
+#[test]
+fn substitute_applies_single_and_ligature_lookups() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph = |c| font.glyph_for_char(c).unwrap();
+    let (f, i, l, a) = (glyph('f'), glyph('i'), glyph('l'), glyph('a'));
+    let (fi, fl) = (glyph('\u{fb01}'), glyph('\u{fb02}'));
+
+    // "ffl" and "fi" ligatures on "f", with the longer one first. The font has no "ffl" glyph, so
+    // "fl" stands in for it.
+    let mut ligatures = vec![];
+    for &value in &[1, 8, 1, 14, 1, 1, f, 2, 6, 14, fl, 3, f, l, fi, 2, i] {
+        ligatures.write_u16::<BigEndian>(value).unwrap();
+    }
+
+    // "a" becomes "fi" as an alternate.
+    let mut single = vec![];
+    for &value in &[2, 8, 1, fi, 1, 1, a] {
+        single.write_u16::<BigEndian>(value).unwrap();
+    }
+
+    // Alternate substitution (type 3) isn't supported, so this lookup is never read.
+    let unsupported = [0xff; 4];
+
+    let gsub = layout_table(&[(b"liga", 0), (b"salt", 1), (b"aalt", 2)],
+                            &[(4, &ligatures), (1, &single), (3, &unsupported)]);
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"GSUB", &gsub)]);
+    let font = Font::new(&bytes).unwrap();
+
+    let (liga, salt, aalt) = (otf::tag(b"liga"), otf::tag(b"salt"), otf::tag(b"aalt"));
+    assert_eq!(font.substitute(&[f, f, l, f, i, f, f], &[liga]), vec![fl, fi, f, f]);
+    assert_eq!(font.substitute(&[a, f, i], &[salt]), vec![fi, f, i]);
+    assert_eq!(font.substitute(&[a, f, i], &[aalt]), vec![a, f, i]);
+
+    // Lookups run in lookup list order, so the ligature is formed before "a" is replaced.
+    assert_eq!(font.substitute(&[f, a, f, i], &[salt, liga]), vec![f, fi, fi]);
+}
+
+#[test]
+fn position_applies_pair_and_mark_to_base_lookups() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph = |c| font.glyph_for_char(c).unwrap();
+    let (a, v, e, acute) = (glyph('A'), glyph('V'), glyph('e'), glyph('\u{b4}'));
+
+    // "AV" is kerned 80 units closer together by shortening the advance of "A".
+    let mut pair = vec![];
+    for &value in &[1, 12, 4, 0, 1, 18, 1, 1, a, 1, v, -80i16 as u16] {
+        pair.write_u16::<BigEndian>(value).unwrap();
+    }
+
+    // The acute accent's anchor at (100, 500) attaches to the anchor of "e" at (250, 600).
+    let mut mark = vec![];
+    for &value in &[1, 12, 18, 1, 24, 36, 1, 1, acute, 1, 1, e, 1, 0, 6, 1, 100, 500, 1, 4,
+                    1, 250, 600] {
+        mark.write_u16::<BigEndian>(value).unwrap();
+    }
+
+    // Single adjustment (type 1) isn't supported, so this lookup is never read.
+    let unsupported = [0xff; 4];
+
+    let gpos = layout_table(&[(b"kern", 0), (b"mark", 1), (b"cpsp", 2)],
+                            &[(2, &pair), (4, &mark), (1, &unsupported)]);
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"GPOS", &gpos)]);
+    let font = Font::new(&bytes).unwrap();
+
+    let kerned = GlyphAdjustment { x_advance: -80, ..GlyphAdjustment::default() };
+    let none = GlyphAdjustment::default();
+    let glyphs = [a, v, a, e, acute];
+    assert_eq!(font.position(&glyphs, &[otf::tag(b"kern")]),
+               vec![kerned, none, none, none, none]);
+    assert_eq!(font.position(&glyphs, &[otf::tag(b"cpsp")]), vec![none; 5]);
+
+    // The mark moves back by the advance of "e" to sit over it.
+    let advance = font.metrics_for_glyph(e).unwrap().advance_width as i32;
+    let attached = GlyphAdjustment {
+        x_placement: 250 - 100 - advance,
+        y_placement: 600 - 500,
+        ..GlyphAdjustment::default()
+    };
+    assert_eq!(font.position(&glyphs, &[otf::tag(b"mark")]),
+               vec![none, none, none, none, attached]);
+}
+
+// Returns a `GSUB` or `GPOS` table with an empty script list, the given features, each with the
+// index of the one lookup it uses, and the given lookups, each with its type and one subtable.
+fn layout_table(features: &[(&[u8; 4], u16)], lookups: &[(u16, &[u8])]) -> Vec<u8> {
+    let mut feature_list = vec![];
+    feature_list.write_u16::<BigEndian>(features.len() as u16).unwrap();
+    for (index, &(tag, _)) in features.iter().enumerate() {
+        feature_list.extend_from_slice(tag);
+        feature_list.write_u16::<BigEndian>((2 + features.len() * 6 + index * 6) as u16).unwrap();
+    }
+    for &(_, lookup_index) in features {
+        for &value in &[0, 1, lookup_index] {
+            feature_list.write_u16::<BigEndian>(value).unwrap();
+        }
+    }
+
+    let mut lookup_list = vec![];
+    lookup_list.write_u16::<BigEndian>(lookups.len() as u16).unwrap();
+    let mut lookup_offset = 2 + lookups.len() * 2;
+    for &(_, subtable) in lookups {
+        lookup_list.write_u16::<BigEndian>(lookup_offset as u16).unwrap();
+        lookup_offset += 8 + subtable.len()
+    }
+    for &(lookup_type, subtable) in lookups {
+        for &value in &[lookup_type, 0, 1, 8] {
+            lookup_list.write_u16::<BigEndian>(value).unwrap();
+        }
+        lookup_list.extend_from_slice(subtable);
+    }
+
+    let mut table = vec![];
+    for &value in &[1, 0, 10, 12, 12 + feature_list.len() as u16, 0] {
+        table.write_u16::<BigEndian>(value).unwrap();
+    }
+    table.extend_from_slice(&feature_list);
+    table.extend_from_slice(&lookup_list);
+    table
+}
+
+#[test]
+fn coverage_set_agrees_with_glyph_for_char() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let coverage_set = font.coverage_set();
+
+    for codepoint in 0..0x10000 {
+        let character = match char::from_u32(codepoint) {
+            Some(character) => character,
+            None => continue,
+        };
+        assert_eq!(coverage_set.contains(character),
+                   font.glyph_for_char(character).is_some(),
+                   "U+{:04X}",
+                   codepoint);
+    }
+    assert!(!coverage_set.contains('\u{10ffff}'));
+
+    let ranges = coverage_set.ranges();
+    assert!(ranges.windows(2).all(|pair| pair[0].end + 1 < pair[1].start));
+}
+
+#[test]
+fn segmented_coverage_stops_at_the_last_code() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+
+    // A format 12 subtable whose last group maps the largest possible code to the missing glyph.
+    let mut cmap = vec![];
+    for &value in &[12, 0] {
+        cmap.write_u16::<BigEndian>(value).unwrap();
+    }
+    for &value in &[40, 0, 2, 0x41, 0x5a, 36, 0xffffffff, 0xffffffff, 0] {
+        cmap.write_u32::<BigEndian>(value).unwrap();
+    }
+
+    let bytes = with_cmap(unsafe { file.as_slice() }, &cmap);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.glyph_for_char('B'), Some(37));
+    let ranges = font.coverage_set().ranges().to_vec();
+    assert_eq!((ranges.len(), ranges[0].start, ranges[0].end), (1, 0x41, 0x5a));
+}
+
+#[test]
+fn device_advance_is_none_without_hdmx() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let glyph_id = font.glyph_for_char('A').unwrap();
+        assert_eq!(font.device_advance(glyph_id, 12), None);
+        assert_eq!(font.linear_threshold(glyph_id), None);
+    }
+}
+
+#[test]
+fn device_advances_come_from_hdmx_and_ltsh() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_count = font.glyph_count() as usize;
+    let glyph_id = font.glyph_for_char('A').unwrap();
+
+    // Records for 12 and 16 pixels per em, each padded to a multiple of 4 bytes. Every glyph is
+    // as many pixels wide as the size, except that "A" is one pixel wider.
+    let record_size = (2 + glyph_count + 3) / 4 * 4;
+    let mut hdmx = vec![];
+    hdmx.write_u16::<BigEndian>(0).unwrap();
+    hdmx.write_i16::<BigEndian>(2).unwrap();
+    hdmx.write_i32::<BigEndian>(record_size as i32).unwrap();
+    for &ppem in &[12, 16] {
+        let mut record = vec![ppem; record_size];
+        record[1] = ppem + 1;
+        record[2 + glyph_id as usize] = ppem + 1;
+        hdmx.extend_from_slice(&record);
+    }
+
+    // Every glyph scales linearly from 20 pixels per em, except "A", from 24.
+    let mut ltsh = vec![];
+    for &value in &[0, glyph_count as u16] {
+        ltsh.write_u16::<BigEndian>(value).unwrap();
+    }
+    ltsh.extend(vec![20; glyph_count]);
+    ltsh[4 + glyph_id as usize] = 24;
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"hdmx", &hdmx), (b"LTSH", &ltsh)]);
+    let font = Font::new(&bytes).unwrap();
+    let other_glyph_id = font.glyph_for_char('B').unwrap();
+    assert_eq!(font.device_advance(glyph_id, 12), Some(13));
+    assert_eq!(font.device_advance(glyph_id, 16), Some(17));
+    assert_eq!(font.device_advance(other_glyph_id, 16), Some(16));
+    assert_eq!(font.device_advance(glyph_id, 14), None);
+    assert_eq!(font.device_advance(glyph_count as u16, 12), None);
+    assert_eq!(font.linear_threshold(glyph_id), Some(24));
+    assert_eq!(font.linear_threshold(other_glyph_id), Some(20));
+    assert_eq!(font.linear_threshold(glyph_count as u16), None);
+}
+
+#[test]
+fn nearest_outline_point_of_stem() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let glyph_id = font.glyph_for_char('I').unwrap();
+        let bounds = font.glyph_bounds(glyph_id).unwrap();
+
+        let middle = (bounds.bottom + bounds.top) as f32 / 2.0;
+        let query = Point2D::new(bounds.left as f32 - 100.0, middle);
+        let (point, distance) = font.nearest_outline_point(glyph_id, query).unwrap();
+        assert_eq!(point, Point2D::new(bounds.left as f32, middle));
+        assert_eq!(distance, 100.0);
+
+        let space = font.glyph_for_char(' ').unwrap();
+        assert!(font.nearest_outline_point(space, query).is_err());
+    }
+}
+
+#[test]
+fn scales_follow_point_size() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let units_per_em = font.units_per_em() as f32;
+        assert_eq!(font.pixels_per_unit(units_per_em * 2.0), 2.0);
+        assert_eq!(font.scale_factor(units_per_em * 3.0, 48.0), 2.0);
+        assert_eq!(font.curve_tolerance(units_per_em, 0.25), 0.25);
+        assert_eq!(font.curve_tolerance(units_per_em / 4.0, 0.25), 1.0);
+
+        // The default tolerance is a quarter of a font unit.
+        let glyph_id = font.glyph_for_char('O').unwrap();
+        let query = Point2D::new(0.0, 0.0);
+        assert_eq!(font.nearest_outline_point(glyph_id, query).unwrap(),
+                   font.nearest_outline_point_with_tolerance(glyph_id, query, 0.25).unwrap());
+
+        // Distances are only as accurate as the flattening.
+        let coarse = font.nearest_outline_point_with_tolerance(glyph_id, query, 16.0).unwrap();
+        let fine = font.nearest_outline_point_with_tolerance(glyph_id, query, 0.25).unwrap();
+        assert!((coarse.1 - fine.1).abs() <= 16.0 + 0.25);
+    }
+}
+
+#[test]
+fn unicode_ranges_come_from_os_2() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let unicode_ranges = font.unicode_ranges();
+        assert_eq!(unicode_ranges.bits(), [0x800003af, 0x78eb, 0, 0]);
+        assert!(unicode_ranges.contains_range(Os2Range::BasicLatin));
+        assert!(unicode_ranges.contains_range(Os2Range::GeneralPunctuation));
+        assert!(unicode_ranges.contains_range(Os2Range::CurrencySymbols));
+        assert!(!unicode_ranges.contains_range(Os2Range::Arabic));
+        assert!(!unicode_ranges.contains_range(Os2Range::CjkUnifiedIdeographs));
+        assert!(!unicode_ranges.contains_range(Os2Range::DominoTiles));
+        assert!(!unicode_ranges.is_empty());
+    }
+}
+
+#[test]
+fn unicode_ranges_come_from_version_0_os_2() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // Rename the original table and add a copy cut down to the 78 bytes of version 0, which has
+    // the same fields up to there.
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    let mut os_2 = vec![];
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        if &bytes[record..record + 4] == b"OS/2" {
+            let offset = BigEndian::read_u32(&bytes[record + 8..]) as usize;
+            os_2 = bytes[offset..offset + 78].to_vec();
+            bytes[record + 3] = b'X';
+        }
+    }
+    BigEndian::write_u16(&mut os_2, 0);
+
+    let bytes = add_tables(&bytes, &[(b"OS/2", &os_2)]);
+    let version_0_font = Font::new(&bytes).unwrap();
+    assert_eq!(version_0_font.unicode_ranges(), font.unicode_ranges());
+    assert_eq!(version_0_font.ascender(), font.ascender());
+    assert_eq!(version_0_font.descender(), font.descender());
+    assert_eq!(version_0_font.line_gap(), font.line_gap());
+}
+
+#[derive(Default)]
+struct ContourRecorder {
+    contours: Vec<(Point2D<f32>, Point2D<f32>)>,
+    quad_count: usize,
+    open: bool,
+}
+
+impl OutlineVisitor for ContourRecorder {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        assert!(!self.open);
+        self.contours.push((*to, *to));
+        self.open = true
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        assert!(self.open);
+        self.contours.last_mut().unwrap().1 = *to
+    }
+
+    fn quad_to(&mut self, _: &Point2D<f32>, to: &Point2D<f32>) {
+        assert!(self.open);
+        self.contours.last_mut().unwrap().1 = *to;
+        self.quad_count += 1
+    }
+
+    fn cubic_to(&mut self, _: &Point2D<f32>, _: &Point2D<f32>, _: &Point2D<f32>) {
+        panic!("TrueType outlines have no cubic curves")
+    }
+
+    fn close(&mut self) {
+        assert!(self.open);
+        self.open = false
+    }
+}
+
+#[test]
+fn walk_glyph_opens_and_closes_contours() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let mut recorder = ContourRecorder::default();
+        font.walk_glyph(font.glyph_for_char('O').unwrap(), &mut recorder).unwrap();
+
+        assert!(!recorder.open);
+        assert_eq!(recorder.contours.len(), 2);
+        assert!(recorder.quad_count > 0);
+        for &(start, end) in &recorder.contours {
+            assert_eq!(start, end)
+        }
+    }
+}
+
+#[test]
+fn cubics_become_quadratics_within_tolerance() {
+    let (from, control_point_0, control_point_1, to) = (Point2D::new(0.0, 0.0),
+                                                         Point2D::new(100.0, 400.0),
+                                                         Point2D::new(500.0, -300.0),
+                                                         Point2D::new(600.0, 100.0));
+    let cubic = |t: f32| {
+        let u = 1.0 - t;
+        from * (u * u * u) + control_point_0 * (3.0 * u * u * t) +
+            control_point_1 * (3.0 * u * t * t) + to * (t * t * t)
+    };
+
+    for &tolerance in &[10.0, 1.0, 0.1] {
+        let mut quadratics: Vec<(Point2D<f32>, Point2D<f32>)> = vec![];
+        flatten::cubic_to_quadratics(&from,
+                                     &control_point_0,
+                                     &control_point_1,
+                                     &to,
+                                     tolerance,
+                                     |control_point, to| quadratics.push((*control_point, *to)));
+        assert_eq!(quadratics.last().unwrap().1, to);
+
+        let mut piece_from = from;
+        for (piece_index, &(control_point, piece_to)) in quadratics.iter().enumerate() {
+            for step in 0..11 {
+                let t = step as f32 / 10.0;
+                let u = 1.0 - t;
+                let point = piece_from * (u * u) + control_point * (2.0 * u * t) +
+                    piece_to * (t * t);
+                let vector = point - cubic((piece_index as f32 + t) / quadratics.len() as f32);
+                assert!(vector.dot(vector).sqrt() <= tolerance * 1.01)
+            }
+            piece_from = piece_to
+        }
+    }
+}
+
+#[test]
+fn quadratic_outlines_keep_truetype_points() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_id = font.glyph_for_char('O').unwrap();
+
+    let mut recorder = ContourRecorder::default();
+    font.walk_glyph(glyph_id, &mut recorder).unwrap();
+    let contours = font.glyph_quadratic_outline(glyph_id, 1.0).unwrap();
+    assert_eq!(contours.len(), recorder.contours.len());
+
+    let mut off_curve_count = 0;
+    for (contour, &(start, _)) in contours.iter().zip(recorder.contours.iter()) {
+        assert!(contour.points[0].on_curve);
+        assert_eq!(contour.points[0].position, start);
+        assert!(contour.points.last().unwrap().position != start);
+        off_curve_count += contour.points.iter().filter(|point| !point.on_curve).count()
+    }
+    assert_eq!(off_curve_count, recorder.quad_count);
+}
+
+#[test]
+fn triangulation_covers_glyph_area() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        for character in (b'!'..(b'~' + 1)).map(|byte| byte as char) {
+            let glyph_id = font.glyph_for_char(character).unwrap();
+
+            // The contours of this font neither overlap nor self-intersect, so the filled area is
+            // the sum of the signed areas of the contours.
+            let mut expected_area = 0.0;
+            for contour in flatten::flatten_contours(&font, glyph_id, 1.0).unwrap() {
+                for (index, from) in contour.iter().enumerate() {
+                    let to = &contour[(index + 1) % contour.len()];
+                    expected_area += (from.x * to.y - to.x * from.y) * 0.5
+                }
+            }
+
+            let (vertices, indices) = font.triangulate_glyph(glyph_id, 1.0).unwrap();
+            assert_eq!(indices.len() % 3, 0);
+            let mut area = 0.0;
+            for triangle in indices.chunks(3) {
+                let (a, b, c) = (vertices[triangle[0] as usize],
+                                 vertices[triangle[1] as usize],
+                                 vertices[triangle[2] as usize]);
+                let triangle_area = ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)) * 0.5;
+                assert!(triangle_area > 0.0);
+                area += triangle_area
+            }
+
+            let expected_area: f32 = expected_area.abs();
+            assert!((area - expected_area).abs() <= expected_area * 0.001,
+                    "{:?}: triangulated area {} != {}", character, area, expected_area)
+        }
+    }
+}
+
+#[test]
+fn truetype_outline_format() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        assert_eq!(font.outline_format(), OutlineFormat::TrueType);
+    }
+}
+
+#[test]
+fn cff_outlines_are_unsupported() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+
+    // Fonts in the CFF container format without a `CFF2` table are rejected when they are loaded.
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    BigEndian::write_u32(&mut bytes, 0x4f54544f);
+    assert_eq!(Font::new(&bytes).err(),
+               Some(otf::Error::UnsupportedOutlineFormat(OutlineFormat::Cff)));
+    assert_eq!(FontReader::new(Cursor::new(&bytes)).err(),
+               Some(otf::Error::UnsupportedOutlineFormat(OutlineFormat::Cff)));
+}
+
+#[test]
+fn cff2_outlines_follow_the_chosen_instance() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+
+    // Glyph 1 is a square whose width grows by 40 units at the heaviest weight. Glyph 2 is a
+    // curve drawn by a global subroutine and closed by a local one, after hints.
+    let square: &[(&[i32], &[u8])] = &[(&[10, 10], &[21]),
+                                       (&[100, 100, -100, 40, 0, -40, 3], &[16, 6])];
+    let curve: &[(&[i32], &[u8])] = &[(&[0, 20, 50, 20], &[1]),
+                                      (&[0, 10], &[19, 0xe0]),
+                                      (&[0, 0], &[21]),
+                                      (&[-107], &[29]),
+                                      (&[-107], &[10])];
+    let char_strings = [char_string(&[]), char_string(square), char_string(curve)];
+    let global_subrs = [char_string(&[(&[0, 100, 100, 0, 0, -100], &[8])])];
+    let local_subrs = [char_string(&[(&[-100, 0], &[5])])];
+    let cff2 = cff2_table(&char_strings, &global_subrs, &local_subrs);
+
+    // A weight axis from 100 to 900, with 650 bent from halfway to three quarters of the way to
+    // the heaviest weight.
+    let mut fvar = vec![];
+    for &value in &[1, 0, 16, 2, 1, 20, 0, 0] {
+        fvar.write_u16::<BigEndian>(value).unwrap();
+    }
+    fvar.extend_from_slice(b"wght");
+    for &value in &[100, 400, 900] {
+        fvar.write_i32::<BigEndian>(value << 16).unwrap();
+    }
+    fvar.write_u32::<BigEndian>(0).unwrap();
+    let mut avar = vec![];
+    for &value in &[1, 0, 0, 1, 4, -16384, -16384, 0, 0, 8192, 12288, 16384, 16384] {
+        avar.write_i16::<BigEndian>(value).unwrap();
+    }
+
+    // Fonts in the CFF container format load if they have CFF2 outlines.
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    BigEndian::write_u32(&mut bytes, 0x4f54544f);
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        if &bytes[record..record + 4] == b"glyf" {
+            bytes[record + 3] = b'X';
+        }
+    }
+    let bytes = add_tables(&bytes, &[(b"CFF2", &cff2), (b"fvar", &fvar), (b"avar", &avar)]);
+    let mut font = Font::new(&bytes).unwrap();
+    assert_eq!(font.outline_format(), OutlineFormat::Cff2);
+    let axes = font.variation_axes();
+    assert_eq!(axes.len(), 1);
+    assert_eq!((axes[0].tag, axes[0].min_value, axes[0].default_value, axes[0].max_value),
+               (otf::tag(b"wght"), 100.0, 400.0, 900.0));
+
+    let points = |font: &Font, glyph_id| {
+        let mut points = vec![];
+        font.for_each_point(glyph_id, |point| {
+            points.push((point.position.x, point.position.y, point.on_curve))
+        }).unwrap();
+        points
+    };
+    assert_eq!(points(&font, 0), vec![]);
+    assert_eq!(points(&font, 1), vec![(10, 10, true), (110, 10, true), (110, 110, true),
+                                      (10, 110, true), (10, 10, true)]);
+    let bounds = font.glyph_bounds(1).unwrap();
+    assert_eq!((bounds.left, bounds.bottom, bounds.right, bounds.top), (10, 10, 110, 110));
+
+    font.set_variations(&[(otf::tag(b"wght"), 900.0)]).unwrap();
+    assert_eq!(points(&font, 1)[1..3], [(150, 10, true), (150, 110, true)]);
+    font.set_variations(&[(otf::tag(b"wght"), 650.0)]).unwrap();
+    assert_eq!(points(&font, 1)[1..3], [(140, 10, true), (140, 110, true)]);
+    let mut outline_builder = OutlineBuilder::new();
+    let glyph_index = outline_builder.add_glyph(&font, 1).unwrap();
+    let bounds = outline_builder.descriptors()[glyph_index as usize].bounds();
+    assert_eq!((bounds.left, bounds.right), (10, 140));
+    font.set_variations(&[(otf::tag(b"wdth"), 50.0)]).unwrap();
+    assert_eq!(points(&font, 1)[1], (110, 10, true));
+
+    // `walk_glyph()` gives the cubic curve exactly, and `for_each_point()` approximates it with
+    // quadratic curves that end where it does.
+    let mut recorder = SegmentRecorder::default();
+    font.walk_glyph(2, &mut recorder).unwrap();
+    let point = |x, y| Point2D::new(x as f32, y as f32);
+    assert_eq!(recorder.segments, vec![
+        ("move", vec![point(0, 0)]),
+        ("cubic", vec![point(0, 100), point(100, 100), point(100, 0)]),
+        ("line", vec![point(0, 0)]),
+        ("close", vec![]),
+    ]);
+    let curve_points = points(&font, 2);
+    assert!(curve_points.len() > 4);
+    assert_eq!(curve_points[0], (0, 0, true));
+    assert!(curve_points.contains(&(100, 0, true)));
+    assert_eq!(curve_points[curve_points.len() - 1], (0, 0, true));
+    assert!(curve_points.iter().all(|&(_, y, _)| y >= 0 && y <= 100));
+
+    // `FontReader` reads CFF2 outlines with the other tables.
+    let font_reader = FontReader::new(Cursor::new(&bytes)).unwrap();
+    let streamed_font = font_reader.font().unwrap();
+    assert_eq!(points(&streamed_font, 1)[1], (110, 10, true));
+}
+
+// Returns a charstring made of the given operands and operators.
+fn char_string(instructions: &[(&[i32], &[u8])]) -> Vec<u8> {
+    let mut char_string = vec![];
+    for &(operands, operator) in instructions {
+        for &operand in operands {
+            char_string.push(28);
+            char_string.write_i16::<BigEndian>(operand as i16).unwrap();
+        }
+        char_string.extend_from_slice(operator);
+    }
+    char_string
+}
+
+// Returns a `CFF2` table with the given charstrings and subroutines, and a variation store with
+// one region that peaks at the maximum of the first axis.
+fn cff2_table(char_strings: &[Vec<u8>], global_subrs: &[Vec<u8>], local_subrs: &[Vec<u8>])
+              -> Vec<u8> {
+    // The Top DICT gives the offsets of the charstrings, the Font DICTs, and the variation store,
+    // each as a 5-byte operand.
+    let top_dict_length = 19;
+    let global_subrs = cff2_index(global_subrs);
+    let vstore_offset = 5 + top_dict_length + global_subrs.len();
+    let mut vstore = vec![];
+    for &value in &[30, 1, 0, 12, 1, 0, 22, 1, 1, 0, 16384, 16384, 0, 0, 1, 0] {
+        vstore.write_u16::<BigEndian>(value).unwrap();
+    }
+    let private_dict_offset = vstore_offset + vstore.len();
+    let mut private_dict = vec![29];
+    private_dict.write_i32::<BigEndian>(6).unwrap();
+    private_dict.push(19);
+    private_dict.extend_from_slice(&cff2_index(local_subrs));
+    let mut font_dict = vec![29];
+    font_dict.write_i32::<BigEndian>(6).unwrap();
+    font_dict.push(29);
+    font_dict.write_i32::<BigEndian>(private_dict_offset as i32).unwrap();
+    font_dict.push(18);
+    let fd_array_offset = private_dict_offset + private_dict.len();
+    let fd_array = cff2_index(&[font_dict]);
+    let char_strings_offset = fd_array_offset + fd_array.len();
+
+    let mut table = vec![2, 0, 5];
+    table.write_u16::<BigEndian>(top_dict_length as u16).unwrap();
+    for &(offset, operator) in &[(char_strings_offset, &[17][..]),
+                                 (fd_array_offset, &[12, 36][..]),
+                                 (vstore_offset, &[24][..])] {
+        table.push(29);
+        table.write_i32::<BigEndian>(offset as i32).unwrap();
+        table.extend_from_slice(operator);
+    }
+    table.extend_from_slice(&global_subrs);
+    table.extend_from_slice(&vstore);
+    table.extend_from_slice(&private_dict);
+    table.extend_from_slice(&fd_array);
+    table.extend_from_slice(&cff2_index(char_strings));
+    table
+}
+
+// Returns a CFF2 INDEX of the given objects, with 2-byte offsets.
+fn cff2_index(objects: &[Vec<u8>]) -> Vec<u8> {
+    let mut index = vec![];
+    index.write_u32::<BigEndian>(objects.len() as u32).unwrap();
+    index.push(2);
+    let mut offset = 1;
+    index.write_u16::<BigEndian>(offset).unwrap();
+    for object in objects {
+        offset += object.len() as u16;
+        index.write_u16::<BigEndian>(offset).unwrap();
+    }
+    for object in objects {
+        index.extend_from_slice(object);
+    }
+    index
+}
+
+#[derive(Default)]
+struct SegmentRecorder {
+    segments: Vec<(&'static str, Vec<Point2D<f32>>)>,
+}
+
+impl OutlineVisitor for SegmentRecorder {
+    fn move_to(&mut self, to: &Point2D<f32>) {
+        self.segments.push(("move", vec![*to]))
+    }
+
+    fn line_to(&mut self, to: &Point2D<f32>) {
+        self.segments.push(("line", vec![*to]))
+    }
+
+    fn quad_to(&mut self, control_point: &Point2D<f32>, to: &Point2D<f32>) {
+        self.segments.push(("quad", vec![*control_point, *to]))
+    }
+
+    fn cubic_to(&mut self,
+                control_point_0: &Point2D<f32>,
+                control_point_1: &Point2D<f32>,
+                to: &Point2D<f32>) {
+        self.segments.push(("cubic", vec![*control_point_0, *control_point_1, *to]))
+    }
+
+    fn close(&mut self) {
+        self.segments.push(("close", vec![]))
+    }
+}
+
+// The expected values were obtained from FreeType with `FT_Set_Char_Size()` at 72 DPI and
+// `FT_LOAD_NO_HINTING`.
+#[test]
+fn fixed_point_metrics_match_freetype() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+
+        let glyph_id = font.glyph_for_char('A').unwrap();
+        assert_eq!(font.advance_width_26_6(glyph_id, 13.3).unwrap(), 568);
+        assert_eq!(font.glyph_bounds_26_6(glyph_id, 13.3).unwrap(),
+                   GlyphFixedBounds { left: 15, bottom: 0, right: 556, top: 620 });
+
+        let glyph_id = font.glyph_for_char('g').unwrap();
+        assert_eq!(font.advance_width_26_6(glyph_id, 7.3).unwrap(), 260);
+        assert_eq!(font.glyph_bounds_26_6(glyph_id, 7.3).unwrap(),
+                   GlyphFixedBounds { left: 13, bottom: -102, right: 228, top: 252 });
+
+        // A composite glyph, whose components FreeType scales separately.
+        assert_eq!(font.advance_width_26_6(117, 7.3).unwrap(), 130);
+        assert_eq!(font.glyph_bounds_26_6(117, 7.3).unwrap(),
+                   GlyphFixedBounds { left: -3, bottom: 0, right: 95, top: 346 });
+    }
+}
+
+#[test]
+fn advances_in_pixels_round_half_up() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    unsafe {
+        let font = Font::new(file.as_slice()).unwrap();
+        let units_per_em = font.units_per_em() as f32;
+
+        // The "i" is 455 units wide on an em of 2048, so it's 227.5 pixels wide at 1024 pixels
+        // per em.
+        let glyph_id = font.glyph_for_char('i').unwrap();
+        assert_eq!(font.advance_pixels(glyph_id, units_per_em).unwrap(), 455.0);
+        assert_eq!(font.advance_pixels(glyph_id, units_per_em / 2.0).unwrap(), 227.5);
+        assert_eq!(font.advance_pixels_rounded(glyph_id, units_per_em / 2.0).unwrap(), 228);
+        assert_eq!(font.advance_pixels_rounded(glyph_id, 12.0).unwrap(), 3);
+
+        let line_height = font.line_height(units_per_em);
+        assert_eq!(font.line_advance_pixels(units_per_em), line_height);
+        assert_eq!(font.line_advance_pixels(units_per_em / 4.0), line_height / 4.0);
+    }
+}
+
+#[test]
+fn line_height_follows_use_typo_metrics() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let units_per_em = font.units_per_em() as f32;
+
+    // The font doesn't ask for its typographic metrics, so the `hhea` metrics apply.
+    assert_eq!((font.ascender(), font.descender(), font.line_gap()), (2007, -451, 0));
+    assert_eq!(font.line_height(units_per_em), 2458.0);
+    assert_eq!(font.line_height(units_per_em / 2.0), 1229.0);
+
+    // Set `USE_TYPO_METRICS` in the `OS/2` table's `fsSelection`.
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    let record = (0..num_tables).map(|index| 12 + index * 16)
+                                .find(|&record| &bytes[record..record + 4] == b"OS/2")
+                                .unwrap();
+    let fs_selection = BigEndian::read_u32(&bytes[record + 8..]) as usize + 62;
+    bytes[fs_selection + 1] |= 1 << 7;
+
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!((font.ascender(), font.descender(), font.line_gap()), (1599, -449, 0));
+    let line_height = font.ascender() - font.descender() + font.line_gap();
+    assert_eq!(font.line_height(units_per_em), line_height as f32);
+}
+
+#[test]
+fn metrics_for_matches_per_glyph_queries() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_ids: Vec<u16> = "Pathfinder".chars()
+                                          .map(|c| font.glyph_for_char(c).unwrap())
+                                          .collect();
+
+    let metrics = font.metrics_for(&glyph_ids).unwrap();
+    assert_eq!(metrics.len(), glyph_ids.len());
+    for (metrics, &glyph_id) in metrics.iter().zip(glyph_ids.iter()) {
+        let horizontal_metrics = font.metrics_for_glyph(glyph_id).unwrap();
+        let bounds = font.glyph_bounds(glyph_id).unwrap();
+        assert_eq!(metrics.advance_width, horizontal_metrics.advance_width);
+        assert_eq!(metrics.lsb, horizontal_metrics.lsb);
+        assert_eq!((metrics.bounds.left, metrics.bounds.bottom), (bounds.left, bounds.bottom));
+        assert_eq!(metrics.bounds.size(), bounds.size());
+    }
+}
+
+#[test]
+fn computed_glyph_bounds_match_accurate_header_bounds() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // This font's header bounds are accurate, so the computed bounds should agree with them.
+    for character in "AgS".chars() {
+        let glyph_id = font.glyph_for_char(character).unwrap();
+        let computed = font.computed_glyph_bounds(glyph_id).unwrap();
+        let header = font.glyph_bounds(glyph_id).unwrap();
+        assert_eq!((computed.left, computed.bottom, computed.right, computed.top),
+                   (header.left, header.bottom, header.right, header.top));
+    }
+
+    let space_bounds = font.computed_glyph_bounds(font.glyph_for_char(' ').unwrap()).unwrap();
+    assert_eq!(space_bounds.size(), Size2D::new(0, 0));
+}
+
+#[test]
+fn ink_extents_touch_the_curves() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // At one pixel per unit, the ink extents match the accurate header bounds to within their
+    // rounding, even where control points stick out past the curves.
+    let point_size = font.units_per_em() as f32;
+    for character in "AgOS".chars() {
+        let glyph_id = font.glyph_for_char(character).unwrap();
+        let ink = font.ink_extents(glyph_id, point_size).unwrap();
+        let header = font.glyph_bounds(glyph_id).unwrap();
+        for &(ink, header) in &[(ink.left, header.left), (ink.bottom, header.bottom),
+                                (ink.right, header.right), (ink.top, header.top)] {
+            assert!((ink - header as f32).abs() <= 1.0, "{:?}: {} != {}", character, ink, header)
+        }
+    }
+
+    let glyph_id = font.glyph_for_char('O').unwrap();
+    let half = font.ink_extents(glyph_id, point_size * 0.5).unwrap();
+    let full = font.ink_extents(glyph_id, point_size).unwrap();
+    assert_eq!(half.right, full.right * 0.5);
+
+    assert!(font.ink_extents(font.glyph_for_char(' ').unwrap(), point_size).is_none());
+}
+
+#[test]
+fn glyph_ids_cover_the_font() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // The glyph count comes from `maxp`, which stores it at byte 4.
+    let glyph_count = BigEndian::read_u16(&font.table(otf::tag(b"maxp")).unwrap()[4..]);
+    assert_eq!(font.glyph_count(), glyph_count);
+    assert_eq!(font.glyph_ids().count(), glyph_count as usize);
+
+    let non_empty: Vec<u16> = font.non_empty_glyph_ids().collect();
+    assert!(non_empty.len() < glyph_count as usize);
+    assert!(non_empty.contains(&font.glyph_for_char('A').unwrap()));
+    assert!(!non_empty.contains(&font.glyph_for_char(' ').unwrap()));
+    for glyph_id in font.glyph_ids() {
+        assert_eq!(non_empty.contains(&glyph_id), font.glyph_data(glyph_id).is_some())
+    }
+}
+
+#[test]
+fn glyph_data_is_the_raw_glyf_entry() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    let data = font.glyph_data(glyph_id).expect("No data for 'A'");
+    let bounds = font.glyph_bounds(glyph_id).unwrap();
+    assert!(BigEndian::read_i16(&data[0..]) > 0);
+    assert_eq!(BigEndian::read_i16(&data[2..]) as i32, bounds.left);
+    assert_eq!(BigEndian::read_i16(&data[4..]) as i32, bounds.bottom);
+    assert_eq!(BigEndian::read_i16(&data[6..]) as i32, bounds.right);
+    assert_eq!(BigEndian::read_i16(&data[8..]) as i32, bounds.top);
+
+    assert_eq!(font.glyph_data(font.glyph_for_char(' ').unwrap()), None);
+    assert_eq!(font.glyph_data(u16::MAX), None);
+}
+
+#[test]
+fn glyphs_without_contours_are_empty() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let original_bytes = unsafe { file.as_slice().to_vec() };
+    let font = Font::new(&original_bytes).unwrap();
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    let data = font.glyph_data(glyph_id).unwrap();
+    let glyph_offset = data.as_ptr() as usize - original_bytes.as_ptr() as usize;
+    let (first_endpoint, last_endpoint) = (BigEndian::read_u16(&data[10..]),
+                                           BigEndian::read_u16(&data[12..]));
+    assert_eq!(BigEndian::read_i16(&data[0..]), 2);
+
+    // With no contours, the rest of the glyph is read as instructions, and its bounding box is
+    // ignored.
+    let mut bytes = original_bytes.clone();
+    BigEndian::write_i16(&mut bytes[glyph_offset..], 0);
+    let font = Font::new(&bytes).unwrap();
+    let mut point_count = 0;
+    font.for_each_point(glyph_id, |_| point_count += 1).unwrap();
+    assert_eq!(point_count, 0);
+    let bounds = font.glyph_bounds(glyph_id).unwrap();
+    assert_eq!((bounds.left, bounds.bottom, bounds.right, bounds.top), (0, 0, 0, 0));
+
+    let mut outline_builder = OutlineBuilder::new();
+    outline_builder.add_glyph(&font, glyph_id).unwrap();
+    assert!(outline_builder.indices().is_empty());
+    let bounds = outline_builder.descriptors()[0].bounds();
+    assert_eq!((bounds.left, bounds.bottom, bounds.right, bounds.top), (0, 0, 0, 0));
+
+    // Contour end points that decrease don't describe the points that follow them.
+    let mut bytes = original_bytes.clone();
+    BigEndian::write_u16(&mut bytes[glyph_offset + 10..], last_endpoint);
+    BigEndian::write_u16(&mut bytes[glyph_offset + 12..], first_endpoint);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.for_each_point(glyph_id, |_| {}), Err(otf::Error::MalformedGlyph));
+}
+
+#[test]
+fn out_of_spec_units_per_em_is_rejected() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let mut bytes = unsafe { file.as_slice().to_vec() };
+
+    // Find the `head` table in the table directory; `unitsPerEm` is 18 bytes into it.
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    let head_offset = (0..num_tables).map(|index| 12 + index * 16)
+                                     .find(|&record| &bytes[record..record + 4] == b"head")
+                                     .map(|record| BigEndian::read_u32(&bytes[record + 8..]))
+                                     .unwrap() as usize;
+
+    for &(units_per_em, valid) in &[(0, false), (1000, true), (2000, true), (16384, true),
+                                    (16385, false), (65535, false)] {
+        BigEndian::write_u16(&mut bytes[head_offset + 18..], units_per_em);
+        match Font::new(&bytes) {
+            Ok(font) => {
+                assert!(valid, "{} units per em was accepted", units_per_em);
+                assert_eq!(font.units_per_em(), units_per_em);
+            }
+            Err(err) => {
+                assert!(!valid, "{} units per em was rejected", units_per_em);
+                assert_eq!(err, otf::Error::InvalidUnitsPerEm);
+            }
+        }
+    }
+}
+
+#[test]
+fn table_errors_name_the_table() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let original_bytes = unsafe { file.as_slice().to_vec() };
+    let num_tables = BigEndian::read_u16(&original_bytes[4..]) as usize;
+    let table_record = |tag: &[u8]| {
+        (0..num_tables).map(|index| 12 + index * 16)
+                       .find(|&record| &original_bytes[record..record + 4] == tag)
+                       .unwrap()
+    };
+
+    // Renaming a required table makes it missing.
+    let mut bytes = original_bytes.clone();
+    bytes[table_record(b"hhea")] = b'x';
+    assert_eq!(Font::new(&bytes).err(), Some(otf::Error::MissingTable(otf::tag(b"hhea"))));
+
+    // A table that runs past the end of the file is too short.
+    let mut bytes = original_bytes.clone();
+    BigEndian::write_u32(&mut bytes[table_record(b"glyf") + 12..], 0x7fff_ffff);
+    assert_eq!(Font::new(&bytes).err(), Some(otf::Error::TableTooShort(otf::tag(b"glyf"))));
+}
+
+#[test]
+fn parse_limits_reject_oversized_fonts() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let bytes = unsafe { file.as_slice() };
+    let limited = |limits: ParseLimits| Font::from_bytes_limited(bytes, limits);
+
+    let font = limited(ParseLimits::default()).unwrap();
+    let (stem, round, accented) = (font.glyph_for_char('l').unwrap(),
+                                   font.glyph_for_char('O').unwrap(),
+                                   font.glyph_for_char('\u{e9}').unwrap());
+    let count_points = |font: &Font, glyph_id| {
+        let mut point_count = 0;
+        font.for_each_point(glyph_id, |_| point_count += 1).map(|_| point_count)
+    };
+    assert!(count_points(&font, accented).unwrap() > 0);
+
+    assert_eq!(limited(ParseLimits { max_glyph_count: 10, ..ParseLimits::default() }).err(),
+               Some(otf::Error::TooManyGlyphs));
+    match limited(ParseLimits { max_table_size: 16, ..ParseLimits::default() }) {
+        Err(otf::Error::TableTooLarge(_)) => {}
+        result => panic!("Expected a table to be too large, got {:?}", result.err()),
+    }
+
+    // Point and component limits apply when outlines are read.
+    let font = limited(ParseLimits { max_points_per_glyph: 8, ..ParseLimits::default() }).unwrap();
+    assert!(count_points(&font, stem).is_ok());
+    assert_eq!(count_points(&font, round), Err(otf::Error::TooManyPoints));
+    assert_eq!(count_points(&font, accented), Err(otf::Error::TooManyPoints));
+
+    let font = limited(ParseLimits { max_component_depth: 0, ..ParseLimits::default() }).unwrap();
+    assert!(count_points(&font, round).is_ok());
+    assert_eq!(count_points(&font, accented), Err(otf::Error::ComponentsTooDeep));
+    assert_eq!(font.glyph_bounds_26_6(accented, 16.0).err(),
+               Some(otf::Error::ComponentsTooDeep));
+}
+
+#[test]
+fn truncated_dfont_resources_are_errors() {
+    // A resource fork with one `sfnt` resource, whose size runs past the end of the file. The
+    // resource data starts at byte 256, as in every `.dfont`, and the map follows the header.
+    let mut bytes = vec![];
+    for &value in &[256, 16, 8, 50] {
+        bytes.write_u32::<BigEndian>(value).unwrap();
+    }
+    bytes.extend_from_slice(&[0; 24]);
+    bytes.write_u16::<BigEndian>(28).unwrap();
+    bytes.write_u16::<BigEndian>(0).unwrap();
+    bytes.write_u16::<BigEndian>(0).unwrap();
+    bytes.extend_from_slice(b"sfnt");
+    bytes.write_u16::<BigEndian>(0).unwrap();
+    bytes.write_u16::<BigEndian>(10).unwrap();
+    bytes.write_u16::<BigEndian>(0).unwrap();
+    bytes.write_u16::<BigEndian>(0xffff).unwrap();
+    bytes.write_u32::<BigEndian>(0).unwrap();
+    bytes.write_u32::<BigEndian>(0).unwrap();
+    bytes.resize(256, 0);
+    bytes.write_u32::<BigEndian>(1000).unwrap();
+    bytes.write_u32::<BigEndian>(0).unwrap();
+
+    assert_eq!(Font::from_bytes_limited(&bytes, ParseLimits::default()).err(),
+               Some(otf::Error::UnexpectedEof));
+
+    // The type count is stored minus one, so its largest value must not overflow.
+    BigEndian::write_u16(&mut bytes[44..], 0x7fff);
+    assert_eq!(Font::from_bytes_limited(&bytes, ParseLimits::default()).err(),
+               Some(otf::Error::UnexpectedEof));
+}
+
+#[test]
+fn tables_are_found_by_tag() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // The `head` table is always 54 bytes long and holds its magic number at byte 12.
+    let head = font.table(otf::tag(b"head")).unwrap();
+    assert_eq!(head.len(), 54);
+    assert_eq!(BigEndian::read_u32(&head[12..16]), 0x5f0f3cf5);
+
+    assert!(font.table(otf::tag(b"name")).is_some());
+    assert!(font.table(otf::tag(b"MATH")).is_none());
+}
+
+#[test]
+fn flatten_cache_reuses_contours_until_cleared() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let mut font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_id = font.glyph_for_char('g').unwrap();
+
+    let uncached = font.flattened_contours(glyph_id, 1.0).unwrap();
+    assert!(!Arc::ptr_eq(&uncached, &font.flattened_contours(glyph_id, 1.0).unwrap()));
+
+    font.set_flatten_cache_enabled(true);
+    let cached = font.flattened_contours(glyph_id, 1.0).unwrap();
+    assert_eq!(cached, uncached);
+    assert!(Arc::ptr_eq(&cached, &font.flattened_contours(glyph_id, 1.0).unwrap()));
+    assert!(!Arc::ptr_eq(&cached, &font.flattened_contours(glyph_id, 0.5).unwrap()));
+
+    font.clear_flatten_cache();
+    assert!(!Arc::ptr_eq(&cached, &font.flattened_contours(glyph_id, 1.0).unwrap()));
+}
+
+// Returns a copy of the font with a format 14 subtable prepended to its character map. Each
+// selector is given with its default sequences and its non-default sequences and their glyphs.
+fn add_variation_sequences(font: &[u8], selectors: &[(u32, &[u32], &[(u32, u16)])]) -> Vec<u8> {
+    fn write_u24(bytes: &mut Vec<u8>, value: u32) {
+        bytes.write_u16::<BigEndian>((value >> 8) as u16).unwrap();
+        bytes.write_u8(value as u8).unwrap();
+    }
+
+    let mut bytes = font.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    let record = (0..num_tables).map(|index| 12 + index * 16)
+                                .find(|&record| &bytes[record..record + 4] == b"cmap")
+                                .unwrap();
+    let cmap_offset = BigEndian::read_u32(&bytes[record + 8..]) as usize;
+    let cmap_length = BigEndian::read_u32(&bytes[record + 12..]) as usize;
+    let cmap = bytes[cmap_offset..(cmap_offset + cmap_length)].to_vec();
+    let num_subtables = BigEndian::read_u16(&cmap[2..]) as usize;
+
+    // Put the new encoding record first, shifting the existing subtables along by its size.
+    let subtables = &cmap[(4 + num_subtables * 8)..];
+    let format_14_offset = 4 + (num_subtables + 1) * 8 + subtables.len();
+    let mut new_cmap = vec![];
+    new_cmap.write_u16::<BigEndian>(0).unwrap();
+    new_cmap.write_u16::<BigEndian>(num_subtables as u16 + 1).unwrap();
+    new_cmap.write_u16::<BigEndian>(0).unwrap();
+    new_cmap.write_u16::<BigEndian>(5).unwrap();
+    new_cmap.write_u32::<BigEndian>(format_14_offset as u32).unwrap();
+    for subtable_index in 0..num_subtables {
+        let record = &cmap[(4 + subtable_index * 8)..];
+        new_cmap.extend_from_slice(&record[0..4]);
+        new_cmap.write_u32::<BigEndian>(BigEndian::read_u32(&record[4..]) + 8).unwrap();
+    }
+    new_cmap.extend_from_slice(subtables);
+
+    let mut format_14 = vec![];
+    let mut mappings = vec![];
+    let mappings_offset = 10 + selectors.len() * 11;
+    format_14.write_u16::<BigEndian>(14).unwrap();
+    format_14.write_u32::<BigEndian>(0).unwrap();
+    format_14.write_u32::<BigEndian>(selectors.len() as u32).unwrap();
+    for &(selector, defaults, non_defaults) in selectors {
+        write_u24(&mut format_14, selector);
+        format_14.write_u32::<BigEndian>((mappings_offset + mappings.len()) as u32).unwrap();
+        mappings.write_u32::<BigEndian>(defaults.len() as u32).unwrap();
+        for &default in defaults {
+            write_u24(&mut mappings, default);
+            mappings.write_u8(0).unwrap();
+        }
+        format_14.write_u32::<BigEndian>((mappings_offset + mappings.len()) as u32).unwrap();
+        mappings.write_u32::<BigEndian>(non_defaults.len() as u32).unwrap();
+        for &(base, glyph_id) in non_defaults {
+            write_u24(&mut mappings, base);
+            mappings.write_u16::<BigEndian>(glyph_id).unwrap();
+        }
+    }
+    format_14.extend_from_slice(&mappings);
+    let format_14_length = format_14.len() as u32;
+    BigEndian::write_u32(&mut format_14[2..], format_14_length);
+    new_cmap.extend_from_slice(&format_14);
+
+    // Append the new character map and point the table directory at it.
+    while bytes.len() % 4 != 0 {
+        bytes.push(0)
+    }
+    let new_cmap_offset = bytes.len() as u32;
+    BigEndian::write_u32(&mut bytes[record + 8..], new_cmap_offset);
+    BigEndian::write_u32(&mut bytes[record + 12..], new_cmap.len() as u32);
+    bytes.extend_from_slice(&new_cmap);
+    bytes
+}
+
+// Returns a copy of the font whose character map has only the given Unicode subtable in place of
+// the original ones.
+fn with_cmap(font: &[u8], subtable: &[u8]) -> Vec<u8> {
+    let mut bytes = font.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        if &bytes[record..record + 4] == b"cmap" {
+            bytes[record + 3] = b'X';
+        }
+    }
+
+    let mut cmap = vec![];
+    for &value in &[0, 1, 3, 10] {
+        cmap.write_u16::<BigEndian>(value).unwrap();
+    }
+    cmap.write_u32::<BigEndian>(12).unwrap();
+    cmap.extend_from_slice(subtable);
+    add_tables(&bytes, &[(b"cmap", &cmap)])
+}
+
+// Returns a copy of the font with the given tables added to it.
+fn add_tables(font: &[u8], tables: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let num_tables = BigEndian::read_u16(&font[4..]) as usize;
+    let directory_end = 12 + num_tables * 16;
+    let shift = tables.len() * 16;
+
+    // Copy the directory, making room for the new records by moving every table along.
+    let mut bytes = font[0..directory_end].to_vec();
+    BigEndian::write_u16(&mut bytes[4..], (num_tables + tables.len()) as u16);
+    for index in 0..num_tables {
+        let offset_position = 12 + index * 16 + 8;
+        let offset = BigEndian::read_u32(&bytes[offset_position..]);
+        BigEndian::write_u32(&mut bytes[offset_position..], offset + shift as u32);
+    }
+
+    let mut table_data = vec![];
+    let mut table_offset = font.len() + shift;
+    for &(tag, data) in tables {
+        bytes.extend_from_slice(tag);
+        bytes.write_u32::<BigEndian>(0).unwrap();
+        bytes.write_u32::<BigEndian>(table_offset as u32).unwrap();
+        bytes.write_u32::<BigEndian>(data.len() as u32).unwrap();
+        table_data.extend_from_slice(data);
+        table_offset += data.len();
+    }
+
+    bytes.extend_from_slice(&font[directory_end..]);
+    bytes.extend_from_slice(&table_data);
+    bytes
+}
+
+#[test]
+fn color_layers_use_the_chosen_palette() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert_eq!(font.palette_count(), 0);
+    let glyph_ids: Vec<u16> = "ABCD".chars().map(|c| font.glyph_for_char(c).unwrap()).collect();
+
+    // "A" is drawn as "B" in palette entry 0, "C" in the text color, and "D" in entry 1.
+    let mut colr = vec![];
+    for &value in &[0, 1] {
+        colr.write_u16::<BigEndian>(value).unwrap();
+    }
+    for &value in &[14, 20] {
+        colr.write_u32::<BigEndian>(value).unwrap();
+    }
+    for &value in &[3, glyph_ids[0], 0, 3, glyph_ids[1], 0, glyph_ids[2], 0xffff,
+                    glyph_ids[3], 1] {
+        colr.write_u16::<BigEndian>(value).unwrap();
+    }
+
+    // Two palettes of two colors each, stored as BGRA.
+    let mut cpal = vec![];
+    for &value in &[0, 2, 2, 4] {
+        cpal.write_u16::<BigEndian>(value).unwrap();
+    }
+    cpal.write_u32::<BigEndian>(16).unwrap();
+    for &value in &[0, 2] {
+        cpal.write_u16::<BigEndian>(value).unwrap();
+    }
+    cpal.extend_from_slice(&[0, 0, 255, 255, 0, 255, 0, 255, 255, 0, 0, 255, 255, 255, 255, 128]);
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"COLR", &colr), (b"CPAL", &cpal)]);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.palette_count(), 2);
+
+    let layers = |colors: [Option<[u8; 4]>; 3]| {
+        vec![
+            otf::ColorLayer { glyph_id: glyph_ids[1], color: colors[0] },
+            otf::ColorLayer { glyph_id: glyph_ids[2], color: colors[1] },
+            otf::ColorLayer { glyph_id: glyph_ids[3], color: colors[2] },
+        ]
+    };
+    let light = layers([Some([255, 0, 0, 255]), None, Some([0, 255, 0, 255])]);
+    let dark = layers([Some([0, 0, 255, 255]), None, Some([255, 255, 255, 128])]);
+    assert_eq!(font.color_layers(glyph_ids[0]), light);
+    assert_eq!(font.color_layers_with_palette(glyph_ids[0], 1), dark);
+    assert_eq!(font.color_layers_with_palette(glyph_ids[0], 2), light);
+    assert!(font.color_layers(glyph_ids[1]).is_empty());
+}
+
+#[test]
+fn svg_documents_cover_glyph_ranges() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+
+    // Glyphs 3 to 5 share a plain document, and glyph 9 has a compressed one.
+    let plain = b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+    let compressed = [0x1f, 0x8b, 0x08, 0x00];
+    let mut svg = vec![];
+    svg.write_u16::<BigEndian>(0).unwrap();
+    svg.write_u32::<BigEndian>(10).unwrap();
+    svg.write_u32::<BigEndian>(0).unwrap();
+    svg.write_u16::<BigEndian>(2).unwrap();
+    let mut offset = 2 + 2 * 12;
+    for &(start_glyph_id, end_glyph_id, length) in &[(3, 5, plain.len()),
+                                                     (9, 9, compressed.len())] {
+        svg.write_u16::<BigEndian>(start_glyph_id).unwrap();
+        svg.write_u16::<BigEndian>(end_glyph_id).unwrap();
+        svg.write_u32::<BigEndian>(offset as u32).unwrap();
+        svg.write_u32::<BigEndian>(length as u32).unwrap();
+        offset += length
+    }
+    svg.extend_from_slice(plain);
+    svg.extend_from_slice(&compressed);
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"SVG ", &svg)]);
+    let font = Font::new(&bytes).unwrap();
+    for glyph_id in 3..6 {
+        let document = font.svg_document(glyph_id).unwrap();
+        assert_eq!(document.bytes, &plain[..]);
+        assert!(!document.compressed);
+        assert_eq!((document.start_glyph_id, document.end_glyph_id), (3, 5));
+    }
+    let document = font.svg_document(9).unwrap();
+    assert_eq!(document.bytes, &compressed[..]);
+    assert!(document.compressed);
+    for &glyph_id in &[0, 2, 6, 8, 10] {
+        assert!(font.svg_document(glyph_id).is_none())
+    }
+}
+
+#[test]
+fn bitmap_strikes_come_from_sbix_and_cblc() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert!(font.bitmap_strikes().is_empty());
+
+    // Two `sbix` strikes, each just a ppem and a resolution with no glyph data.
+    let mut sbix = vec![];
+    sbix.write_u16::<BigEndian>(1).unwrap();
+    sbix.write_u16::<BigEndian>(1).unwrap();
+    sbix.write_u32::<BigEndian>(2).unwrap();
+    sbix.write_u32::<BigEndian>(16).unwrap();
+    sbix.write_u32::<BigEndian>(20).unwrap();
+    for &ppem in &[40, 20] {
+        sbix.write_u16::<BigEndian>(ppem).unwrap();
+        sbix.write_u16::<BigEndian>(72).unwrap();
+    }
+
+    // Two `CBLC` bitmap size records, of which only the ppems matter.
+    let mut cblc = vec![];
+    cblc.write_u16::<BigEndian>(3).unwrap();
+    cblc.write_u16::<BigEndian>(0).unwrap();
+    cblc.write_u32::<BigEndian>(2).unwrap();
+    for &ppem in &[64, 20] {
+        cblc.extend_from_slice(&[0; 44]);
+        cblc.extend_from_slice(&[ppem, ppem, 32, 1]);
+    }
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"sbix", &sbix), (b"CBLC", &cblc)]);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.bitmap_strikes(), vec![20, 40, 64]);
+}
+
+#[test]
+fn gasp_behavior_follows_ranges() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // The test font antialiases up to 8 ppem, grid-fits up to 16 ppem, and does both above that.
+    assert_eq!(font.gasp_behavior(8), otf::GASP_DOGRAY);
+    assert_eq!(font.gasp_behavior(9), otf::GASP_GRIDFIT);
+    assert_eq!(font.gasp_behavior(16), otf::GASP_GRIDFIT);
+    assert_eq!(font.gasp_behavior(200), otf::GASP_GRIDFIT | otf::GASP_DOGRAY);
+
+    // Rewrite the table in place as a version 1 table that smooths symmetrically at every size.
+    let original = unsafe { file.as_slice() };
+    let gasp_offset = font.table(otf::tag(b"gasp")).unwrap().as_ptr() as usize -
+        original.as_ptr() as usize;
+    let mut bytes = original.to_vec();
+    let mut gasp = vec![];
+    for &value in &[1, 3, 8, 0xa, 16, 0x9, 0xffff, 0xb] {
+        gasp.write_u16::<BigEndian>(value).unwrap();
+    }
+    bytes[gasp_offset..(gasp_offset + gasp.len())].copy_from_slice(&gasp);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.gasp_behavior(8), otf::GASP_DOGRAY | otf::GASP_SYMMETRIC_SMOOTHING);
+    assert_eq!(font.gasp_behavior(200), otf::GASP_GRIDFIT | otf::GASP_DOGRAY |
+               otf::GASP_SYMMETRIC_SMOOTHING);
+
+    // Version 0 tables can't use the symmetric flags.
+    bytes[gasp_offset + 1] = 0;
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.gasp_behavior(8), otf::GASP_DOGRAY);
+}
+
+#[test]
+fn meta_languages_come_from_dlng_and_slng() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert!(font.design_languages().is_empty());
+    assert!(font.supported_languages().is_empty());
+
+    let (design, supported): (&[u8], &[u8]) = (b"Latn", b"Latn, Cyrl,en-Latn ,");
+    let mut meta = vec![];
+    for &value in &[1, 0, 0, 2] {
+        meta.write_u32::<BigEndian>(value).unwrap();
+    }
+    let mut offset = 16 + 2 * 12;
+    for &(tag, data) in &[(b"dlng", design), (b"slng", supported)] {
+        meta.extend_from_slice(tag);
+        meta.write_u32::<BigEndian>(offset as u32).unwrap();
+        meta.write_u32::<BigEndian>(data.len() as u32).unwrap();
+        offset += data.len()
+    }
+    meta.extend_from_slice(design);
+    meta.extend_from_slice(supported);
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"meta", &meta)]);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.design_languages(), vec!["Latn".to_owned()]);
+    assert_eq!(font.supported_languages(),
+               vec!["Latn".to_owned(), "Cyrl".to_owned(), "en-Latn".to_owned()]);
+}
+
+#[test]
+fn gdef_classifies_marks() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let (glyph_a, glyph_b) = (font.glyph_for_char('A').unwrap(), font.glyph_for_char('B').unwrap());
+    let glyph_c = font.glyph_for_char('C').unwrap();
+    assert_eq!(font.glyph_class(glyph_a), GlyphClass::Unassigned);
+    assert!(!font.is_mark_glyph(glyph_b));
+
+    // A version 1.0 header followed by a format 2 class definition table.
+    let mut gdef = vec![];
+    for &value in &[1, 0, 12, 0, 0, 0, 2, 2] {
+        gdef.write_u16::<BigEndian>(value).unwrap();
+    }
+    let mut ranges = vec![(glyph_a, 1), (glyph_b, 3)];
+    ranges.sort();
+    for &(glyph_id, class) in &ranges {
+        for &value in &[glyph_id, glyph_id, class] {
+            gdef.write_u16::<BigEndian>(value).unwrap();
+        }
+    }
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"GDEF", &gdef)]);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.glyph_class(glyph_a), GlyphClass::Base);
+    assert_eq!(font.glyph_class(glyph_b), GlyphClass::Mark);
+    assert_eq!(font.glyph_class(glyph_c), GlyphClass::Unassigned);
+    assert!(font.is_mark_glyph(glyph_b));
+    assert!(!font.is_mark_glyph(glyph_a));
+}
+
+#[test]
+fn baselines_come_from_base_table() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert_eq!(font.baseline_offset(BaselineTag::Roman, otf::tag(b"latn")), None);
+
+    // Each base script table points to its values, which list the coordinates of the `ideo` and
+    // `romn` baselines, in the order of the tag list.
+    let base_script = |ideographic: i16, roman: i16| {
+        let mut base_script = vec![];
+        for &value in &[6, 0, 0, 1, 2, 8, 12] {
+            base_script.write_u16::<BigEndian>(value).unwrap();
+        }
+        for &coordinate in &[ideographic, roman] {
+            base_script.write_u16::<BigEndian>(1).unwrap();
+            base_script.write_i16::<BigEndian>(coordinate).unwrap();
+        }
+        base_script
+    };
+    let (default_script, hani_script) = (base_script(-120, 0), base_script(-100, 20));
+
+    let mut base = vec![];
+    for &value in &[1, 0, 8, 0, 4, 14, 2] {
+        base.write_u16::<BigEndian>(value).unwrap();
+    }
+    base.extend_from_slice(b"ideoromn");
+    base.write_u16::<BigEndian>(2).unwrap();
+    base.extend_from_slice(b"DFLT");
+    base.write_u16::<BigEndian>(14).unwrap();
+    base.extend_from_slice(b"hani");
+    base.write_u16::<BigEndian>(14 + default_script.len() as u16).unwrap();
+    base.extend_from_slice(&default_script);
+    base.extend_from_slice(&hani_script);
+
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"BASE", &base)]);
+    let font = Font::new(&bytes).unwrap();
+    let hani = otf::tag(b"hani");
+    assert_eq!(font.baseline_offset(BaselineTag::IdeographicBottom, hani), Some(-100));
+    assert_eq!(font.baseline_offset(BaselineTag::Roman, hani), Some(20));
+
+    // Scripts without an entry of their own use the `DFLT` entry.
+    let latn = otf::tag(b"latn");
+    assert_eq!(font.baseline_offset(BaselineTag::IdeographicBottom, latn), Some(-120));
+    assert_eq!(font.baseline_offset(BaselineTag::Roman, latn), Some(0));
+    assert_eq!(font.baseline_offset(BaselineTag::Hanging, latn), None);
+}
+
+#[test]
+fn glyph_for_char_variation_uses_format_14_subtable() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let text_selector = 0xfe0e;
+    let ideographic_selector = 0xe0100;
+    let bytes = add_variation_sequences(unsafe { file.as_slice() }, &[
+        (text_selector, &['A' as u32], &[('B' as u32, 5)]),
+        (ideographic_selector, &[], &[('A' as u32, 7), ('C' as u32, 9)]),
+    ]);
+    let font = Font::new(&bytes).unwrap();
+
+    // Plain lookups skip the variation sequences subtable.
+    let glyph_a = font.glyph_for_char('A').unwrap();
+    let glyph_b = font.glyph_for_char('B').unwrap();
+
+    assert_eq!(font.glyph_for_char_variation('B', '\u{fe0e}'), Some(5));
+    assert_eq!(font.glyph_for_char_variation('A', '\u{e0100}'), Some(7));
+    assert_eq!(font.glyph_for_char_variation('C', '\u{e0100}'), Some(9));
+
+    // Default sequences, unknown sequences, and unknown selectors fall back to the base.
+    assert_eq!(font.glyph_for_char_variation('A', '\u{fe0e}'), Some(glyph_a));
+    assert_eq!(font.glyph_for_char_variation('B', '\u{e0100}'), Some(glyph_b));
+    assert_eq!(font.glyph_for_char_variation('A', '\u{fe0f}'), Some(glyph_a));
+    assert_eq!(font.glyph_for_char_variation('\u{4e00}', '\u{fe0e}'), None);
+}
+
+#[test]
+fn msdf_edges_differ_at_corners() {
+    let square = [
+        Segment::Line(Point2D::new(0.0, 0.0), Point2D::new(0.0, 1.0)),
+        Segment::Line(Point2D::new(0.0, 1.0), Point2D::new(1.0, 1.0)),
+        Segment::Line(Point2D::new(1.0, 1.0), Point2D::new(1.0, 0.0)),
+        Segment::Line(Point2D::new(1.0, 0.0), Point2D::new(0.0, 0.0)),
+    ];
+    let edges = msdf::color_edges(&square);
+    assert_eq!(edges.len(), 4);
+    for (index, &(_, color)) in edges.iter().enumerate() {
+        let (_, next_color) = edges[(index + 1) % edges.len()];
+        assert!(color != next_color);
+        assert_eq!(color.count_ones(), 2);
+        assert!((color & next_color).count_ones() == 1)
+    }
+
+    // A teardrop made of one curve has a single corner where it meets itself, so it's cut into
+    // thirds and colored in three runs.
+    let teardrop = [
+        Segment::Cubic(Point2D::new(0.0, 0.0),
+                       Point2D::new(2.0, 2.0),
+                       Point2D::new(-2.0, 2.0),
+                       Point2D::new(0.0, 0.0)),
+    ];
+    let edges = msdf::color_edges(&teardrop);
+    let colors: Vec<u8> = edges.iter().map(|&(_, color)| color).collect();
+    assert_eq!(colors, vec![msdf::MAGENTA, msdf::WHITE, msdf::YELLOW]);
+
+    // A circle has no corners at all.
+    let circle = [
+        Segment::Quad(Point2D::new(-1.0, 0.0), Point2D::new(-1.0, 1.0), Point2D::new(0.0, 1.0)),
+        Segment::Quad(Point2D::new(0.0, 1.0), Point2D::new(1.0, 1.0), Point2D::new(1.0, 0.0)),
+        Segment::Quad(Point2D::new(1.0, 0.0), Point2D::new(1.0, -1.0), Point2D::new(0.0, -1.0)),
+        Segment::Quad(Point2D::new(0.0, -1.0), Point2D::new(-1.0, -1.0), Point2D::new(-1.0, 0.0)),
+    ];
+    assert!(msdf::color_edges(&circle).iter().all(|&(_, color)| color == msdf::WHITE))
+}
+
+#[test]
+fn msdf_median_matches_the_outline() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    let (point_size, range) = (64.0, 4.0);
+    let glyph_id = font.glyph_for_char('I').unwrap();
+    let bounds = font.glyph_msdf_bounds(glyph_id, point_size, range).unwrap();
+    let pixel_bounds = font.glyph_bounds(glyph_id)
+                           .unwrap()
+                           .subpixel_bounds(font.units_per_em(), point_size)
+                           .round_out();
+    assert_eq!(bounds.left, pixel_bounds.left - 2);
+    assert_eq!(bounds.top, pixel_bounds.top + 2);
+
+    let field = font.glyph_msdf(glyph_id, point_size, range).unwrap();
+    let size = bounds.size();
+    assert_eq!(field.len(), (size.width * size.height) as usize);
+
+    let median = |x: i32, y: i32| {
+        let mut channels = field[(y * size.width + x) as usize];
+        channels.sort();
+        channels[1]
+    };
+
+    // The middle of the stem is well inside, and the corners of the field are well outside.
+    assert_eq!(median(size.width / 2, size.height / 2), 255);
+    assert!(median(0, 0) < 64);
+    assert!(median(size.width - 1, size.height - 1) < 64);
+
+    // Just outside a corner of the stem, the median stays outside even though the pixel is
+    // within range of both edges.
+    assert!(median(1, 1) < 128);
+
+    assert!(font.glyph_msdf(font.glyph_for_char(' ').unwrap(), point_size, range)
+                .unwrap()
+                .is_empty());
+    assert!(font.glyph_msdf(glyph_id, point_size, 0.0).is_err())
+}
+
+#[test]
+fn scales_fit_pixel_heights() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert_eq!(font.scale_for_pixel_height(32.0), font.pixels_per_unit(32.0));
+    assert_eq!(font.scale_for_pixel_height(font.units_per_em() as f32), 1.0);
+
+    // The test font's `OS/2` table is version 1, so the cap height comes from "H".
+    let glyph_h = font.glyph_for_char('H').unwrap();
+    let top = font.glyph_bounds(glyph_h).unwrap().top;
+    assert_eq!(font.cap_height(), Some(top as i16));
+    let scale = font.scale_for_cap_height(20.0).unwrap();
+    assert!((top as f32 * scale - 20.0).abs() < 0.001);
+
+    // Rename the original table and add a version 2 table with an explicit cap height.
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    let mut os_2 = vec![];
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        if &bytes[record..record + 4] == b"OS/2" {
+            let offset = BigEndian::read_u32(&bytes[record + 8..]) as usize;
+            let length = BigEndian::read_u32(&bytes[record + 12..]) as usize;
+            os_2 = bytes[offset..offset + length].to_vec();
+            bytes[record + 3] = b'X';
+        }
+    }
+    BigEndian::write_u16(&mut os_2, 2);
+    os_2.write_i16::<BigEndian>(500).unwrap();
+    os_2.write_i16::<BigEndian>(700).unwrap();
+    os_2.extend_from_slice(&[0; 6]);
+
+    let bytes = add_tables(&bytes, &[(b"OS/2", &os_2)]);
+    let font = Font::new(&bytes).unwrap();
+    assert_eq!(font.cap_height(), Some(700));
+    assert_eq!(font.scale_for_cap_height(35.0), Some(0.05));
+}
+
+#[test]
+fn ids_are_stable_across_loads() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let original = unsafe { file.as_slice() };
+    let font = Font::new(original).unwrap();
+    let copy = original.to_vec();
+    let reloaded = Font::new(&copy).unwrap();
+    assert_eq!(font.id(), reloaded.id());
+    assert_eq!(font.content_hash(), reloaded.content_hash());
+
+    // The identifier doesn't depend on the build, so it can be checked against a known value.
+    assert_eq!(font.id(), 0x77d404129a08c32b);
+
+    // Changing the checksum adjustment changes the identifier.
+    let head_offset = font.table(otf::tag(b"head")).unwrap().as_ptr() as usize -
+        original.as_ptr() as usize;
+    let mut bytes = copy.clone();
+    let checksum_adjustment = BigEndian::read_u32(&bytes[head_offset + 8..]);
+    BigEndian::write_u32(&mut bytes[head_offset + 8..], checksum_adjustment ^ 1);
+    assert!(Font::new(&bytes).unwrap().id() != font.id());
+
+    // Changing a glyph leaves the identifier alone, but not the content hash.
+    let mut bytes = copy.clone();
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    let glyph_offset = font.glyph_data(glyph_id).unwrap().as_ptr() as usize -
+        original.as_ptr() as usize;
+    bytes[glyph_offset + 2] ^= 1;
+    let changed = Font::new(&bytes).unwrap();
+    assert_eq!(changed.id(), font.id());
+    assert!(changed.content_hash() != font.content_hash());
+}
+
+#[test]
+fn italics_come_from_post_os_2_and_head() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert_eq!(font.italic_angle(), 0.0);
+    assert!(!font.is_italic());
+
+    let original = unsafe { file.as_slice() };
+    let offset_of = |tag| font.table(otf::tag(tag)).unwrap().as_ptr() as usize -
+        original.as_ptr() as usize;
+    let (post_offset, os_2_offset) = (offset_of(b"post"), offset_of(b"OS/2"));
+    let head_offset = offset_of(b"head");
+
+    // The italic angle is 16.16 fixed point.
+    let mut bytes = original.to_vec();
+    BigEndian::write_i32(&mut bytes[post_offset + 4..], -12 * 65536 - 32768);
+    assert_eq!(Font::new(&bytes).unwrap().italic_angle(), -12.5);
+
+    // The oblique bit of `fsSelection` counts as italic.
+    let fs_selection = BigEndian::read_u16(&bytes[os_2_offset + 62..]);
+    BigEndian::write_u16(&mut bytes[os_2_offset + 62..], fs_selection | 0x200);
+    assert!(Font::new(&bytes).unwrap().is_italic());
+
+    // So does the italic bit of `macStyle` alone.
+    BigEndian::write_u16(&mut bytes[os_2_offset + 62..], fs_selection);
+    assert!(!Font::new(&bytes).unwrap().is_italic());
+    BigEndian::write_u16(&mut bytes[head_offset + 44..], 2);
+    assert!(Font::new(&bytes).unwrap().is_italic());
+}
+
+#[test]
+fn has_hinting_needs_instruction_tables() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    assert!(font.has_hinting());
+
+    // Rename the `fpgm`, `prep`, and `cvt ` tables.
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        if [b"fpgm", b"prep", b"cvt "].iter().any(|tag| &bytes[record..record + 4] == &tag[..]) {
+            bytes[record + 3] = b'X';
+        }
+    }
+    assert!(!Font::new(&bytes).unwrap().has_hinting());
+}
+
+#[cfg(feature = "hinting")]
+#[test]
+fn hinting_fits_stems_to_pixels() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let hinter = font.hinter(11.6).unwrap();
+    assert_eq!(hinter.pixels_per_em(), 12);
+
+    let points = |glyph_id| {
+        let (mut original, mut hinted) = (vec![], vec![]);
+        font.for_each_point(glyph_id, |point| original.push(point.position)).unwrap();
+        font.for_each_hinted_point(&hinter, glyph_id, |point| hinted.push(point.position))
+            .unwrap();
+        (original, hinted)
+    };
+
+    // Every corner of "H" moves onto a pixel boundary.
+    let glyph_h = font.glyph_for_char('H').unwrap();
+    let (original, hinted) = points(glyph_h);
+    assert_eq!(original.len(), hinted.len());
+    assert!(original != hinted);
+    let units_per_pixel = font.units_per_em() as f32 / 12.0;
+    for position in &hinted {
+        let (x, y) = (position.x as f32 / units_per_pixel, position.y as f32 / units_per_pixel);
+        assert!((x - x.round()).abs() < 0.01 && (y - y.round()).abs() < 0.01);
+    }
+
+    // Hinting one glyph doesn't affect the next.
+    points(font.glyph_for_char('o').unwrap());
+    assert_eq!(points(glyph_h).1, hinted);
+}
+
+#[cfg(feature = "hinting")]
+#[test]
+fn hinting_arithmetic_wraps_instead_of_overflowing() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let mut bytes = unsafe { file.as_slice() }.to_vec();
+    let num_tables = BigEndian::read_u16(&bytes[4..]) as usize;
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        if &bytes[record..record + 4] == b"prep" {
+            bytes[record + 3] = b'X';
+        }
+    }
+
+    // Build 2^31 - 2 by multiplying and adding 16-bit words, then round it to the grid, which
+    // passes the largest distance, and round it again after `SROUND`.
+    let mut prep = vec![];
+    for &(word, opcode) in &[(0x7fff, None), (4096, Some(0x63)), (4096, Some(0x63)),
+                             (1024, Some(0x63)), (0x7fff, Some(0x60)), (0x7fff, Some(0x60))] {
+        prep.push(0xb8);
+        prep.write_u16::<BigEndian>(word).unwrap();
+        prep.extend(opcode);
+    }
+    prep.extend_from_slice(&[0x20, 0x68, 0x21, 0xb0, 0x48, 0x76, 0x68, 0x21]);
+
+    let bytes = add_tables(&bytes, &[(b"prep", &prep)]);
+    let font = Font::new(&bytes).unwrap();
+    assert!(font.hinter(12.0).is_ok());
+}
+
+#[test]
+fn validation_catches_corruption() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let original_bytes = unsafe { file.as_slice().to_vec() };
+    assert_eq!(Font::new(&original_bytes).unwrap().validate(), Ok(()));
+
+    let num_tables = BigEndian::read_u16(&original_bytes[4..]) as usize;
+    let table_offset = |tag: &[u8]| {
+        let record = (0..num_tables).map(|index| 12 + index * 16)
+                                    .find(|&record| &original_bytes[record..record + 4] == tag)
+                                    .unwrap();
+        BigEndian::read_u32(&original_bytes[record + 8..]) as usize
+    };
+
+    // Changing a byte of a table breaks its checksum.
+    let mut bytes = original_bytes.clone();
+    bytes[table_offset(b"glyf") + 100] ^= 1;
+    match Font::new(&bytes).unwrap().validate() {
+        Err(otf::ValidationError::TableChecksumMismatch { tag, .. }) => {
+            assert_eq!(tag, otf::tag(b"glyf"))
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    // The checksum adjustment doesn't count toward the `head` table's checksum, but it must
+    // balance the whole file.
+    let mut bytes = original_bytes.clone();
+    let checksum_adjustment = table_offset(b"head") + 8;
+    let expected = BigEndian::read_u32(&bytes[checksum_adjustment..]);
+    BigEndian::write_u32(&mut bytes[checksum_adjustment..], expected.wrapping_add(1));
+    assert_eq!(Font::new(&bytes).unwrap().validate(),
+               Err(otf::ValidationError::ChecksumAdjustmentMismatch {
+                   expected: expected.wrapping_add(1),
+                   actual: expected,
+               }));
+}
+
+#[test]
+fn run_width_includes_kerning_between_pairs() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_ids: Vec<u16> = "AVi".chars().map(|c| font.glyph_for_char(c).unwrap()).collect();
+
+    // A format 0 kerning subtable that pulls "V" 100 units toward "A".
+    let mut kern = vec![];
+    for &value in &[0, 1, 0, 6 + 8 + 6, 1, 1, 0, 0, 0, glyph_ids[0], glyph_ids[1]] {
+        kern.write_u16::<BigEndian>(value).unwrap();
+    }
+    kern.write_i16::<BigEndian>(-100).unwrap();
+    let bytes = add_tables(unsafe { file.as_slice() }, &[(b"kern", &kern)]);
+    let font_with_kerning = Font::new(&bytes).unwrap();
+
+    // "A", "V", and "i" are 1366, 1366, and 455 units wide on an em of 2048.
+    let units_per_em = font.units_per_em() as f32;
+    assert_eq!(font.run_width(&glyph_ids, units_per_em).unwrap(), 3187.0);
+    assert_eq!(font_with_kerning.run_width(&glyph_ids, units_per_em).unwrap(), 3087.0);
+    assert_eq!(font_with_kerning.run_width(&glyph_ids, units_per_em / 2.0).unwrap(), 1543.5);
+
+    // A single glyph is as wide as its advance, and an empty run has no width.
+    assert_eq!(font.run_width(&glyph_ids[2..], units_per_em).unwrap(), 455.0);
+    assert_eq!(font.run_width(&[], units_per_em).unwrap(), 0.0);
+}
+
+// Counts the bytes read from a stream.
+struct CountingReader<R> {
+    reader: R,
+    bytes_read: Rc<Cell<usize>>,
+}
+
+impl<R> Read for CountingReader<R> where R: Read {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let count = try!(self.reader.read(buffer));
+        self.bytes_read.set(self.bytes_read.get() + count);
+        Ok(count)
+    }
+}
+
+impl<R> Seek for CountingReader<R> where R: Seek {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(position)
+    }
+}
+
+#[test]
+fn font_reader_reads_outlines_on_demand() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let original = unsafe { file.as_slice() };
+    let font = Font::new(original).unwrap();
+
+    // The outlines and the tables that fonts don't use stay in the stream.
+    let bytes_read = Rc::new(Cell::new(0));
+    let mut font_reader = FontReader::new(CountingReader {
+        reader: Cursor::new(original),
+        bytes_read: bytes_read.clone(),
+    }).unwrap();
+    let glyf_length = font.table(otf::tag(b"glyf")).unwrap().len();
+    assert!(bytes_read.get() + glyf_length < original.len());
+
+    let glyph_id = font.glyph_for_char('g').unwrap();
+    {
+        let streamed_font = font_reader.font().unwrap();
+        assert_eq!(streamed_font.id(), font.id());
+        assert_eq!(streamed_font.validate(), Ok(()));
+        assert_eq!(streamed_font.outline_format(), OutlineFormat::TrueType);
+        assert!(font.table(otf::tag(b"name")).is_some());
+        assert!(streamed_font.table(otf::tag(b"name")).is_none());
+        assert!(streamed_font.table(otf::tag(b"glyf")).is_none());
+        for &name in &[b"cmap", b"hmtx", b"fpgm"] {
+            assert_eq!(streamed_font.table(otf::tag(name)), font.table(otf::tag(name)));
+        }
+
+        assert_eq!(streamed_font.glyph_for_char('g'), Some(glyph_id));
+        assert_eq!(streamed_font.for_each_point(glyph_id, |_| {}),
+                   Err(otf::Error::GlyphNotLoaded(glyph_id)));
+    }
+
+    // Reading a glyph reads only its data, and composite glyphs bring their components along.
+    let (bytes_read_before, composite_glyph_id) = (bytes_read.get(), 117);
+    font_reader.load_glyphs(&[glyph_id, composite_glyph_id]).unwrap();
+    assert!(bytes_read.get() - bytes_read_before < 1024);
+
+    let streamed_font = font_reader.font().unwrap();
+    let points = |font: &Font, glyph_id: u16| {
+        let mut points = vec![];
+        font.for_each_point(glyph_id, |point| points.push(point.position)).unwrap();
+        points
+    };
+    assert_eq!(points(&streamed_font, glyph_id), points(&font, glyph_id));
+    assert_eq!(points(&streamed_font, composite_glyph_id), points(&font, composite_glyph_id));
+    assert_eq!(streamed_font.glyph_data(glyph_id), font.glyph_data(glyph_id));
+}
+
+#[test]
+fn font_reader_reports_truncated_streams() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let original = unsafe { file.as_slice() };
+
+    // The `head` table is in the second half of the file.
+    match FontReader::new(Cursor::new(&original[0..original.len() / 2])) {
+        Err(otf::Error::TableTooShort(tag)) => assert_eq!(tag, otf::tag(b"head")),
+        Err(err) => panic!("unexpected error: {:?}", err),
+        Ok(_) => panic!("a truncated font was read"),
+    }
+}
@@ -0,0 +1,682 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+use error::OutlineError;
+use euclid::{Matrix2D, Point2D, Rect, Size2D};
+use memmap::{Mmap, Protection};
+use otf::Font;
+use outline::{GlyphFixedBounds, GlyphSubpixelBounds, OutlineBuilder, OutlineOptions};
+use outline::{self, OutlineData, PlacedGlyph};
+use overlap;
+use shaper::ShapedGlyph;
+use simplify::{self, ContourPoint};
+use std::u16;
+use winding;
+
+static TEST_FONT_PATH: &'static str = "resources/tests/nimbus-sans/NimbusSanL-Regu.ttf";
+
+#[test]
+fn draw_offset_is_consistent_with_round_out() {
+    let subpixel_bounds = GlyphSubpixelBounds {
+        left: -0.5,
+        bottom: -2.25,
+        right: 3.125,
+        top: 7.5,
+    };
+    let pixel_bounds = subpixel_bounds.round_out();
+    assert_eq!(pixel_bounds.draw_offset(), Point2D::new(-1, -8));
+
+    // The bottom-right corner of the quad is the bottom-right corner of the bounds.
+    let size = pixel_bounds.size();
+    assert_eq!(pixel_bounds.draw_offset() + Point2D::new(size.width, size.height),
+               Point2D::new(4, 3));
+}
+
+#[test]
+fn expanded_grows_every_side() {
+    let pixel_bounds = GlyphSubpixelBounds {
+        left: 1.0,
+        bottom: -2.0,
+        right: 5.0,
+        top: 6.0,
+    }.round_out();
+    let expanded = pixel_bounds.expanded(3);
+    assert_eq!((expanded.left, expanded.bottom, expanded.right, expanded.top), (-2, -5, 8, 9));
+    assert_eq!(expanded.draw_offset(), pixel_bounds.draw_offset() - Point2D::new(3, 3));
+}
+
+#[test]
+fn bounds_convert_to_y_up_rects() {
+    let subpixel_bounds = GlyphSubpixelBounds {
+        left: -0.5,
+        bottom: -2.25,
+        right: 3.125,
+        top: 7.5,
+    };
+    let rect: Rect<f32> = subpixel_bounds.into();
+    assert_eq!(rect, Rect::new(Point2D::new(-0.5, -2.25), Size2D::new(3.625, 9.75)));
+
+    let rect: Rect<i32> = subpixel_bounds.round_out().into();
+    assert_eq!(rect, Rect::new(Point2D::new(-1, -3), Size2D::new(5, 11)));
+}
+
+#[test]
+fn y_down_rects_measure_from_the_ascent() {
+    let subpixel_bounds = GlyphSubpixelBounds {
+        left: -0.5,
+        bottom: -2.25,
+        right: 3.125,
+        top: 7.5,
+    };
+    let rect = subpixel_bounds.to_y_down(10.0);
+    assert_eq!(rect, Rect::new(Point2D::new(-0.5, 2.5), Size2D::new(3.625, 9.75)));
+    assert_eq!(rect.max_y(), 12.25);
+
+    // At an ascent of zero, the origin is the draw offset.
+    let pixel_bounds = subpixel_bounds.round_out();
+    assert_eq!(pixel_bounds.to_y_down(0),
+               Rect::new(pixel_bounds.draw_offset(), pixel_bounds.size()));
+    assert_eq!(pixel_bounds.to_y_down(10).max_y(), 13);
+}
+
+#[test]
+fn fixed_bounds_round_out_like_freetype() {
+    let bounds = GlyphSubpixelBounds { left: -0.2, bottom: -1.0, right: 3.01, top: 4.5 };
+    let fixed = bounds.to_26_6();
+    assert_eq!(fixed, GlyphFixedBounds { left: -13, bottom: -64, right: 193, top: 288 });
+
+    let pixel_bounds = fixed.round_out();
+    assert_eq!((pixel_bounds.left, pixel_bounds.bottom, pixel_bounds.right, pixel_bounds.top),
+               (-1, -1, 4, 5));
+}
+
+#[test]
+fn subpixel_buckets_follow_left_edge() {
+    let bounds = GlyphSubpixelBounds { left: -0.2, bottom: -1.0, right: 3.01, top: 4.5 };
+    assert_eq!(bounds.subpixel_bucket(4), 3);
+    assert_eq!(bounds.translated(10.0, 0.0).subpixel_bucket(4), 3);
+    assert_eq!(bounds.translated(0.3, 0.0).subpixel_bucket(4), 0);
+    assert_eq!(bounds.translated(0.1, 0.0).subpixel_bucket(4), 0);
+    assert_eq!(bounds.translated(0.5, 0.0).subpixel_bucket(4), 1);
+    assert_eq!(bounds.subpixel_bucket(0), 0);
+}
+
+#[test]
+fn simplification_drops_only_flat_points() {
+    fn point(x: i16, y: i16, on_curve: bool) -> ContourPoint {
+        ContourPoint { position: Point2D::new(x, y), on_curve: on_curve }
+    }
+
+    // A square with a nearly colinear point on its bottom edge, a nearly flat curve on its right
+    // edge, and a real curve on its top edge.
+    let contour = [
+        point(0, 0, true),
+        point(50, 1, true),
+        point(100, 0, true),
+        point(101, 50, false),
+        point(100, 100, true),
+        point(50, 150, false),
+        point(0, 100, true),
+        point(0, 0, true),
+    ];
+
+    let simplified = simplify::simplify_contour(&contour, 2.0);
+    assert_eq!(simplified, vec![
+        point(0, 0, true),
+        point(100, 0, true),
+        point(100, 100, true),
+        point(50, 150, false),
+        point(0, 100, true),
+        point(0, 0, true),
+    ]);
+
+    // Nothing is within a tolerance of zero.
+    assert_eq!(simplify::simplify_contour(&contour, 0.0), contour.to_vec());
+}
+
+#[test]
+fn overlapping_squares_merge_into_one_contour() {
+    fn square(left: f32, bottom: f32, size: f32) -> Vec<Point2D<f32>> {
+        let (right, top) = (left + size, bottom + size);
+        vec![
+            Point2D::new(left, bottom),
+            Point2D::new(left, top),
+            Point2D::new(right, top),
+            Point2D::new(right, bottom),
+            Point2D::new(left, bottom),
+        ]
+    }
+
+    // A square with a hole doesn't overlap itself.
+    let mut hole = square(25.0, 25.0, 50.0);
+    hole.reverse();
+    let holed_square = [square(0.0, 0.0, 100.0), hole];
+    assert!(!overlap::has_overlaps(&holed_square));
+    assert!(overlap::remove_overlaps(&holed_square).is_none());
+
+    // Neither do separate squares wound in opposite directions, whether apart or touching.
+    let mut reversed = square(200.0, 0.0, 100.0);
+    reversed.reverse();
+    assert!(!overlap::has_overlaps(&[square(0.0, 0.0, 100.0), reversed.clone()]));
+    assert!(!overlap::has_overlaps(&[square(100.0, 100.0, 100.0), square(0.0, 0.0, 100.0)]));
+
+    // But a square crossing into a reversed one does, even though no region is covered twice.
+    assert!(overlap::has_overlaps(&[square(150.0, 50.0, 100.0), reversed]));
+
+    // Two clockwise squares overlapping at a corner merge into one clockwise octagon.
+    let squares = [square(0.0, 0.0, 100.0), square(50.0, 50.0, 100.0)];
+    assert!(overlap::has_overlaps(&squares));
+    let merged = overlap::remove_overlaps(&squares).unwrap();
+    assert_eq!(merged.len(), 1);
+    let contour = &merged[0];
+    assert_eq!(contour.first(), contour.last());
+
+    let mut corners: Vec<(i16, i16)> =
+        contour[1..].iter().map(|point| (point.x, point.y)).collect();
+    corners.sort();
+    assert_eq!(corners, vec![
+        (0, 0), (0, 100), (50, 100), (50, 150), (100, 0), (100, 50), (150, 50), (150, 150),
+    ]);
+
+    let doubled_area: i32 = contour.windows(2).map(|line| {
+        line[0].x as i32 * line[1].y as i32 - line[1].x as i32 * line[0].y as i32
+    }).sum();
+    assert_eq!(doubled_area, -2 * (100 * 100 * 2 - 50 * 50));
+
+    // A square inside another of the same direction is covered twice, and merges away.
+    let nested = [square(0.0, 0.0, 100.0), square(25.0, 25.0, 50.0)];
+    assert_eq!(overlap::remove_overlaps(&nested).unwrap(), vec![
+        vec![
+            Point2D::new(0, 0),
+            Point2D::new(0, 100),
+            Point2D::new(100, 100),
+            Point2D::new(100, 0),
+            Point2D::new(0, 0),
+        ],
+    ]);
+}
+
+#[test]
+fn overlap_removal_only_changes_overlapping_glyphs() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    // The medium shade block, U+2592, is drawn with overlapping contours; "l" isn't.
+    let (overlapping_glyph_id, clean_glyph_id) = (font.glyph_for_char('\u{2592}').unwrap(),
+                                                  font.glyph_for_char('l').unwrap());
+    assert!(font.has_overlapping_contours(overlapping_glyph_id).unwrap());
+    assert!(!font.has_overlapping_contours(clean_glyph_id).unwrap());
+
+    let build = |remove_overlaps: bool| {
+        let mut outline_builder = OutlineBuilder::new();
+        outline_builder.set_overlap_removal_enabled(remove_overlaps);
+        outline_builder.add_glyph(&font, clean_glyph_id).unwrap();
+        outline_builder.add_glyph(&font, overlapping_glyph_id).unwrap();
+        outline_builder
+    };
+    let (original, merged) = (build(false), build(true));
+
+    // The clean glyph is untouched, and the merged glyph keeps its bounds.
+    let glyph_vertices = |outline_builder: &OutlineBuilder, glyph_index: u16| {
+        outline_builder.vertices()[1..].iter().filter(|vertex| {
+            vertex.glyph_index() == glyph_index
+        }).cloned().collect::<Vec<_>>()
+    };
+    assert_eq!(glyph_vertices(&merged, 0), glyph_vertices(&original, 0));
+    assert_ne!(glyph_vertices(&merged, 1), glyph_vertices(&original, 1));
+    assert_eq!(format!("{:?}", merged.descriptors()[1].bounds()),
+               format!("{:?}", original.descriptors()[1].bounds()));
+
+    // Every point of the merged glyph is on the curve, so every triangle is a line segment.
+    let start_index = merged.descriptors()[1].start_index() as usize;
+    assert!(merged.indices()[start_index..].chunks(3).all(|triangle| triangle[1] == 0));
+}
+
+#[test]
+fn builder_emits_one_triangle_per_segment() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    let mut outline_builder = OutlineBuilder::new();
+    let glyph_id = font.glyph_for_char('l').unwrap();
+    let glyph_index = outline_builder.add_glyph(&font, glyph_id).unwrap();
+
+    // The stem of "l" is a rectangle: four line segments back to the first point.
+    let vertices = outline_builder.vertices();
+    assert_eq!(vertices.len(), 1 + 5);
+    assert_eq!(vertices[1].position(), vertices[5].position());
+    assert!(vertices[1..].iter().all(|vertex| vertex.glyph_index() == glyph_index));
+    assert_eq!(outline_builder.indices(), &[1, 0, 2, 2, 0, 3, 3, 0, 4, 4, 0, 5][..]);
+    assert_eq!(outline_builder.descriptors().len(), 1);
+
+    // Every triangle of a curvy glyph is either a line segment or a curve through consecutive
+    // points.
+    let glyph_id = font.glyph_for_char('S').unwrap();
+    let glyph_index = outline_builder.add_glyph(&font, glyph_id).unwrap();
+    let start_index = outline_builder.descriptors()[glyph_index as usize].start_index() as usize;
+    let indices = &outline_builder.indices()[start_index..];
+    assert!(indices.len() % 3 == 0);
+    for triangle in indices.chunks(3) {
+        assert!(triangle[1] == 0 || (triangle[1] == triangle[0] + 1 &&
+                                     triangle[2] == triangle[1] + 1));
+        assert!(triangle[2] == triangle[0] + 1 || triangle[2] == triangle[0] + 2);
+        let vertex = &outline_builder.vertices()[triangle[2] as usize];
+        assert_eq!(vertex.glyph_index(), glyph_index);
+    }
+}
+
+#[test]
+fn lines_pivot_on_contour_start_without_dummy_vertex() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let options = OutlineOptions {
+        dummy_vertex: false,
+    };
+
+    let mut outline_builder = OutlineBuilder::with_options(options);
+    let glyph_id = font.glyph_for_char('l').unwrap();
+    outline_builder.add_glyph(&font, glyph_id).unwrap();
+    assert_eq!(outline_builder.vertices().len(), 5);
+    assert_eq!(outline_builder.indices(), &[0, 0, 1, 1, 0, 2, 2, 0, 3, 3, 0, 4][..]);
+
+    // In the two contours of 'D', lines pivot on the first point of their own contour, 0 or 12,
+    // and curves on their control point.
+    let glyph_id = font.glyph_for_char('D').unwrap();
+    let mut other = OutlineBuilder::with_options(options);
+    other.add_glyph(&font, glyph_id).unwrap();
+    let triangles: Vec<&[u32]> = other.indices().chunks(3).collect();
+    assert_eq!(triangles[0], &[0, 0, 1][..]);
+    assert_eq!(triangles[2], &[2, 3, 4][..]);
+    assert_eq!(triangles[6], &[10, 0, 11][..]);
+    assert_eq!(triangles[7], &[12, 12, 13][..]);
+    assert_eq!(triangles[12], &[21, 12, 22][..]);
+
+    // Caching the glyph into an empty builder puts its first point at index 0 again.
+    let cached = other.cached_glyph(0).unwrap();
+    let mut empty = OutlineBuilder::with_options(options);
+    assert_eq!(empty.add_cached_glyph(&cached), Ok(0));
+    assert_eq!(empty.indices(), other.indices());
+
+    // Merging and caching after the 5 points of 'l' shift every index, pivots included.
+    let index_count = other.indices().len();
+    let merged_indices: Vec<u32> = other.indices().iter().map(|&index| index + 5).collect();
+    let cached_indices: Vec<u32> = other.indices().iter().map(|&index| index + 5 + 24).collect();
+    outline_builder.merge(other).unwrap();
+    outline_builder.add_cached_glyph(&cached).unwrap();
+    assert_eq!(&outline_builder.indices()[12..12 + index_count], &merged_indices[..]);
+    assert_eq!(&outline_builder.indices()[12 + index_count..], &cached_indices[..]);
+}
+
+#[test]
+fn builders_with_different_layouts_do_not_combine() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_id = font.glyph_for_char('l').unwrap();
+    let mut without_dummy_vertex = OutlineBuilder::with_options(OutlineOptions {
+        dummy_vertex: false,
+    });
+    without_dummy_vertex.add_glyph(&font, glyph_id).unwrap();
+    let cached = without_dummy_vertex.cached_glyph(0).unwrap();
+
+    let mut outline_builder = OutlineBuilder::new();
+    outline_builder.add_glyph(&font, glyph_id).unwrap();
+    let vertices = outline_builder.vertices().to_vec();
+    let indices = outline_builder.indices().to_vec();
+    assert_eq!(outline_builder.add_cached_glyph(&cached),
+               Err(OutlineError::DummyVertexMismatch));
+    assert_eq!(outline_builder.merge(without_dummy_vertex).err(),
+               Some(OutlineError::DummyVertexMismatch));
+    assert_eq!(outline_builder.descriptors().len(), 1);
+    assert_eq!(outline_builder.vertices(), &vertices[..]);
+    assert_eq!(outline_builder.indices(), &indices[..]);
+}
+
+#[test]
+fn shaped_runs_add_each_glyph_once() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let (l, o) = (font.glyph_for_char('l').unwrap(), font.glyph_for_char('o').unwrap());
+    let shaped_glyph = |glyph_id: u16, x_offset: f32, y_offset: f32| {
+        ShapedGlyph {
+            glyph_id: glyph_id,
+            x_offset: x_offset,
+            y_offset: y_offset,
+            x_advance: 500.0,
+        }
+    };
+
+    let mut outline_builder = OutlineBuilder::new();
+    let placed_glyphs = outline_builder.add_shaped_run(&font, &[
+        shaped_glyph(l, 0.0, 0.0),
+        shaped_glyph(o, 20.0, -10.0),
+        shaped_glyph(l, 0.0, 0.0),
+    ]).unwrap();
+
+    assert_eq!(outline_builder.descriptors().len(), 2);
+    assert_eq!(placed_glyphs, vec![
+        PlacedGlyph { glyph_index: 0, origin: Point2D::new(0.0, 0.0) },
+        PlacedGlyph { glyph_index: 1, origin: Point2D::new(520.0, -10.0) },
+        PlacedGlyph { glyph_index: 0, origin: Point2D::new(1000.0, 0.0) },
+    ]);
+}
+
+#[test]
+fn tessellation_is_reproducible() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let build = |flatten_cache_enabled: bool| {
+        let mut font = unsafe { Font::new(file.as_slice()).unwrap() };
+        font.set_flatten_cache_enabled(flatten_cache_enabled);
+        let mut outline_builder = OutlineBuilder::new();
+        for character in "Reproducible, Qq & 0123456789".chars() {
+            let glyph_id = font.glyph_for_char(character).unwrap();
+            font.flattened_contours(glyph_id, 0.5).unwrap();
+            outline_builder.add_glyph(&font, glyph_id).unwrap();
+        }
+        outline_builder
+    };
+
+    let (first, second, cached) = (build(false), build(false), build(true));
+    for outline_builder in &[second, cached] {
+        assert_eq!(outline_builder.vertices(), first.vertices());
+        assert_eq!(outline_builder.indices(), first.indices());
+        assert_eq!(format!("{:?}", outline_builder.descriptors()),
+                   format!("{:?}", first.descriptors()));
+    }
+}
+
+#[test]
+fn merged_builders_match_a_single_builder() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_ids: Vec<u16> = "lSo".chars().map(|c| font.glyph_for_char(c).unwrap()).collect();
+
+    let mut expected = OutlineBuilder::new();
+    for &glyph_id in &glyph_ids {
+        expected.add_glyph(&font, glyph_id).unwrap();
+    }
+
+    let mut merged = OutlineBuilder::new();
+    merged.add_glyph(&font, glyph_ids[0]).unwrap();
+    let mut other = OutlineBuilder::new();
+    for &glyph_id in &glyph_ids[1..] {
+        other.add_glyph(&font, glyph_id).unwrap();
+    }
+    assert_eq!(merged.merge(other), Ok(vec![1, 2]));
+
+    assert_eq!(merged.vertices(), expected.vertices());
+    assert_eq!(merged.indices(), expected.indices());
+    let start_indices = |builder: &OutlineBuilder| -> Vec<u32> {
+        builder.descriptors().iter().map(|descriptor| descriptor.start_index()).collect()
+    };
+    assert_eq!(start_indices(&merged), start_indices(&expected));
+}
+
+#[test]
+fn outline_data_keeps_the_built_outlines() {
+    fn assert_send_and_sync<T: Send + Sync>() {}
+    assert_send_and_sync::<OutlineData>();
+
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let mut outline_builder = OutlineBuilder::new();
+    for character in "Data".chars() {
+        outline_builder.add_glyph(&font, font.glyph_for_char(character).unwrap()).unwrap();
+    }
+
+    let vertices = outline_builder.vertices().to_vec();
+    let indices = outline_builder.indices().to_vec();
+    let descriptors = format!("{:?}", outline_builder.descriptors());
+    let outline_data = outline_builder.into_data();
+    assert_eq!(outline_data.vertices(), &vertices[..]);
+    assert_eq!(outline_data.indices(), &indices[..]);
+    assert_eq!(format!("{:?}", outline_data.descriptors()), descriptors);
+}
+
+#[test]
+fn glyph_index_ranges_cover_the_index_buffer() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let mut outline_builder = OutlineBuilder::new();
+    for character in "Data".chars() {
+        outline_builder.add_glyph(&font, font.glyph_for_char(character).unwrap()).unwrap();
+    }
+
+    let (descriptors, indices_count) = (outline_builder.descriptors(),
+                                        outline_builder.indices().len());
+    let ranges: Vec<_> = (0..4).map(|glyph_index| {
+        outline::glyph_index_range(descriptors, indices_count, glyph_index).unwrap()
+    }).collect();
+    assert_eq!(ranges[0].start, descriptors[0].start_index());
+    for pair in ranges.windows(2) {
+        assert!(pair[0].start < pair[0].end);
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+    assert_eq!(ranges[3].end as usize, indices_count);
+
+    assert_eq!(outline::glyph_index_range(descriptors, indices_count, 4), None);
+    assert_eq!(outline::glyph_index_range(descriptors, indices_count, u16::MAX), None);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_add_glyphs_matches_serial() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_ids: Vec<u16> = "Pathfinder, in parallel".chars()
+                                                       .map(|c| font.glyph_for_char(c).unwrap())
+                                                       .collect();
+
+    let mut serial = OutlineBuilder::new();
+    serial.add_glyph(&font, glyph_ids[0]).unwrap();
+    let mut expected_indices = vec![];
+    for &glyph_id in &glyph_ids {
+        expected_indices.push(serial.add_glyph(&font, glyph_id).unwrap());
+    }
+
+    let mut parallel = OutlineBuilder::new();
+    parallel.add_glyph(&font, glyph_ids[0]).unwrap();
+    assert_eq!(parallel.par_add_glyphs(&font, &glyph_ids).unwrap(), expected_indices);
+
+    assert_eq!(parallel.vertices(), serial.vertices());
+    assert_eq!(parallel.indices(), serial.indices());
+    let start_indices = |builder: &OutlineBuilder| -> Vec<u32> {
+        builder.descriptors().iter().map(|descriptor| descriptor.start_index()).collect()
+    };
+    assert_eq!(start_indices(&parallel), start_indices(&serial));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn stats_count_tessellated_glyphs() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+
+    let mut builder = OutlineBuilder::new();
+    for character in "Stats".chars() {
+        builder.add_glyph(&font, font.glyph_for_char(character).unwrap()).unwrap();
+    }
+    let cached = builder.cached_glyph(0).unwrap();
+    builder.add_cached_glyph(&cached).unwrap();
+
+    let stats = builder.stats();
+    assert_eq!(stats.glyphs_tessellated, 5);
+    assert_eq!(stats.vertices_emitted,
+               (builder.vertices().len() - 1 - cached.vertices().len()) as u64);
+    assert_eq!(stats.indices_emitted, (builder.indices().len() - cached.indices().len()) as u64);
+    assert_eq!(stats.upload_time_ns, 0);
+
+    let mut merged = OutlineBuilder::new();
+    merged.merge(builder).unwrap();
+    assert_eq!(merged.stats(), stats);
+}
+
+#[test]
+fn builder_transform_applies_to_points_and_bounds() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_id = font.glyph_for_char('S').unwrap();
+
+    let mut plain = OutlineBuilder::new();
+    plain.add_glyph(&font, glyph_id).unwrap();
+    let bounds = plain.descriptors()[0].bounds();
+
+    let mut scaled = OutlineBuilder::with_transform(&Matrix2D::create_scale(2.0, 3.0));
+    scaled.add_glyph(&font, glyph_id).unwrap();
+    assert_eq!(scaled.indices(), plain.indices());
+    for (vertex, plain_vertex) in scaled.vertices().iter().zip(plain.vertices().iter()) {
+        let position = plain_vertex.position();
+        assert_eq!(vertex.position(), Point2D::new(position.x * 2, position.y * 3));
+    }
+    let scaled_bounds = scaled.descriptors()[0].bounds();
+    assert_eq!((scaled_bounds.left, scaled_bounds.bottom, scaled_bounds.right, scaled_bounds.top),
+               (bounds.left * 2, bounds.bottom * 3, bounds.right * 2, bounds.top * 3));
+
+    // A flip reverses each contour, and the bounds are flipped along with the points.
+    let mut flipped = OutlineBuilder::with_transform(&Matrix2D::create_scale(1.0, -1.0));
+    flipped.add_glyph(&font, glyph_id).unwrap();
+    assert_eq!(flipped.vertices().len(), plain.vertices().len());
+    assert_eq!(flipped.indices().len(), plain.indices().len());
+    let first = plain.vertices()[1].position();
+    let flipped_first = flipped.vertices()[1].position();
+    assert_eq!(flipped_first, Point2D::new(first.x, -first.y));
+    let flipped_bounds = flipped.descriptors()[0].bounds();
+    assert_eq!((flipped_bounds.bottom, flipped_bounds.top), (-bounds.top, -bounds.bottom));
+}
+
+#[test]
+fn notdef_glyph_has_visible_outline() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let notdef = font.notdef_glyph();
+    assert_eq!(notdef, 0);
+
+    let mut outline_builder = OutlineBuilder::new();
+    let glyph_index = outline_builder.add_glyph(&font, notdef).unwrap();
+    assert!(outline_builder.indices().len() > 0);
+    assert!(outline_builder.vertices()[1..].iter().all(|vertex| {
+        vertex.glyph_index() == glyph_index
+    }));
+
+    // This font's `.notdef` is a hollow box, whose two contours enclose a nonzero area.
+    let size = font.glyph_bounds(notdef).unwrap().size();
+    assert!(size.width > 0 && size.height > 0);
+    let (_, indices) = font.triangulate_glyph(notdef, 1.0).unwrap();
+    assert!(indices.len() >= 3);
+}
+
+#[test]
+fn cached_glyphs_mix_with_fresh_ones() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_ids: Vec<u16> = "lSo".chars().map(|c| font.glyph_for_char(c).unwrap()).collect();
+
+    let mut expected = OutlineBuilder::new();
+    for &glyph_id in &glyph_ids {
+        expected.add_glyph(&font, glyph_id).unwrap();
+    }
+
+    // Cache the middle glyph from the end of another builder.
+    let mut source = OutlineBuilder::new();
+    source.add_glyph(&font, glyph_ids[2]).unwrap();
+    let cached_index = source.add_glyph(&font, glyph_ids[1]).unwrap();
+    let cached = source.cached_glyph(cached_index).unwrap();
+    assert_eq!(cached.glyph_id(), glyph_ids[1]);
+    assert!(source.cached_glyph(cached_index + 1).is_none());
+
+    let mut mixed = OutlineBuilder::new();
+    mixed.add_glyph(&font, glyph_ids[0]).unwrap();
+    assert_eq!(mixed.add_cached_glyph(&cached), Ok(1));
+    mixed.add_glyph(&font, glyph_ids[2]).unwrap();
+
+    assert_eq!(mixed.vertices(), expected.vertices());
+    assert_eq!(mixed.indices(), expected.indices());
+    assert_eq!(format!("{:?}", mixed.descriptors()), format!("{:?}", expected.descriptors()));
+}
+
+#[test]
+fn tofu_boxes_need_no_font() {
+    let mut outline_builder = OutlineBuilder::new();
+    let empty_box = outline_builder.add_tofu_box(1000);
+    let labeled_box = outline_builder.add_tofu_box_for_char(1000, 'A');
+    assert_eq!((empty_box, labeled_box), (0, 1));
+
+    let descriptor = outline_builder.descriptors()[empty_box as usize];
+    let bounds = descriptor.bounds();
+    assert_eq!((bounds.left, bounds.bottom, bounds.right, bounds.top), (100, 0, 700, 700));
+    assert_eq!(descriptor.units_per_em(), 1000);
+
+    // The hollow box is two rectangles of four lines each. The code point of 'A' adds the
+    // segments of the digits 0, 0, 4, and 1: 6 + 6 + 4 + 2 more rectangles.
+    let start_index = outline_builder.descriptors()[labeled_box as usize].start_index();
+    assert_eq!(start_index, 2 * 4 * 3);
+    assert_eq!(outline_builder.indices().len() as u32 - start_index, (2 + 18) * 4 * 3);
+
+    // Every point lies within the box.
+    for vertex in &outline_builder.vertices()[1..] {
+        let position = vertex.position();
+        assert!(position.x >= 100 && position.x <= 700 && position.y >= 0 && position.y <= 700)
+    }
+
+    // Oversized ems are clamped to what fonts may declare.
+    let huge_box = outline_builder.add_tofu_box(u16::MAX);
+    assert_eq!(outline_builder.descriptors()[huge_box as usize].units_per_em(), 16384);
+}
+
+#[test]
+fn winding_normalization_reverses_only_misdirected_contours() {
+    fn square(left: i16, bottom: i16, size: i16, clockwise: bool) -> Vec<ContourPoint> {
+        let mut corners = vec![(left, bottom),
+                               (left, bottom + size),
+                               (left + size, bottom + size),
+                               (left + size, bottom),
+                               (left, bottom)];
+        if !clockwise {
+            corners.reverse()
+        }
+        corners.into_iter().map(|(x, y)| {
+            ContourPoint { position: Point2D::new(x, y), on_curve: true }
+        }).collect()
+    }
+
+    // A counterclockwise outer square with a clockwise hole, a correct square beside them, and a
+    // square that straddles the corner of that one, which leaves both alone.
+    let mut contours = vec![square(0, 0, 100, false),
+                            square(25, 25, 50, true),
+                            square(200, 0, 100, true),
+                            square(290, 90, 20, false)];
+    assert_eq!(winding::normalize(&mut contours), 2);
+    assert_eq!(contours[0], square(0, 0, 100, true));
+    assert_eq!(contours[1], square(25, 25, 50, false));
+    assert_eq!(contours[2], square(200, 0, 100, true));
+    assert_eq!(contours[3], square(290, 90, 20, false));
+    assert_eq!(winding::normalize(&mut contours), 0);
+}
+
+#[test]
+fn winding_normalization_counts_reversed_contours() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let (glyph_male_sign, glyph_o) = (font.glyph_for_char('\u{2642}').unwrap(),
+                                      font.glyph_for_char('o').unwrap());
+
+    // The test font winds the hole in the circle of the male sign, U+2642, the same way as the
+    // contour around it.
+    let mut plain_builder = OutlineBuilder::new();
+    let mut normalizing_builder = OutlineBuilder::new();
+    normalizing_builder.set_winding_normalization_enabled(true);
+    assert_eq!(plain_builder.add_glyph_counting_reversals(&font, glyph_male_sign).unwrap(),
+               (0, 0));
+    assert_eq!(normalizing_builder.add_glyph_counting_reversals(&font, glyph_male_sign).unwrap(),
+               (0, 1));
+    let positions = |builder: &OutlineBuilder, start: usize| -> Vec<Point2D<i16>> {
+        builder.vertices()[start..].iter().map(|vertex| vertex.position()).collect()
+    };
+    assert!(positions(&plain_builder, 0) != positions(&normalizing_builder, 0));
+
+    // Well-formed glyphs come out exactly as they would without normalization.
+    let start = plain_builder.vertices().len();
+    assert_eq!(plain_builder.add_glyph_counting_reversals(&font, glyph_o).unwrap(), (1, 0));
+    assert_eq!(normalizing_builder.add_glyph_counting_reversals(&font, glyph_o).unwrap(), (1, 0));
+    assert_eq!(positions(&plain_builder, start), positions(&normalizing_builder, start));
+    assert_eq!(plain_builder.indices(), normalizing_builder.indices());
+}
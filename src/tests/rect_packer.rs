@@ -1,12 +1,18 @@
 /* Any copyright is dedicated to the Public Domain.
  * http://creativecommons.org/publicdomain/zero/1.0/ */
 
-use rect_packer::RectPacker;
-use euclid::{Rect, Size2D};
+use rect_packer::{self, PackingMode, RectPacker};
+use error::AtlasError;
+use euclid::{Point2D, Rect, Size2D};
 use std::cmp;
 
 fn pack_objects(available_width: u32, objects: Vec<(u32, u32)>)
                 -> (RectPacker, Vec<Rect<u32>>, u32) {
+    pack_objects_with_mode(available_width, objects, PackingMode::Guillotine)
+}
+
+fn pack_objects_with_mode(available_width: u32, objects: Vec<(u32, u32)>, mode: PackingMode)
+                          -> (RectPacker, Vec<Rect<u32>>, u32) {
     let objects: Vec<_> = objects.iter()
                                  .map(|&(width, height)| Size2D::new(width, height))
                                  .collect();
@@ -15,7 +21,10 @@ fn pack_objects(available_width: u32, objects: Vec<(u32, u32)>)
         cmp::max(available_width, objects.iter().map(|object| object.width).max().unwrap_or(0));
     let shelf_height = objects.iter().map(|object| object.height).max().unwrap_or(0) + 2;
 
-    let mut rect_packer = RectPacker::new(available_width, shelf_height);
+    let mut rect_packer = RectPacker::new(available_width,
+                                         shelf_height,
+                                         rect_packer::DEFAULT_PADDING,
+                                         mode);
     let rects = objects.iter()
                        .map(|object| Rect::new(rect_packer.pack(object).unwrap(), *object))
                        .collect();
@@ -45,5 +54,110 @@ quickcheck! {
             rect.is_empty() || rect.origin.y / shelf_height == (rect.max_y() - 1) / shelf_height
         })
     }
+
+    fn objects_respect_padding(padding: u8, objects: Vec<(u32, u32)>) -> bool {
+        let padding = padding as u32 % 8;
+        let objects: Vec<_> = objects.iter()
+                                     .map(|&(width, height)| Size2D::new(width % 64, height % 64))
+                                     .collect();
+        let shelf_height = objects.iter().map(|object| object.height).max().unwrap_or(0) +
+            padding * 2;
+        let available_width = 256 + padding * 2;
+
+        let mut rect_packer = RectPacker::new(available_width,
+                                              shelf_height,
+                                              padding,
+                                              PackingMode::Guillotine);
+        let rects: Vec<_> = objects.iter().map(|object| {
+            let origin = rect_packer.pack(object).unwrap();
+            Rect::new(origin - Point2D::new(padding, padding),
+                      *object + Size2D::new(padding * 2, padding * 2))
+        }).collect();
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[(i + 1)..] {
+                assert!(!a.intersects(b))
+            }
+        }
+        true
+    }
+
+    fn skyline_objects_dont_overlap(available_width: u32, objects: Vec<(u32, u32)>) -> bool {
+        let (_, rects, _) = pack_objects_with_mode(available_width, objects, PackingMode::Skyline);
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[(i + 1)..] {
+                assert!(!a.intersects(b))
+            }
+        }
+        true
+    }
+
+    fn skyline_objects_dont_cross_shelves(available_width: u32, objects: Vec<(u32, u32)>)
+                                          -> bool {
+        let (rect_packer, rects, available_width) =
+            pack_objects_with_mode(available_width, objects, PackingMode::Skyline);
+        rects.iter().all(|rect| {
+            let shelf_height = rect_packer.shelf_height();
+            rect.max_x() <= available_width &&
+                (rect.is_empty() ||
+                 rect.origin.y / shelf_height == (rect.max_y() - 1) / shelf_height)
+        })
+    }
+}
+
+#[test]
+fn packing_fails_when_out_of_space() {
+    let mut rect_packer = RectPacker::new(8, 4, 0, PackingMode::Guillotine);
+    rect_packer.set_available_height(8);
+
+    let size = Size2D::new(8, 4);
+    for _ in 0..2 {
+        assert!(rect_packer.can_pack(&size));
+        rect_packer.pack(&size).unwrap();
+    }
+
+    assert!(!rect_packer.can_pack(&size));
+    assert_eq!(rect_packer.pack(&size),
+               Err(AtlasError::OutOfSpace { needed: size, remaining: Size2D::new(8, 0) }));
+    assert_eq!(rect_packer.pack(&Size2D::new(9, 1)),
+               Err(AtlasError::GlyphTooLarge {
+                   needed: Size2D::new(9, 1),
+                   shelf_size: Size2D::new(8, 4),
+               }));
 }
 
+#[test]
+fn packing_avoids_reserved_rects() {
+    for &mode in &[PackingMode::Guillotine, PackingMode::Skyline] {
+        let mut rect_packer = RectPacker::new(32, 10, 1, mode);
+        rect_packer.set_available_height(40);
+
+        // Reserve the middle of the second shelf, which adds the first two shelves.
+        let reserved = Rect::new(Point2D::new(8, 12), Size2D::new(12, 6));
+        rect_packer.reserve(&reserved).unwrap();
+        assert!(rect_packer.shelf_columns() >= 32 + 20);
+
+        let size = Size2D::new(4, 4);
+        let mut padded_rects = vec![reserved];
+        while let Ok(origin) = rect_packer.pack(&size) {
+            let padded_rect = Rect::new(origin - Point2D::new(1, 1), Size2D::new(6, 6));
+            assert!(padded_rects.iter().all(|rect| !rect.intersects(&padded_rect)));
+            padded_rects.push(padded_rect)
+        }
+        assert!(padded_rects.len() > 10);
+    }
+}
+
+#[test]
+fn reserved_rects_stay_within_one_shelf() {
+    let mut rect_packer = RectPacker::new(32, 10, 1, PackingMode::Guillotine);
+    rect_packer.set_available_height(20);
+
+    for rect in &[Rect::new(Point2D::new(24, 0), Size2D::new(10, 6)),
+                  Rect::new(Point2D::new(0, 8), Size2D::new(6, 6)),
+                  Rect::new(Point2D::new(0, 20), Size2D::new(6, 6))] {
+        assert_eq!(rect_packer.reserve(rect), Err(AtlasError::OutOfBounds { rect: *rect }));
+    }
+    assert_eq!(rect_packer.shelf_columns(), 0);
+    rect_packer.reserve(&Rect::new(Point2D::new(26, 4), Size2D::new(6, 6))).unwrap();
+    assert_eq!(rect_packer.shelf_columns(), 32);
+}
@@ -0,0 +1,32 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+use charmap::CodepointRange;
+use memmap::{Mmap, Protection};
+use otf::Font;
+use shaper::{self, Direction};
+
+static TEST_FONT_PATH: &'static str = "resources/tests/nimbus-sans/NimbusSanL-Regu.ttf";
+
+#[test]
+fn right_to_left_runs_mirror_left_to_right_runs() {
+    let file = Mmap::open_path(TEST_FONT_PATH, Protection::Read).expect("Couldn't open test font");
+    let font = unsafe { Font::new(file.as_slice()).unwrap() };
+    let glyph_mapping = font.glyph_mapping_for_codepoint_ranges(&[CodepointRange::new(' ' as u32,
+                                                                                      '~' as u32)])
+                            .unwrap();
+
+    let ltr = shaper::shape_run(&font, &glyph_mapping, "AVAWA", Direction::LeftToRight);
+    let rtl = shaper::shape_run(&font, &glyph_mapping, "AWAVA", Direction::RightToLeft);
+    assert_eq!(ltr[0].x, 0);
+
+    // Written backward, the same glyphs appear in the same visual order, kerned the same way,
+    // but the run ends at the origin instead of starting there.
+    let ltr_glyph_positions = shaper::shape_text(&font, &glyph_mapping, "AVAWA");
+    let width: i32 = ltr_glyph_positions.iter().map(|glyph_pos| glyph_pos.advance as i32).sum();
+    assert_eq!(rtl.len(), ltr.len());
+    for (rtl_glyph, ltr_glyph) in rtl.iter().zip(ltr.iter()) {
+        assert_eq!(rtl_glyph.glyph_id, ltr_glyph.glyph_id);
+        assert_eq!(rtl_glyph.x, ltr_glyph.x - width);
+    }
+}
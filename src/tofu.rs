@@ -0,0 +1,141 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Outlines of placeholder "tofu" boxes for characters that no font can draw.
+
+use euclid::Point2D;
+use outline::GlyphBounds;
+
+/// The largest em that fonts may declare, which keeps every coordinate of a box in 16 bits.
+pub const MAX_EM_SIZE: u16 = 16384;
+
+// The segments of a seven-segment display lit for each hexadecimal digit, with bit 0 for the top
+// segment, then clockwise around the digit, and bit 6 for the middle segment.
+static DIGIT_SEGMENTS: [u8; 16] = [
+    0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07,
+    0x7f, 0x6f, 0x77, 0x7c, 0x39, 0x5e, 0x79, 0x71,
+];
+
+/// Returns the bounds of a tofu box on an em of `em_size` units.
+///
+/// The box sits on the baseline, is 0.7 em tall, and is inset 0.1 em from both sides of a 0.8 em
+/// advance.
+pub fn bounds(em_size: u16) -> GlyphBounds {
+    let em_size = em_size as i32;
+    GlyphBounds {
+        left: em_size / 10,
+        bottom: 0,
+        right: em_size * 7 / 10,
+        top: em_size * 7 / 10,
+    }
+}
+
+/// Returns the closed contours of a tofu box on an em of `em_size` units, each as a list of
+/// on-curve points that ends where it began.
+///
+/// The box is a hollow square. If a character is given, its code point is written inside in
+/// uppercase hexadecimal, on two rows of seven-segment digits: four digits for characters in the
+/// Basic Multilingual Plane and six for the rest. Outer edges are wound clockwise and holes
+/// counterclockwise, as in TrueType outlines, and no two contours overlap.
+pub fn contours(em_size: u16, character: Option<char>) -> Vec<Vec<Point2D<i16>>> {
+    let bounds = bounds(em_size);
+    let (left, bottom) = (bounds.left as f32, bounds.bottom as f32);
+    let (right, top) = (bounds.right as f32, bounds.top as f32);
+    let stroke = (em_size as f32 / 20.0).max(1.0);
+
+    let mut contours = vec![
+        rectangle(left, bottom, right, top, false),
+        rectangle(left + stroke, bottom + stroke, right - stroke, top - stroke, true),
+    ];
+
+    let character = match character {
+        None => return contours,
+        Some(character) => character,
+    };
+
+    let digits: Vec<u32> = if (character as u32) <= 0xffff {
+        (0..4).rev().map(|index| (character as u32 >> (index * 4)) & 0xf).collect()
+    } else {
+        (0..6).rev().map(|index| (character as u32 >> (index * 4)) & 0xf).collect()
+    };
+
+    // Lay the digits out in two rows inside the hole, with a gap of one stroke around and
+    // between them.
+    let columns = digits.len() / 2;
+    let (inner_left, inner_bottom) = (left + stroke * 2.0, bottom + stroke * 2.0);
+    let (inner_right, inner_top) = (right - stroke * 2.0, top - stroke * 2.0);
+    let digit_width = (inner_right - inner_left - stroke * (columns - 1) as f32) /
+        columns as f32;
+    let digit_height = (inner_top - inner_bottom - stroke) / 2.0;
+    let thickness = (digit_width / 5.0).max(1.0);
+
+    for (digit_index, &digit) in digits.iter().enumerate() {
+        let (row, column) = (digit_index / columns, digit_index % columns);
+        let digit_left = inner_left + (digit_width + stroke) * column as f32;
+        let digit_bottom = inner_top - digit_height - (digit_height + stroke) * row as f32;
+        add_digit(&mut contours,
+                  digit,
+                  &Point2D::new(digit_left, digit_bottom),
+                  digit_width,
+                  digit_height,
+                  thickness)
+    }
+
+    contours
+}
+
+// Adds a rectangle for each lit segment of a seven-segment digit whose lower left corner is at
+// `origin`. Neighboring segments only touch at their corners.
+fn add_digit(contours: &mut Vec<Vec<Point2D<i16>>>,
+             digit: u32,
+             origin: &Point2D<f32>,
+             width: f32,
+             height: f32,
+             thickness: f32) {
+    let (w, h, t) = (width, height, thickness);
+    let (middle_bottom, middle_top) = ((h - t) * 0.5, (h + t) * 0.5);
+    let segments = [
+        (t, h - t, w - t, h),
+        (w - t, middle_top, w, h - t),
+        (w - t, t, w, middle_bottom),
+        (t, 0.0, w - t, t),
+        (0.0, t, t, middle_bottom),
+        (0.0, middle_top, t, h - t),
+        (t, middle_bottom, w - t, middle_top),
+    ];
+
+    let lit = DIGIT_SEGMENTS[digit as usize];
+    for (segment_index, &(left, bottom, right, top)) in segments.iter().enumerate() {
+        if lit & (1 << segment_index) != 0 {
+            contours.push(rectangle(origin.x + left,
+                                    origin.y + bottom,
+                                    origin.x + right,
+                                    origin.y + top,
+                                    false))
+        }
+    }
+}
+
+// Returns a closed rectangular contour, wound clockwise, or counterclockwise for a hole.
+fn rectangle(left: f32, bottom: f32, right: f32, top: f32, hole: bool) -> Vec<Point2D<i16>> {
+    let (left, bottom) = (left.round() as i16, bottom.round() as i16);
+    let (right, top) = (right.round() as i16, top.round() as i16);
+    let mut contour = vec![
+        Point2D::new(left, bottom),
+        Point2D::new(left, top),
+        Point2D::new(right, top),
+        Point2D::new(right, bottom),
+        Point2D::new(left, bottom),
+    ];
+    if hole {
+        contour.reverse()
+    }
+    contour
+}
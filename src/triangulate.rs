@@ -0,0 +1,310 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Triangulation of flattened glyph outlines on the CPU by ear clipping.
+
+use euclid::Point2D;
+use std::cmp::Ordering;
+
+struct Contour {
+    indices: Vec<u32>,
+    // Positive if counterclockwise in a y-up coordinate system.
+    signed_area: f32,
+}
+
+/// Triangulates the interior of the given closed polygons under the nonzero winding rule.
+///
+/// Returns the vertices and the indices of the triangles, three per triangle, wound
+/// counterclockwise in a y-up coordinate system.
+///
+/// A polygon is treated as a hole if the innermost polygon that contains it is filled and has the
+/// opposite orientation. Polygons that overlap without nesting are triangulated independently, so
+/// their triangles overlap too.
+pub fn triangulate(polygons: &[Vec<Point2D<f32>>]) -> (Vec<Point2D<f32>>, Vec<u32>) {
+    let mut vertices = vec![];
+    let mut contours = vec![];
+    for polygon in polygons {
+        // Drop repeated points, including the closing point.
+        let mut points: Vec<Point2D<f32>> = vec![];
+        for point in polygon {
+            if points.last() != Some(point) {
+                points.push(*point)
+            }
+        }
+        while points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+
+        let signed_area = signed_area(&points);
+        if points.len() < 3 || signed_area == 0.0 {
+            continue
+        }
+
+        let start = vertices.len() as u32;
+        contours.push(Contour {
+            indices: (start..(start + points.len() as u32)).collect(),
+            signed_area: signed_area,
+        });
+        vertices.extend(points.into_iter());
+    }
+
+    // Visit the contours from largest to smallest so that containers come before the contours
+    // they contain.
+    let mut order: Vec<usize> = (0..contours.len()).collect();
+    order.sort_by(|&a, &b| {
+        contours[b].signed_area.abs()
+                   .partial_cmp(&contours[a].signed_area.abs())
+                   .unwrap_or(Ordering::Equal)
+    });
+
+    // Sort the contours into filled regions, each with its holes.
+    let mut filled_regions: Vec<(usize, Vec<usize>)> = vec![];
+    let mut filled_region_of_contour = vec![None; contours.len()];
+    for (position, &contour_index) in order.iter().enumerate() {
+        let contour = &contours[contour_index];
+        let first_point = &vertices[contour.indices[0] as usize];
+        let container = order[..position].iter().rev().cloned().find(|&container_index| {
+            polygon_contains(&vertices, &contours[container_index].indices, first_point)
+        });
+
+        if let Some(container_index) = container {
+            if let Some(filled_region_index) = filled_region_of_contour[container_index] {
+                let container = &contours[container_index];
+                if (container.signed_area > 0.0) != (contour.signed_area > 0.0) {
+                    let filled_region: &mut (usize, Vec<usize>) =
+                        &mut filled_regions[filled_region_index];
+                    filled_region.1.push(contour_index);
+                    continue
+                }
+            }
+        }
+
+        filled_region_of_contour[contour_index] = Some(filled_regions.len());
+        filled_regions.push((contour_index, vec![]));
+    }
+
+    let mut indices = vec![];
+    for &(outer_index, ref hole_indices) in &filled_regions {
+        let mut polygon = oriented(&contours[outer_index], true);
+
+        // Bridge the holes into the outer polygon, starting with the rightmost.
+        let mut holes: Vec<Vec<u32>> = hole_indices.iter().map(|&hole_index| {
+            oriented(&contours[hole_index], false)
+        }).collect();
+        holes.sort_by(|a, b| {
+            max_x(&vertices, b).partial_cmp(&max_x(&vertices, a)).unwrap_or(Ordering::Equal)
+        });
+        for hole in &holes {
+            bridge_hole(&vertices, &mut polygon, hole)
+        }
+
+        clip_ears(&vertices, polygon, &mut indices)
+    }
+
+    (vertices, indices)
+}
+
+fn signed_area(points: &[Point2D<f32>]) -> f32 {
+    let mut area = 0.0;
+    for (index, from) in points.iter().enumerate() {
+        let to = &points[(index + 1) % points.len()];
+        area += from.x * to.y - to.x * from.y
+    }
+    area * 0.5
+}
+
+fn oriented(contour: &Contour, counterclockwise: bool) -> Vec<u32> {
+    let mut indices = contour.indices.clone();
+    if (contour.signed_area > 0.0) != counterclockwise {
+        indices.reverse()
+    }
+    indices
+}
+
+fn max_x(vertices: &[Point2D<f32>], polygon: &[u32]) -> f32 {
+    polygon.iter().fold(-::std::f32::INFINITY, |max_x, &index| {
+        max_x.max(vertices[index as usize].x)
+    })
+}
+
+// Connects a clockwise hole to the counterclockwise polygon that contains it with a pair of
+// coincident edges, following David Eberly's "Triangulation by Ear Clipping".
+fn bridge_hole(vertices: &[Point2D<f32>], polygon: &mut Vec<u32>, hole: &[u32]) {
+    // Find the rightmost vertex of the hole.
+    let mut hole_start = 0;
+    for (position, &index) in hole.iter().enumerate() {
+        if vertices[index as usize].x > vertices[hole[hole_start] as usize].x {
+            hole_start = position
+        }
+    }
+    let hole_point = vertices[hole[hole_start] as usize];
+
+    // Cast a ray to the right and find the nearest edge of the polygon that it hits. The endpoint
+    // of that edge farther to the right is a candidate for the bridge.
+    let mut hit: Option<(f32, usize)> = None;
+    for position in 0..polygon.len() {
+        let next_position = (position + 1) % polygon.len();
+        let from = vertices[polygon[position] as usize];
+        let to = vertices[polygon[next_position] as usize];
+        if (from.y > hole_point.y) == (to.y > hole_point.y) {
+            continue
+        }
+
+        let x = from.x + (hole_point.y - from.y) * (to.x - from.x) / (to.y - from.y);
+        if x < hole_point.x || hit.map_or(false, |(hit_x, _)| hit_x <= x) {
+            continue
+        }
+        hit = Some((x, if from.x > to.x { position } else { next_position }))
+    }
+
+    let (hit_x, mut bridge_position) = match hit {
+        Some(hit) => hit,
+        None => {
+            // The hole isn't inside the polygon after all. Fall back to the nearest vertex.
+            let mut nearest_position = 0;
+            for position in 0..polygon.len() {
+                let distance = distance_squared(&vertices[polygon[position] as usize],
+                                                &hole_point);
+                let nearest_distance =
+                    distance_squared(&vertices[polygon[nearest_position] as usize], &hole_point);
+                if distance < nearest_distance {
+                    nearest_position = position
+                }
+            }
+            (hole_point.x, nearest_position)
+        }
+    };
+
+    // Vertices of the polygon inside the triangle formed by the hole point, the hit point, and
+    // the candidate may block the bridge. If so, bridge to the one closest in angle to the ray.
+    let hit_point = Point2D::new(hit_x, hole_point.y);
+    let candidate = vertices[polygon[bridge_position] as usize];
+    let mut best_slope = slope(&hole_point, &candidate);
+    for position in 0..polygon.len() {
+        let point = vertices[polygon[position] as usize];
+        if position == bridge_position || point.x < hole_point.x ||
+                !point_in_triangle(&point, &hole_point, &hit_point, &candidate) {
+            continue
+        }
+        let point_slope = slope(&hole_point, &point);
+        if point_slope < best_slope {
+            best_slope = point_slope;
+            bridge_position = position
+        }
+    }
+
+    // Splice the hole in: ..., bridge, hole start, ..., hole start, bridge, ...
+    let mut spliced = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    spliced.extend_from_slice(&polygon[..(bridge_position + 1)]);
+    spliced.extend_from_slice(&hole[hole_start..]);
+    spliced.extend_from_slice(&hole[..(hole_start + 1)]);
+    spliced.extend_from_slice(&polygon[bridge_position..]);
+    *polygon = spliced
+}
+
+// Triangulates a counterclockwise polygon.
+fn clip_ears(vertices: &[Point2D<f32>], mut polygon: Vec<u32>, indices: &mut Vec<u32>) {
+    let mut position = 0;
+    while polygon.len() > 3 {
+        // Look for an ear, starting where the last one was clipped.
+        let length = polygon.len();
+        let mut ear_position = None;
+        for offset in 0..length {
+            let candidate = (position + offset) % length;
+            if is_ear(vertices, &polygon, candidate) {
+                ear_position = Some(candidate);
+                break
+            }
+        }
+
+        // If the polygon is degenerate there may be no ear. Clip a vertex anyway so that we
+        // terminate.
+        position = ear_position.unwrap_or(position % length);
+        let (previous, current, next) = corner(&polygon, position);
+        if cross(vertices, previous, current, next) > 0.0 {
+            indices.extend_from_slice(&[previous, current, next])
+        }
+        polygon.remove(position);
+        if position > 0 {
+            position -= 1
+        }
+    }
+
+    if polygon.len() == 3 && cross(vertices, polygon[0], polygon[1], polygon[2]) > 0.0 {
+        indices.extend_from_slice(&polygon)
+    }
+}
+
+fn is_ear(vertices: &[Point2D<f32>], polygon: &[u32], position: usize) -> bool {
+    let (previous, current, next) = corner(polygon, position);
+    if cross(vertices, previous, current, next) <= 0.0 {
+        return false
+    }
+
+    let (a, b, c) = (vertices[previous as usize],
+                     vertices[current as usize],
+                     vertices[next as usize]);
+    polygon.iter().all(|&index| {
+        let point = vertices[index as usize];
+        index == previous || index == current || index == next ||
+            point == a || point == b || point == c ||
+            !point_in_triangle(&point, &a, &b, &c)
+    })
+}
+
+#[inline]
+fn corner(polygon: &[u32], position: usize) -> (u32, u32, u32) {
+    let length = polygon.len();
+    (polygon[(position + length - 1) % length], polygon[position], polygon[(position + 1) % length])
+}
+
+#[inline]
+fn cross(vertices: &[Point2D<f32>], a: u32, b: u32, c: u32) -> f32 {
+    let (a, b, c) = (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+// Includes points on the boundary. The triangle may have either orientation.
+fn point_in_triangle(point: &Point2D<f32>,
+                     a: &Point2D<f32>,
+                     b: &Point2D<f32>,
+                     c: &Point2D<f32>)
+                     -> bool {
+    let side = |from: &Point2D<f32>, to: &Point2D<f32>| {
+        (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+    };
+    let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+}
+
+// Even-odd test, which is enough to tell whether one contour lies inside another.
+fn polygon_contains(vertices: &[Point2D<f32>], polygon: &[u32], point: &Point2D<f32>) -> bool {
+    let mut inside = false;
+    for position in 0..polygon.len() {
+        let from = vertices[polygon[position] as usize];
+        let to = vertices[polygon[(position + 1) % polygon.len()] as usize];
+        if (from.y > point.y) != (to.y > point.y) &&
+                point.x < from.x + (point.y - from.y) * (to.x - from.x) / (to.y - from.y) {
+            inside = !inside
+        }
+    }
+    inside
+}
+
+#[inline]
+fn slope(from: &Point2D<f32>, to: &Point2D<f32>) -> f32 {
+    (to.y - from.y).abs() / (to.x - from.x)
+}
+
+#[inline]
+fn distance_squared(a: &Point2D<f32>, b: &Point2D<f32>) -> f32 {
+    let vector = *a - *b;
+    vector.dot(vector)
+}
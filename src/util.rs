@@ -26,3 +26,45 @@ impl<'a> Jump for &'a [u8] {
     }
 }
 
+/// Multiplies a value by a 16.16 fixed-point factor, rounding to nearest with ties away from zero.
+///
+/// This is bit-for-bit compatible with FreeType's `FT_MulFix()`.
+#[inline]
+pub fn mul_fix(a: i32, b: i32) -> i32 {
+    let ab = a as i64 * b as i64;
+    ((ab + 0x8000 + (ab >> 63)) >> 16) as i32
+}
+
+/// Divides two values, yielding a 16.16 fixed-point quotient rounded to nearest.
+///
+/// This is bit-for-bit compatible with FreeType's `FT_DivFix()`, including its saturation when
+/// dividing by zero.
+#[inline]
+pub fn div_fix(a: i32, b: i32) -> i32 {
+    let negative = (a < 0) != (b < 0);
+    let (a, b) = ((a as i64).abs() as u64, (b as i64).abs() as u64);
+    let quotient = if b > 0 {
+        (((a << 16) + (b >> 1)) / b) as i32
+    } else {
+        0x7fffffff
+    };
+    if negative {
+        quotient.wrapping_neg()
+    } else {
+        quotient
+    }
+}
+
+/// Returns the 16.16 fixed-point factor that converts font units to 26.6 fixed-point pixels at
+/// the given point size, computed the way FreeType computes `x_scale` for `FT_Set_Char_Size()` at
+/// 72 DPI.
+///
+/// A point size that is not positive or a units per em of zero yields a scale of zero.
+#[inline]
+pub fn scale_26_6(units_per_em: u16, point_size: f32) -> i32 {
+    if !(point_size > 0.0) || units_per_em == 0 {
+        return 0
+    }
+    let char_size = (point_size as f64 * 64.0).round().min(0x7fffffff as f64) as i32;
+    div_fix(char_size, units_per_em as i32)
+}
@@ -0,0 +1,123 @@
+// Copyright 2017 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Normalization of the direction in which glyph contours wind.
+
+use euclid::Point2D;
+use simplify::ContourPoint;
+
+/// Reverses those of the given contours that wind the wrong way, so that outer contours wind
+/// clockwise and holes counterclockwise in the font's y-up coordinates, as in TrueType outlines.
+/// Returns the number of contours reversed.
+///
+/// The contours must be in the form that `Font::for_each_point()` produces: each starts and ends
+/// with the same on-curve point, and every off-curve point lies between two on-curve points. A
+/// contour is a hole if it lies entirely within an odd number of the other contours. Contours that
+/// enclose no area, or that another contour encloses only part of, are left alone.
+pub fn normalize(contours: &mut [Vec<ContourPoint>]) -> u32 {
+    let polygons: Vec<Vec<Point2D<f32>>> = contours.iter().map(|contour| polygon(contour))
+                                                   .collect();
+
+    let mut reversed_count = 0;
+    for (contour_index, contour) in contours.iter_mut().enumerate() {
+        let area = signed_area(contour);
+        if area == 0.0 {
+            continue
+        }
+
+        // Count the contours that enclose this one. If another contour encloses only part of
+        // it, whether it's meant to be a hole is anyone's guess, so leave it as it is.
+        let polygon = &polygons[contour_index];
+        let mut depth = Some(0);
+        for (other_index, other) in polygons.iter().enumerate() {
+            if other_index == contour_index {
+                continue
+            }
+            let inside_count = polygon.iter().filter(|point| contains(other, point)).count();
+            if inside_count == polygon.len() {
+                depth = depth.map(|depth| depth + 1)
+            } else if inside_count > 0 {
+                depth = None
+            }
+        }
+        let depth = match depth {
+            None => continue,
+            Some(depth) => depth,
+        };
+
+        // Counterclockwise contours have positive area.
+        let hole = depth % 2 == 1;
+        if (area > 0.0) != hole {
+            contour.reverse();
+            reversed_count += 1
+        }
+    }
+    reversed_count
+}
+
+// Returns the area that the contour encloses, including the area under its curves, which is
+// positive if it winds counterclockwise.
+fn signed_area(contour: &[ContourPoint]) -> f32 {
+    let mut area = 0.0;
+    for (index, point) in contour.iter().enumerate() {
+        let next = match contour.get(index + 1) {
+            None => break,
+            Some(next) => next,
+        };
+        area += cross(&to_f32(&point.position), &to_f32(&next.position));
+
+        // A quadratic curve covers two thirds of the triangle formed by its control points, but
+        // the sum above counts all of it.
+        if !point.on_curve && index > 0 {
+            let (from, to) = (&contour[index - 1].position, &next.position);
+            let (from, control, to) = (to_f32(from), to_f32(&point.position), to_f32(to));
+            area -= cross(&(control - from), &(to - from)) / 3.0
+        }
+    }
+    area * 0.5
+}
+
+// Approximates the contour with a polygon through its on-curve points and the midpoint of each
+// curve.
+fn polygon(contour: &[ContourPoint]) -> Vec<Point2D<f32>> {
+    let mut polygon = vec![];
+    for (index, point) in contour.iter().enumerate() {
+        let position = to_f32(&point.position);
+        if point.on_curve || index == 0 || index + 1 == contour.len() {
+            polygon.push(position);
+            continue
+        }
+        let (from, to) = (to_f32(&contour[index - 1].position),
+                          to_f32(&contour[index + 1].position));
+        polygon.push((from + to) * 0.25 + position * 0.5)
+    }
+    polygon
+}
+
+// Returns true if the point lies within the closed polygon under the even-odd rule.
+fn contains(polygon: &[Point2D<f32>], point: &Point2D<f32>) -> bool {
+    let mut inside = false;
+    for (index, from) in polygon.iter().enumerate() {
+        let to = &polygon[(index + 1) % polygon.len()];
+        if (from.y > point.y) != (to.y > point.y) &&
+                point.x < from.x + (point.y - from.y) * (to.x - from.x) / (to.y - from.y) {
+            inside = !inside
+        }
+    }
+    inside
+}
+
+fn cross(a: &Point2D<f32>, b: &Point2D<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn to_f32(point: &Point2D<i16>) -> Point2D<f32> {
+    Point2D::new(point.x as f32, point.y as f32)
+}